@@ -114,6 +114,46 @@ pub struct RunCmd {
 	/// commonly `127.0.0.1:6831`.
 	#[structopt(long)]
 	pub jaeger_agent: Option<std::net::SocketAddr>,
+
+	/// Override the timeout of outgoing requests of a request-response protocol.
+	///
+	/// Takes a protocol and a timeout in milliseconds, as in `--request-timeout chunk=2000`, and
+	/// may be given once per protocol. The protocols are `chunk`, `multi-chunk`, `collation`,
+	/// `collation-v2`, `pov`, `compressed-pov`, `available-data`, `compressed-available-data`,
+	/// `available-data-v2`, `statement`, `statement-v2`, `dispute` and `dispute-batch`.
+	#[structopt(
+		long = "request-timeout",
+		value_name = "PROTOCOL=MILLISECONDS",
+		parse(try_from_str = parse_protocol_value),
+	)]
+	pub request_timeouts: Vec<(String, u64)>,
+
+	/// Override the number of incoming requests of a request-response protocol that are
+	/// queued before further requests are dropped.
+	///
+	/// Takes a protocol and a size, as in `--request-queue-size pov=50`, and may be given once
+	/// per protocol. The protocols are the same as for `--request-timeout`.
+	#[structopt(
+		long = "request-queue-size",
+		value_name = "PROTOCOL=SIZE",
+		parse(try_from_str = parse_protocol_value),
+	)]
+	pub request_queue_sizes: Vec<(String, u64)>,
+
+	/// Only use the request-response protocol names derived from the genesis hash.
+	///
+	/// By default the protocols are registered under their legacy names as well, and requests
+	/// are sent under these, so that nodes not yet knowing the new names can be talked to.
+	#[structopt(long)]
+	pub no_legacy_request_protocol_names: bool,
+}
+
+/// Parse a `PROTOCOL=VALUE` pair of a request-response option.
+fn parse_protocol_value(s: &str) -> Result<(String, u64), String> {
+	let (protocol, value) = s.split_once('=')
+		.ok_or_else(|| format!("Expected `PROTOCOL=VALUE`, got `{}`", s))?;
+	let value = value.parse().map_err(|e| format!("Invalid value `{}`: {}", value, e))?;
+	Ok((protocol.to_owned(), value))
 }
 
 #[allow(missing_docs)]
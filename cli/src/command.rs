@@ -17,8 +17,9 @@
 use log::info;
 use service::{IdentifyVariant, self};
 use sc_cli::{SubstrateCli, RuntimeVersion, Role};
-use crate::cli::{Cli, Subcommand};
+use crate::cli::{Cli, RunCmd, Subcommand};
 use futures::future::TryFutureExt;
+use std::time::Duration;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -182,6 +183,24 @@ fn ensure_dev(spec: &Box<dyn service::ChainSpec>) -> std::result::Result<(), Str
 	}
 }
 
+/// The request-response settings given by `--request-timeout`, `--request-queue-size` and
+/// `--no-legacy-request-protocol-names`.
+fn request_response_settings(run: &RunCmd) -> Result<service::RequestResponseSettings> {
+	let mut settings = service::RequestResponseSettings::default();
+	settings.set_legacy_names(!run.no_legacy_request_protocol_names);
+	for (protocol, millis) in &run.request_timeouts {
+		let protocol = protocol.parse::<service::RequestProtocol>()?;
+		settings.set_request_timeout(protocol, Duration::from_millis(*millis))
+			.map_err(|e| Error::Other(e.to_string()))?;
+	}
+	for (protocol, size) in &run.request_queue_sizes {
+		let protocol = protocol.parse::<service::RequestProtocol>()?;
+		settings.set_channel_size(protocol, *size as usize)
+			.map_err(|e| Error::Other(e.to_string()))?;
+	}
+	Ok(settings)
+}
+
 /// Launch a node, accepting arguments just like a regular node,
 /// accepts an alternative overseer generator, to adjust behavior
 /// for integration tests as needed.
@@ -212,6 +231,7 @@ fn run_node_inner(cli: Cli, overseer_gen: impl service::OverseerGen) -> Result<(
 	}
 
 	let jaeger_agent = cli.run.jaeger_agent;
+	let request_response_settings = request_response_settings(&cli.run)?;
 
 	runner.run_node_until_exit(move |config| async move {
 		let role = config.role.clone();
@@ -228,6 +248,7 @@ fn run_node_inner(cli: Cli, overseer_gen: impl service::OverseerGen) -> Result<(
 				cli.run.no_beefy,
 				jaeger_agent,
 				None,
+				request_response_settings,
 				overseer_gen,
 			).map(|full| full.task_manager).map_err(Into::into)
 		}
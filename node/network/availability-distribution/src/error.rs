@@ -17,7 +17,7 @@
 
 //! Error handling related code and Error/Result definitions.
 
-use polkadot_node_network_protocol::request_response::request::RequestError;
+use polkadot_node_network_protocol::request_response::{request::RequestError, v1::RefusalReason};
 use thiserror::Error;
 
 use futures::channel::oneshot;
@@ -98,6 +98,9 @@ pub enum NonFatal {
 	#[error("Remote responded with `NoSuchPoV`")]
 	NoSuchPoV,
 
+	#[error("Remote refused to serve the PoV: {0:?}")]
+	PoVRefused(RefusalReason),
+
 	/// No validator with the index could be found in current session.
 	#[error("Given validator index could not be found")]
 	InvalidValidatorIndex,
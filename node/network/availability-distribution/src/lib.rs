@@ -40,7 +40,10 @@ mod pov_requester;
 
 /// Responding to erasure chunk requests:
 mod responder;
-use responder::{answer_chunk_request_log, answer_pov_request_log};
+use responder::{
+	answer_chunk_request_log, answer_compressed_pov_request_log, answer_multi_chunk_request_log,
+	answer_pov_request_log,
+};
 
 mod metrics;
 /// Prometheus `Metrics` for availability distribution.
@@ -128,11 +131,21 @@ impl AvailabilityDistributionSubsystem {
 				} => {
 					answer_chunk_request_log(&mut ctx, req, &self.metrics).await
 				}
+				FromOverseer::Communication {
+					msg: AvailabilityDistributionMessage::MultiChunkFetchingRequest(req),
+				} => {
+					answer_multi_chunk_request_log(&mut ctx, req, &self.metrics).await
+				}
 				FromOverseer::Communication {
 					msg: AvailabilityDistributionMessage::PoVFetchingRequest(req),
 				} => {
 					answer_pov_request_log(&mut ctx, req, &self.metrics).await
 				}
+				FromOverseer::Communication {
+					msg: AvailabilityDistributionMessage::CompressedPoVFetchingRequest(req),
+				} => {
+					answer_compressed_pov_request_log(&mut ctx, req, &self.metrics).await
+				}
 				FromOverseer::Communication {
 					msg: AvailabilityDistributionMessage::FetchPoV {
 						relay_parent,
@@ -20,13 +20,14 @@ use futures::{FutureExt, channel::oneshot, future::BoxFuture};
 
 use polkadot_subsystem::jaeger;
 use polkadot_node_network_protocol::request_response::{OutgoingRequest, Recipient, request::{RequestError, Requests},
-	v1::{PoVFetchingRequest, PoVFetchingResponse}};
+	compression::Compressed,
+	v1::{CompressedPoVFetchingRequest, PoVFetchingRequest, PoVFetchingResponse}};
 use polkadot_primitives::v1::{
-	CandidateHash, Hash, ValidatorIndex,
+	AuthorityDiscoveryId, CandidateHash, Hash, ValidatorIndex,
 };
 use polkadot_node_primitives::PoV;
 use polkadot_subsystem::{
-	SubsystemContext,
+	SubsystemContext, SubsystemSender,
 	messages::{NetworkBridgeMessage, IfDisconnected}
 };
 use polkadot_node_subsystem_util::runtime::RuntimeInfo;
@@ -35,6 +36,9 @@ use crate::error::{Fatal, NonFatal};
 use crate::LOG_TARGET;
 
 /// Start background worker for taking care of fetching the requested `PoV` from the network.
+///
+/// The `PoV` is requested compressed, validators not supporting this yet are asked again with an
+/// uncompressed request.
 pub async fn fetch_pov<Context>(
 	ctx: &mut Context,
 	runtime: &mut RuntimeInfo,
@@ -52,12 +56,12 @@ where
 		.ok_or(NonFatal::InvalidValidatorIndex)?
 		.clone();
 	let (req, pending_response) = OutgoingRequest::new(
-		Recipient::Authority(authority_id),
-		PoVFetchingRequest {
+		Recipient::Authority(authority_id.clone()),
+		CompressedPoVFetchingRequest(PoVFetchingRequest {
 			candidate_hash,
-		},
+		}),
 	);
-	let full_req = Requests::PoVFetching(req);
+	let full_req = Requests::CompressedPoVFetching(req);
 
 	ctx.send_message(
 			NetworkBridgeMessage::SendRequests(
@@ -72,19 +76,51 @@ where
 	let span = jaeger::Span::new(candidate_hash, "fetch-pov")
 		.with_validator_index(from_validator)
 		.with_relay_parent(parent);
-	ctx.spawn("pov-fetcher", fetch_pov_job(pov_hash, pending_response.boxed(), span, tx).boxed())
+	let fallback = Fallback { sender: ctx.sender().clone(), authority_id, candidate_hash };
+	ctx.spawn(
+		"pov-fetcher",
+		fetch_pov_job(pov_hash, pending_response.boxed(), fallback, span, tx).boxed(),
+	)
 		.map_err(|e| Fatal::SpawnTask(e))?;
 	Ok(())
 }
 
+/// What is needed to request the `PoV` uncompressed, if the validator does not support compressed
+/// responses.
+struct Fallback<Sender> {
+	sender: Sender,
+	authority_id: AuthorityDiscoveryId,
+	candidate_hash: CandidateHash,
+}
+
+impl<Sender: SubsystemSender> Fallback<Sender> {
+	/// Request the `PoV` with an uncompressed response.
+	async fn request(mut self) -> Result<PoVFetchingResponse, RequestError> {
+		let (req, pending_response) = OutgoingRequest::new(
+			Recipient::Authority(self.authority_id),
+			PoVFetchingRequest {
+				candidate_hash: self.candidate_hash,
+			},
+		);
+		self.sender.send_message(
+			NetworkBridgeMessage::SendRequests(
+				vec![Requests::PoVFetching(req)],
+				IfDisconnected::TryConnect
+			).into()
+		).await;
+		pending_response.await
+	}
+}
+
 /// Future to be spawned for taking care of handling reception and sending of PoV.
-async fn fetch_pov_job(
+async fn fetch_pov_job<Sender: SubsystemSender>(
 	pov_hash: Hash,
-	pending_response: BoxFuture<'static, Result<PoVFetchingResponse, RequestError>>,
+	pending_response: BoxFuture<'static, Result<Compressed<PoVFetchingResponse>, RequestError>>,
+	fallback: Fallback<Sender>,
 	span: jaeger::Span,
 	tx: oneshot::Sender<PoV>,
 ) {
-	if let Err(err) = do_fetch_pov(pov_hash, pending_response, span, tx).await {
+	if let Err(err) = do_fetch_pov(pov_hash, pending_response, fallback, span, tx).await {
 		tracing::warn!(
 			target: LOG_TARGET,
 			?err,
@@ -94,20 +130,29 @@ async fn fetch_pov_job(
 }
 
 /// Do the actual work of waiting for the response.
-async fn do_fetch_pov(
+async fn do_fetch_pov<Sender: SubsystemSender>(
 	pov_hash: Hash,
-	pending_response: BoxFuture<'static, Result<PoVFetchingResponse, RequestError>>,
+	pending_response: BoxFuture<'static, Result<Compressed<PoVFetchingResponse>, RequestError>>,
+	fallback: Fallback<Sender>,
 	_span: jaeger::Span,
 	tx: oneshot::Sender<PoV>,
 )
 	-> std::result::Result<(), NonFatal>
 {
-	let response = pending_response.await.map_err(NonFatal::FetchPoV)?;
+	let response = match pending_response.await {
+		Ok(Compressed(response)) => response,
+		Err(err) if err.is_unsupported_protocol() =>
+			fallback.request().await.map_err(NonFatal::FetchPoV)?,
+		Err(err) => return Err(NonFatal::FetchPoV(err)),
+	};
 	let pov = match response {
 		PoVFetchingResponse::PoV(pov) => pov,
 		PoVFetchingResponse::NoSuchPoV => {
 			return Err(NonFatal::NoSuchPoV)
 		}
+		PoVFetchingResponse::Refused(reason) => {
+			return Err(NonFatal::PoVRefused(reason))
+		}
 	};
 	if pov.hash() == pov_hash {
 		tx.send(pov).map_err(|_| NonFatal::SendResponse)
@@ -123,6 +168,7 @@ mod tests {
 
 	use parity_scale_codec::Encode;
 	use sp_core::testing::TaskExecutor;
+	use sc_network::{OutboundFailure, RequestFailure};
 
 	use polkadot_primitives::v1::{CandidateHash, Hash, ValidatorIndex};
 	use polkadot_node_primitives::BlockData;
@@ -140,7 +186,7 @@ mod tests {
 		let pov = PoV {
 			block_data: BlockData(vec![1,2,3,4,5,6]),
 		};
-		test_run(Hash::default(), pov);
+		test_run(Hash::default(), pov, true);
 	}
 
 	#[test]
@@ -149,10 +195,20 @@ mod tests {
 		let pov = PoV {
 			block_data: BlockData(vec![1,2,3,4,5,6]),
 		};
-		test_run(pov.hash(), pov);
+		test_run(pov.hash(), pov, true);
+	}
+
+	#[test]
+	fn falls_back_to_uncompressed_pov() {
+		sp_tracing::try_init_simple();
+		let pov = PoV {
+			block_data: BlockData(vec![1,2,3,4,5,6]),
+		};
+		test_run(pov.hash(), pov, false);
 	}
 
-	fn test_run(pov_hash: Hash, pov: PoV) {
+	/// Fetch `pov`, from a validator supporting compressed responses or not.
+	fn test_run(pov_hash: Hash, pov: PoV, supports_compression: bool) {
 		let pool = TaskExecutor::new();
 		let (mut context, mut virtual_overseer) =
 			test_helpers::make_subsystem_context::<AvailabilityDistributionMessage, TaskExecutor>(pool.clone());
@@ -192,13 +248,24 @@ mod tests {
 						tx.send(Ok(Some(make_session_info()))).unwrap();
 					}
 					AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(mut reqs, _)) => {
-						let req = assert_matches!(
-							reqs.pop(),
-							Some(Requests::PoVFetching(outgoing)) => {outgoing}
-						);
-						req.pending_response.send(Ok(PoVFetchingResponse::PoV(pov.clone()).encode()))
-							.unwrap();
-						break
+						match reqs.pop() {
+							Some(Requests::CompressedPoVFetching(outgoing)) if supports_compression => {
+								let response = Compressed(PoVFetchingResponse::PoV(pov.clone()));
+								outgoing.pending_response.send(Ok(response.encode())).unwrap();
+								break
+							}
+							Some(Requests::CompressedPoVFetching(outgoing)) => {
+								outgoing.pending_response.send(Err(RequestFailure::Network(
+									OutboundFailure::UnsupportedProtocols,
+								))).unwrap();
+							}
+							Some(Requests::PoVFetching(outgoing)) if !supports_compression => {
+								let response = PoVFetchingResponse::PoV(pov.clone());
+								outgoing.pending_response.send(Ok(response.encode())).unwrap();
+								break
+							}
+							reqs => panic!("Unexpected requests: {:?}", reqs),
+						}
 					},
 					msg => tracing::debug!(target: LOG_TARGET, msg = ?msg, "Received msg"),
 				}
@@ -302,6 +302,16 @@ impl RunningTask {
 					bad_validators.push(validator);
 					continue
 				}
+				ChunkFetchingResponse::Refused(reason) => {
+					tracing::debug!(
+						target: LOG_TARGET,
+						validator = ?validator,
+						?reason,
+						"Validator refused to serve our chunk"
+					);
+					bad_validators.push(validator);
+					continue
+				}
 			};
 
 			// Data genuine?
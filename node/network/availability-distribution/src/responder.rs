@@ -20,7 +20,9 @@ use std::sync::Arc;
 
 use futures::channel::oneshot;
 
-use polkadot_node_network_protocol::request_response::{request::IncomingRequest, v1};
+use polkadot_node_network_protocol::request_response::{
+	compression::Compressed, request::IncomingRequest, v1,
+};
 use polkadot_primitives::v1::{CandidateHash, ValidatorIndex};
 use polkadot_node_primitives::{AvailableData, ErasureChunk};
 use polkadot_subsystem::{
@@ -57,6 +59,32 @@ where
 	}
 }
 
+/// Variant of `answer_compressed_pov_request` that does Prometheus metric and logging on errors.
+///
+/// Any errors of `answer_compressed_pov_request` will simply be logged.
+pub async fn answer_compressed_pov_request_log<Context>(
+	ctx: &mut Context,
+	req: IncomingRequest<v1::CompressedPoVFetchingRequest>,
+	metrics: &Metrics,
+)
+where
+	Context: SubsystemContext,
+{
+	let res = answer_compressed_pov_request(ctx, req).await;
+	match res {
+		Ok(result) =>
+			metrics.on_served_pov(if result {SUCCEEDED} else {NOT_FOUND}),
+		Err(err) => {
+			tracing::warn!(
+				target: LOG_TARGET,
+				err= ?err,
+				"Serving compressed PoV failed with error"
+			);
+			metrics.on_served_pov(FAILED);
+		}
+	}
+}
+
 /// Variant of `answer_chunk_request` that does Prometheus metric and logging on errors.
 ///
 /// Any errors of `answer_request` will simply be logged.
@@ -83,6 +111,33 @@ where
 	}
 }
 
+/// Variant of `answer_multi_chunk_request` that does Prometheus metric and logging on errors.
+///
+/// Every requested chunk counts as a served chunk.
+pub async fn answer_multi_chunk_request_log<Context>(
+	ctx: &mut Context,
+	req: IncomingRequest<v1::MultiChunkFetchingRequest>,
+	metrics: &Metrics,
+)
+where
+	Context: SubsystemContext,
+{
+	let res = answer_multi_chunk_request(ctx, req).await;
+	match res {
+		Ok(found) => for found in found {
+			metrics.on_served_chunk(if found {SUCCEEDED} else {NOT_FOUND})
+		},
+		Err(err) => {
+			tracing::warn!(
+				target: LOG_TARGET,
+				err= ?err,
+				"Serving multiple chunks failed with error"
+			);
+			metrics.on_served_chunk(FAILED);
+		}
+	}
+}
+
 /// Answer an incoming PoV fetch request by querying the av store.
 ///
 /// Returns: Ok(true) if chunk was found and served.
@@ -95,20 +150,50 @@ where
 {
 	let _span = jaeger::Span::new(req.payload.candidate_hash, "answer-pov-request");
 
-	let av_data = query_available_data(ctx, req.payload.candidate_hash).await?;
+	let response = pov_response(ctx, req.payload.candidate_hash).await?;
+	let result = !matches!(response, v1::PoVFetchingResponse::NoSuchPoV);
 
-	let result = av_data.is_some();
+	req.send_response(response).map_err(|_| NonFatal::SendResponse)?;
+	Ok(result)
+}
 
-	let response = match av_data {
+/// Answer an incoming compressed PoV fetch request by querying the av store.
+///
+/// Returns: Ok(true) if the PoV was found and served.
+pub async fn answer_compressed_pov_request<Context>(
+	ctx: &mut Context,
+	req: IncomingRequest<v1::CompressedPoVFetchingRequest>,
+) -> Result<bool>
+where
+	Context: SubsystemContext,
+{
+	let candidate_hash = req.payload.0.candidate_hash;
+	let _span = jaeger::Span::new(candidate_hash, "answer-compressed-pov-request");
+
+	let response = pov_response(ctx, candidate_hash).await?;
+	let result = !matches!(response, v1::PoVFetchingResponse::NoSuchPoV);
+
+	req.send_response(Compressed(response)).map_err(|_| NonFatal::SendResponse)?;
+	Ok(result)
+}
+
+/// Look up the PoV of `candidate_hash` in the av store, for answering a request.
+async fn pov_response<Context>(
+	ctx: &mut Context,
+	candidate_hash: CandidateHash,
+) -> Result<v1::PoVFetchingResponse>
+where
+	Context: SubsystemContext,
+{
+	let av_data = query_available_data(ctx, candidate_hash).await?;
+
+	Ok(match av_data {
 		None => v1::PoVFetchingResponse::NoSuchPoV,
 		Some(av_data) => {
 			let pov = Arc::try_unwrap(av_data.pov).unwrap_or_else(|a| (&*a).clone());
 			v1::PoVFetchingResponse::PoV(pov)
 		}
-	};
-
-	req.send_response(response).map_err(|_| NonFatal::SendResponse)?;
-	Ok(result)
+	})
 }
 
 /// Answer an incoming chunk request by querying the av store.
@@ -148,6 +233,56 @@ where
 	Ok(result)
 }
 
+/// Answer an incoming request for several chunks by querying the av store for each of them.
+///
+/// Requests for more than `v1::MAX_CHUNKS_PER_REQUEST` chunks are refused as invalid.
+///
+/// Returns: For each requested chunk, whether it was found and served.
+pub async fn answer_multi_chunk_request<Context>(
+	ctx: &mut Context,
+	req: IncomingRequest<v1::MultiChunkFetchingRequest>,
+) -> Result<Vec<bool>>
+where
+	Context: SubsystemContext,
+{
+	if req.payload.indices.len() > v1::MAX_CHUNKS_PER_REQUEST {
+		tracing::debug!(
+			target: LOG_TARGET,
+			peer = ?req.peer,
+			requested = req.payload.indices.len(),
+			"Refusing request for too many chunks",
+		);
+		req.respond_refusal(v1::RefusalReason::Invalid).map_err(|_| NonFatal::SendResponse)?;
+		return Ok(Vec::new())
+	}
+
+	let span = jaeger::Span::new(req.payload.candidate_hash, "answer-multi-chunk-request");
+
+	let mut chunks = Vec::with_capacity(req.payload.indices.len());
+	for index in req.payload.indices.iter().copied() {
+		let _child_span = span.child("answer-chunk-request").with_chunk_index(index.0);
+		chunks.push(query_chunk(ctx, req.payload.candidate_hash, index).await?);
+	}
+
+	let result: Vec<bool> = chunks.iter().map(Option::is_some).collect();
+
+	tracing::trace!(
+		target: LOG_TARGET,
+		hash = ?req.payload.candidate_hash,
+		indices = ?req.payload.indices,
+		peer = ?req.peer,
+		has_data = ?result,
+		"Serving chunks",
+	);
+
+	let response = v1::MultiChunkFetchingResponse::Chunks(
+		chunks.into_iter().map(|chunk| chunk.map(Into::into)).collect(),
+	);
+
+	req.send_response(response).map_err(|_| NonFatal::SendResponse)?;
+	Ok(result)
+}
+
 /// Query chunk from the availability store.
 async fn query_chunk<Context>(
 	ctx: &mut Context,
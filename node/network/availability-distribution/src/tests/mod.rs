@@ -16,9 +16,15 @@
 
 use std::collections::HashSet;
 
-use futures::{executor, future, Future};
-
-use polkadot_primitives::v1::CoreState;
+use assert_matches::assert_matches;
+use futures::{channel::oneshot, executor, future, Future};
+use parity_scale_codec::Decode;
+
+use polkadot_node_network_protocol::request_response::{IncomingRequest, v1};
+use polkadot_node_primitives::ErasureChunk;
+use polkadot_primitives::v1::{CandidateHash, CoreState, Hash, ValidatorIndex};
+use polkadot_subsystem::messages::{AllMessages, AvailabilityStoreMessage};
+use sc_network::PeerId;
 use sp_keystore::SyncCryptoStorePtr;
 
 use polkadot_subsystem_testhelpers as test_helpers;
@@ -124,3 +130,58 @@ fn check_fetch_retry() {
 		state.run(harness)
 	});
 }
+
+/// Check that multi chunk requests are answered with every requested chunk in order, and refused
+/// if they ask for too many.
+#[test]
+fn check_multi_chunk_requests() {
+	let keystore = test_helpers::mock::make_ferdie_keystore();
+	test_harness(keystore, |TestHarness { mut virtual_overseer, .. }| async move {
+		let candidate_hash = CandidateHash(Hash::repeat_byte(1));
+		let request = |indices: Vec<ValidatorIndex>| {
+			let (tx, rx) = oneshot::channel();
+			let payload = v1::MultiChunkFetchingRequest { candidate_hash, indices };
+			let msg = AvailabilityDistributionMessage::MultiChunkFetchingRequest(
+				IncomingRequest::new(PeerId::random(), payload, tx),
+			);
+			(FromOverseer::Communication { msg }, async move {
+				let response = rx.await.expect("request is answered").result.expect("request is served");
+				v1::MultiChunkFetchingResponse::decode(&mut &response[..]).expect("response decodes")
+			})
+		};
+
+		let (msg, response) = request(vec![ValidatorIndex(0); v1::MAX_CHUNKS_PER_REQUEST + 1]);
+		virtual_overseer.send(msg).await;
+		assert_matches!(
+			response.await,
+			v1::MultiChunkFetchingResponse::Refused(v1::RefusalReason::Invalid)
+		);
+
+		let (msg, response) = request(vec![ValidatorIndex(2), ValidatorIndex(0)]);
+		virtual_overseer.send(msg).await;
+		assert_matches!(
+			virtual_overseer.recv().await,
+			AllMessages::AvailabilityStore(AvailabilityStoreMessage::QueryChunk(hash, ValidatorIndex(2), tx)) => {
+				assert_eq!(hash, candidate_hash);
+				tx.send(Some(ErasureChunk { chunk: vec![2], index: ValidatorIndex(2), proof: vec![] }))
+					.expect("subsystem waits for the chunk");
+			}
+		);
+		assert_matches!(
+			virtual_overseer.recv().await,
+			AllMessages::AvailabilityStore(AvailabilityStoreMessage::QueryChunk(_, ValidatorIndex(0), tx)) => {
+				tx.send(None).expect("subsystem waits for the chunk");
+			}
+		);
+		assert_matches!(
+			response.await,
+			v1::MultiChunkFetchingResponse::Chunks(chunks) => {
+				assert_eq!(chunks.len(), 2);
+				assert_eq!(chunks[0].as_ref().map(|c| c.chunk.clone()), Some(vec![2]));
+				assert!(chunks[1].is_none());
+			}
+		);
+
+		virtual_overseer.send(FromOverseer::Signal(OverseerSignal::Conclude)).await;
+	});
+}
@@ -19,6 +19,8 @@
 use futures::channel::oneshot;
 use thiserror::Error;
 
+use polkadot_node_network_protocol::request_response::{request::RequestError, v2::SegmentError};
+
 /// Error type used by the Availability Recovery subsystem.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -45,3 +47,16 @@ pub enum Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors of fetching the full available data from a single backer.
+#[derive(Debug, Error)]
+pub enum FetchError {
+	#[error(transparent)]
+	Request(#[from] RequestError),
+
+	#[error("received invalid segments")]
+	Segments(#[from] SegmentError),
+
+	#[error("received a response not matching the request")]
+	UnexpectedResponse,
+}
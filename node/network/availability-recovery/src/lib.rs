@@ -20,11 +20,13 @@
 
 use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
+use std::time::{Duration, Instant};
 
 use futures::{channel::oneshot, prelude::*, stream::FuturesUnordered};
 use futures::future::{BoxFuture, RemoteHandle, FutureExt};
 use futures::task::{Context, Poll};
 use lru::LruCache;
+use parity_scale_codec::Encode;
 use rand::seq::SliceRandom;
 
 use polkadot_primitives::v1::{
@@ -46,7 +48,7 @@ use polkadot_subsystem::{
 use polkadot_node_network_protocol::{
 	IfDisconnected,
 	request_response::{
-		self as req_res, OutgoingRequest, Recipient, Requests,
+		self as req_res, IncomingRequest, OutgoingRequest, Recipient, Requests,
 		request::RequestError,
 	},
 };
@@ -66,6 +68,13 @@ const N_PARALLEL: usize = 50;
 // Size of the LRU cache where we keep recovered data.
 const LRU_SIZE: usize = 16;
 
+// Number of candidates whose encoded available data we keep for serving its segments.
+const SEGMENT_CACHE_SIZE: usize = 4;
+
+// How long we keep encoded available data for serving its segments. Requesters fetch the
+// segments right after each other, so this only needs to cover a single transfer.
+const SEGMENT_CACHE_TTL: Duration = Duration::from_secs(30);
+
 /// The Availability Recovery Subsystem.
 pub struct AvailabilityRecoverySubsystem {
 	fast_path: bool,
@@ -146,19 +155,8 @@ impl RequestFromBackersPhase {
 			// Pop the next backer, and proceed to next phase if we're out.
 			let validator_index = self.shuffled_backers.pop().ok_or_else(|| RecoveryError::Unavailable)?;
 
-			// Request data.
-			let (req, res) = OutgoingRequest::new(
-				Recipient::Authority(params.validator_authority_keys[validator_index.0 as usize].clone()),
-				req_res::v1::AvailableDataFetchingRequest { candidate_hash: params.candidate_hash },
-			);
-
-			sender.send_message(NetworkBridgeMessage::SendRequests(
-				vec![Requests::AvailableDataFetching(req)],
-				IfDisconnected::TryConnect,
-			).into()).await;
-
-			match res.await {
-				Ok(req_res::v1::AvailableDataFetchingResponse::AvailableData(data)) => {
+			match fetch_from_backer(params, sender, validator_index).await {
+				Ok(Some(data)) => {
 					if reconstructed_data_matches_root(params.validators.len(), &params.erasure_root, &data) {
 						tracing::trace!(
 							target: LOG_TARGET,
@@ -178,7 +176,7 @@ impl RequestFromBackersPhase {
 						// it doesn't help to report the peer with req/res.
 					}
 				}
-				Ok(req_res::v1::AvailableDataFetchingResponse::NoSuchData) => {}
+				Ok(None) => {}
 				Err(e) => tracing::debug!(
 					target: LOG_TARGET,
 					candidate_hash = ?params.candidate_hash,
@@ -191,6 +189,132 @@ impl RequestFromBackersPhase {
 	}
 }
 
+/// Fetch the full available data from a backer, `None` if it doesn't have it or refused to serve
+/// it.
+///
+/// Data too large for a single response is fetched segment by segment, backers not supporting
+/// this yet are asked with a `v1` request instead. The data still needs to be checked against the
+/// erasure root.
+async fn fetch_from_backer(
+	params: &InteractionParams,
+	sender: &mut impl SubsystemSender,
+	validator_index: ValidatorIndex,
+) -> Result<Option<AvailableData>, error::FetchError> {
+	let authority = &params.validator_authority_keys[validator_index.0 as usize];
+	let log_refusal = |reason: req_res::v1::RefusalReason| tracing::debug!(
+		target: LOG_TARGET,
+		candidate_hash = ?params.candidate_hash,
+		?validator_index,
+		?reason,
+		"Validator refused to serve full available data."
+	);
+
+	let response = request_available_data(sender, authority, params.candidate_hash, None).await;
+	let size = match response {
+		Err(e) if e.is_unsupported_protocol() => {
+			return match request_available_data_v1(sender, authority, params.candidate_hash).await? {
+				req_res::v1::AvailableDataFetchingResponse::AvailableData(data) => Ok(Some(data)),
+				req_res::v1::AvailableDataFetchingResponse::NoSuchData => Ok(None),
+				req_res::v1::AvailableDataFetchingResponse::Refused(reason) => {
+					log_refusal(reason);
+					Ok(None)
+				}
+			}
+		}
+		Err(e) => return Err(e.into()),
+		Ok(req_res::v2::AvailableDataFetchingResponse::AvailableData(data)) => return Ok(Some(data)),
+		Ok(req_res::v2::AvailableDataFetchingResponse::Segmented { size }) => size,
+		Ok(req_res::v2::AvailableDataFetchingResponse::Segment(_)) =>
+			return Err(error::FetchError::UnexpectedResponse),
+		Ok(req_res::v2::AvailableDataFetchingResponse::NoSuchData) => return Ok(None),
+		Ok(req_res::v2::AvailableDataFetchingResponse::Refused(reason)) => {
+			log_refusal(reason);
+			return Ok(None)
+		}
+	};
+
+	let mut segments = req_res::v2::AvailableDataSegments::new(size)?;
+	while let Some(index) = segments.next_segment() {
+		match request_available_data(sender, authority, params.candidate_hash, Some(index)).await? {
+			req_res::v2::AvailableDataFetchingResponse::Segment(segment) => segments.push(segment)?,
+			// The data might have been pruned in the meantime.
+			req_res::v2::AvailableDataFetchingResponse::NoSuchData => return Ok(None),
+			req_res::v2::AvailableDataFetchingResponse::Refused(reason) => {
+				log_refusal(reason);
+				return Ok(None)
+			}
+			req_res::v2::AvailableDataFetchingResponse::AvailableData(_) |
+			req_res::v2::AvailableDataFetchingResponse::Segmented { .. } =>
+				return Err(error::FetchError::UnexpectedResponse),
+		}
+	}
+
+	tracing::trace!(
+		target: LOG_TARGET,
+		candidate_hash = ?params.candidate_hash,
+		?validator_index,
+		size,
+		"Received all segments of full data",
+	);
+
+	Ok(Some(segments.finish()?))
+}
+
+/// Request the available data of `candidate_hash` from `authority` with a `v1` request.
+///
+/// The response is requested compressed, unless the backer does not support this yet.
+async fn request_available_data_v1(
+	sender: &mut impl SubsystemSender,
+	authority: &AuthorityDiscoveryId,
+	candidate_hash: CandidateHash,
+) -> Result<req_res::v1::AvailableDataFetchingResponse, RequestError> {
+	let request = req_res::v1::AvailableDataFetchingRequest { candidate_hash };
+	let (req, res) = OutgoingRequest::new(
+		Recipient::Authority(authority.clone()),
+		req_res::v1::CompressedAvailableDataFetchingRequest(request.clone()),
+	);
+
+	sender.send_message(NetworkBridgeMessage::SendRequests(
+		vec![Requests::CompressedAvailableDataFetching(req)],
+		IfDisconnected::TryConnect,
+	).into()).await;
+
+	match res.await {
+		Ok(req_res::compression::Compressed(response)) => return Ok(response),
+		Err(e) if e.is_unsupported_protocol() => {}
+		Err(e) => return Err(e),
+	}
+
+	let (req, res) = OutgoingRequest::new(Recipient::Authority(authority.clone()), request);
+
+	sender.send_message(NetworkBridgeMessage::SendRequests(
+		vec![Requests::AvailableDataFetching(req)],
+		IfDisconnected::TryConnect,
+	).into()).await;
+
+	res.await
+}
+
+/// Request the available data of `candidate_hash` from `authority`, or a segment of it.
+async fn request_available_data(
+	sender: &mut impl SubsystemSender,
+	authority: &AuthorityDiscoveryId,
+	candidate_hash: CandidateHash,
+	segment: Option<u32>,
+) -> Result<req_res::v2::AvailableDataFetchingResponse, RequestError> {
+	let (req, res) = OutgoingRequest::new(
+		Recipient::Authority(authority.clone()),
+		req_res::v2::AvailableDataFetchingRequest { candidate_hash, segment },
+	);
+
+	sender.send_message(NetworkBridgeMessage::SendRequests(
+		vec![Requests::AvailableDataFetchingV2(req)],
+		IfDisconnected::TryConnect,
+	).into()).await;
+
+	res.await
+}
+
 impl RequestChunksPhase {
 	fn new(n_validators: u32) -> Self {
 		let mut shuffling: Vec<_> = (0..n_validators).map(ValidatorIndex).collect();
@@ -254,6 +378,7 @@ impl RequestChunksPhase {
 						Ok(req_res::v1::ChunkFetchingResponse::Chunk(chunk))
 							=> Ok(Some(chunk.recombine_into_chunk(&raw_request))),
 						Ok(req_res::v1::ChunkFetchingResponse::NoSuchChunk) => Ok(None),
+						Ok(req_res::v1::ChunkFetchingResponse::Refused(_)) => Ok(None),
 						Err(e) => Err((validator_index, e)),
 					}
 				}));
@@ -562,6 +687,9 @@ struct State {
 
 	/// An LRU cache of recently recovered data.
 	availability_lru: LruCache<CandidateHash, Result<AvailableData, RecoveryError>>,
+
+	/// Encoded available data we recently served segments of.
+	segment_cache: SegmentCache,
 }
 
 impl Default for State {
@@ -570,10 +698,41 @@ impl Default for State {
 			interactions: FuturesUnordered::new(),
 			live_block: (0, Hash::default()),
 			availability_lru: LruCache::new(LRU_SIZE),
+			segment_cache: SegmentCache::new(),
 		}
 	}
 }
 
+/// Encoded available data of the candidates we recently served segments of, so that the requests
+/// for all segments of a candidate are answered from a single query and encoding.
+struct SegmentCache {
+	entries: LruCache<CandidateHash, (Instant, Vec<u8>)>,
+}
+
+impl SegmentCache {
+	fn new() -> Self {
+		SegmentCache { entries: LruCache::new(SEGMENT_CACHE_SIZE) }
+	}
+
+	/// The encoded available data of `candidate_hash`, unless it was cached more than
+	/// `SEGMENT_CACHE_TTL` before `now`.
+	fn get(&mut self, candidate_hash: &CandidateHash, now: Instant) -> Option<&[u8]> {
+		let expired = match self.entries.peek(candidate_hash) {
+			Some((cached_at, _)) => now.saturating_duration_since(*cached_at) >= SEGMENT_CACHE_TTL,
+			None => return None,
+		};
+		if expired {
+			self.entries.pop(candidate_hash);
+			return None
+		}
+		self.entries.get(candidate_hash).map(|(_, encoded)| &encoded[..])
+	}
+
+	fn insert(&mut self, candidate_hash: CandidateHash, encoded: Vec<u8>, now: Instant) {
+		self.entries.put(candidate_hash, (now, encoded));
+	}
+}
+
 impl<Context> Subsystem<Context, SubsystemError> for AvailabilityRecoverySubsystem
 where
 	Context: SubsystemContext<Message = AvailabilityRecoveryMessage>,
@@ -734,6 +893,52 @@ where
 	}
 }
 
+/// Answers a `v2` request for available data, serving segments from `state.segment_cache`.
+async fn handle_available_data_request_v2<Context>(
+	state: &mut State,
+	ctx: &mut Context,
+	req: IncomingRequest<req_res::v2::AvailableDataFetchingRequest>,
+)
+where
+	Context: SubsystemContext<Message = AvailabilityRecoveryMessage>,
+	Context: overseer::SubsystemContext<Message = AvailabilityRecoveryMessage>,
+{
+	let candidate_hash = req.payload.candidate_hash;
+	let segment = req.payload.segment;
+	let now = Instant::now();
+
+	if let Some(index) = segment {
+		if let Some(encoded) = state.segment_cache.get(&candidate_hash, now) {
+			let response = req_res::v2::AvailableDataFetchingResponse::segment(encoded, index);
+			let _ = req.send_response(response);
+			return
+		}
+	}
+
+	let data = match query_full_data(ctx, candidate_hash).await {
+		Ok(data) => data,
+		Err(e) => {
+			tracing::debug!(
+				target: LOG_TARGET,
+				err = ?e,
+				"Failed to query available data.",
+			);
+
+			None
+		}
+	};
+	let response = match (data, segment) {
+		(Some(data), Some(index)) => {
+			let encoded = data.encode();
+			let response = req_res::v2::AvailableDataFetchingResponse::segment(&encoded, index);
+			state.segment_cache.insert(candidate_hash, encoded, now);
+			response
+		}
+		(data, segment) => req_res::v2::AvailableDataFetchingResponse::new(data, segment),
+	};
+	let _ = req.send_response(response);
+}
+
 /// Queries a chunk from av-store.
 async fn query_full_data<Context>(
 	ctx: &mut Context,
@@ -821,6 +1026,26 @@ impl AvailabilityRecoverySubsystem {
 										}
 									}
 								}
+								AvailabilityRecoveryMessage::CompressedAvailableDataFetchingRequest(req) => {
+									let candidate_hash = req.payload.0.candidate_hash;
+									let response: req_res::v1::AvailableDataFetchingResponse =
+										match query_full_data(&mut ctx, candidate_hash).await {
+											Ok(res) => res.into(),
+											Err(e) => {
+												tracing::debug!(
+													target: LOG_TARGET,
+													err = ?e,
+													"Failed to query available data.",
+												);
+
+												None.into()
+											}
+										};
+									let _ = req.send_response(req_res::compression::Compressed(response));
+								}
+								AvailabilityRecoveryMessage::AvailableDataFetchingRequestV2(req) => {
+									handle_available_data_request_v2(&mut state, &mut ctx, req).await;
+								}
 							}
 						}
 					}
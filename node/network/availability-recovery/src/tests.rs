@@ -22,7 +22,7 @@ use futures_timer::Delay;
 use assert_matches::assert_matches;
 use smallvec::smallvec;
 
-use parity_scale_codec::Encode;
+use parity_scale_codec::{Decode, Encode};
 
 use super::*;
 
@@ -98,6 +98,7 @@ fn test_harness_chunks_only<T: Future<Output = VirtualOverseer>>(
 }
 
 const TIMEOUT: Duration = Duration::from_millis(100);
+const LARGE_DATA_TIMEOUT: Duration = Duration::from_secs(10);
 
 macro_rules! delay {
 	($delay:expr) => {
@@ -315,8 +316,9 @@ impl TestState {
 
 					assert_matches!(
 						requests.pop().unwrap(),
-						Requests::AvailableDataFetching(req) => {
+						Requests::AvailableDataFetchingV2(req) => {
 							assert_eq!(req.payload.candidate_hash, candidate_hash);
+							assert_eq!(req.payload.segment, None);
 							let validator_index = self.validator_authority_id
 								.iter()
 								.position(|a| Recipient::Authority(a.clone()) == req.peer)
@@ -332,7 +334,7 @@ impl TestState {
 
 							let _ = req.pending_response.send(
 								available_data.map(|r|
-									req_res::v1::AvailableDataFetchingResponse::from(r).encode()
+									req_res::v2::AvailableDataFetchingResponse::new(r, None).encode()
 								)
 							);
 
@@ -343,6 +345,108 @@ impl TestState {
 			);
 		}
 	}
+
+	/// Answer `requests` requests for the full data of the candidate with the segments of `data`,
+	/// altered by `alter_segment`.
+	///
+	/// All the requests must be sent to the same backer, which is returned.
+	async fn serve_segments(
+		&self,
+		virtual_overseer: &mut VirtualOverseer,
+		data: &AvailableData,
+		requests: usize,
+		alter_segment: impl Fn(u32, &mut Vec<u8>),
+	) -> Recipient {
+		let mut backer = None;
+		for _ in 0..requests {
+			// Checking large data against the erasure root takes a while in debug builds.
+			let message = virtual_overseer
+				.recv()
+				.timeout(LARGE_DATA_TIMEOUT)
+				.await
+				.expect("LARGE_DATA_TIMEOUT is enough to recv.");
+			assert_matches!(
+				message,
+				AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(mut requests, _)) => {
+					assert_matches!(
+						requests.pop().unwrap(),
+						Requests::AvailableDataFetchingV2(req) => {
+							assert_eq!(req.payload.candidate_hash, self.candidate.hash());
+							assert_eq!(*backer.get_or_insert_with(|| req.peer.clone()), req.peer);
+
+							let segment = req.payload.segment;
+							let mut response = req_res::v2::AvailableDataFetchingResponse::new(
+								Some(data.clone()),
+								segment,
+							);
+							if let req_res::v2::AvailableDataFetchingResponse::Segment(s) = &mut response {
+								alter_segment(segment.unwrap(), s);
+							}
+							let _ = req.pending_response.send(Ok(response.encode()));
+						}
+					)
+				}
+			);
+		}
+		backer.unwrap()
+	}
+
+	/// Replace the available data by data with a PoV of `pov_size`, along with the chunks and
+	/// erasure root derived from it.
+	fn with_pov_size(mut self, pov_size: usize) -> Self {
+		self.available_data = available_data_with_pov_size(pov_size, 42);
+		let (chunks, erasure_root) = derive_erasure_chunks_with_proofs_and_root(
+			self.validators.len(),
+			&self.available_data,
+			|_, _| {},
+		);
+		self.chunks = chunks;
+		self.candidate.descriptor.erasure_root = erasure_root;
+		self
+	}
+
+	/// Start the recovery of the candidate from the backing group, up to the requests for full
+	/// data.
+	async fn recover_from_backers(
+		&self,
+		virtual_overseer: &mut VirtualOverseer,
+	) -> oneshot::Receiver<Result<AvailableData, RecoveryError>> {
+		overseer_signal(
+			virtual_overseer,
+			OverseerSignal::ActiveLeaves(ActiveLeavesUpdate {
+				activated: smallvec![ActivatedLeaf {
+					hash: self.current.clone(),
+					number: 1,
+					status: LeafStatus::Fresh,
+					span: Arc::new(jaeger::Span::Disabled),
+				}],
+				deactivated: smallvec![],
+			}),
+		).await;
+
+		let (tx, rx) = oneshot::channel();
+
+		overseer_send(
+			virtual_overseer,
+			AvailabilityRecoveryMessage::RecoverAvailableData(
+				self.candidate.clone(),
+				self.session_index,
+				Some(GroupIndex(0)),
+				tx,
+			)
+		).await;
+
+		self.test_runtime_api(virtual_overseer).await;
+		self.respond_to_available_data_query(virtual_overseer, false).await;
+		rx
+	}
+}
+
+fn available_data_with_pov_size(pov_size: usize, byte: u8) -> AvailableData {
+	AvailableData {
+		validation_data: Default::default(),
+		pov: Arc::new(PoV { block_data: BlockData(vec![byte; pov_size]) }),
+	}
 }
 
 
@@ -1101,3 +1205,231 @@ fn does_not_query_local_validator() {
 		virtual_overseer
 	});
 }
+
+#[test]
+fn large_data_is_fetched_from_backers_in_segments() {
+	let segment_size = req_res::v2::AVAILABLE_DATA_SEGMENT_SIZE;
+	let test_state = TestState::default().with_pov_size(2 * segment_size + 1000);
+
+	test_harness_fast_path(|mut virtual_overseer| async move {
+		let rx = test_state.recover_from_backers(&mut virtual_overseer).await;
+
+		// The announcement and three segments.
+		let data = test_state.available_data.clone();
+		test_state.serve_segments(&mut virtual_overseer, &data, 4, |_, _| {}).await;
+
+		assert_eq!(rx.await.unwrap().unwrap(), test_state.available_data);
+		virtual_overseer
+	});
+}
+
+#[test]
+fn truncated_segments_are_rejected() {
+	let segment_size = req_res::v2::AVAILABLE_DATA_SEGMENT_SIZE;
+	let test_state = TestState::default().with_pov_size(2 * segment_size + 1000);
+
+	test_harness_fast_path(|mut virtual_overseer| async move {
+		let rx = test_state.recover_from_backers(&mut virtual_overseer).await;
+
+		// No more segments are requested after the truncated one.
+		let data = test_state.available_data.clone();
+		let truncating = test_state.serve_segments(
+			&mut virtual_overseer,
+			&data,
+			3,
+			|index, segment| if index == 1 {
+				segment.truncate(segment_size / 2);
+			},
+		).await;
+		let honest = test_state.serve_segments(&mut virtual_overseer, &data, 4, |_, _| {}).await;
+		assert_ne!(truncating, honest);
+
+		assert_eq!(rx.await.unwrap().unwrap(), test_state.available_data);
+		virtual_overseer
+	});
+}
+
+#[test]
+fn segments_not_matching_the_erasure_root_are_rejected() {
+	let pov_size = 2 * req_res::v2::AVAILABLE_DATA_SEGMENT_SIZE + 1000;
+	let test_state = TestState::default().with_pov_size(pov_size);
+
+	test_harness_fast_path(|mut virtual_overseer| async move {
+		let rx = test_state.recover_from_backers(&mut virtual_overseer).await;
+
+		// Valid available data, but not the one of the candidate.
+		let other_data = available_data_with_pov_size(pov_size, 43);
+		let dishonest = test_state.serve_segments(&mut virtual_overseer, &other_data, 4, |_, _| {}).await;
+		let data = test_state.available_data.clone();
+		let honest = test_state.serve_segments(&mut virtual_overseer, &data, 4, |_, _| {}).await;
+		assert_ne!(dishonest, honest);
+
+		assert_eq!(rx.await.unwrap().unwrap(), test_state.available_data);
+		virtual_overseer
+	});
+}
+
+/// Refuse the next request for segmented available data as unsupported, returning the backer.
+async fn reject_segmented_request(virtual_overseer: &mut VirtualOverseer) -> Recipient {
+	assert_matches!(
+		overseer_recv(virtual_overseer).await,
+		AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(mut requests, _)) => {
+			assert_matches!(
+				requests.pop().unwrap(),
+				Requests::AvailableDataFetchingV2(req) => {
+					let _ = req.pending_response.send(Err(sc_network::RequestFailure::Network(
+						sc_network::OutboundFailure::UnsupportedProtocols,
+					)));
+					req.peer
+				}
+			)
+		}
+	)
+}
+
+#[test]
+fn backers_without_segments_are_asked_with_compressed_v1_requests() {
+	let test_state = TestState::default();
+
+	test_harness_fast_path(|mut virtual_overseer| async move {
+		let rx = test_state.recover_from_backers(&mut virtual_overseer).await;
+		let backer = reject_segmented_request(&mut virtual_overseer).await;
+
+		assert_matches!(
+			overseer_recv(&mut virtual_overseer).await,
+			AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(mut requests, _)) => {
+				assert_matches!(
+					requests.pop().unwrap(),
+					Requests::CompressedAvailableDataFetching(req) => {
+						assert_eq!(req.peer, backer);
+						assert_eq!(req.payload.0.candidate_hash, test_state.candidate.hash());
+						let response = req_res::compression::Compressed(
+							req_res::v1::AvailableDataFetchingResponse::AvailableData(
+								test_state.available_data.clone(),
+							),
+						);
+						let _ = req.pending_response.send(Ok(response.encode()));
+					}
+				)
+			}
+		);
+
+		assert_eq!(rx.await.unwrap().unwrap(), test_state.available_data);
+		virtual_overseer
+	});
+}
+
+#[test]
+fn backers_without_compression_are_asked_with_v1_requests() {
+	let test_state = TestState::default();
+
+	test_harness_fast_path(|mut virtual_overseer| async move {
+		let rx = test_state.recover_from_backers(&mut virtual_overseer).await;
+		let backer = reject_segmented_request(&mut virtual_overseer).await;
+
+		assert_matches!(
+			overseer_recv(&mut virtual_overseer).await,
+			AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(mut requests, _)) => {
+				assert_matches!(
+					requests.pop().unwrap(),
+					Requests::CompressedAvailableDataFetching(req) => {
+						assert_eq!(req.peer, backer);
+						let _ = req.pending_response.send(Err(sc_network::RequestFailure::Network(
+							sc_network::OutboundFailure::UnsupportedProtocols,
+						)));
+					}
+				)
+			}
+		);
+
+		assert_matches!(
+			overseer_recv(&mut virtual_overseer).await,
+			AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(mut requests, _)) => {
+				assert_matches!(
+					requests.pop().unwrap(),
+					Requests::AvailableDataFetching(req) => {
+						assert_eq!(req.peer, backer);
+						assert_eq!(req.payload.candidate_hash, test_state.candidate.hash());
+						let response = req_res::v1::AvailableDataFetchingResponse::AvailableData(
+							test_state.available_data.clone(),
+						);
+						let _ = req.pending_response.send(Ok(response.encode()));
+					}
+				)
+			}
+		);
+
+		assert_eq!(rx.await.unwrap().unwrap(), test_state.available_data);
+		virtual_overseer
+	});
+}
+
+/// Request `segment` of the available data of `candidate_hash` from the subsystem.
+async fn request_segment(
+	virtual_overseer: &mut VirtualOverseer,
+	candidate_hash: CandidateHash,
+	segment: u32,
+) -> oneshot::Receiver<sc_network::config::OutgoingResponse> {
+	let (tx, rx) = oneshot::channel();
+	let req = IncomingRequest::new(
+		sc_network::PeerId::random(),
+		req_res::v2::AvailableDataFetchingRequest { candidate_hash, segment: Some(segment) },
+		tx,
+	);
+	overseer_send(virtual_overseer, AvailabilityRecoveryMessage::AvailableDataFetchingRequestV2(req)).await;
+	rx
+}
+
+fn decode_segment(response: sc_network::config::OutgoingResponse) -> Vec<u8> {
+	let response = req_res::v2::AvailableDataFetchingResponse::decode(
+		&mut &response.result.expect("segments are served")[..],
+	).unwrap();
+	assert_matches!(response, req_res::v2::AvailableDataFetchingResponse::Segment(s) => s)
+}
+
+#[test]
+fn segments_are_served_from_a_single_query() {
+	let test_state = TestState::default();
+	let segment_size = req_res::v2::AVAILABLE_DATA_SEGMENT_SIZE;
+	let data = available_data_with_pov_size(3 * segment_size, 7);
+	let encoded = data.encode();
+	let candidate_hash = test_state.candidate.hash();
+
+	test_harness_fast_path(|mut virtual_overseer| async move {
+		let rx = request_segment(&mut virtual_overseer, candidate_hash, 0).await;
+		assert_matches!(
+			overseer_recv(&mut virtual_overseer).await,
+			AllMessages::AvailabilityStore(
+				AvailabilityStoreMessage::QueryAvailableData(hash, tx)
+			) => {
+				assert_eq!(hash, candidate_hash);
+				let _ = tx.send(Some(data));
+			}
+		);
+		assert_eq!(decode_segment(rx.await.unwrap()), encoded[..segment_size].to_vec());
+
+		// The other segments are served without querying the store again.
+		for index in 1..4 {
+			let rx = request_segment(&mut virtual_overseer, candidate_hash, index as u32).await;
+			let response = rx.timeout(TIMEOUT).await
+				.expect("cached segments are served right away")
+				.unwrap();
+			let end = std::cmp::min((index + 1) * segment_size, encoded.len());
+			assert_eq!(decode_segment(response), encoded[index * segment_size..end].to_vec());
+		}
+		virtual_overseer
+	});
+}
+
+#[test]
+fn cached_segments_expire() {
+	let mut cache = SegmentCache::new();
+	let candidate_hash = CandidateHash(Hash::repeat_byte(1));
+	let now = Instant::now();
+	cache.insert(candidate_hash, vec![1, 2, 3], now);
+
+	assert_eq!(cache.get(&candidate_hash, now + SEGMENT_CACHE_TTL / 2), Some(&[1u8, 2, 3][..]));
+	assert_eq!(cache.get(&candidate_hash, now + SEGMENT_CACHE_TTL), None);
+	// Expired data is dropped, rather than kept until it is evicted.
+	assert_eq!(cache.get(&candidate_hash, now), None);
+}
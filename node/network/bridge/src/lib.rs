@@ -23,6 +23,7 @@
 use parity_scale_codec::{Encode, Decode};
 use parking_lot::Mutex;
 use futures::prelude::*;
+use futures::channel::oneshot;
 use futures::stream::BoxStream;
 use polkadot_subsystem::messages::DisputeDistributionMessage;
 use sc_network::Event as NetworkEvent;
@@ -49,7 +50,7 @@ use polkadot_subsystem::{
 use polkadot_primitives::v1::{Hash, BlockNumber};
 use polkadot_node_network_protocol::{
 	PeerId, peer_set::PeerSet, View, v1 as protocol_v1, OurView, UnifiedReputationChange as Rep,
-	ObservedRole,
+	ObservedRole, request_response::{Protocol, ReqProtocolNames},
 };
 use polkadot_node_subsystem_util::metrics::{self, prometheus};
 
@@ -61,6 +62,7 @@ pub use polkadot_node_network_protocol::peer_set::{peer_sets_info, IsAuthority};
 use std::collections::HashSet;
 use std::collections::{HashMap, hash_map};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 mod validator_discovery;
 
@@ -72,7 +74,18 @@ use network::{Network, send_message};
 
 /// Request multiplexer for combining the multiple request sources into a single `Stream` of `AllMessages`.
 mod multiplexer;
-pub use multiplexer::RequestMultiplexer;
+pub use multiplexer::{
+	BufferConfig, MultiplexerSettings, ProtocolConfigInfo, RateLimit, RateLimitConfig,
+	RequestMultiplexer,
+};
+pub use polkadot_node_network_protocol::request_response::{
+	InvalidSetting, Protocol as RequestProtocol, RequestResponseSettings,
+};
+use multiplexer::{RequestMultiplexError, RequestMultiplexErrorKind};
+
+/// Lanes of outgoing requests, so bulk requests don't delay latency critical ones.
+mod outgoing;
+use outgoing::OutgoingRequests;
 
 use crate::network::get_peer_id_by_authority_id;
 
@@ -89,6 +102,25 @@ const MALFORMED_MESSAGE_COST: Rep = Rep::CostMajor("Malformed Network-bridge mes
 const UNCONNECTED_PEERSET_COST: Rep = Rep::CostMinor("Message sent to un-connected peer-set");
 const MALFORMED_VIEW_COST: Rep = Rep::CostMajor("Malformed view");
 const EMPTY_VIEW_COST: Rep = Rep::CostMajor("Peer sent us an empty view");
+const COST_INVALID_REQUEST: Rep = Rep::CostMajor("Peer sent us an invalid request");
+
+/// Peers are reported for invalid requests at most once within this time.
+const INVALID_REQUEST_REPORT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Backed up incoming requests are logged at most once within this time.
+const BACKPRESSURE_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The most incoming requests taken from the multiplexer at once, before serving other events.
+const MAX_REQUEST_BATCH: usize = 64;
+
+/// The number of critical outgoing requests sent for each bulk request, while both are queued.
+const CRITICAL_REQUESTS_PER_BULK_REQUEST: usize = 4;
+
+/// The most queued outgoing requests sent at once, before taking the next message.
+const MAX_OUTGOING_REQUEST_BATCH: usize = 16;
+
+/// The most time spent on refusing queued incoming requests on shutdown.
+const SHUTDOWN_RESPONSE_BUDGET: Duration = Duration::from_millis(500);
 
 // network bridge log target
 const LOG_TARGET: &'static str = "parachain::network-bridge";
@@ -260,6 +292,36 @@ impl metrics::Metrics for Metrics {
 	}
 }
 
+/// Limits reputation reports of a peer to one within a window, so that a burst of misbehaviour
+/// doesn't flood the reputation system.
+struct ReportLimiter {
+	window: Duration,
+	last_reports: HashMap<PeerId, Instant>,
+}
+
+impl ReportLimiter {
+	fn new(window: Duration) -> Self {
+		ReportLimiter {
+			window,
+			last_reports: HashMap::new(),
+		}
+	}
+
+	/// Whether `peer` should be reported at `now`, noting the report if so.
+	fn should_report(&mut self, peer: PeerId, now: Instant) -> bool {
+		let window = self.window;
+		// Forget reports outside of the window, so this doesn't grow with every peer ever reported.
+		self.last_reports.retain(|_, last| now.saturating_duration_since(*last) < window);
+		match self.last_reports.entry(peer) {
+			hash_map::Entry::Occupied(_) => false,
+			hash_map::Entry::Vacant(vacant) => {
+				vacant.insert(now);
+				true
+			}
+		}
+	}
+}
+
 /// Messages from and to the network.
 ///
 /// As transmitted to and received from subsystems.
@@ -372,10 +434,24 @@ enum Mode {
 	Active,
 }
 
+/// Resolves on its second poll, giving other tasks the chance to run in between.
+fn yield_now() -> impl Future<Output = ()> + Unpin {
+	let mut yielded = false;
+	future::poll_fn(move |cx| {
+		if yielded {
+			return std::task::Poll::Ready(())
+		}
+		yielded = true;
+		cx.waker().wake_by_ref();
+		std::task::Poll::Pending
+	})
+}
+
 async fn handle_subsystem_messages<Context, N, AD>(
 	mut ctx: Context,
 	mut network_service: N,
 	mut authority_discovery_service: AD,
+	req_protocol_names: ReqProtocolNames,
 	shared: Shared,
 	sync_oracle: Box<dyn SyncOracle + Send>,
 	metrics: Metrics,
@@ -390,11 +466,22 @@ where
 	let mut live_heads: Vec<ActivatedLeaf> = Vec::with_capacity(MAX_VIEW_HEADS);
 	let mut finalized_number = 0;
 	let mut validator_discovery = validator_discovery::Service::<N, AD>::new();
+	let mut outgoing_requests = OutgoingRequests::new(CRITICAL_REQUESTS_PER_BULK_REQUEST);
 
 	let mut mode = Mode::Syncing(sync_oracle);
 
 	loop {
-		futures::select! {
+		// Queued requests are only sent once no message is waiting, a batch at a time and after
+		// yielding to the senders, so that requests of later messages still get the priority of
+		// their lane.
+		let send_queued = yield_now().fuse();
+		let mut send_queued = if outgoing_requests.is_empty() {
+			future::Fuse::terminated()
+		} else {
+			send_queued
+		};
+
+		futures::select_biased! {
 			msg = ctx.recv().fuse() => match msg {
 				Ok(FromOverseer::Signal(OverseerSignal::ActiveLeaves(active_leaves))) => {
 					let ActiveLeavesUpdate { activated, deactivated } = active_leaves;
@@ -545,9 +632,7 @@ where
 						);
 
 						for req in reqs {
-							network_service
-								.start_request(&mut authority_discovery_service, req, if_disconnected)
-								.await;
+							outgoing_requests.push(req, if_disconnected);
 						}
 					}
 					NetworkBridgeMessage::ConnectToValidators {
@@ -607,6 +692,22 @@ where
 				}
 				Err(e) => return Err(e.into()),
 			},
+			() = send_queued => {
+				for _ in 0..MAX_OUTGOING_REQUEST_BATCH {
+					let (req, if_disconnected) = match outgoing_requests.pop() {
+						Some(next) => next,
+						None => break,
+					};
+					network_service
+						.start_request(
+							&mut authority_discovery_service,
+							req,
+							&req_protocol_names,
+							if_disconnected,
+						)
+						.await;
+				}
+			},
 		}
 	}
 }
@@ -617,12 +718,21 @@ async fn handle_network_messages<AD: validator_discovery::AuthorityDiscovery>(
 	network_stream: BoxStream<'static, NetworkEvent>,
 	mut authority_discovery_service: AD,
 	mut request_multiplexer: RequestMultiplexer,
+	mut shutdown: oneshot::Receiver<()>,
 	metrics: Metrics,
 	shared: Shared,
 ) -> Result<(), UnexpectedAbort> {
 	let mut network_stream = network_stream.fuse();
+	let request_metrics = request_multiplexer.metrics().clone();
+	let mut invalid_request_reports = ReportLimiter::new(INVALID_REQUEST_REPORT_WINDOW);
+	let mut last_backpressure_report = Instant::now();
 	loop {
 		futures::select! {
+			_ = shutdown => {
+				// Peers would otherwise wait for their timeout on the queued requests.
+				request_multiplexer.shutdown(SHUTDOWN_RESPONSE_BUDGET);
+				return Ok(())
+			}
 			network_event = network_stream.next() => match network_event {
 				None => return Err(UnexpectedAbort::EventStreamConcluded),
 				Some(NetworkEvent::Dht(_))
@@ -850,13 +960,59 @@ async fn handle_network_messages<AD: validator_discovery::AuthorityDiscovery>(
 					}
 				}
 			},
-			req_res_event = request_multiplexer.next() => match req_res_event {
-				None => return Err(UnexpectedAbort::RequestStreamConcluded),
-				Some(Err(err)) => {
-					network_service.report_peer(err.peer, MALFORMED_MESSAGE_COST);
+			req_res_batch = request_multiplexer.next_batch(MAX_REQUEST_BATCH) => {
+				let req_res_batch = match req_res_batch {
+					None => return Err(UnexpectedAbort::RequestStreamConcluded),
+					Some(req_res_batch) => req_res_batch,
+				};
+				for req_res_event in req_res_batch {
+					match req_res_event {
+						Err(RequestMultiplexError {
+							peer,
+							error: RequestMultiplexErrorKind::Decoding(err),
+						}) => {
+							tracing::debug!(
+								target: LOG_TARGET,
+								?peer,
+								?err,
+								"Failed to decode incoming request",
+							);
+							if invalid_request_reports.should_report(peer, Instant::now()) {
+								network_service.report_peer(peer, COST_INVALID_REQUEST);
+							}
+						}
+						Err(RequestMultiplexError {
+							peer,
+							error: RequestMultiplexErrorKind::NotMultiplexed(protocol),
+						}) => {
+							// Not the peer's fault, the receiver of the protocol should have been taken.
+							tracing::warn!(
+								target: LOG_TARGET,
+								?peer,
+								?protocol,
+								"Incoming request of a protocol which is not multiplexed",
+							);
+						}
+						Ok(msg) => {
+							let _timer = request_metrics.time_hand_off();
+							sender.send_message(msg).await;
+						}
+					}
 				}
-				Some(Ok(msg)) => {
-					sender.send_message(msg).await;
+
+				if last_backpressure_report.elapsed() >= BACKPRESSURE_REPORT_INTERVAL {
+					last_backpressure_report = Instant::now();
+					let congested = request_multiplexer.take_backpressure_report()
+						.map_or_else(Vec::new, |report| report.congested);
+					for congestion in congested {
+						tracing::warn!(
+							target: LOG_TARGET,
+							protocol = ?congestion.protocol,
+							high_water_mark = congestion.high_water_mark,
+							channel_size = congestion.channel_size,
+							"Incoming requests are backing up, the overseer is not keeping up",
+						);
+					}
 				}
 			},
 		}
@@ -895,12 +1051,19 @@ where
 	} = bridge;
 
 	let statement_receiver = request_multiplexer
-		.get_statement_fetching()
-		.expect("Gets initialized, must be `Some` on startup. qed.");
+		.take_versioned_receiver(&[Protocol::StatementFetching, Protocol::StatementFetchingV2])
+		.expect("Not taken before, must be `Some` on startup. qed.");
 
 	let dispute_receiver = request_multiplexer
-		.get_dispute_sending()
-		.expect("Gets initialized, must be `Some` on startup. qed.");
+		.take_receiver(Protocol::DisputeSending)
+		.expect("Not taken before, must be `Some` on startup. qed.");
+
+	let dispute_batch_receiver = request_multiplexer
+		.take_receiver(Protocol::DisputeBatchSending)
+		.expect("Not taken before, must be `Some` on startup. qed.");
+
+	let req_protocol_names = request_multiplexer.protocol_names().clone();
+	let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
 	let (remote, network_event_handler) = handle_network_messages::<>(
 		ctx.sender().clone(),
@@ -908,6 +1071,7 @@ where
 		network_stream,
 		authority_discovery_service.clone(),
 		request_multiplexer,
+		shutdown_rx,
 		metrics.clone(),
 		shared.clone(),
 	).remote_handle();
@@ -915,7 +1079,10 @@ where
 	ctx.spawn("network-bridge-network-worker", Box::pin(remote))?;
 
 	ctx.send_message(
-		DisputeDistributionMessage::DisputeSendingReceiver(dispute_receiver)
+		DisputeDistributionMessage::DisputeSendingReceiver {
+			single: dispute_receiver,
+			batched: dispute_batch_receiver,
+		}
 	).await;
 	ctx.send_message(
 		StatementDistributionMessage::StatementFetchingReceiver(statement_receiver)
@@ -925,6 +1092,7 @@ where
 		ctx,
 		network_service,
 		authority_discovery_service,
+		req_protocol_names,
 		shared,
 		sync_oracle,
 		metrics,
@@ -932,11 +1100,17 @@ where
 
 	futures::pin_mut!(subsystem_event_handler);
 
-	match futures::future::select(subsystem_event_handler, network_event_handler)
-		.await
-		.factor_first()
-		.0
-	{
+	let result = match futures::future::select(subsystem_event_handler, network_event_handler).await {
+		futures::future::Either::Left((Ok(()), network_event_handler)) => {
+			// We were asked to conclude, let the network worker answer the queued requests first.
+			let _ = shutdown_tx.send(());
+			network_event_handler.await
+		}
+		futures::future::Either::Left((result, _))
+		| futures::future::Either::Right((result, _)) => result,
+	};
+
+	match result {
 		Ok(()) => Ok(()),
 		Err(UnexpectedAbort::SubsystemError(err)) => {
 			tracing::warn!(
@@ -14,37 +14,324 @@
 // You should have received a copy of the GNU General Public License
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::pin::Pin;
-use std::unreachable;
+use std::time::{Duration, Instant};
 
 use futures::channel::mpsc;
-use futures::stream::{FusedStream, Stream};
+use futures::future::{FusedFuture, Future};
+use futures::stream::{FusedStream, Stream, StreamExt};
 use futures::task::{Context, Poll};
+use lru::LruCache;
 use strum::IntoEnumIterator;
 
-use parity_scale_codec::{Decode, Error as DecodingError};
+use parity_scale_codec::{Decode, Encode, Error as DecodingError};
 
 use sc_network::config as network;
 use sc_network::PeerId;
 
-use polkadot_node_network_protocol::request_response::{
-	request::IncomingRequest, v1, Protocol, RequestResponseConfig,
+use polkadot_node_network_protocol::{
+	request_response::{
+		request::{IncomingRequest, IsRequest, ReceiveError, VersionedReceiver},
+		v1, v2, Protocol, ReqProtocolNames, RequestResponseConfig, RequestResponseSettings,
+	},
+	UnifiedReputationChange as Rep,
 };
+use polkadot_node_subsystem_util::metrics::{self, prometheus};
 use polkadot_overseer::AllMessages;
+use polkadot_primitives::v1::Hash;
+
+use crate::LOG_TARGET;
 
 /// Multiplex incoming network requests.
 ///
 /// This multiplexer consumes all request streams and makes them a `Stream` of a single message
 /// type, useful for the network bridge to send them via the `Overseer` to other subsystems.
 ///
-/// The resulting stream will end once any of its input ends.
+/// A request stream which ends is dropped from the multiplexer, the resulting stream will only end
+/// once all of its inputs ended.
+///
+/// Subsystems handling the raw requests of a protocol themselves take its receiver out of the
+/// multiplexer with `take_receiver`, before the multiplexer is polled.
 ///
-/// TODO: Get rid of this: https://github.com/paritytech/polkadot/issues/2842
+/// The multiplexer takes a single request of a protocol out of its channel per poll, so the
+/// channel size bounds the requests buffered for a protocol. The channel doesn't tell how many
+/// requests it holds, so the backlog of a protocol is counted as the requests taken in a row before
+/// its channel was found empty. Protocols with a large backlog are reported by
+/// `take_backpressure_report`.
 pub struct RequestMultiplexer {
-	receivers: Vec<(Protocol, mpsc::Receiver<network::IncomingRequest>)>,
-	statement_fetching: Option<mpsc::Receiver<network::IncomingRequest>>,
-	dispute_sending: Option<mpsc::Receiver<network::IncomingRequest>>,
+	receivers: Vec<ProtocolReceiver>,
 	next_poll: usize,
+	backpressure_threshold_percent: usize,
+	rate_limiter: RateLimiter,
+	metrics: Metrics,
+	protocol_names: ReqProtocolNames,
+	configs: Vec<ProtocolConfigInfo>,
+}
+
+/// Everything a `RequestMultiplexer` is created with.
+#[derive(Clone, Debug, Default)]
+pub struct MultiplexerSettings {
+	/// The genesis hash of the chain, the protocols are named after.
+	pub genesis_hash: Hash,
+	/// Overrides of the protocol defaults and their names.
+	pub request_response: RequestResponseSettings,
+	/// How queued requests are reported.
+	pub buffer: BufferConfig,
+	/// How requests of peers are limited.
+	pub rate_limit: RateLimitConfig,
+}
+
+/// The limits in effect for one of the `RequestResponseConfig`s passed to the network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolConfigInfo {
+	/// The protocol configured.
+	pub protocol: Protocol,
+	/// The name the protocol is registered under. Protocols registered under their legacy name as
+	/// well have two configs.
+	pub name: Cow<'static, str>,
+	/// Maximum allowed size of a request.
+	pub max_request_size: u64,
+	/// Maximum allowed size of a response.
+	pub max_response_size: u64,
+	/// Timeout of outgoing requests.
+	pub request_timeout: Duration,
+	/// The number of incoming requests which are queued, before further ones are dropped.
+	pub inbound_queue_len: usize,
+}
+
+impl ProtocolConfigInfo {
+	fn new(protocol: Protocol, cfg: &RequestResponseConfig, inbound_queue_len: usize) -> Self {
+		ProtocolConfigInfo {
+			protocol,
+			name: cfg.name.clone(),
+			max_request_size: cfg.max_request_size,
+			max_response_size: cfg.max_response_size,
+			request_timeout: cfg.request_timeout,
+			inbound_queue_len,
+		}
+	}
+}
+
+/// The first name used by more than one of `configs`, if any.
+fn duplicate_name(configs: &[ProtocolConfigInfo]) -> Option<&str> {
+	let mut names = HashSet::new();
+	configs.iter().map(|cfg| cfg.name.as_ref()).find(|name| !names.insert(*name))
+}
+
+/// Buffering of the incoming requests of the multiplexed protocols.
+///
+/// The sizes of the channels of incoming requests are part of the `RequestResponseSettings`.
+#[derive(Clone, Debug)]
+pub struct BufferConfig {
+	/// The percentage of its channel size, which the backlog of a protocol must reach for it to be
+	/// reported as congested.
+	pub backpressure_threshold_percent: usize,
+}
+
+impl Default for BufferConfig {
+	fn default() -> Self {
+		BufferConfig {
+			backpressure_threshold_percent: 80,
+		}
+	}
+}
+
+/// Refused requests of peers exceeding their rate limit cost this, if peers are reported.
+const COST_RATE_LIMITED: Rep = Rep::CostMinor("Peer exceeded its rate limit of requests");
+
+/// The most requests refused for exceeding a rate limit by a single `poll_next`.
+///
+/// A flood is refused across several polls, so that the task polling the multiplexer still
+/// yields to the others in between.
+const MAX_REFUSALS_PER_POLL: usize = 64;
+
+/// Per-peer rate limiting of the incoming requests of the multiplexed protocols.
+///
+/// Requests of a peer exceeding the rate limit of their protocol are refused, without being
+/// decoded or handed on.
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+	/// The rate limits per protocol. Protocols without one are not limited.
+	pub limits: HashMap<Protocol, RateLimit>,
+	/// The number of peers and protocols for which the remaining requests are tracked, the least
+	/// recently active ones are forgotten beyond that.
+	pub max_tracked: usize,
+	/// Whether refusals also cost the peer reputation.
+	pub report_peers: bool,
+}
+
+impl Default for RateLimitConfig {
+	fn default() -> Self {
+		// A peer needs about one chunk per candidate we backed per block and collations are
+		// only fetched by the validators of a group, so these are far above what honest peers
+		// send.
+		let limits = vec![
+			(
+				Protocol::ChunkFetching,
+				RateLimit { burst: 100, refill_interval: Duration::from_millis(100) },
+			),
+			(
+				Protocol::CollationFetching,
+				RateLimit { burst: 10, refill_interval: Duration::from_millis(500) },
+			),
+			(
+				Protocol::CollationFetchingV2,
+				RateLimit { burst: 10, refill_interval: Duration::from_millis(500) },
+			),
+		];
+		RateLimitConfig {
+			limits: limits.into_iter().collect(),
+			max_tracked: 1000,
+			report_peers: true,
+		}
+	}
+}
+
+/// A token bucket rate limit of the requests of a peer on a protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+	/// The number of requests a peer may send at once.
+	pub burst: u32,
+	/// A peer may send another request after each interval, up to `burst`.
+	pub refill_interval: Duration,
+}
+
+/// The requests a peer may still send on a protocol.
+struct TokenBucket {
+	tokens: u32,
+	last_refill: Instant,
+}
+
+impl TokenBucket {
+	fn full(limit: &RateLimit, now: Instant) -> Self {
+		TokenBucket { tokens: limit.burst, last_refill: now }
+	}
+
+	/// Take a token for a request at `now`, if any is left.
+	fn try_take(&mut self, limit: &RateLimit, now: Instant) -> bool {
+		let interval = std::cmp::max(limit.refill_interval, Duration::from_nanos(1));
+		let refills = now.saturating_duration_since(self.last_refill).as_nanos() / interval.as_nanos();
+		let missing = limit.burst.saturating_sub(self.tokens);
+		if refills >= missing as u128 {
+			self.tokens = limit.burst;
+			self.last_refill = now;
+		} else {
+			// Less than `missing`, so this fits.
+			let refills = refills as u32;
+			self.tokens += refills;
+			self.last_refill += interval * refills;
+		}
+
+		if self.tokens == 0 {
+			return false
+		}
+		self.tokens -= 1;
+		true
+	}
+}
+
+/// Tracks the remaining requests of peers on rate limited protocols.
+struct RateLimiter {
+	limits: HashMap<Protocol, RateLimit>,
+	buckets: LruCache<(PeerId, Protocol), TokenBucket>,
+	report_peers: bool,
+}
+
+impl RateLimiter {
+	fn new(config: RateLimitConfig) -> Self {
+		RateLimiter {
+			limits: config.limits,
+			buckets: LruCache::new(config.max_tracked),
+			report_peers: config.report_peers,
+		}
+	}
+
+	/// Whether `peer` may send a request on `protocol` at `now`, taking it into account if so.
+	fn check(&mut self, peer: PeerId, protocol: Protocol, now: Instant) -> bool {
+		let limit = match self.limits.get(&protocol) {
+			Some(limit) => limit,
+			None => return true,
+		};
+		if let Some(bucket) = self.buckets.get_mut(&(peer, protocol)) {
+			return bucket.try_take(limit, now)
+		}
+		let mut bucket = TokenBucket::full(limit, now);
+		let allowed = bucket.try_take(limit, now);
+		self.buckets.put((peer, protocol), bucket);
+		allowed
+	}
+
+	/// Refuse a request of a peer over its limit on `protocol`, so it may retry later.
+	fn refuse(&self, protocol: Protocol, request: network::IncomingRequest) {
+		let reputation_changes = if self.report_peers {
+			vec![COST_RATE_LIMITED.into_base_rep()]
+		} else {
+			Vec::new()
+		};
+		// The peer may have given up on the request already, which doesn't matter here.
+		let _ = request.pending_response.send(network::OutgoingResponse {
+			result: Ok(protocol.encode_refusal(v1::RefusalReason::Busy)),
+			reputation_changes,
+			sent_feedback: None,
+		});
+	}
+}
+
+/// Protocols with a large backlog of incoming requests since the last report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackpressureReport {
+	/// The congested protocols, in no particular order.
+	pub congested: Vec<ProtocolBackpressure>,
+}
+
+/// The backpressure on the incoming requests of a protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolBackpressure {
+	/// The congested protocol.
+	pub protocol: Protocol,
+	/// The most requests taken in a row before the channel was found empty, since the last report.
+	pub high_water_mark: usize,
+	/// The size of the channel of the protocol.
+	pub channel_size: usize,
+}
+
+/// The incoming requests of a single protocol.
+struct ProtocolReceiver {
+	protocol: Protocol,
+	rx: mpsc::Receiver<network::IncomingRequest>,
+	channel_size: usize,
+	/// The requests taken out of `rx` since it was last found empty.
+	backlog: usize,
+	/// The largest `backlog` since the last backpressure report.
+	high_water_mark: usize,
+}
+
+impl ProtocolReceiver {
+	fn new(protocol: Protocol, rx: mpsc::Receiver<network::IncomingRequest>, channel_size: usize) -> Self {
+		ProtocolReceiver {
+			protocol,
+			rx,
+			channel_size,
+			backlog: 0,
+			high_water_mark: 0,
+		}
+	}
+
+	fn poll_request(&mut self, cx: &mut Context<'_>) -> Poll<Option<network::IncomingRequest>> {
+		match Pin::new(&mut self.rx).poll_next(cx) {
+			Poll::Ready(Some(request)) => {
+				self.backlog += 1;
+				self.high_water_mark = std::cmp::max(self.high_water_mark, self.backlog);
+				Poll::Ready(Some(request))
+			}
+			other => {
+				self.backlog = 0;
+				other
+			}
+		}
+	}
 }
 
 /// Multiplexing can fail in case of invalid messages.
@@ -53,173 +340,641 @@ pub struct RequestMultiplexError {
 	/// The peer that sent the invalid message.
 	pub peer: PeerId,
 	/// The error that occurred.
-	pub error: DecodingError,
+	pub error: RequestMultiplexErrorKind,
+}
+
+/// The reason an incoming request could not be multiplexed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RequestMultiplexErrorKind {
+	/// The request failed to decode.
+	Decoding(DecodingError),
+	/// The receiver of the protocol was taken, so its requests are not multiplexed.
+	NotMultiplexed(Protocol),
 }
 
 impl RequestMultiplexer {
 	/// Create a new `RequestMultiplexer`.
 	///
-	/// This function uses `Protocol::get_config` for each available protocol and creates a
+	/// This function uses `Protocol::get_config_with` for each available protocol and creates a
 	/// `RequestMultiplexer` from it. The returned `RequestResponseConfig`s must be passed to the
 	/// network implementation.
-	pub fn new() -> (Self, Vec<RequestResponseConfig>) {
-		let (mut receivers, cfgs): (Vec<_>, Vec<_>) = Protocol::iter()
-			.map(|p| {
-				let (rx, cfg) = p.get_config();
-				((p, rx), cfg)
-			})
-			.unzip();
+	///
+	/// The protocols are named after `genesis_hash`, and registered under their legacy names as
+	/// well if `settings` ask for it. The defaults of the protocols are overridden by `settings`, queued requests are reported
+	/// according to `buffer_config`, and requests of peers are limited according to
+	/// `rate_limit_config`. Metrics of incoming requests are registered with `registry`, if any.
+	pub fn new(
+		registry: Option<&prometheus::Registry>,
+		genesis_hash: Hash,
+		settings: RequestResponseSettings,
+		buffer_config: BufferConfig,
+		rate_limit_config: RateLimitConfig,
+	) -> Result<(Self, Vec<RequestResponseConfig>), prometheus::PrometheusError> {
+		Self::new_with(registry, MultiplexerSettings {
+			genesis_hash,
+			request_response: settings,
+			buffer: buffer_config,
+			rate_limit: rate_limit_config,
+		})
+	}
 
-		// Ok this code is ugly as hell, it is also a hack, see https://github.com/paritytech/polkadot/issues/2842.
-		// But it works and is executed on startup so, if anything is wrong here it will be noticed immediately.
-		let index = receivers.iter().enumerate().find_map(|(i, (p, _))|
-			if let Protocol::StatementFetching = p {
-				Some(i)
-			} else {
-				None
-			}
-		).expect("Statement fetching must be registered. qed.");
-		let statement_fetching = Some(receivers.remove(index).1);
-
-		let index = receivers.iter().enumerate().find_map(|(i, (p, _))|
-			if let Protocol::DisputeSending = p {
-				Some(i)
-			} else {
-				None
+	/// Create a new `RequestMultiplexer` from `settings`, see `new`.
+	///
+	/// The limits in effect for each of the returned `RequestResponseConfig`s are logged, and kept
+	/// for inspection with `configs`.
+	pub fn new_with(
+		registry: Option<&prometheus::Registry>,
+		settings: MultiplexerSettings,
+	) -> Result<(Self, Vec<RequestResponseConfig>), prometheus::PrometheusError> {
+		let MultiplexerSettings {
+			genesis_hash,
+			request_response: settings,
+			buffer: buffer_config,
+			rate_limit: rate_limit_config,
+		} = settings;
+		let metrics = <Metrics as metrics::Metrics>::register(registry)?;
+		for (protocol, overrides) in settings.overrides() {
+			tracing::info!(
+				target: LOG_TARGET,
+				?protocol,
+				request_timeout = ?overrides.request_timeout,
+				channel_size = ?overrides.channel_size,
+				"Overriding request-response defaults",
+			);
+		}
+		let protocol_names = settings.protocol_names(genesis_hash);
+		let mut receivers = Vec::new();
+		let mut cfgs = Vec::new();
+		let mut configs = Vec::new();
+		for p in Protocol::iter() {
+			let overrides = settings.get(p);
+			let channel_size = overrides.channel_size.unwrap_or_else(|| p.get_channel_size());
+			let (rx, cfg) = p.get_config_with(&protocol_names, overrides);
+			receivers.push(ProtocolReceiver::new(p, rx, channel_size));
+			if protocol_names.legacy_names() {
+				// Requests of nodes not yet knowing the new names end up in the same channel.
+				let mut legacy_cfg = cfg.clone();
+				legacy_cfg.name = p.get_protocol_name_static().into();
+				configs.push(ProtocolConfigInfo::new(p, &legacy_cfg, channel_size));
+				cfgs.push(legacy_cfg);
 			}
-		).expect("Dispute sending must be registered. qed.");
-		let dispute_sending = Some(receivers.remove(index).1);
+			configs.push(ProtocolConfigInfo::new(p, &cfg, channel_size));
+			cfgs.push(cfg);
+		}
+		for cfg in &configs {
+			tracing::debug!(
+				target: LOG_TARGET,
+				protocol = ?cfg.protocol,
+				name = %cfg.name,
+				max_request_size = cfg.max_request_size,
+				max_response_size = cfg.max_response_size,
+				request_timeout = ?cfg.request_timeout,
+				inbound_queue_len = cfg.inbound_queue_len,
+				"Configured request-response protocol",
+			);
+		}
+		debug_assert_eq!(duplicate_name(&configs), None, "Protocols must not share a name.");
 
-		(
+		Ok((
 			Self {
 				receivers,
-				statement_fetching,
-                dispute_sending,
 				next_poll: 0,
+				backpressure_threshold_percent: buffer_config.backpressure_threshold_percent,
+				rate_limiter: RateLimiter::new(rate_limit_config),
+				metrics,
+				protocol_names,
+				configs,
 			},
 			cfgs,
-		)
+		))
 	}
 
-	/// Get the receiver for handling statement fetching requests.
+	/// The limits in effect for each of the `RequestResponseConfig`s passed to the network, in the
+	/// same order.
+	pub fn configs(&self) -> &[ProtocolConfigInfo] {
+		&self.configs
+	}
+
+	/// The names of the multiplexed protocols, for sending requests under the same names.
+	pub fn protocol_names(&self) -> &ReqProtocolNames {
+		&self.protocol_names
+	}
+
+	/// The metrics of incoming requests, for timing their hand-off to the overseer.
+	pub fn metrics(&self) -> &Metrics {
+		&self.metrics
+	}
+
+	/// Take the receiver of `protocol` out of the multiplexer, for handling its raw requests
+	/// directly.
 	///
-	/// This function will only return `Some` once.
-	pub fn get_statement_fetching(&mut self) -> Option<mpsc::Receiver<network::IncomingRequest>> {
-		std::mem::take(&mut self.statement_fetching)
+	/// Requests of `protocol` are no longer multiplexed afterwards. This function will only return
+	/// `Some` once per protocol, and `None` for a protocol whose stream already ended.
+	pub fn take_receiver(
+		&mut self,
+		protocol: Protocol,
+	) -> Option<mpsc::Receiver<network::IncomingRequest>> {
+		let index = self.receivers.iter().position(|r| r.protocol == protocol)?;
+		if index < self.next_poll {
+			self.next_poll -= 1;
+		}
+		let receiver = self.receivers.remove(index);
+		if self.next_poll >= self.receivers.len() {
+			self.next_poll = 0;
+		}
+		Some(receiver.rx)
 	}
 
-	/// Get the receiver for handling dispute sending requests.
+	/// Take the receivers of all versions of a protocol out of the multiplexer, for handling their
+	/// raw requests in one place.
 	///
-	/// This function will only return `Some` once.
-	pub fn get_dispute_sending(&mut self) -> Option<mpsc::Receiver<network::IncomingRequest>> {
-		std::mem::take(&mut self.dispute_sending)
+	/// The `protocols` are expected to be distinct. Returns `None` without taking any receiver if
+	/// one of them was taken already.
+	pub fn take_versioned_receiver(&mut self, protocols: &[Protocol]) -> Option<VersionedReceiver> {
+		if !protocols.iter().all(|p| self.receivers.iter().any(|r| r.protocol == *p)) {
+			return None
+		}
+		let receivers = protocols.iter()
+			.map(|p| (*p, self.take_receiver(*p).expect("Checked above, so not taken before. qed.")))
+			.collect();
+		Some(VersionedReceiver::new(receivers))
+	}
+
+	/// Refuse all requests which are still queued, as the multiplexer is shut down.
+	///
+	/// No further requests are accepted afterwards and the stream ends. Requests are refused as
+	/// busy, so peers may retry them with another node right away instead of waiting for their
+	/// timeout. Once `budget` is used up, the remaining requests are dropped without a response.
+	///
+	/// Returns the number of refused requests.
+	pub fn shutdown(&mut self, budget: Duration) -> usize {
+		let deadline = Instant::now() + budget;
+		let mut refused = 0;
+		let mut dropped = 0;
+		for mut receiver in std::mem::take(&mut self.receivers) {
+			receiver.rx.close();
+			let mut pending = Vec::new();
+			while let Ok(Some(request)) = receiver.rx.try_next() {
+				pending.push(request);
+			}
+			let refusal = receiver.protocol.encode_refusal(v1::RefusalReason::Busy);
+			for request in pending {
+				if Instant::now() >= deadline {
+					dropped += 1;
+					continue
+				}
+				// The peer may have given up on the request already, which doesn't matter here.
+				let _ = request.pending_response.send(network::OutgoingResponse {
+					result: Ok(refusal.clone()),
+					reputation_changes: Vec::new(),
+					sent_feedback: None,
+				});
+				refused += 1;
+			}
+		}
+		self.next_poll = 0;
+
+		if dropped > 0 {
+			tracing::warn!(
+				target: LOG_TARGET,
+				refused,
+				dropped,
+				"Shutdown budget used up, dropping queued requests without a response",
+			);
+		} else {
+			tracing::debug!(target: LOG_TARGET, refused, "Refused queued requests on shutdown");
+		}
+		refused
+	}
+
+	/// Report the protocols whose backlog reached the threshold since the last report, if any.
+	pub fn take_backpressure_report(&mut self) -> Option<BackpressureReport> {
+		let threshold_percent = self.backpressure_threshold_percent;
+		let congested: Vec<_> = self.receivers.iter_mut()
+			.filter_map(|r| {
+				let high_water_mark = std::mem::take(&mut r.high_water_mark);
+				if high_water_mark > 0 && high_water_mark * 100 >= r.channel_size * threshold_percent {
+					Some(ProtocolBackpressure {
+						protocol: r.protocol,
+						high_water_mark,
+						channel_size: r.channel_size,
+					})
+				} else {
+					None
+				}
+			})
+			.collect();
+
+		if congested.is_empty() {
+			None
+		} else {
+			Some(BackpressureReport { congested })
+		}
 	}
 }
 
 impl Stream for RequestMultiplexer {
-	type Item = Result<AllMessages, RequestMultiplexError>;
+	type Item = MultiplexResult;
 
 	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-		let len = self.receivers.len();
-		let mut count = len;
-		let mut i = self.next_poll;
-		let mut result = Poll::Ready(None);
-		// Poll streams in round robin fashion:
-		while count > 0 {
-			// % safe, because count initialized to len, loop would not be entered if 0, also
-			// length of receivers is fixed.
-			let (p, rx): &mut (_, _) = &mut self.receivers[i % len];
-			// Avoid panic:
-			if rx.is_terminated() {
-				// Early return, we don't want to update next_poll.
-				return Poll::Ready(None);
-			}
-			i += 1;
-			count -= 1;
-			match Pin::new(rx).poll_next(cx) {
-				Poll::Pending => result = Poll::Pending,
-				// We are done, once a single receiver is done.
-				Poll::Ready(None) => return Poll::Ready(None),
+		// Poll streams in round robin fashion, starting after the one which yielded last:
+		let mut polled = 0;
+		let mut refused = 0;
+		while polled < self.receivers.len() {
+			let len = self.receivers.len();
+			let index = (self.next_poll + polled) % len;
+			let receiver = &mut self.receivers[index];
+			let p = receiver.protocol;
+			match receiver.poll_request(cx) {
+				Poll::Pending => polled += 1,
+				Poll::Ready(None) => {
+					tracing::warn!(
+						target: LOG_TARGET,
+						protocol = ?p,
+						"Incoming request stream concluded, no more requests will be received for this protocol",
+					);
+					// The receivers after `index` move down by one, so the next one to poll is at
+					// `index` again.
+					self.receivers.remove(index);
+					if index < self.next_poll {
+						self.next_poll -= 1;
+					}
+				}
 				Poll::Ready(Some(v)) => {
-					result = Poll::Ready(Some(multiplex_single(*p, v)));
-					break;
+					// A busy receiver must not starve the others, so it is polled last next time.
+					self.next_poll = (index + 1) % len;
+					self.metrics.on_incoming_request(p);
+					if !self.rate_limiter.check(v.peer, p, Instant::now()) {
+						self.metrics.on_rate_limited(p);
+						self.rate_limiter.refuse(p, v);
+						refused += 1;
+						if refused >= MAX_REFUSALS_PER_POLL {
+							// More requests may be ready, so we want to be polled again soon.
+							cx.waker().wake_by_ref();
+							return Poll::Pending
+						}
+						// Carry on after the refused request, as if it was yielded.
+						polled = 0;
+						continue
+					}
+					return Poll::Ready(Some(multiplex_single(p, v, &self.metrics)));
 				}
 			}
 		}
-		self.next_poll = i;
-		result
+		if self.receivers.is_empty() {
+			// We are done, once all receivers are done.
+			Poll::Ready(None)
+		} else {
+			Poll::Pending
+		}
 	}
 }
 
 impl FusedStream for RequestMultiplexer {
 	fn is_terminated(&self) -> bool {
-		let len = self.receivers.len();
-		if len == 0 {
-			return true;
+		self.receivers.is_empty()
+	}
+}
+
+/// The result of multiplexing a single incoming request.
+pub type MultiplexResult = Result<AllMessages, RequestMultiplexError>;
+
+impl RequestMultiplexer {
+	/// Poll for up to `max` requests at once.
+	///
+	/// Requests are taken from the receivers in the same round robin order as by `poll_next`, but
+	/// all which are ready are yielded by a single poll. Yields `None` once all receivers ended.
+	pub fn poll_next_batch(
+		&mut self,
+		cx: &mut Context<'_>,
+		max: usize,
+	) -> Poll<Option<Vec<MultiplexResult>>> {
+		let mut batch = Vec::new();
+		while batch.len() < std::cmp::max(max, 1) {
+			match self.poll_next_unpin(cx) {
+				Poll::Ready(Some(result)) => batch.push(result),
+				Poll::Ready(None) if batch.is_empty() => return Poll::Ready(None),
+				Poll::Pending if batch.is_empty() => return Poll::Pending,
+				// Yield what we have, the end or the next request is seen by the next poll.
+				Poll::Ready(None) | Poll::Pending => break,
+			}
 		}
-		let (_, rx) = &self.receivers[self.next_poll % len];
-		rx.is_terminated()
+		Poll::Ready(Some(batch))
+	}
+
+	/// The next batch of up to `max` requests, see `poll_next_batch`.
+	pub fn next_batch(&mut self, max: usize) -> NextBatch<'_> {
+		NextBatch { multiplexer: self, max }
+	}
+}
+
+/// Future for `RequestMultiplexer::next_batch`.
+pub struct NextBatch<'a> {
+	multiplexer: &'a mut RequestMultiplexer,
+	max: usize,
+}
+
+impl Future for NextBatch<'_> {
+	type Output = Option<Vec<MultiplexResult>>;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let max = self.max;
+		self.multiplexer.poll_next_batch(cx, max)
+	}
+}
+
+impl FusedFuture for NextBatch<'_> {
+	fn is_terminated(&self) -> bool {
+		self.multiplexer.is_terminated()
 	}
 }
 
 /// Convert a single raw incoming request into a `MultiplexMessage`.
 fn multiplex_single(
 	p: Protocol,
-	network::IncomingRequest {
-		payload,
-		peer,
-		pending_response,
-	}: network::IncomingRequest,
+	request: network::IncomingRequest,
+	metrics: &Metrics,
+) -> Result<AllMessages, RequestMultiplexError> {
+	let result = decode_single(p, request);
+	if let Err(RequestMultiplexError { error: RequestMultiplexErrorKind::Decoding(_), .. }) = result {
+		metrics.on_decode_failure(p);
+	}
+	result
+}
+
+fn decode_single(
+	p: Protocol,
+	request: network::IncomingRequest,
 ) -> Result<AllMessages, RequestMultiplexError> {
 	let r = match p {
-		Protocol::ChunkFetching => AllMessages::from(IncomingRequest::new(
-			peer,
-			decode_with_peer::<v1::ChunkFetchingRequest>(peer, payload)?,
-			pending_response,
-		)),
-		Protocol::CollationFetching => AllMessages::from(IncomingRequest::new(
-			peer,
-			decode_with_peer::<v1::CollationFetchingRequest>(peer, payload)?,
-			pending_response,
-		)),
-		Protocol::PoVFetching => AllMessages::from(IncomingRequest::new(
-			peer,
-			decode_with_peer::<v1::PoVFetchingRequest>(peer, payload)?,
-			pending_response,
-		)),
-		Protocol::AvailableDataFetching => AllMessages::from(IncomingRequest::new(
-			peer,
-			decode_with_peer::<v1::AvailableDataFetchingRequest>(peer, payload)?,
-			pending_response,
-		)),
-		Protocol::StatementFetching => {
-			unreachable!("Statement fetching requests are handled directly. qed.");
-		}
-		Protocol::DisputeSending => {
-			unreachable!("Dispute sending request are handled directly. qed.");
+		Protocol::ChunkFetching => AllMessages::from(
+			decode_with_peer::<v1::ChunkFetchingRequest>(request)?,
+		),
+		Protocol::CollationFetching => AllMessages::from(
+			decode_with_peer::<v1::CollationFetchingRequest>(request)?,
+		),
+		Protocol::PoVFetching => AllMessages::from(
+			decode_with_peer::<v1::PoVFetchingRequest>(request)?,
+		),
+		Protocol::AvailableDataFetching => AllMessages::from(
+			decode_with_peer::<v1::AvailableDataFetchingRequest>(request)?,
+		),
+		Protocol::MultiChunkFetching => AllMessages::from(
+			decode_with_peer::<v1::MultiChunkFetchingRequest>(request)?,
+		),
+		Protocol::CompressedPoVFetching => AllMessages::from(
+			decode_with_peer::<v1::CompressedPoVFetchingRequest>(request)?,
+		),
+		Protocol::CompressedAvailableDataFetching => AllMessages::from(
+			decode_with_peer::<v1::CompressedAvailableDataFetchingRequest>(request)?,
+		),
+		Protocol::CollationFetchingV2 => AllMessages::from(
+			decode_with_peer::<v2::CollationFetchingRequest>(request)?,
+		),
+		Protocol::AvailableDataFetchingV2 => AllMessages::from(
+			decode_with_peer::<v2::AvailableDataFetchingRequest>(request)?,
+		),
+		// There are no messages for these, their receivers are taken by the network bridge for
+		// statement distribution and dispute distribution.
+		Protocol::StatementFetching |
+		Protocol::StatementFetchingV2 |
+		Protocol::DisputeSending |
+		Protocol::DisputeBatchSending => {
+			return Err(RequestMultiplexError {
+				peer: request.peer,
+				error: RequestMultiplexErrorKind::NotMultiplexed(p),
+			})
 		}
 	};
 	Ok(r)
 }
 
-fn decode_with_peer<Req: Decode>(
-	peer: PeerId,
-	payload: Vec<u8>,
-) -> Result<Req, RequestMultiplexError> {
-	Req::decode(&mut payload.as_ref()).map_err(|error| RequestMultiplexError { peer, error })
+/// Decode `request`, refusing it as invalid if that fails.
+fn decode_with_peer<Req>(
+	request: network::IncomingRequest,
+) -> Result<IncomingRequest<Req>, RequestMultiplexError>
+where
+	Req: IsRequest + Decode,
+	Req::Response: Encode,
+{
+	// The network bridge reports the peer, rate limiting the reports.
+	IncomingRequest::try_from_raw(request, Vec::new()).map_err(|err| match err {
+		ReceiveError::DecodingError(peer, error)
+		| ReceiveError::DecodingErrorNoReputationChange(peer, error) => RequestMultiplexError {
+			peer,
+			error: RequestMultiplexErrorKind::Decoding(error),
+		},
+	})
+}
+
+/// Metrics of the incoming requests of the multiplexed protocols.
+#[derive(Clone, Default)]
+pub struct Metrics(Option<MetricsInner>);
+
+impl Metrics {
+	fn on_incoming_request(&self, protocol: Protocol) {
+		if let Some(metrics) = &self.0 {
+			metrics.incoming_requests
+				.with_label_values(&[protocol.get_protocol_name_static()])
+				.inc();
+		}
+	}
+
+	fn on_decode_failure(&self, protocol: Protocol) {
+		if let Some(metrics) = &self.0 {
+			metrics.decode_failures
+				.with_label_values(&[protocol.get_protocol_name_static()])
+				.inc();
+		}
+	}
+
+	fn on_rate_limited(&self, protocol: Protocol) {
+		if let Some(metrics) = &self.0 {
+			metrics.rate_limited
+				.with_label_values(&[protocol.get_protocol_name_static()])
+				.inc();
+		}
+	}
+
+	/// Provide a timer for handing an incoming request off to the overseer which observes on drop.
+	pub fn time_hand_off(&self) -> Option<metrics::prometheus::prometheus::HistogramTimer> {
+		self.0.as_ref().map(|metrics| metrics.hand_off.start_timer())
+	}
+}
+
+#[derive(Clone)]
+struct MetricsInner {
+	incoming_requests: prometheus::CounterVec<prometheus::U64>,
+	decode_failures: prometheus::CounterVec<prometheus::U64>,
+	rate_limited: prometheus::CounterVec<prometheus::U64>,
+	hand_off: prometheus::Histogram,
+}
+
+impl metrics::Metrics for Metrics {
+	fn try_register(registry: &prometheus::Registry)
+		-> std::result::Result<Self, prometheus::PrometheusError>
+	{
+		let metrics = MetricsInner {
+			incoming_requests: prometheus::register(
+				prometheus::CounterVec::new(
+					prometheus::Opts::new(
+						"parachain_incoming_requests_total",
+						"The number of incoming requests on a parachain request-response protocol",
+					),
+					&["protocol"]
+				)?,
+				registry,
+			)?,
+			decode_failures: prometheus::register(
+				prometheus::CounterVec::new(
+					prometheus::Opts::new(
+						"parachain_incoming_request_decode_failures_total",
+						"The number of incoming requests which failed to decode on a parachain request-response protocol",
+					),
+					&["protocol"]
+				)?,
+				registry,
+			)?,
+			rate_limited: prometheus::register(
+				prometheus::CounterVec::new(
+					prometheus::Opts::new(
+						"parachain_incoming_requests_rate_limited_total",
+						"The number of incoming requests refused for exceeding the rate limit of their peer",
+					),
+					&["protocol"]
+				)?,
+				registry,
+			)?,
+			hand_off: prometheus::register(
+				prometheus::Histogram::with_opts(
+					prometheus::HistogramOpts::new(
+						"parachain_incoming_request_hand_off",
+						"Time spent handing an incoming request off to the overseer",
+					)
+				)?,
+				registry,
+			)?,
+		};
+
+		Ok(Metrics(Some(metrics)))
+	}
 }
 
 #[cfg(test)]
 mod tests {
+	use futures::channel::{mpsc, oneshot};
 	use futures::prelude::*;
 	use futures::stream::FusedStream;
+	use futures::task::{noop_waker, waker, ArcWake, Context, Poll};
+
+	use std::collections::HashMap;
+	use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+	use std::time::{Duration, Instant};
+
+	use sc_network::config as network;
+	use sc_network::PeerId;
+
+	use parity_scale_codec::{Decode, Encode};
+
+	use strum::IntoEnumIterator;
+
+	use polkadot_node_network_protocol::request_response::{
+		fuzzing::{self, Entropy, PayloadKind},
+		v1::{self, RefusalReason}, v2, Protocol, ReqProtocolNames, RequestResponseSettings,
+	};
+	use polkadot_node_subsystem_util::metrics::{Metrics as _, prometheus};
+	use polkadot_overseer::AllMessages;
+	use polkadot_primitives::v1::{CandidateHash, Hash, ValidatorIndex};
+	use polkadot_subsystem::messages::CollatorProtocolMessage;
+
+	use super::{
+		duplicate_name, multiplex_single, BackpressureReport, BufferConfig, Metrics,
+		MultiplexerSettings, ProtocolBackpressure, ProtocolReceiver, MultiplexResult, RateLimit,
+		RateLimitConfig, RateLimiter, RequestMultiplexer, RequestMultiplexErrorKind, TokenBucket,
+		COST_RATE_LIMITED, MAX_REFUSALS_PER_POLL,
+	};
+
+	fn unlimited() -> RateLimitConfig {
+		RateLimitConfig {
+			limits: HashMap::new(),
+			..Default::default()
+		}
+	}
+
+	/// A multiplexer over receivers for the given protocols, with their senders.
+	///
+	/// Requests have an empty payload and thus fail to decode, so the error reveals which sender
+	/// they came from.
+	fn multiplexer_with(
+		protocols: &[Protocol],
+	) -> (RequestMultiplexer, Vec<(PeerId, mpsc::Sender<network::IncomingRequest>)>) {
+		let (receivers, senders) = protocols.iter()
+			.map(|p| {
+				let (tx, rx) = mpsc::channel(100);
+				(ProtocolReceiver::new(*p, rx, 100), (PeerId::random(), tx))
+			})
+			.unzip();
+		let multiplexer = RequestMultiplexer {
+			receivers,
+			next_poll: 0,
+			backpressure_threshold_percent: BufferConfig::default().backpressure_threshold_percent,
+			rate_limiter: RateLimiter::new(unlimited()),
+			metrics: Metrics::default(),
+			protocol_names: ReqProtocolNames::new(Hash::zero(), None, false),
+			configs: Vec::new(),
+		};
+		(multiplexer, senders)
+	}
+
+	fn try_send_request(sender: &mut (PeerId, mpsc::Sender<network::IncomingRequest>)) -> bool {
+		let (pending_response, _) = oneshot::channel();
+		sender.1.try_send(network::IncomingRequest {
+			peer: sender.0,
+			payload: Vec::new(),
+			pending_response,
+		}).is_ok()
+	}
+
+	fn send_request(sender: &mut (PeerId, mpsc::Sender<network::IncomingRequest>)) {
+		assert!(try_send_request(sender), "channel has capacity");
+	}
+
+	/// Send a request, keeping the receiver of its response.
+	fn send_request_awaiting_response(
+		sender: &mut (PeerId, mpsc::Sender<network::IncomingRequest>),
+	) -> oneshot::Receiver<network::OutgoingResponse> {
+		let (pending_response, response_rx) = oneshot::channel();
+		sender.1.try_send(network::IncomingRequest {
+			peer: sender.0,
+			payload: Vec::new(),
+			pending_response,
+		}).expect("channel has capacity");
+		response_rx
+	}
+
+	/// Poll the multiplexer once, returning the peer of the yielded request if any.
+	fn poll_peer(multiplexer: &mut RequestMultiplexer) -> Option<PeerId> {
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+		match multiplexer.poll_next_unpin(&mut cx) {
+			Poll::Ready(Some(Err(err))) => Some(err.peer),
+			Poll::Ready(Some(Ok(_))) => panic!("empty payloads don't decode"),
+			Poll::Ready(None) => panic!("not all receivers were closed"),
+			Poll::Pending => None,
+		}
+	}
 
-	use super::RequestMultiplexer;
 	#[test]
 	fn check_exhaustion_safety() {
-		// Create and end streams:
+		// Create and end all streams:
 		fn drop_configs() -> RequestMultiplexer {
-			let (multiplexer, _) = RequestMultiplexer::new();
+			let (multiplexer, _) = RequestMultiplexer::new(
+				None,
+				Hash::zero(),
+				Default::default(),
+				BufferConfig::default(),
+				unlimited(),
+			).expect("metrics don't fail to register without a registry");
 			multiplexer
 		}
 		let multiplexer = drop_configs();
@@ -233,4 +988,768 @@ mod tests {
 			assert!(f.is_terminated());
 		});
 	}
+
+	fn multiplexed_protocols(multiplexer: &RequestMultiplexer) -> Vec<Protocol> {
+		multiplexer.receivers.iter().map(|r| r.protocol).collect()
+	}
+
+	#[test]
+	fn all_protocols_are_multiplexed_unless_taken() {
+		let (multiplexer, cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::zero(),
+			Default::default(),
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		assert_eq!(multiplexed_protocols(&multiplexer), Protocol::iter().collect::<Vec<_>>());
+		// Every protocol is registered under its legacy name as well.
+		assert_eq!(cfgs.len(), 2 * Protocol::iter().count());
+	}
+
+	#[test]
+	fn configs_describe_the_network_configs() {
+		let mut request_response = RequestResponseSettings::default();
+		request_response.set_channel_size(Protocol::ChunkFetching, 42).unwrap();
+		request_response.set_request_timeout(Protocol::ChunkFetching, Duration::from_secs(3)).unwrap();
+		let (multiplexer, cfgs) = RequestMultiplexer::new_with(None, MultiplexerSettings {
+			genesis_hash: Hash::repeat_byte(1),
+			request_response,
+			rate_limit: unlimited(),
+			..Default::default()
+		}).expect("metrics don't fail to register without a registry");
+
+		let configs = multiplexer.configs();
+		assert_eq!(configs.len(), cfgs.len());
+		for (info, cfg) in configs.iter().zip(cfgs.iter()) {
+			assert_eq!(info.name, cfg.name);
+			assert_eq!(info.max_request_size, cfg.max_request_size);
+			assert_eq!(info.max_response_size, cfg.max_response_size);
+			assert_eq!(info.request_timeout, cfg.request_timeout);
+		}
+
+		// Overrides apply under both names of a protocol:
+		let chunk_fetching: Vec<_> = configs.iter()
+			.filter(|info| info.protocol == Protocol::ChunkFetching)
+			.collect();
+		assert_eq!(chunk_fetching.len(), 2);
+		assert!(chunk_fetching.iter().all(|info|
+			info.inbound_queue_len == 42 && info.request_timeout == Duration::from_secs(3)
+		));
+		assert!(configs.iter()
+			.filter(|info| info.protocol == Protocol::PoVFetching)
+			.all(|info| info.inbound_queue_len == Protocol::PoVFetching.get_channel_size()));
+	}
+
+	#[test]
+	fn duplicate_names_are_caught() {
+		let (multiplexer, _cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::zero(),
+			Default::default(),
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		assert_eq!(duplicate_name(multiplexer.configs()), None);
+
+		let mut configs = multiplexer.configs().to_vec();
+		let mut duplicate = configs[0].clone();
+		duplicate.protocol = Protocol::DisputeSending;
+		configs.push(duplicate);
+		assert_eq!(duplicate_name(&configs), Some(configs[0].name.as_ref()));
+	}
+
+	#[test]
+	fn legacy_names_feed_the_same_receiver() {
+		let (mut multiplexer, cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::repeat_byte(1),
+			Default::default(),
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		let mut rx = multiplexer.take_receiver(Protocol::ChunkFetching).expect("not taken before");
+		let sender = |name: &str| cfgs.iter()
+			.find(|cfg| cfg.name == name)
+			.and_then(|cfg| cfg.inbound_queue.clone())
+			.expect("protocol is configured under the name");
+		let new_name = multiplexer.protocol_names().get_name(Protocol::ChunkFetching);
+		assert_ne!(new_name, Protocol::ChunkFetching.get_protocol_name_static());
+
+		for name in [&*new_name, Protocol::ChunkFetching.get_protocol_name_static()].iter().copied() {
+			let peer = PeerId::random();
+			let (pending_response, _) = oneshot::channel();
+			sender(name)
+				.try_send(network::IncomingRequest { peer, payload: Vec::new(), pending_response })
+				.expect("channel has capacity");
+			assert_eq!(rx.try_next().expect("request was sent").map(|req| req.peer), Some(peer));
+		}
+
+		let mut settings = RequestResponseSettings::default();
+		settings.set_legacy_names(false);
+		let (_, cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::repeat_byte(1),
+			settings,
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		assert_eq!(cfgs.len(), Protocol::iter().count());
+		assert!(cfgs.iter().all(|cfg| cfg.name != Protocol::ChunkFetching.get_protocol_name_static()));
+	}
+
+	#[test]
+	fn taken_receiver_is_not_multiplexed() {
+		let (mut multiplexer, cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::zero(),
+			Default::default(),
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		let mut rx = multiplexer.take_receiver(Protocol::StatementFetching)
+			.expect("not taken before");
+		assert!(multiplexer.take_receiver(Protocol::StatementFetching).is_none());
+		assert_eq!(
+			multiplexed_protocols(&multiplexer),
+			Protocol::iter().filter(|p| *p != Protocol::StatementFetching).collect::<Vec<_>>(),
+		);
+
+		// Requests of the protocol go to the taken receiver.
+		let cfg = cfgs.iter()
+			.find(|cfg| cfg.name == multiplexer.protocol_names().get_name(Protocol::StatementFetching))
+			.expect("all protocols are configured");
+		let peer = PeerId::random();
+		let (pending_response, _) = oneshot::channel();
+		cfg.inbound_queue.clone().expect("inbound queue is configured")
+			.try_send(network::IncomingRequest { peer, payload: Vec::new(), pending_response })
+			.expect("channel has capacity");
+		assert_eq!(rx.try_next().expect("request was sent").map(|req| req.peer), Some(peer));
+		assert_eq!(poll_peer(&mut multiplexer), None);
+	}
+
+	#[test]
+	fn versioned_receiver_receives_all_versions() {
+		let (mut multiplexer, cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::zero(),
+			Default::default(),
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		let versions = [Protocol::StatementFetching, Protocol::StatementFetchingV2];
+		let mut rx = multiplexer.take_versioned_receiver(&versions).expect("not taken before");
+		assert!(multiplexer.take_versioned_receiver(&versions).is_none());
+
+		// No version is taken if another one was taken before.
+		assert!(multiplexer.take_receiver(Protocol::AvailableDataFetching).is_some());
+		assert!(multiplexer.take_versioned_receiver(
+			&[Protocol::AvailableDataFetching, Protocol::AvailableDataFetchingV2],
+		).is_none());
+
+		assert_eq!(
+			multiplexed_protocols(&multiplexer),
+			Protocol::iter()
+				.filter(|p| !versions.contains(p) && *p != Protocol::AvailableDataFetching)
+				.collect::<Vec<_>>(),
+		);
+
+		for protocol in versions.iter() {
+			let cfg = cfgs.iter()
+				.find(|cfg| cfg.name == multiplexer.protocol_names().get_name(*protocol))
+				.expect("all protocols are configured");
+			let peer = PeerId::random();
+			let (pending_response, _) = oneshot::channel();
+			cfg.inbound_queue.clone().expect("inbound queue is configured")
+				.try_send(network::IncomingRequest { peer, payload: Vec::new(), pending_response })
+				.expect("channel has capacity");
+			assert_eq!(
+				rx.next().now_or_never().expect("request was sent").map(|(p, req)| (p, req.peer)),
+				Some((*protocol, peer)),
+			);
+		}
+		assert_eq!(poll_peer(&mut multiplexer), None);
+	}
+
+	#[test]
+	fn taking_all_receivers_ends_the_stream() {
+		let (mut multiplexer, _cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::zero(),
+			Default::default(),
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		for p in Protocol::iter() {
+			assert!(multiplexer.take_receiver(p).is_some(), "{:?} taken once", p);
+		}
+		assert!(multiplexer.is_terminated());
+		futures::executor::block_on(async move {
+			assert!(multiplexer.next().await.is_none());
+		});
+	}
+
+	#[test]
+	fn queued_requests_are_refused_on_shutdown() {
+		let protocols = [Protocol::ChunkFetching, Protocol::PoVFetching];
+		let (mut multiplexer, mut senders) = multiplexer_with(&protocols);
+		let mut responses = Vec::new();
+		for _ in 0..3 {
+			for (protocol, sender) in protocols.iter().zip(senders.iter_mut()) {
+				responses.push((*protocol, send_request_awaiting_response(sender)));
+			}
+		}
+		// Yielding a request takes the others of its channel into the queue of the multiplexer.
+		assert_eq!(poll_peer(&mut multiplexer), Some(senders[0].0));
+		responses.remove(0);
+
+		assert_eq!(multiplexer.shutdown(Duration::from_secs(1)), 5);
+		for (protocol, mut response_rx) in responses {
+			let response = response_rx.try_recv()
+				.expect("response was not dropped")
+				.expect("response was sent");
+			assert_eq!(response.result, Ok(protocol.encode_refusal(RefusalReason::Busy)));
+		}
+		assert!(multiplexer.is_terminated());
+		assert!(!try_send_request(&mut senders[1]), "no requests are accepted after shutdown");
+	}
+
+	#[test]
+	fn requests_beyond_the_shutdown_budget_are_dropped() {
+		let (mut multiplexer, mut senders) = multiplexer_with(&[Protocol::ChunkFetching]);
+		let mut response_rx = send_request_awaiting_response(&mut senders[0]);
+
+		assert_eq!(multiplexer.shutdown(Duration::from_secs(0)), 0);
+		assert!(response_rx.try_recv().is_err(), "response was dropped");
+		assert!(multiplexer.is_terminated());
+	}
+
+	#[test]
+	fn requests_of_taken_protocols_are_an_error() {
+		let (mut multiplexer, mut senders) = multiplexer_with(
+			&[Protocol::ChunkFetching, Protocol::DisputeSending],
+		);
+		send_request(&mut senders[1]);
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+		match multiplexer.poll_next_unpin(&mut cx) {
+			Poll::Ready(Some(Err(err))) => {
+				assert_eq!(err.peer, senders[1].0);
+				assert_eq!(
+					err.error,
+					RequestMultiplexErrorKind::NotMultiplexed(Protocol::DisputeSending),
+				);
+			}
+			_ => panic!("request is yielded as an error"),
+		}
+	}
+
+	#[test]
+	fn taking_a_receiver_keeps_round_robin_order() {
+		let (mut multiplexer, mut senders) = multiplexer_with(
+			&[Protocol::ChunkFetching, Protocol::CollationFetching, Protocol::PoVFetching],
+		);
+		let first = senders[0].0;
+		let last = senders[2].0;
+		send_request(&mut senders[0]);
+		assert_eq!(poll_peer(&mut multiplexer), Some(first));
+
+		// `CollationFetching` would have been polled next.
+		assert!(multiplexer.take_receiver(Protocol::CollationFetching).is_some());
+		send_request(&mut senders[0]);
+		send_request(&mut senders[2]);
+		assert_eq!(poll_peer(&mut multiplexer), Some(last));
+		assert_eq!(poll_peer(&mut multiplexer), Some(first));
+	}
+
+	#[test]
+	fn full_channel_applies_backpressure_and_is_reported() {
+		let mut settings = RequestResponseSettings::default();
+		settings.set_channel_size(Protocol::ChunkFetching, 2).expect("2 is a valid channel size");
+		let (mut multiplexer, cfgs) = RequestMultiplexer::new(
+			None,
+			Hash::zero(),
+			settings,
+			BufferConfig::default(),
+			unlimited(),
+		).expect("metrics don't fail to register without a registry");
+		let sender = |protocol: Protocol| cfgs.iter()
+			.find(|cfg| cfg.name == multiplexer.protocol_names().get_name(protocol))
+			.and_then(|cfg| cfg.inbound_queue.clone())
+			.expect("all protocols are configured");
+		let mut chunk_tx = sender(Protocol::ChunkFetching);
+		let mut pov_tx = sender(Protocol::PoVFetching);
+		let peer = PeerId::random();
+		let request = || {
+			let (pending_response, _) = oneshot::channel();
+			network::IncomingRequest { peer, payload: Vec::new(), pending_response }
+		};
+
+		// The channel holds its size, plus one request of the sender.
+		for _ in 0..3 {
+			chunk_tx.try_send(request()).expect("channel has capacity");
+		}
+		assert!(chunk_tx.try_send(request()).expect_err("channel is full").is_full());
+		pov_tx.try_send(request()).expect("channel has capacity");
+
+		// A poll only takes a single request out of the channel, which leaves room for one more.
+		assert_eq!(poll_peer(&mut multiplexer), Some(peer));
+		chunk_tx.try_send(request()).expect("a request was taken");
+		assert!(chunk_tx.try_send(request()).expect_err("channel is full again").is_full());
+		assert_eq!(multiplexer.take_backpressure_report(), None);
+
+		// Every request is still yielded, without the chunk channel running empty in between.
+		for _ in 0..4 {
+			assert_eq!(poll_peer(&mut multiplexer), Some(peer));
+		}
+		assert_eq!(poll_peer(&mut multiplexer), None);
+		assert_eq!(
+			multiplexer.take_backpressure_report(),
+			Some(BackpressureReport {
+				congested: vec![ProtocolBackpressure {
+					protocol: Protocol::ChunkFetching,
+					high_water_mark: 4,
+					channel_size: 2,
+				}],
+			}),
+		);
+		assert_eq!(multiplexer.take_backpressure_report(), None);
+	}
+
+	#[test]
+	fn flooding_peer_is_refused_without_affecting_others() {
+		let limit = RateLimit { burst: 3, refill_interval: Duration::from_secs(3600) };
+		let (mut multiplexer, mut senders) = multiplexer_with(&[Protocol::ChunkFetching]);
+		multiplexer.rate_limiter = RateLimiter::new(RateLimitConfig {
+			limits: vec![(Protocol::ChunkFetching, limit)].into_iter().collect(),
+			..Default::default()
+		});
+		let flooder = PeerId::random();
+		let honest = PeerId::random();
+		let mut send_from = |peer: PeerId| {
+			let (pending_response, response_rx) = oneshot::channel();
+			senders[0].1.try_send(network::IncomingRequest {
+				peer,
+				payload: Vec::new(),
+				pending_response,
+			}).expect("channel has capacity");
+			response_rx
+		};
+
+		let flood: Vec<_> = (0..10).map(|_| send_from(flooder)).collect();
+		let honest_responses: Vec<_> = (0..2).map(|_| send_from(honest)).collect();
+
+		let yielded: Vec<_> = std::iter::from_fn(|| poll_peer(&mut multiplexer)).collect();
+		assert_eq!(yielded, vec![flooder, flooder, flooder, honest, honest]);
+
+		// Yielded requests fail to decode, so they are refused as invalid, the others as busy.
+		let refusal = |mut rx: oneshot::Receiver<network::OutgoingResponse>| {
+			let response = rx.try_recv()
+				.expect("response was sent")
+				.expect("refused requests are answered");
+			let reason = v1::ChunkFetchingResponse::decode(
+				&mut response.result.expect("refusals are responses").as_ref(),
+			);
+			(reason.expect("refusals decode"), response.reputation_changes)
+		};
+		let refusals: Vec<_> = flood.into_iter().map(refusal).collect();
+		for (response, reputation_changes) in &refusals[..3] {
+			assert!(matches!(response, v1::ChunkFetchingResponse::Refused(RefusalReason::Invalid)));
+			assert!(reputation_changes.is_empty());
+		}
+		for (response, reputation_changes) in &refusals[3..] {
+			assert!(matches!(response, v1::ChunkFetchingResponse::Refused(RefusalReason::Busy)));
+			assert_eq!(reputation_changes, &vec![COST_RATE_LIMITED.into_base_rep()]);
+		}
+		for rx in honest_responses {
+			let (response, _) = refusal(rx);
+			assert!(matches!(response, v1::ChunkFetchingResponse::Refused(RefusalReason::Invalid)));
+		}
+	}
+
+	#[test]
+	fn refusals_of_a_flood_are_spread_over_polls() {
+		struct Woken(AtomicBool);
+		impl ArcWake for Woken {
+			fn wake_by_ref(arc_self: &Arc<Self>) {
+				arc_self.0.store(true, Ordering::SeqCst);
+			}
+		}
+
+		let limit = RateLimit { burst: 1, refill_interval: Duration::from_secs(3600) };
+		let (mut multiplexer, mut senders) = multiplexer_with(&[Protocol::ChunkFetching]);
+		multiplexer.rate_limiter = RateLimiter::new(RateLimitConfig {
+			limits: vec![(Protocol::ChunkFetching, limit)].into_iter().collect(),
+			..Default::default()
+		});
+		let flooder = PeerId::random();
+		let mut send_from = |peer: PeerId| {
+			let (pending_response, response_rx) = oneshot::channel();
+			senders[0].1.try_send(network::IncomingRequest {
+				peer,
+				payload: Vec::new(),
+				pending_response,
+			}).expect("channel has capacity");
+			response_rx
+		};
+		let flood: Vec<_> = (0..MAX_REFUSALS_PER_POLL + 6).map(|_| send_from(flooder)).collect();
+		let honest = PeerId::random();
+		let _honest_response = send_from(honest);
+
+		assert_eq!(poll_peer(&mut multiplexer), Some(flooder));
+
+		// The first poll gives up after refusing as many as allowed, but asks to be polled again.
+		let woken = Arc::new(Woken(AtomicBool::new(false)));
+		let waker = waker(woken.clone());
+		let mut cx = Context::from_waker(&waker);
+		assert!(multiplexer.poll_next_unpin(&mut cx).is_pending());
+		assert!(woken.0.load(Ordering::SeqCst));
+		let mut flood = flood.into_iter();
+		let answered = flood.by_ref()
+			.take(1 + MAX_REFUSALS_PER_POLL)
+			.filter_map(|mut rx| rx.try_recv().expect("response was sent"))
+			.count();
+		assert_eq!(answered, 1 + MAX_REFUSALS_PER_POLL);
+		let mut rest: Vec<_> = flood.collect();
+		assert!(rest.iter_mut().all(|rx| matches!(rx.try_recv(), Ok(None))));
+
+		// The next one refuses the rest of the flood and yields the honest request.
+		assert_eq!(poll_peer(&mut multiplexer), Some(honest));
+		assert!(rest.iter_mut().all(|rx| matches!(rx.try_recv(), Ok(Some(_)))));
+	}
+
+	#[test]
+	fn token_bucket_refills_up_to_burst() {
+		let limit = RateLimit { burst: 2, refill_interval: Duration::from_secs(1) };
+		let start = Instant::now();
+		let mut bucket = TokenBucket::full(&limit, start);
+		assert!(bucket.try_take(&limit, start));
+		assert!(bucket.try_take(&limit, start));
+		assert!(!bucket.try_take(&limit, start + Duration::from_millis(999)));
+
+		assert!(bucket.try_take(&limit, start + Duration::from_millis(1500)));
+		// The half interval which passed is kept.
+		assert!(bucket.try_take(&limit, start + Duration::from_secs(2)));
+		assert!(!bucket.try_take(&limit, start + Duration::from_secs(2)));
+
+		// A long pause only refills up to the burst.
+		let later = start + Duration::from_secs(3600);
+		assert!(bucket.try_take(&limit, later));
+		assert!(bucket.try_take(&limit, later));
+		assert!(!bucket.try_take(&limit, later));
+	}
+
+	#[test]
+	fn rate_limiter_forgets_least_recently_active_peers() {
+		let limit = RateLimit { burst: 1, refill_interval: Duration::from_secs(3600) };
+		let mut limiter = RateLimiter::new(RateLimitConfig {
+			limits: vec![(Protocol::ChunkFetching, limit)].into_iter().collect(),
+			max_tracked: 2,
+			report_peers: false,
+		});
+		let now = Instant::now();
+		let peers: Vec<_> = (0..3).map(|_| PeerId::random()).collect();
+		for peer in &peers {
+			assert!(limiter.check(*peer, Protocol::ChunkFetching, now));
+		}
+		assert_eq!(limiter.buckets.len(), 2);
+		// Unlimited protocols are not tracked.
+		assert!(limiter.check(peers[2], Protocol::PoVFetching, now));
+		assert_eq!(limiter.buckets.len(), 2);
+
+		assert!(!limiter.check(peers[2], Protocol::ChunkFetching, now));
+		// The first peer was forgotten, so it starts with a full bucket again.
+		assert!(limiter.check(peers[0], Protocol::ChunkFetching, now));
+	}
+
+	#[test]
+	fn batches_need_fewer_polls_for_the_same_throughput() {
+		const REQUESTS: usize = 10_000;
+
+		/// Polls needed to receive `REQUESTS` requests, fed as fast as the channels take them.
+		fn polls_to_drain(max: usize) -> usize {
+			let (mut multiplexer, mut senders) = multiplexer_with(&[
+				Protocol::ChunkFetching,
+				Protocol::CollationFetching,
+				Protocol::PoVFetching,
+				Protocol::AvailableDataFetching,
+			]);
+			let waker = noop_waker();
+			let mut cx = Context::from_waker(&waker);
+			let (mut sent, mut received, mut polls) = (0, 0, 0);
+			while received < REQUESTS {
+				for sender in senders.iter_mut() {
+					while sent < REQUESTS && try_send_request(sender) {
+						sent += 1;
+					}
+				}
+				polls += 1;
+				match multiplexer.poll_next_batch(&mut cx, max) {
+					Poll::Ready(Some(batch)) => received += batch.len(),
+					_ => panic!("requests are queued"),
+				}
+			}
+			polls
+		}
+
+		assert_eq!(polls_to_drain(1), REQUESTS);
+		let batched = polls_to_drain(100);
+		assert!(batched <= REQUESTS / 100, "took {} polls", batched);
+	}
+
+	#[test]
+	fn batches_interleave_protocols() {
+		let (mut multiplexer, mut senders) = multiplexer_with(
+			&[Protocol::ChunkFetching, Protocol::CollationFetching, Protocol::PoVFetching],
+		);
+		for _ in 0..10 {
+			send_request(&mut senders[0]);
+		}
+		send_request(&mut senders[1]);
+		for _ in 0..10 {
+			send_request(&mut senders[2]);
+		}
+
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+		let peers = |batch: Vec<MultiplexResult>| batch.into_iter()
+			.map(|result| result.expect_err("empty payloads don't decode").peer)
+			.collect::<Vec<_>>();
+		let (a, b, c) = (senders[0].0, senders[1].0, senders[2].0);
+		match multiplexer.poll_next_batch(&mut cx, 6) {
+			Poll::Ready(Some(batch)) => assert_eq!(peers(batch), vec![a, b, c, a, c, a]),
+			_ => panic!("requests are queued"),
+		}
+		// The next batch carries on with the round robin, and is cut short by what is queued.
+		match multiplexer.poll_next_batch(&mut cx, 100) {
+			Poll::Ready(Some(batch)) => assert_eq!(peers(batch).len(), 15),
+			_ => panic!("requests are queued"),
+		}
+		assert!(multiplexer.poll_next_batch(&mut cx, 100).is_pending());
+
+		drop(senders);
+		assert!(matches!(multiplexer.poll_next_batch(&mut cx, 100), Poll::Ready(None)));
+	}
+
+	#[test]
+	fn closed_receiver_does_not_end_the_stream() {
+		let (mut multiplexer, mut senders) = multiplexer_with(
+			&[Protocol::ChunkFetching, Protocol::CollationFetching, Protocol::PoVFetching],
+		);
+		let first = senders[0].0;
+		let last = senders[2].0;
+		send_request(&mut senders[0]);
+		send_request(&mut senders[1]);
+		send_request(&mut senders[2]);
+		assert_eq!(poll_peer(&mut multiplexer), Some(first));
+
+		// Close the middle receiver while it still has a request queued, which is delivered first.
+		let middle = senders.remove(1);
+		let middle_peer = middle.0;
+		drop(middle);
+		assert_eq!(poll_peer(&mut multiplexer), Some(middle_peer));
+		assert_eq!(poll_peer(&mut multiplexer), Some(last));
+		assert_eq!(poll_peer(&mut multiplexer), None);
+		assert_eq!(multiplexer.receivers.len(), 2);
+		assert!(!multiplexer.is_terminated());
+
+		// The remaining two keep delivering requests.
+		send_request(&mut senders[1]);
+		send_request(&mut senders[0]);
+		assert_eq!(poll_peer(&mut multiplexer), Some(first));
+		assert_eq!(poll_peer(&mut multiplexer), Some(last));
+
+		senders.clear();
+		futures::executor::block_on(async move {
+			assert!(multiplexer.next().await.is_none());
+			assert!(multiplexer.is_terminated());
+		});
+	}
+
+	#[test]
+	fn busy_receiver_does_not_starve_others() {
+		let (mut multiplexer, mut senders) = multiplexer_with(
+			&[Protocol::ChunkFetching, Protocol::CollationFetching],
+		);
+		let busy = senders[0].0;
+		let quiet = senders[1].0;
+		for _ in 0..10 {
+			send_request(&mut senders[0]);
+		}
+
+		assert_eq!(poll_peer(&mut multiplexer), Some(busy));
+		send_request(&mut senders[1]);
+		// The quiet receiver is next, even though the busy one still has requests queued.
+		assert_eq!(poll_peer(&mut multiplexer), Some(quiet));
+		assert_eq!(poll_peer(&mut multiplexer), Some(busy));
+
+		send_request(&mut senders[1]);
+		assert_eq!(poll_peer(&mut multiplexer), Some(quiet));
+		assert_eq!(poll_peer(&mut multiplexer), Some(busy));
+	}
+
+	#[test]
+	fn receivers_are_served_fairly_for_random_ready_sets() {
+		let protocols = [
+			Protocol::ChunkFetching,
+			Protocol::CollationFetching,
+			Protocol::PoVFetching,
+			Protocol::AvailableDataFetching,
+		];
+		let len = protocols.len();
+		let (mut multiplexer, mut senders) = multiplexer_with(&protocols);
+		let mut queued = vec![0usize; len];
+		// Polls since each receiver had a request queued but wasn't served.
+		let mut waiting = vec![0usize; len];
+		let mut served = vec![0usize; len];
+
+		// xorshift, so the ready sets are arbitrary but the test is reproducible.
+		let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+		for _ in 0..10_000 {
+			seed ^= seed << 13;
+			seed ^= seed >> 7;
+			seed ^= seed << 17;
+			for i in 0..len {
+				if seed & (1 << i) != 0 && queued[i] < 5 {
+					send_request(&mut senders[i]);
+					queued[i] += 1;
+				}
+			}
+
+			let ready = queued.iter().filter(|q| **q > 0).count();
+			match poll_peer(&mut multiplexer) {
+				Some(peer) => {
+					let i = senders.iter().position(|s| s.0 == peer).expect("peer of a sender");
+					queued[i] -= 1;
+					served[i] += 1;
+					waiting[i] = 0;
+				}
+				None => assert_eq!(ready, 0, "a request was queued but not yielded"),
+			}
+			for i in 0..len {
+				if queued[i] > 0 {
+					waiting[i] += 1;
+					assert!(waiting[i] < len, "receiver {} waited {} polls", i, waiting[i]);
+				} else {
+					waiting[i] = 0;
+				}
+			}
+		}
+
+		// Every receiver was ready about as often, so each was served about equally often.
+		let (min, max) = (served.iter().min().unwrap(), served.iter().max().unwrap());
+		assert!(max - min < max / 10, "served {:?}", served);
+	}
+
+	#[test]
+	fn incoming_requests_and_decode_failures_are_counted() {
+		let registry = prometheus::Registry::new();
+		let (mut multiplexer, mut senders) = multiplexer_with(&[Protocol::ChunkFetching]);
+		multiplexer.metrics = Metrics::register(Some(&registry)).expect("metrics register");
+
+		let (pending_response, _) = oneshot::channel();
+		let request = v1::ChunkFetchingRequest {
+			candidate_hash: CandidateHash::default(),
+			index: ValidatorIndex(0),
+		};
+		senders[0].1.try_send(network::IncomingRequest {
+			peer: senders[0].0,
+			payload: request.encode(),
+			pending_response,
+		}).expect("channel has capacity");
+		send_request(&mut senders[0]);
+
+		let waker = noop_waker();
+		let mut cx = Context::from_waker(&waker);
+		assert!(matches!(multiplexer.poll_next_unpin(&mut cx), Poll::Ready(Some(Ok(_)))));
+		assert!(matches!(multiplexer.poll_next_unpin(&mut cx), Poll::Ready(Some(Err(_)))));
+
+		let metrics = multiplexer.metrics.0.as_ref().expect("metrics are registered");
+		let name = Protocol::ChunkFetching.get_protocol_name_static();
+		assert_eq!(metrics.incoming_requests.with_label_values(&[name]).get(), 2);
+		assert_eq!(metrics.decode_failures.with_label_values(&[name]).get(), 1);
+		let other = Protocol::PoVFetching.get_protocol_name_static();
+		assert_eq!(metrics.incoming_requests.with_label_values(&[other]).get(), 0);
+	}
+
+	#[test]
+	fn fuzzed_requests_are_multiplexed_or_refused() {
+		// Bounded, so it runs with the other tests. See `fuzzer/` of the protocol crate for more.
+		const ITERATIONS: u64 = 500;
+		let metrics = Metrics::default();
+		for seed in 0..ITERATIONS {
+			let data = fuzzing::seeded_bytes(seed, 2048);
+			let mut entropy = Entropy::new(&data);
+			for p in Protocol::iter() {
+				let payload = fuzzing::arbitrary_payload(PayloadKind::Request, p, &mut entropy);
+				let decodes = fuzzing::decode(PayloadKind::Request, p, &payload).is_ok();
+				let (pending_response, mut response_rx) = oneshot::channel();
+				let request = network::IncomingRequest {
+					peer: PeerId::random(),
+					payload: payload.clone(),
+					pending_response,
+				};
+				match multiplex_single(p, request, &metrics) {
+					Ok(_) => assert!(decodes, "{:?} multiplexed undecodable {:?}", p, payload),
+					Err(err) => match err.error {
+						RequestMultiplexErrorKind::Decoding(_) => {
+							assert!(!decodes, "{:?} rejected decodable {:?}", p, payload);
+							let response = response_rx.try_recv()
+								.expect("response was not dropped")
+								.expect("invalid requests are refused");
+							assert_eq!(response.result, Ok(p.encode_refusal(RefusalReason::Invalid)));
+						}
+						RequestMultiplexErrorKind::NotMultiplexed(protocol) => {
+							assert_eq!(protocol, p);
+							assert!(matches!(
+								p,
+								Protocol::StatementFetching |
+									Protocol::StatementFetchingV2 |
+									Protocol::DisputeSending |
+									Protocol::DisputeBatchSending
+							));
+						}
+					},
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn corpus_requests_are_refused() {
+		let metrics = Metrics::default();
+		let requests = fuzzing::corpus().filter(|(kind, _, _)| *kind == PayloadKind::Request);
+		for (_, p, payload) in requests {
+			let (pending_response, _) = oneshot::channel();
+			let request = network::IncomingRequest { peer: PeerId::random(), payload, pending_response };
+			assert!(multiplex_single(p, request, &metrics).is_err());
+		}
+	}
+
+	#[test]
+	fn collation_requests_of_both_versions_reach_the_collator_protocol() {
+		let metrics = Metrics::default();
+		let request = v1::CollationFetchingRequest { relay_parent: Hash::repeat_byte(1), para_id: 1.into() };
+		let incoming = |payload| {
+			let (pending_response, _) = oneshot::channel();
+			network::IncomingRequest { peer: PeerId::random(), payload, pending_response }
+		};
+
+		let v1_message = multiplex_single(Protocol::CollationFetching, incoming(request.encode()), &metrics);
+		assert!(matches!(
+			v1_message,
+			Ok(AllMessages::CollatorProtocol(CollatorProtocolMessage::CollationFetchingRequest(req)))
+				if req.payload.relay_parent == request.relay_parent
+		));
+		let v2_message = multiplex_single(
+			Protocol::CollationFetchingV2,
+			incoming(v2::CollationFetchingRequest::from(request.clone()).encode()),
+			&metrics,
+		);
+		assert!(matches!(
+			v2_message,
+			Ok(AllMessages::CollatorProtocol(CollatorProtocolMessage::CollationFetchingRequestV2(req)))
+				if req.payload.para_id == request.para_id
+		));
+	}
 }
@@ -30,7 +30,7 @@ use sc_network::{config::parse_addr, multiaddr::Multiaddr};
 
 use polkadot_node_network_protocol::{
 	peer_set::PeerSet,
-	request_response::{OutgoingRequest, Requests, Recipient},
+	request_response::{OutgoingRequest, ReqProtocolNames, Requests, Recipient},
 	PeerId, UnifiedReputationChange as Rep,
 };
 use polkadot_primitives::v1::{AuthorityDiscoveryId, Block, Hash};
@@ -97,11 +97,12 @@ pub trait Network: Clone + Send + 'static {
 		multiaddresses: HashSet<Multiaddr>,
 	) -> Result<(), String>;
 
-	/// Send a request to a remote peer.
+	/// Send a request to a remote peer, under the name of its protocol in `req_protocol_names`.
 	async fn start_request<AD: AuthorityDiscovery>(
 		&self,
 		authority_discovery: &mut AD,
 		req: Requests,
+		req_protocol_names: &ReqProtocolNames,
 		if_disconnected: IfDisconnected,
 	);
 
@@ -168,6 +169,7 @@ impl Network for Arc<NetworkService<Block, Hash>> {
 		&self,
 		authority_discovery: &mut AD,
 		req: Requests,
+		req_protocol_names: &ReqProtocolNames,
 		if_disconnected: IfDisconnected,
 	) {
 		let (
@@ -176,6 +178,7 @@ impl Network for Arc<NetworkService<Block, Hash>> {
 				peer,
 				payload,
 				pending_response,
+				..
 			},
 		) = req.encode_request();
 
@@ -224,7 +227,7 @@ impl Network for Arc<NetworkService<Block, Hash>> {
 		NetworkService::start_request(
 			&*self,
 			peer_id,
-			protocol.into_protocol_name(),
+			req_protocol_names.get_outgoing_name(protocol),
 			payload,
 			pending_response,
 			if_disconnected,
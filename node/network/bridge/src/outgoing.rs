@@ -0,0 +1,134 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+
+use polkadot_node_network_protocol::{
+	request_response::{Priority, Requests},
+	IfDisconnected,
+};
+
+/// Outgoing requests waiting to be sent, in one lane per `Priority`.
+///
+/// As long as requests of both lanes are queued, `critical_per_bulk` critical requests are sent
+/// for each bulk request. The lanes are kept across `SendRequests` messages, so critical requests
+/// also overtake bulk requests that were queued by earlier messages.
+pub struct OutgoingRequests {
+	critical: VecDeque<(Requests, IfDisconnected)>,
+	bulk: VecDeque<(Requests, IfDisconnected)>,
+	critical_per_bulk: usize,
+	/// Critical requests sent since the last bulk request.
+	critical_in_a_row: usize,
+}
+
+impl OutgoingRequests {
+	/// Create empty lanes, sending `critical_per_bulk` critical requests per bulk request.
+	pub fn new(critical_per_bulk: usize) -> Self {
+		OutgoingRequests {
+			critical: VecDeque::new(),
+			bulk: VecDeque::new(),
+			critical_per_bulk,
+			critical_in_a_row: 0,
+		}
+	}
+
+	/// Queue `req` in the lane of its priority.
+	pub fn push(&mut self, req: Requests, if_disconnected: IfDisconnected) {
+		match req.priority() {
+			Priority::Critical => self.critical.push_back((req, if_disconnected)),
+			Priority::Bulk => self.bulk.push_back((req, if_disconnected)),
+		}
+	}
+
+	/// Whether no request is queued in either lane.
+	pub fn is_empty(&self) -> bool {
+		self.critical.is_empty() && self.bulk.is_empty()
+	}
+
+	/// The next request to send, if any.
+	pub fn pop(&mut self) -> Option<(Requests, IfDisconnected)> {
+		let bulk_is_due = self.critical_in_a_row >= self.critical_per_bulk && !self.bulk.is_empty();
+		if self.critical.is_empty() || bulk_is_due {
+			let next = self.bulk.pop_front()?;
+			self.critical_in_a_row = 0;
+			Some(next)
+		} else {
+			self.critical_in_a_row += 1;
+			self.critical.pop_front()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use polkadot_node_network_protocol::request_response::{v1, OutgoingRequest, Recipient};
+	use polkadot_node_network_protocol::PeerId;
+	use polkadot_primitives::v1::{CandidateHash, Hash, ValidatorIndex};
+
+	fn request(priority: Priority) -> Requests {
+		let recipient = Recipient::Peer(PeerId::random());
+		match priority {
+			Priority::Critical => Requests::StatementFetching(OutgoingRequest::new_with_priority(
+				recipient,
+				v1::StatementFetchingRequest {
+					relay_parent: Hash::zero(),
+					candidate_hash: CandidateHash::default(),
+				},
+				priority,
+			).0),
+			Priority::Bulk => Requests::ChunkFetching(OutgoingRequest::new(
+				recipient,
+				v1::ChunkFetchingRequest {
+					candidate_hash: CandidateHash::default(),
+					index: ValidatorIndex(0),
+				},
+			).0),
+		}
+	}
+
+	fn drain(queue: &mut OutgoingRequests) -> Vec<Priority> {
+		std::iter::from_fn(|| queue.pop()).map(|(req, _)| req.priority()).collect()
+	}
+
+	#[test]
+	fn bulk_requests_are_not_starved() {
+		let mut queue = OutgoingRequests::new(2);
+		for _ in 0..3 {
+			queue.push(request(Priority::Bulk), IfDisconnected::TryConnect);
+		}
+		for _ in 0..5 {
+			queue.push(request(Priority::Critical), IfDisconnected::TryConnect);
+		}
+		use Priority::{Bulk, Critical};
+		assert_eq!(
+			drain(&mut queue),
+			vec![Critical, Critical, Bulk, Critical, Critical, Bulk, Critical, Bulk],
+		);
+		assert!(queue.pop().is_none());
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn a_single_lane_is_sent_in_order() {
+		let mut queue = OutgoingRequests::new(0);
+		queue.push(request(Priority::Critical), IfDisconnected::ImmediateError);
+		queue.push(request(Priority::Critical), IfDisconnected::TryConnect);
+		let sent: Vec<_> = std::iter::from_fn(|| queue.pop()).map(|(_, d)| d).collect();
+		assert!(matches!(sent[..], [IfDisconnected::ImmediateError, IfDisconnected::TryConnect]));
+	}
+}
@@ -17,7 +17,7 @@
 use super::*;
 use futures::executor;
 use futures::stream::BoxStream;
-use futures::channel::oneshot;
+use futures::channel::{mpsc, oneshot};
 
 use std::borrow::Cow;
 use std::collections::HashSet;
@@ -40,10 +40,13 @@ use polkadot_node_subsystem_test_helpers::{
 use polkadot_node_subsystem_util::metered;
 use polkadot_node_network_protocol::view;
 use sc_network::Multiaddr;
-use sc_network::config::RequestResponseConfig;
+use sc_network::config::{IncomingRequest, RequestResponseConfig};
 use sp_keyring::Sr25519Keyring;
 use polkadot_primitives::v1::AuthorityDiscoveryId;
-use polkadot_node_network_protocol::{ObservedRole, request_response::request::Requests};
+use polkadot_node_network_protocol::{
+	ObservedRole,
+	request_response::{request::Requests, Protocol, ReqProtocolNames},
+};
 
 use crate::network::Network;
 use crate::validator_discovery::AuthorityDiscovery;
@@ -57,6 +60,8 @@ pub enum NetworkAction {
 	DisconnectPeer(PeerId, PeerSet),
 	/// Write a notification to a given peer on the given peer-set.
 	WriteNotification(PeerId, PeerSet, Vec<u8>),
+	/// Send a request of the given protocol.
+	StartRequest(Protocol),
 }
 
 // The subsystem's view of the network - only supports a single call to `event_stream`.
@@ -75,15 +80,23 @@ struct TestAuthorityDiscovery;
 struct TestNetworkHandle {
 	action_rx: metered::UnboundedMeteredReceiver<NetworkAction>,
 	net_tx: SingleItemSink<NetworkEvent>,
+	request_txs: HashMap<Cow<'static, str>, mpsc::Sender<IncomingRequest>>,
+	req_protocol_names: ReqProtocolNames,
 }
 
-fn new_test_network(req_configs: Vec<RequestResponseConfig>) -> (
+fn new_test_network(
+	req_configs: Vec<RequestResponseConfig>,
+	req_protocol_names: ReqProtocolNames,
+) -> (
 	TestNetwork,
 	TestNetworkHandle,
 	TestAuthorityDiscovery,
 ) {
 	let (net_tx, net_rx) = polkadot_node_subsystem_test_helpers::single_item_sink();
 	let (action_tx, action_rx) = metered::unbounded();
+	let request_txs = req_configs.iter()
+		.filter_map(|cfg| cfg.inbound_queue.clone().map(|tx| (cfg.name.clone(), tx)))
+		.collect();
 
 	(
 		TestNetwork {
@@ -94,6 +107,8 @@ fn new_test_network(req_configs: Vec<RequestResponseConfig>) -> (
 		TestNetworkHandle {
 			action_rx,
 			net_tx,
+			request_txs,
+			req_protocol_names,
 		},
 		TestAuthorityDiscovery,
 	)
@@ -116,7 +131,16 @@ impl Network for TestNetwork {
 		Ok(())
 	}
 
-	async fn start_request<AD: AuthorityDiscovery>(&self, _: &mut AD, _: Requests, _: IfDisconnected) {
+	async fn start_request<AD: AuthorityDiscovery>(
+		&self,
+		_: &mut AD,
+		req: Requests,
+		_: &ReqProtocolNames,
+		_: IfDisconnected,
+	) {
+		self.action_tx.lock().unbounded_send(
+			NetworkAction::StartRequest(req.get_protocol())
+		).unwrap();
 	}
 
 	fn report_peer(&self, who: PeerId, cost_benefit: Rep) {
@@ -196,6 +220,15 @@ impl TestNetworkHandle {
 	async fn send_network_event(&mut self, event: NetworkEvent) {
 		self.net_tx.send(event).await.expect("subsystem concluded early");
 	}
+
+	async fn send_request(&mut self, peer: PeerId, protocol: Protocol, payload: Vec<u8>) {
+		let (pending_response, _) = oneshot::channel();
+		self.request_txs.get_mut(&self.req_protocol_names.get_name(protocol))
+			.expect("all protocols are registered")
+			.send(IncomingRequest { peer, payload, pending_response })
+			.await
+			.expect("subsystem concluded early");
+	}
 }
 
 /// Assert that the given actions contain the given `action`.
@@ -278,8 +311,15 @@ fn test_harness<T: Future<Output=VirtualOverseer>>(
 	test: impl FnOnce(TestHarness) -> T,
 ) {
 	let pool = sp_core::testing::TaskExecutor::new();
-	let (request_multiplexer, req_configs) = RequestMultiplexer::new();
-	let (mut network, network_handle, discovery) = new_test_network(req_configs);
+	let (request_multiplexer, req_configs) = RequestMultiplexer::new(
+		None,
+		Hash::zero(),
+		Default::default(),
+		BufferConfig::default(),
+		RateLimitConfig::default(),
+	).expect("metrics don't fail to register without a registry");
+	let req_protocol_names = request_multiplexer.protocol_names().clone();
+	let (mut network, network_handle, discovery) = new_test_network(req_configs, req_protocol_names);
 	let (context, virtual_overseer) = polkadot_node_subsystem_test_helpers::make_subsystem_context(pool);
 	let network_stream = network.event_stream();
 
@@ -691,7 +731,7 @@ fn peer_view_updates_sent_via_overseer() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -749,7 +789,7 @@ fn peer_messages_sent_via_overseer() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -827,7 +867,7 @@ fn peer_disconnect_from_just_one_peerset() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -915,7 +955,7 @@ fn relays_collation_protocol_messages() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -1019,7 +1059,7 @@ fn different_views_on_different_peer_sets() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -1186,7 +1226,7 @@ fn send_messages_to_peers() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -1365,7 +1405,7 @@ fn our_view_updates_decreasing_order_and_limited_to_max() {
 		assert_matches!(
 			virtual_overseer.recv().await,
 			AllMessages::DisputeDistribution(
-				DisputeDistributionMessage::DisputeSendingReceiver(_)
+				DisputeDistributionMessage::DisputeSendingReceiver { .. }
 			)
 		);
 		assert_matches!(
@@ -1387,3 +1427,109 @@ fn our_view_updates_decreasing_order_and_limited_to_max() {
 		virtual_overseer
 	});
 }
+
+#[test]
+fn invalid_requests_are_reported_once_per_window() {
+	test_harness(done_syncing_oracle(), |test_harness| async move {
+		let TestHarness { mut network_handle, virtual_overseer } = test_harness;
+
+		let peer_a = PeerId::random();
+		let peer_b = PeerId::random();
+
+		// Empty payloads don't decode.
+		for _ in 0..3 {
+			network_handle.send_request(peer_a.clone(), Protocol::ChunkFetching, Vec::new()).await;
+		}
+		network_handle.send_request(peer_b.clone(), Protocol::ChunkFetching, Vec::new()).await;
+
+		// Requests are handled in order, so `peer_a` would be reported again before `peer_b`.
+		assert_eq!(
+			network_handle.next_network_actions(2).await,
+			vec![
+				NetworkAction::ReputationChange(peer_a, COST_INVALID_REQUEST),
+				NetworkAction::ReputationChange(peer_b, COST_INVALID_REQUEST),
+			],
+		);
+		virtual_overseer
+	});
+}
+
+#[test]
+fn critical_requests_are_sent_first_at_the_configured_ratio() {
+	use polkadot_node_network_protocol::request_response::{v1, OutgoingRequest, Priority, Recipient};
+	use polkadot_primitives::v1::{CandidateHash, ValidatorIndex};
+
+	test_harness(done_syncing_oracle(), |test_harness| async move {
+		let TestHarness {
+			mut network_handle,
+			mut virtual_overseer,
+		} = test_harness;
+
+		let chunk_request = || Requests::ChunkFetching(OutgoingRequest::new(
+			Recipient::Peer(PeerId::random()),
+			v1::ChunkFetchingRequest {
+				candidate_hash: CandidateHash::default(),
+				index: ValidatorIndex(0),
+			},
+		).0);
+		let statement_request = || Requests::StatementFetching(OutgoingRequest::new_with_priority(
+			Recipient::Peer(PeerId::random()),
+			v1::StatementFetchingRequest {
+				relay_parent: Hash::zero(),
+				candidate_hash: CandidateHash::default(),
+			},
+			Priority::Critical,
+		).0);
+
+		// Both lanes fill up, with the bulk requests queued first by a message of their own. The
+		// bridge yields before sending them, which lets the critical requests catch up.
+		let bulk = CRITICAL_REQUESTS_PER_BULK_REQUEST + 1;
+		let critical = 2 * CRITICAL_REQUESTS_PER_BULK_REQUEST;
+		virtual_overseer.send(FromOverseer::Communication {
+			msg: NetworkBridgeMessage::SendRequests(
+				(0..bulk).map(|_| chunk_request()).collect(),
+				IfDisconnected::ImmediateError,
+			),
+		}).await;
+		virtual_overseer.send(FromOverseer::Communication {
+			msg: NetworkBridgeMessage::SendRequests(
+				(0..critical).map(|_| statement_request()).collect(),
+				IfDisconnected::ImmediateError,
+			),
+		}).await;
+
+		let mut expected = Vec::new();
+		for _ in 0..2 {
+			expected.extend(
+				std::iter::repeat(Protocol::StatementFetching).take(CRITICAL_REQUESTS_PER_BULK_REQUEST)
+			);
+			expected.push(Protocol::ChunkFetching);
+		}
+		expected.extend(std::iter::repeat(Protocol::ChunkFetching).take(bulk - 2));
+		let expected: Vec<_> = expected.into_iter().map(NetworkAction::StartRequest).collect();
+
+		assert_eq!(network_handle.next_network_actions(bulk + critical).await, expected);
+		virtual_overseer
+	});
+}
+
+#[test]
+fn report_limiter_reports_once_per_window() {
+	let window = Duration::from_secs(10);
+	let mut limiter = ReportLimiter::new(window);
+	let peer_a = PeerId::random();
+	let peer_b = PeerId::random();
+	let start = Instant::now();
+
+	assert!(limiter.should_report(peer_a.clone(), start));
+	assert!(!limiter.should_report(peer_a.clone(), start + Duration::from_secs(1)));
+	assert!(limiter.should_report(peer_b.clone(), start + Duration::from_secs(1)));
+	assert!(!limiter.should_report(peer_a.clone(), start + window - Duration::from_millis(1)));
+
+	// The next window for `peer_a` starts with its next report.
+	assert!(limiter.should_report(peer_a.clone(), start + window));
+	assert!(!limiter.should_report(peer_a.clone(), start + window + Duration::from_secs(1)));
+	assert!(!limiter.should_report(peer_b.clone(), start + window));
+	assert!(limiter.should_report(peer_b, start + window + Duration::from_secs(1)));
+	assert_eq!(limiter.last_reports.len(), 2);
+}
@@ -129,7 +129,10 @@ mod tests {
 	use async_trait::async_trait;
 	use sc_network::{Event as NetworkEvent, IfDisconnected};
 	use sp_keyring::Sr25519Keyring;
-	use polkadot_node_network_protocol::{PeerId, request_response::request::Requests};
+	use polkadot_node_network_protocol::{
+		PeerId,
+		request_response::{request::Requests, ReqProtocolNames},
+	};
 
 	fn new_service() -> Service<TestNetwork, TestAuthorityDiscovery> {
 		Service::new()
@@ -183,7 +186,13 @@ mod tests {
 			Ok(())
 		}
 
-		async fn start_request<AD: AuthorityDiscovery>(&self, _: &mut AD, _: Requests, _: IfDisconnected) {
+		async fn start_request<AD: AuthorityDiscovery>(
+			&self,
+			_: &mut AD,
+			_: Requests,
+			_: &ReqProtocolNames,
+			_: IfDisconnected,
+		) {
 		}
 
 		fn report_peer(&self, _: PeerId, _: crate::Rep) {
@@ -32,9 +32,7 @@ use polkadot_subsystem::{
 };
 use polkadot_node_network_protocol::{
 	OurView, PeerId, UnifiedReputationChange as Rep, View, peer_set::PeerSet,
-	request_response::{
-		IncomingRequest, request::OutgoingResponse, v1::{CollationFetchingRequest, CollationFetchingResponse}
-	},
+	request_response::{IncomingRequest, request::OutgoingResponse, v1 as req_v1, v2 as req_v2},
 	v1 as protocol_v1,
 };
 use polkadot_node_subsystem_util::{
@@ -215,13 +213,45 @@ struct Collation {
 	status: CollationStatus,
 }
 
+/// An incoming collation fetch, of either version of the protocol.
+///
+/// Validators which know the second version request signed collations with it, older ones are
+/// still served the first version.
+enum CollationFetch {
+	V1(IncomingRequest<req_v1::CollationFetchingRequest>),
+	V2(IncomingRequest<req_v2::CollationFetchingRequest>),
+}
+
+impl CollationFetch {
+	fn peer(&self) -> PeerId {
+		match self {
+			CollationFetch::V1(req) => req.peer,
+			CollationFetch::V2(req) => req.peer,
+		}
+	}
+
+	fn relay_parent(&self) -> Hash {
+		match self {
+			CollationFetch::V1(req) => req.payload.relay_parent,
+			CollationFetch::V2(req) => req.payload.relay_parent,
+		}
+	}
+
+	fn para_id(&self) -> ParaId {
+		match self {
+			CollationFetch::V1(req) => req.payload.para_id,
+			CollationFetch::V2(req) => req.payload.para_id,
+		}
+	}
+}
+
 /// Stores the state for waiting collation fetches.
 #[derive(Default)]
 struct WaitingCollationFetches {
 	/// Is there currently a collation getting fetched?
 	collation_fetch_active: bool,
 	/// The collation fetches waiting to be fulfilled.
-	waiting: VecDeque<IncomingRequest<CollationFetchingRequest>>,
+	waiting: VecDeque<CollationFetch>,
 	/// All peers that are waiting or actively uploading.
 	///
 	/// We will not accept multiple requests from the same peer, otherwise our DoS protection of
@@ -680,89 +710,126 @@ where
 			}
 		},
 		CollationFetchingRequest(incoming) => {
-			let _span = state.span_per_relay_parent.get(&incoming.payload.relay_parent).map(|s| s.child("request-collation"));
-			match state.collating_on {
-				Some(our_para_id) => {
-					if our_para_id == incoming.payload.para_id {
-						let (receipt, pov) = if let Some(collation) = state.collations.get_mut(&incoming.payload.relay_parent) {
-							collation.status.advance_to_requested();
-							(collation.receipt.clone(), collation.pov.clone())
-						} else {
-							tracing::warn!(
-								target: LOG_TARGET,
-								relay_parent = %incoming.payload.relay_parent,
-								"received a `RequestCollation` for a relay parent we don't have collation stored.",
-							);
-
-							return Ok(());
-						};
-
-						state.metrics.on_collation_sent_requested();
-
-						let _span = _span.as_ref().map(|s| s.child("sending"));
-
-						let waiting = state.waiting_collation_fetches.entry(incoming.payload.relay_parent).or_default();
-
-						if !waiting.waiting_peers.insert(incoming.peer) {
-							tracing::debug!(
-								target: LOG_TARGET,
-								"Dropping incoming request as peer has a request in flight already."
-							);
-							ctx.send_message(
-								NetworkBridgeMessage::ReportPeer(incoming.peer, COST_APPARENT_FLOOD)
-							).await;
-							return Ok(())
-						}
-
-						if waiting.collation_fetch_active {
-							waiting.waiting.push_back(incoming);
-						} else {
-							waiting.collation_fetch_active = true;
-							send_collation(state, incoming, receipt, pov).await;
-						}
-					} else {
-						tracing::warn!(
-							target: LOG_TARGET,
-							for_para_id = %incoming.payload.para_id,
-							our_para_id = %our_para_id,
-							"received a `CollationFetchingRequest` for unexpected para_id",
-						);
-					}
-				}
-				None => {
+			handle_collation_fetch(ctx, state, CollationFetch::V1(incoming)).await;
+		}
+		CollationFetchingRequestV2(incoming) => {
+			handle_collation_fetch(ctx, state, CollationFetch::V2(incoming)).await;
+		}
+		_ => {},
+	}
+
+	Ok(())
+}
+
+/// Answer `incoming` right away, or queue it while another collation fetch is active.
+async fn handle_collation_fetch<Context>(
+	ctx: &mut Context,
+	state: &mut State,
+	incoming: CollationFetch,
+)
+where
+	Context: SubsystemContext<Message = CollatorProtocolMessage>,
+	Context: overseer::SubsystemContext<Message = CollatorProtocolMessage>,
+{
+	let _span = state.span_per_relay_parent.get(&incoming.relay_parent()).map(|s| s.child("request-collation"));
+	match state.collating_on {
+		Some(our_para_id) => {
+			if our_para_id == incoming.para_id() {
+				let (receipt, pov) = if let Some(collation) = state.collations.get_mut(&incoming.relay_parent()) {
+					collation.status.advance_to_requested();
+					(collation.receipt.clone(), collation.pov.clone())
+				} else {
 					tracing::warn!(
 						target: LOG_TARGET,
-						for_para_id = %incoming.payload.para_id,
-						"received a `RequestCollation` while not collating on any para",
+						relay_parent = %incoming.relay_parent(),
+						"received a `RequestCollation` for a relay parent we don't have collation stored.",
 					);
+
+					return;
+				};
+
+				state.metrics.on_collation_sent_requested();
+
+				let _span = _span.as_ref().map(|s| s.child("sending"));
+
+				let waiting = state.waiting_collation_fetches.entry(incoming.relay_parent()).or_default();
+
+				if !waiting.waiting_peers.insert(incoming.peer()) {
+					tracing::debug!(
+						target: LOG_TARGET,
+						"Dropping incoming request as peer has a request in flight already."
+					);
+					ctx.send_message(
+						NetworkBridgeMessage::ReportPeer(incoming.peer(), COST_APPARENT_FLOOD)
+					).await;
+					return
+				}
+
+				if waiting.collation_fetch_active {
+					waiting.waiting.push_back(incoming);
+				} else {
+					waiting.collation_fetch_active = true;
+					send_collation(state, incoming, receipt, pov).await;
 				}
+			} else {
+				tracing::warn!(
+					target: LOG_TARGET,
+					for_para_id = %incoming.para_id(),
+					our_para_id = %our_para_id,
+					"received a `CollationFetchingRequest` for unexpected para_id",
+				);
 			}
 		}
-		_ => {},
+		None => {
+			tracing::warn!(
+				target: LOG_TARGET,
+				for_para_id = %incoming.para_id(),
+				"received a `RequestCollation` while not collating on any para",
+			);
+		}
 	}
-
-	Ok(())
 }
 
 /// Issue a response to a previously requested collation.
 async fn send_collation(
 	state: &mut State,
-	request: IncomingRequest<CollationFetchingRequest>,
+	request: CollationFetch,
 	receipt: CandidateReceipt,
 	pov: PoV,
 ) {
 	let (tx, rx) = oneshot::channel();
 
-	let relay_parent = request.payload.relay_parent;
-	let peer_id = request.peer;
-
-	let response = OutgoingResponse {
-		result: Ok(CollationFetchingResponse::Collation(receipt, pov)),
-		reputation_changes: Vec::new(),
-		sent_feedback: Some(tx),
+	let relay_parent = request.relay_parent();
+	let peer_id = request.peer();
+
+	let sent = match request {
+		CollationFetch::V1(request) => request.send_outgoing_response(OutgoingResponse {
+			result: Ok(req_v1::CollationFetchingResponse::Collation(receipt, pov)),
+			reputation_changes: Vec::new(),
+			sent_feedback: Some(tx),
+		}),
+		CollationFetch::V2(request) => {
+			let pov_hash = pov.hash();
+			let payload = req_v2::collation_signature_payload(
+				&relay_parent,
+				&request.payload.para_id,
+				&pov_hash,
+			);
+			let collation = req_v2::SignedCollation {
+				receipt,
+				pov,
+				pov_hash,
+				signature: state.collator_pair.sign(&payload),
+			};
+			request.send_outgoing_response(OutgoingResponse {
+				result: Ok(req_v2::CollationFetchingResponse::Collation(collation)),
+				reputation_changes: Vec::new(),
+				sent_feedback: Some(tx),
+			})
+		}
 	};
 
-	if let Err(_) = request.send_outgoing_response(response) {
+	if let Err(_) = sent {
 		tracing::warn!(
 			target: LOG_TARGET,
 			"Sending collation response failed",
@@ -29,7 +29,10 @@ use sp_runtime::traits::AppVerify;
 use polkadot_node_network_protocol::{
 	our_view,
 	view,
-	request_response::request::IncomingRequest,
+	request_response::{
+		request::IncomingRequest,
+		v1::{CollationFetchingRequest, CollationFetchingResponse},
+	},
 };
 use polkadot_node_subsystem_util::TimeoutExt;
 use polkadot_primitives::v1::{
@@ -592,12 +595,14 @@ fn advertise_and_send_collation() {
 		assert_matches!(
 			rx.await,
 			Ok(full_response) => {
-				let CollationFetchingResponse::Collation(receipt, pov): CollationFetchingResponse
-					= CollationFetchingResponse::decode(
+				let (receipt, pov) = assert_matches!(
+					CollationFetchingResponse::decode(
 						&mut full_response.result
 						.expect("We should have a proper answer").as_ref()
-				)
-				.expect("Decoding should work");
+					)
+					.expect("Decoding should work"),
+					CollationFetchingResponse::Collation(receipt, pov) => (receipt, pov)
+				);
 				assert_eq!(receipt, candidate);
 				assert_eq!(pov, pov_block);
 			}
@@ -646,6 +651,60 @@ fn advertise_and_send_collation() {
 	});
 }
 
+#[test]
+fn signed_collations_are_sent_to_v2_requesters() {
+	let test_state = TestState::default();
+	let local_peer_id = test_state.local_peer_id.clone();
+	let collator_pair = test_state.collator_pair.clone();
+
+	test_harness(local_peer_id, collator_pair, |test_harness| async move {
+		let mut virtual_overseer = test_harness.virtual_overseer;
+
+		setup_system(&mut virtual_overseer, &test_state).await;
+
+		let DistributeCollation { candidate, pov_block } =
+			distribute_collation(&mut virtual_overseer, &test_state, true).await;
+
+		let peer = test_state.current_group_validator_peer_ids()[0].clone();
+		let validator_id = test_state.current_group_validator_authority_ids()[0].clone();
+		connect_peer(&mut virtual_overseer, peer.clone(), Some(validator_id)).await;
+		expect_declare_msg(&mut virtual_overseer, &test_state, &peer).await;
+		send_peer_view_change(&mut virtual_overseer, &peer, vec![test_state.relay_parent]).await;
+		expect_advertise_collation_msg(&mut virtual_overseer, &peer, test_state.relay_parent).await;
+
+		let (tx, rx) = oneshot::channel();
+		overseer_send(
+			&mut virtual_overseer,
+			CollatorProtocolMessage::CollationFetchingRequestV2(
+				IncomingRequest::new(
+					peer,
+					req_v2::CollationFetchingRequest {
+						relay_parent: test_state.relay_parent,
+						para_id: test_state.para_id,
+					},
+					tx,
+				)
+			)
+		).await;
+
+		let response = rx.await.expect("the collation is sent").result.expect("the request is not refused");
+		let collation = assert_matches!(
+			req_v2::CollationFetchingResponse::decode(&mut response.as_ref()).expect("Decoding should work"),
+			req_v2::CollationFetchingResponse::Collation(collation) => collation
+		);
+		assert_eq!(collation.receipt, candidate);
+		assert_eq!(collation.pov, pov_block);
+		assert_eq!(collation.pov_hash, pov_block.hash());
+		let payload = req_v2::collation_signature_payload(
+			&test_state.relay_parent,
+			&test_state.para_id,
+			&collation.pov_hash,
+		);
+		assert!(collation.signature.verify(&*payload, &test_state.collator_pair.public()));
+		virtual_overseer
+	});
+}
+
 #[test]
 fn send_only_one_collation_per_relay_parent_at_a_time() {
 	test_validator_send_sequence(|mut second_response_receiver, feedback_first_tx| async move {
@@ -931,12 +990,14 @@ where
 		let feedback_tx = assert_matches!(
 			rx.await,
 			Ok(full_response) => {
-				let CollationFetchingResponse::Collation(receipt, pov): CollationFetchingResponse
-					= CollationFetchingResponse::decode(
+				let (receipt, pov) = assert_matches!(
+					CollationFetchingResponse::decode(
 						&mut full_response.result
 						.expect("We should have a proper answer").as_ref()
-				)
-				.expect("Decoding should work");
+					)
+					.expect("Decoding should work"),
+					CollationFetchingResponse::Collation(receipt, pov) => (receipt, pov)
+				);
 				assert_eq!(receipt, candidate);
 				assert_eq!(pov, pov_block);
 
@@ -966,12 +1027,14 @@ where
 		assert_matches!(
 			rx.await,
 			Ok(full_response) => {
-				let CollationFetchingResponse::Collation(receipt, pov): CollationFetchingResponse
-					= CollationFetchingResponse::decode(
+				let (receipt, pov) = assert_matches!(
+					CollationFetchingResponse::decode(
 						&mut full_response.result
 						.expect("We should have a proper answer").as_ref()
-				)
-				.expect("Decoding should work");
+					)
+					.expect("Decoding should work"),
+					CollationFetchingResponse::Collation(receipt, pov) => (receipt, pov)
+				);
 				assert_eq!(receipt, candidate);
 				assert_eq!(pov, pov_block);
 
@@ -25,13 +25,15 @@ use futures::{
 use futures_timer::Delay;
 
 use sp_keystore::SyncCryptoStorePtr;
+use sp_runtime::traits::AppVerify;
 
 use polkadot_node_network_protocol::{
 	request_response as req_res, v1 as protocol_v1,
 	peer_set::PeerSet,
 	request_response::{
 		request::{Recipient, RequestError},
-		v1::{CollationFetchingRequest, CollationFetchingResponse},
+		v1::{self as req_v1, CollationFetchingResponse},
+		v2 as req_v2,
 		OutgoingRequest, Requests,
 	},
 	OurView, PeerId, UnifiedReputationChange as Rep, View,
@@ -169,9 +171,15 @@ impl metrics::Metrics for Metrics {
 	}
 }
 
+/// A response of a collator, of the protocol version the collation was requested with.
+enum CollationResponse {
+	V1(req_res::OutgoingResult<req_v1::CollationFetchingResponse>),
+	V2(req_res::OutgoingResult<req_v2::CollationFetchingResponse>),
+}
+
 struct PerRequest {
 	/// Responses from collator.
-	from_collator: Fuse<BoxFuture<'static, req_res::OutgoingResult<CollationFetchingResponse>>>,
+	from_collator: Fuse<BoxFuture<'static, CollationResponse>>,
 	/// Sender to forward to initial requester.
 	to_requester: oneshot::Sender<(CandidateReceipt, PoV)>,
 	/// A jaeger span corresponding to the lifetime of the request.
@@ -774,15 +782,19 @@ where
 		return;
 	}
 
-	let (full_request, response_recv) =
-		OutgoingRequest::new(Recipient::Peer(peer_id), CollationFetchingRequest {
-			relay_parent,
-			para_id,
-		});
-	let requests = Requests::CollationFetching(full_request);
+	tracing::debug!(
+		target: LOG_TARGET,
+		peer_id = %peer_id,
+		%para_id,
+		?relay_parent,
+		"Requesting collation",
+	);
 
+	// Collators not knowing signed collations yet are asked again with the first version, see
+	// `poll_collation_response`.
+	let from_collator = send_collation_request(ctx, &pending_collation, true).await;
 	let per_request = PerRequest {
-		from_collator: response_recv.boxed().fuse(),
+		from_collator,
 		to_requester: result,
 		span: state.span_per_relay_parent.get(&relay_parent).map(|s| {
 			s.child("collation-request")
@@ -790,22 +802,74 @@ where
 		}),
 	};
 
-	state.requested_collations.insert(
-		PendingCollation::new(relay_parent, &para_id, &peer_id),
-		per_request
-	);
+	state.requested_collations.insert(pending_collation, per_request);
+}
 
-	tracing::debug!(
-		target: LOG_TARGET,
-		peer_id = %peer_id,
-		%para_id,
-		?relay_parent,
-		"Requesting collation",
-	);
+/// Request the collation of `pending_collation`, signed if `signed` is set.
+///
+/// Returns the future of the response.
+async fn send_collation_request<Context>(
+	ctx: &mut Context,
+	pending_collation: &PendingCollation,
+	signed: bool,
+) -> Fuse<BoxFuture<'static, CollationResponse>>
+where
+	Context: overseer::SubsystemContext<Message=CollatorProtocolMessage>,
+	Context: SubsystemContext<Message=CollatorProtocolMessage>,
+{
+	let recipient = Recipient::Peer(pending_collation.peer_id.clone());
+	let relay_parent = pending_collation.relay_parent;
+	let para_id = pending_collation.para_id;
+	let (request, from_collator) = if signed {
+		let (request, response_recv) = OutgoingRequest::new(
+			recipient,
+			req_v2::CollationFetchingRequest { relay_parent, para_id },
+		);
+		(Requests::CollationFetchingV2(request), response_recv.map(CollationResponse::V2).boxed())
+	} else {
+		let (request, response_recv) = OutgoingRequest::new(
+			recipient,
+			req_v1::CollationFetchingRequest { relay_parent, para_id },
+		);
+		(Requests::CollationFetching(request), response_recv.map(CollationResponse::V1).boxed())
+	};
 
 	ctx.send_message(
-		NetworkBridgeMessage::SendRequests(vec![requests], IfDisconnected::ImmediateError)
+		NetworkBridgeMessage::SendRequests(vec![request], IfDisconnected::ImmediateError)
 	).await;
+	from_collator.fuse()
+}
+
+/// Check the pov hash and signature of a signed collation, turning it into an unsigned one.
+///
+/// The signature is checked against the collator of the candidate, over the relay parent and
+/// para the collation was requested for. Returns `None` if the collation is not properly signed.
+/// Collations of the wrong para are not checked, they are punished as such anyway.
+fn check_signed_collation(
+	pending_collation: &PendingCollation,
+	response: req_v2::CollationFetchingResponse,
+) -> Option<CollationFetchingResponse> {
+	let collation = match response {
+		req_v2::CollationFetchingResponse::Refused(reason) =>
+			return Some(CollationFetchingResponse::Refused(reason)),
+		req_v2::CollationFetchingResponse::Collation(collation) => collation,
+	};
+	let descriptor = collation.receipt.descriptor();
+	if descriptor.para_id != pending_collation.para_id {
+		return Some(CollationFetchingResponse::Collation(collation.receipt, collation.pov))
+	}
+	if collation.pov_hash != collation.pov.hash() || collation.pov_hash != descriptor.pov_hash {
+		return None
+	}
+	let payload = req_v2::collation_signature_payload(
+		&pending_collation.relay_parent,
+		&pending_collation.para_id,
+		&collation.pov_hash,
+	);
+	if !collation.signature.verify(&*payload, &descriptor.collator) {
+		return None
+	}
+	Some(CollationFetchingResponse::Collation(collation.receipt, collation.pov))
 }
 
 /// Networking message has been received.
@@ -1105,7 +1169,7 @@ where
 				);
 			}
 		}
-		CollationFetchingRequest(_) => {
+		CollationFetchingRequest(_) | CollationFetchingRequestV2(_) => {
 			tracing::warn!(
 				target: LOG_TARGET,
 				"CollationFetchingRequest message is not expected on the validator side of the protocol",
@@ -1345,7 +1409,10 @@ where
 /// Ready responses are handled, by logging and decreasing peer's reputation on error and by
 /// forwarding proper responses to the requester.
 ///
-/// Returns: `true` if `from_collator` future was ready.
+/// Collators not knowing the protocol of signed collations are sent the request again with the
+/// first version of the protocol.
+///
+/// Returns: `true` if `from_collator` future was ready and no request was sent again.
 async fn poll_collation_response<Context>(
 	ctx: &mut Context,
 	metrics: &Metrics,
@@ -1356,7 +1423,7 @@ async fn poll_collation_response<Context>(
 -> bool
 where
 	Context: overseer::SubsystemContext<Message=CollatorProtocolMessage>,
-	Context: SubsystemContext,
+	Context: SubsystemContext<Message=CollatorProtocolMessage>,
 {
 	if never!(per_req.from_collator.is_terminated()) {
 		tracing::error!(
@@ -1367,6 +1434,38 @@ where
 	}
 
 	if let Poll::Ready(response) = futures::poll!(&mut per_req.from_collator) {
+		let response = match response {
+			CollationResponse::V1(response) => response,
+			CollationResponse::V2(Err(err)) if err.is_unsupported_protocol() => {
+				tracing::debug!(
+					target: LOG_TARGET,
+					hash = ?pending_collation.relay_parent,
+					para_id = ?pending_collation.para_id,
+					peer_id = ?pending_collation.peer_id,
+					"Collator doesn't serve signed collations, requesting an unsigned one",
+				);
+				per_req.from_collator = send_collation_request(ctx, pending_collation, false).await;
+				return false
+			}
+			CollationResponse::V2(Err(err)) => Err(err),
+			CollationResponse::V2(Ok(response)) => match check_signed_collation(pending_collation, response) {
+				Some(response) => Ok(response),
+				None => {
+					tracing::debug!(
+						target: LOG_TARGET,
+						hash = ?pending_collation.relay_parent,
+						para_id = ?pending_collation.para_id,
+						peer_id = ?pending_collation.peer_id,
+						"Collator provided a collation with an invalid signature or pov hash",
+					);
+					modify_reputation(ctx, pending_collation.peer_id.clone(), COST_INVALID_SIGNATURE).await;
+					metrics.on_request(Err(()));
+					per_req.span.as_mut().map(|s| s.add_string_tag("success", "false"));
+					return true
+				}
+			},
+		};
+
 		let _span = spans.get(&pending_collation.relay_parent)
 				.map(|s| s.child("received-collation"));
 		let _timer = metrics.time_handle_collation_request_result();
@@ -1419,6 +1518,16 @@ where
 				// same can happen for penalties on timeouts, which we also have.
 				modify_reputation(ctx, pending_collation.peer_id.clone(), COST_REQUEST_TIMED_OUT).await;
 			}
+			Ok(CollationFetchingResponse::Refused(reason)) => {
+				tracing::debug!(
+					target: LOG_TARGET,
+					hash = ?pending_collation.relay_parent,
+					para_id = ?pending_collation.para_id,
+					peer_id = ?pending_collation.peer_id,
+					?reason,
+					"Collator refused to serve the collation"
+				);
+			}
 			Ok(CollationFetchingResponse::Collation(receipt, _))
 				if receipt.descriptor().para_id != pending_collation.para_id =>
 			{
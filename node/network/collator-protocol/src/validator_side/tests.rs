@@ -22,6 +22,7 @@ use sp_core::{crypto::Pair, Encode};
 use sp_keystore::SyncCryptoStore;
 use sp_keystore::testing::KeyStore as TestKeyStore;
 use sp_keyring::Sr25519Keyring;
+use sc_network::{OutboundFailure, RequestFailure};
 use assert_matches::assert_matches;
 
 use polkadot_primitives::v1::{
@@ -292,11 +293,19 @@ async fn assert_collator_disconnect(
 }
 
 /// Assert that a fetch collation request was send.
+///
+/// The signed collation is requested first, which is answered as a collator not knowing signed
+/// collations would, so this returns the response sender of the following unsigned request.
 async fn assert_fetch_collation_request(
 	virtual_overseer: &mut VirtualOverseer,
 	relay_parent: Hash,
 	para_id: ParaId,
 ) -> ResponseSender {
+	assert_fetch_signed_collation_request(virtual_overseer, relay_parent, para_id)
+		.await
+		.send(Err(RequestFailure::Network(OutboundFailure::UnsupportedProtocols)))
+		.expect("Sending response should succeed");
+
 	assert_matches!(
 		overseer_recv(virtual_overseer).await,
 		AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(reqs, IfDisconnected::ImmediateError)
@@ -315,6 +324,52 @@ async fn assert_fetch_collation_request(
 	})
 }
 
+/// Assert that a signed collation was requested.
+async fn assert_fetch_signed_collation_request(
+	virtual_overseer: &mut VirtualOverseer,
+	relay_parent: Hash,
+	para_id: ParaId,
+) -> ResponseSender {
+	assert_matches!(
+		overseer_recv(virtual_overseer).await,
+		AllMessages::NetworkBridge(NetworkBridgeMessage::SendRequests(reqs, IfDisconnected::ImmediateError)
+	) => {
+		let req = reqs.into_iter().next()
+			.expect("There should be exactly one request");
+		match req {
+			Requests::CollationFetchingV2(req) => {
+				let payload = req.payload;
+				assert_eq!(payload.relay_parent, relay_parent);
+				assert_eq!(payload.para_id, para_id);
+				req.pending_response
+			}
+			_ => panic!("Unexpected request"),
+		}
+	})
+}
+
+/// A collation of `pov` by `collator`, signed by `signer`.
+fn signed_collation(
+	relay_parent: Hash,
+	para_id: ParaId,
+	collator: &CollatorPair,
+	signer: &CollatorPair,
+	pov: PoV,
+) -> req_v2::SignedCollation {
+	let mut receipt = CandidateReceipt::default();
+	receipt.descriptor.para_id = para_id;
+	receipt.descriptor.relay_parent = relay_parent;
+	receipt.descriptor.pov_hash = pov.hash();
+	receipt.descriptor.collator = collator.public();
+	let payload = req_v2::collation_signature_payload(&relay_parent, &para_id, &pov.hash());
+	req_v2::SignedCollation {
+		receipt,
+		pov_hash: pov.hash(),
+		pov,
+		signature: signer.sign(&payload),
+	}
+}
+
 /// Connect and declare a collator
 async fn connect_and_declare_collator(
 	virtual_overseer: &mut VirtualOverseer,
@@ -689,6 +744,170 @@ fn fetch_collations_works() {
 	});
 }
 
+#[test]
+fn signed_collations_are_seconded() {
+	let test_state = TestState::default();
+
+	test_harness(|test_harness| async move {
+		let TestHarness {
+			mut virtual_overseer,
+		} = test_harness;
+
+		overseer_send(
+			&mut virtual_overseer,
+			CollatorProtocolMessage::NetworkBridgeUpdateV1(
+				NetworkBridgeEvent::OurViewChange(our_view![test_state.relay_parent])
+			),
+		).await;
+
+		respond_to_core_info_queries(&mut virtual_overseer, &test_state).await;
+
+		let peer_b = PeerId::random();
+		let collator = test_state.collators[0].clone();
+		connect_and_declare_collator(
+			&mut virtual_overseer,
+			peer_b.clone(),
+			collator.clone(),
+			test_state.chain_ids[0],
+		).await;
+		advertise_collation(&mut virtual_overseer, peer_b.clone(), test_state.relay_parent).await;
+
+		let response_channel = assert_fetch_signed_collation_request(
+			&mut virtual_overseer,
+			test_state.relay_parent,
+			test_state.chain_ids[0],
+		).await;
+
+		let pov = PoV { block_data: BlockData(vec![1]) };
+		let collation = signed_collation(
+			test_state.relay_parent,
+			test_state.chain_ids[0],
+			&collator,
+			&collator,
+			pov.clone(),
+		);
+		response_channel.send(Ok(
+			req_v2::CollationFetchingResponse::Collation(collation).encode()
+		)).expect("Sending response should succeed");
+
+		assert_candidate_backing_second(
+			&mut virtual_overseer,
+			test_state.relay_parent,
+			test_state.chain_ids[0],
+			&pov,
+		).await;
+
+		virtual_overseer
+	});
+}
+
+#[test]
+fn collations_with_invalid_signatures_are_punished() {
+	let test_state = TestState::default();
+
+	test_harness(|test_harness| async move {
+		let TestHarness {
+			mut virtual_overseer,
+		} = test_harness;
+
+		overseer_send(
+			&mut virtual_overseer,
+			CollatorProtocolMessage::NetworkBridgeUpdateV1(
+				NetworkBridgeEvent::OurViewChange(our_view![test_state.relay_parent])
+			),
+		).await;
+
+		respond_to_core_info_queries(&mut virtual_overseer, &test_state).await;
+
+		let peer_b = PeerId::random();
+		let collator = test_state.collators[0].clone();
+		connect_and_declare_collator(
+			&mut virtual_overseer,
+			peer_b.clone(),
+			collator.clone(),
+			test_state.chain_ids[0],
+		).await;
+		advertise_collation(&mut virtual_overseer, peer_b.clone(), test_state.relay_parent).await;
+
+		let response_channel = assert_fetch_signed_collation_request(
+			&mut virtual_overseer,
+			test_state.relay_parent,
+			test_state.chain_ids[0],
+		).await;
+
+		// Signed by another collator than the one of the candidate.
+		let collation = signed_collation(
+			test_state.relay_parent,
+			test_state.chain_ids[0],
+			&collator,
+			&test_state.collators[1],
+			PoV { block_data: BlockData(vec![1]) },
+		);
+		response_channel.send(Ok(
+			req_v2::CollationFetchingResponse::Collation(collation).encode()
+		)).expect("Sending response should succeed");
+
+		assert_matches!(
+			overseer_recv(&mut virtual_overseer).await,
+			AllMessages::NetworkBridge(NetworkBridgeMessage::ReportPeer(peer, rep)) => {
+				assert_eq!(peer, peer_b);
+				assert_eq!(rep, COST_INVALID_SIGNATURE);
+			}
+		);
+		assert!(
+			overseer_recv_with_timeout(&mut virtual_overseer, Duration::from_millis(30)).await.is_none(),
+			"The collation should not be seconded",
+		);
+
+		virtual_overseer
+	});
+}
+
+#[test]
+fn signed_collations_must_match_the_request_and_pov() {
+	let collator = CollatorPair::generate().0;
+	let relay_parent = Hash::repeat_byte(1);
+	let para_id = ParaId::from(1);
+	let pending_collation = PendingCollation::new(relay_parent, &para_id, &PeerId::random());
+	let pov = PoV { block_data: BlockData(vec![1]) };
+	let check = |collation| check_signed_collation(
+		&pending_collation,
+		req_v2::CollationFetchingResponse::Collation(collation),
+	);
+
+	assert_matches!(
+		check(signed_collation(relay_parent, para_id, &collator, &collator, pov.clone())),
+		Some(CollationFetchingResponse::Collation(_, received)) => assert_eq!(received, pov)
+	);
+
+	// Signed for another relay parent.
+	let mut collation = signed_collation(Hash::repeat_byte(2), para_id, &collator, &collator, pov.clone());
+	collation.receipt.descriptor.relay_parent = relay_parent;
+	assert!(check(collation).is_none());
+
+	// A pov hash not matching the pov.
+	let mut collation = signed_collation(relay_parent, para_id, &collator, &collator, pov.clone());
+	collation.pov = PoV { block_data: BlockData(vec![2]) };
+	assert!(check(collation).is_none());
+
+	// A pov hash not matching the descriptor.
+	let mut collation = signed_collation(relay_parent, para_id, &collator, &collator, pov.clone());
+	collation.receipt.descriptor.pov_hash = Hash::repeat_byte(3);
+	assert!(check(collation).is_none());
+
+	// Collations of another para are passed on, to be punished as such.
+	let collation = signed_collation(relay_parent, ParaId::from(2), &collator, &collator, pov);
+	assert_matches!(check(collation), Some(CollationFetchingResponse::Collation(..)));
+
+	assert_matches!(
+		check_signed_collation(
+			&pending_collation,
+			req_v2::CollationFetchingResponse::Refused(req_v1::RefusalReason::Busy),
+		),
+		Some(CollationFetchingResponse::Refused(req_v1::RefusalReason::Busy))
+	);
+}
+
 // Ensure that we fetch a second collation, after the first checked collation was found to be invalid.
 #[test]
 fn fetch_next_collation_on_invalid_collation() {
@@ -54,7 +54,9 @@ use polkadot_node_subsystem_util::{
 ///
 ///	The actual work of sending and keeping track of transmission attempts to each validator for a
 ///	particular dispute are done by [`SendTask`].  The purpose of the `DisputeSender` is to keep
-///	track of all ongoing disputes and start and clean up `SendTask`s accordingly.
+///	track of all ongoing disputes and start and clean up `SendTask`s accordingly. Requests of
+///	several disputes for the same validator are sent in a single `DisputeBatchRequest`, if the
+///	validator supports them.
 mod sender;
 use self::sender::{DisputeSender, TaskFinish};
 
@@ -80,6 +82,10 @@ use self::sender::{DisputeSender, TaskFinish};
 /// For successfully imported votes, we will confirm the receipt of the message back to the sender.
 /// This way a received confirmation guarantees, that the vote has been stored to disk by the
 /// receiver.
+///
+/// Batch requests are treated just the same, except that each of their disputes is checked and
+/// imported on its own and gets its own response: A single bad dispute does not keep the others
+/// of the batch from being imported.
 mod receiver;
 use self::receiver::DisputesReceiver;
 
@@ -218,10 +224,11 @@ where
 			DisputeDistributionMessage::SendDispute(dispute_msg) =>
 				self.disputes_sender.start_sender(ctx, &mut self.runtime, dispute_msg).await?,
 			// This message will only arrive once:
-			DisputeDistributionMessage::DisputeSendingReceiver(receiver) => {
+			DisputeDistributionMessage::DisputeSendingReceiver { single, batched } => {
 				let receiver = DisputesReceiver::new(
 					ctx.sender().clone(),
-					receiver,
+					single,
+					batched,
 					self.authority_discovery.clone(),
 					self.metrics.clone()
 				);
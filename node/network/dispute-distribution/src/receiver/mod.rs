@@ -19,7 +19,7 @@ use std::collections::HashSet;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures::FutureExt;
+use futures::{Future, FutureExt};
 use futures::Stream;
 use futures::future::{BoxFuture, poll_fn};
 use futures::stream::FusedStream;
@@ -36,9 +36,13 @@ use polkadot_node_network_protocol::{
 		request::OutgoingResponseSender,
 		v1::DisputeRequest,
 		v1::DisputeResponse,
+		v1::RefusalReason,
+		v2::DisputeBatchRequest,
+		v2::DisputeBatchResponse,
+		v2::MAX_DISPUTES_PER_BATCH,
 	},
 };
-use polkadot_node_primitives::DISPUTE_WINDOW;
+use polkadot_node_primitives::{DISPUTE_WINDOW, UncheckedDisputeMessage};
 use polkadot_node_subsystem_util::{
 	runtime,
 	runtime::RuntimeInfo,
@@ -62,9 +66,11 @@ const COST_INVALID_CANDIDATE: Rep = Rep::Malicious("Reported candidate was not a
 const COST_NOT_A_VALIDATOR: Rep = Rep::CostMajor("Reporting peer was not a validator.");
 
 /// How many statement imports we want to issue in parallel:
+///
+/// The imports of a batch request count as a single one.
 pub const MAX_PARALLEL_IMPORTS: usize = 10;
 
-/// State for handling incoming `DisputeRequest` messages.
+/// State for handling incoming `DisputeRequest` and `DisputeBatchRequest` messages.
 ///
 /// This is supposed to run as its own task in order to easily impose back pressure on the incoming
 /// request channel and at the same time to drop flood messages as fast as possible.
@@ -78,6 +84,9 @@ pub struct DisputesReceiver<Sender, AD> {
 	/// Channel to retrieve incoming requests from.
 	receiver: mpsc::Receiver<sc_network::config::IncomingRequest>,
 
+	/// Channel to retrieve incoming batch requests from.
+	batch_receiver: mpsc::Receiver<sc_network::config::IncomingRequest>,
+
 	/// Authority discovery service:
 	authority_discovery: AD,
 
@@ -108,12 +117,16 @@ enum MuxedMessage {
 
 	/// A new request has arrived and should be handled.
 	NewRequest(sc_network::config::IncomingRequest),
+
+	/// A new batch request has arrived and should be handled.
+	NewBatchRequest(sc_network::config::IncomingRequest),
 }
 
 impl MuxedMessage {
 	async fn receive(
 		pending_imports: &mut PendingImports,
 		pending_requests: &mut mpsc::Receiver<sc_network::config::IncomingRequest>,
+		pending_batch_requests: &mut mpsc::Receiver<sc_network::config::IncomingRequest>,
 	) -> FatalResult<MuxedMessage> {
 		poll_fn(|ctx| {
 			if let Poll::Ready(v) = pending_requests.poll_next_unpin(ctx) {
@@ -123,6 +136,13 @@ impl MuxedMessage {
 				};
 				return Poll::Ready(r)
 			}
+			if let Poll::Ready(v) = pending_batch_requests.poll_next_unpin(ctx) {
+				let r = match v {
+					None => Err(Fatal::RequestChannelFinished),
+					Some(msg) => Ok(MuxedMessage::NewBatchRequest(msg)),
+				};
+				return Poll::Ready(r)
+			}
 			// In case of Ready(None) return `Pending` below - we want to wait for the next request
 			// in that case.
 			if let Poll::Ready(Some(v)) = pending_imports.poll_next_unpin(ctx) {
@@ -141,6 +161,7 @@ where
 	pub fn new(
 		sender: Sender,
 		receiver: mpsc::Receiver<sc_network::config::IncomingRequest>,
+		batch_receiver: mpsc::Receiver<sc_network::config::IncomingRequest>,
 		authority_discovery: AD,
 		metrics: Metrics,
 	) -> Self {
@@ -152,6 +173,7 @@ where
 			runtime,
 			sender,
 			receiver,
+			batch_receiver,
 			authority_discovery,
 			pending_imports: PendingImports::new(),
 			// Size of MAX_PARALLEL_IMPORTS ensures we are going to immediately get rid of any
@@ -192,17 +214,19 @@ where
 
 		let msg = MuxedMessage::receive(
 			&mut self.pending_imports,
-			&mut self.receiver
+			&mut self.receiver,
+			&mut self.batch_receiver,
 		)
 		.await?;
 
-		let raw = match msg {
+		let (raw, batched) = match msg {
 			// We need to clean up futures, to make sure responses are sent:
 			MuxedMessage::ConfirmedImport(m_bad) => {
 				self.ban_bad_peer(m_bad)?;
 				return Ok(())
 			}
-			MuxedMessage::NewRequest(req) => req,
+			MuxedMessage::NewRequest(req) => (req, false),
+			MuxedMessage::NewBatchRequest(req) => (req, true),
 		};
 
 		self.metrics.on_received_request();
@@ -223,32 +247,80 @@ where
 			return Err(NonFatal::NotAValidator(peer).into())
 		}
 
-		let incoming = IncomingRequest::<DisputeRequest>::try_from_raw(
-			raw,
-			vec![COST_INVALID_REQUEST]
-		)
-		.map_err(NonFatal::FromRawRequest)?;
+		if batched {
+			let incoming = IncomingRequest::<DisputeBatchRequest>::try_from_raw(
+				raw,
+				vec![COST_INVALID_REQUEST]
+			)
+			.map_err(NonFatal::FromRawRequest)?;
+
+			let refusal = if incoming.payload.0.len() > MAX_DISPUTES_PER_BATCH {
+				tracing::debug!(
+					target: LOG_TARGET,
+					?peer,
+					disputes = incoming.payload.0.len(),
+					"Refusing batch request with too many disputes"
+				);
+				Some(RefusalReason::Invalid)
+			} else {
+				self.refusal_reason(&peer)
+			};
+			if let Some(reason) = refusal {
+				incoming.respond_refusal(reason).map_err(|_| NonFatal::SendResponse(peer))?;
+				return Ok(())
+			}
+
+			self.wait_for_free_slot().await?;
+			self.start_batch_import(incoming).await
+		} else {
+			let incoming = IncomingRequest::<DisputeRequest>::try_from_raw(
+				raw,
+				vec![COST_INVALID_REQUEST]
+			)
+			.map_err(NonFatal::FromRawRequest)?;
+
+			if let Some(reason) = self.refusal_reason(&peer) {
+				incoming.respond_refusal(reason).map_err(|_| NonFatal::SendResponse(peer))?;
+				return Ok(())
+			}
 
-		// Immediately drop requests from peers that already have requests in flight or have
-		// been banned recently (flood protection):
-		if self.pending_imports.peer_is_pending(&peer) || self.banned_peers.contains(&peer) {
+			self.wait_for_free_slot().await?;
+			self.start_import(incoming).await
+		}
+	}
+
+	/// Whether to immediately refuse requests from `peer`.
+	///
+	/// Peers that already have requests in flight or have been banned recently get refused (flood
+	/// protection).
+	fn refusal_reason(&self, peer: &PeerId) -> Option<RefusalReason> {
+		let refusal = if self.banned_peers.contains(peer) {
+			Some(RefusalReason::Invalid)
+		} else if self.pending_imports.peer_is_pending(peer) {
+			// The peer may try again, once its request in flight is answered.
+			Some(RefusalReason::Busy)
+		} else {
+			None
+		};
+		if let Some(reason) = refusal {
 			tracing::trace!(
 				target: LOG_TARGET,
 				?peer,
-				"Dropping message from peer (banned/pending import)"
+				?reason,
+				"Refusing message from peer (banned/pending import)"
 			);
-			return Ok(())
 		}
+		refusal
+	}
 
-		// Wait for a free slot:
+	/// Wait for a free import slot.
+	async fn wait_for_free_slot(&mut self) -> NonFatalResult<()> {
 		if self.pending_imports.len() >= MAX_PARALLEL_IMPORTS as usize {
 			// Wait for one to finish:
 			let r = self.pending_imports.next().await;
 			self.ban_bad_peer(r.expect("pending_imports.len() is greater 0. qed."))?;
 		}
-
-		// All good - initiate import.
-		self.start_import(incoming).await
+		Ok(())
 	}
 
 	/// Start importing votes for the given request.
@@ -261,17 +333,8 @@ where
 			peer, payload, pending_response,
 		} = incoming;
 
-		let info = self.runtime.get_session_info_by_index(
-			&mut self.sender,
-			payload.0.candidate_receipt.descriptor.relay_parent,
-			payload.0.session_index
-		)
-		.await?;
-
-		let votes_result = payload.0.try_into_signed_votes(&info.session_info);
-
-		let (candidate_receipt, valid_vote, invalid_vote) = match votes_result {
-			Err(()) => { // Signature invalid:
+		let confirmation_rx = match self.import_votes(payload.0).await? {
+			None => { // Signature invalid:
 				pending_response.send_outgoing_response(
 					OutgoingResponse {
 						result: Err(()),
@@ -283,9 +346,71 @@ where
 
 				return Err(From::from(NonFatal::InvalidSignature(peer)))
 			}
-			Ok(votes) => votes,
+			Some(confirmation_rx) => confirmation_rx,
 		};
 
+		self.pending_imports.push(peer, respond_to_request(peer, confirmation_rx, pending_response));
+		Ok(())
+	}
+
+	/// Start importing votes for every dispute of the given batch request.
+	///
+	/// Every dispute is checked and imported independently, so a bad one does not keep the others
+	/// from being imported.
+	async fn start_batch_import(
+		&mut self,
+		incoming: IncomingRequest<DisputeBatchRequest>,
+	) -> Result<()> {
+
+		let IncomingRequest {
+			peer, payload, pending_response,
+		} = incoming;
+
+		let mut imports = Vec::with_capacity(payload.0.len());
+		for message in payload.0 {
+			let import = match self.import_votes(message).await {
+				Ok(Some(confirmation_rx)) => BatchImport::Pending(confirmation_rx),
+				Ok(None) => {
+					tracing::debug!(
+						target: LOG_TARGET,
+						?peer,
+						"Dispute in batch request with invalid signatures"
+					);
+					BatchImport::InvalidSignature
+				}
+				Err(err) => {
+					log_error(Err(err))?;
+					BatchImport::Failed
+				}
+			};
+			imports.push(import);
+		}
+
+		self.pending_imports.push(peer, respond_to_batch_request(peer, imports, pending_response));
+		Ok(())
+	}
+
+	/// Check the signatures of the votes in `message` and send them to the dispute coordinator.
+	///
+	/// Returns: The receiver of the import result, or `None` if a signature was invalid.
+	async fn import_votes(
+		&mut self,
+		message: UncheckedDisputeMessage,
+	) -> Result<Option<oneshot::Receiver<ImportStatementsResult>>> {
+
+		let info = self.runtime.get_session_info_by_index(
+			&mut self.sender,
+			message.candidate_receipt.descriptor.relay_parent,
+			message.session_index
+		)
+		.await?;
+
+		let (candidate_receipt, valid_vote, invalid_vote) =
+			match message.try_into_signed_votes(&info.session_info) {
+				Err(()) => return Ok(None),
+				Ok(votes) => votes,
+			};
+
 		let (pending_confirmation, confirmation_rx) = oneshot::channel();
 		let candidate_hash = candidate_receipt.hash();
 		self.sender.send_message(
@@ -301,8 +426,7 @@ where
 		)
 		.await;
 
-		self.pending_imports.push(peer, confirmation_rx, pending_response);
-		Ok(())
+		Ok(Some(confirmation_rx))
 	}
 
 	/// Await an import and ban any misbehaving peers.
@@ -341,16 +465,16 @@ impl PendingImports {
 		}
 	}
 
+	/// Track the import of a request of `peer`, answered by `respond`.
 	pub fn push(
 		&mut self,
 		peer: PeerId,
-		handled: oneshot::Receiver<ImportStatementsResult>,
-		pending_response: OutgoingResponseSender<DisputeRequest>
+		respond: impl Future<Output = NonFatalResult<ImportStatementsResult>> + Send + 'static,
 	) {
 		self.peers.insert(peer);
 		self.futures.push(
 			async move {
-				let r = respond_to_request(peer, handled, pending_response).await;
+				let r = respond.await;
 				(peer, r)
 			}.boxed()
 		)
@@ -427,3 +551,68 @@ async fn respond_to_request(
 
 	Ok(result)
 }
+
+/// The import of a single dispute of a batch request.
+enum BatchImport {
+	/// The votes are being imported.
+	Pending(oneshot::Receiver<ImportStatementsResult>),
+	/// The votes were not imported, as a signature was invalid.
+	InvalidSignature,
+	/// The votes could not be checked, e.g. because session information was not available.
+	Failed,
+}
+
+// Future for `PendingImports`, for batch requests
+//
+// - Wait for all imports
+// - Punish peer, once per kind of offence
+// - Deliver a response per dispute
+//
+// Results in `InvalidImport` if any of the imports did.
+async fn respond_to_batch_request(
+	peer: PeerId,
+	imports: Vec<BatchImport>,
+	pending_response: OutgoingResponseSender<DisputeBatchRequest>
+) -> NonFatalResult<ImportStatementsResult> {
+
+	let mut result = ImportStatementsResult::ValidImport;
+	let mut invalid_signature = false;
+	let mut responses = Vec::with_capacity(imports.len());
+	for import in imports {
+		let response = match import {
+			BatchImport::Pending(handled) => match handled.await {
+				Ok(ImportStatementsResult::ValidImport) => DisputeResponse::Confirmed,
+				Ok(ImportStatementsResult::InvalidImport) => {
+					result = ImportStatementsResult::InvalidImport;
+					DisputeResponse::Refused(RefusalReason::Invalid)
+				}
+				// Import failed for some reason, the peer may try again.
+				Err(_) => DisputeResponse::Refused(RefusalReason::Busy),
+			},
+			BatchImport::InvalidSignature => {
+				invalid_signature = true;
+				DisputeResponse::Refused(RefusalReason::Invalid)
+			}
+			BatchImport::Failed => DisputeResponse::Refused(RefusalReason::Busy),
+		};
+		responses.push(response);
+	}
+
+	let mut reputation_changes = Vec::new();
+	if invalid_signature {
+		reputation_changes.push(COST_INVALID_SIGNATURE);
+	}
+	if result == ImportStatementsResult::InvalidImport {
+		reputation_changes.push(COST_INVALID_CANDIDATE);
+	}
+
+	pending_response
+		.send_outgoing_response(OutgoingResponse {
+			result: Ok(DisputeBatchResponse::Responses(responses)),
+			reputation_changes,
+			sent_feedback: None,
+		})
+		.map_err(|_| NonFatal::SendResponse(peer))?;
+
+	Ok(result)
+}
@@ -22,7 +22,9 @@ use futures::channel::{mpsc, oneshot};
 use polkadot_node_network_protocol::request_response::v1::DisputeRequest;
 use polkadot_node_primitives::{CandidateVotes, DisputeMessage, SignedDisputeStatement};
 use polkadot_node_subsystem_util::runtime::RuntimeInfo;
-use polkadot_primitives::v1::{CandidateHash, DisputeStatement, Hash, SessionIndex};
+use polkadot_primitives::v1::{
+	AuthorityDiscoveryId, CandidateHash, DisputeStatement, Hash, SessionIndex,
+};
 use polkadot_subsystem::{
 	ActiveLeavesUpdate, SubsystemContext,
 	messages::{AllMessages, DisputeCoordinatorMessage}
@@ -34,7 +36,7 @@ use polkadot_subsystem::{
 /// It is going to spawn real tasks as it sees fit for getting the votes of the particular dispute
 /// out.
 mod send_task;
-use send_task::SendTask;
+use send_task::{SendTask, TaskResult, send_requests};
 pub use send_task::TaskFinish;
 
 /// Error and [`Result`] type for sender
@@ -44,11 +46,17 @@ pub use error::{Result, Error, Fatal, NonFatal};
 use crate::{LOG_TARGET, Metrics};
 use self::error::NonFatalResult;
 
+/// Candidates whose requests need to be sent, per authority.
+///
+/// Collected from all `SendTask`s, so requests for the same authority can be batched.
+type PendingSends = HashMap<AuthorityDiscoveryId, Vec<CandidateHash>>;
+
 /// The `DisputeSender` keeps track of all ongoing disputes we need to send statements out.
 ///
 /// For each dispute a `SendTask` is responsible for sending to the concerned validators for that
 /// particular dispute. The `DisputeSender` keeps track of those tasks, informs them about new
-/// sessions/validator sets and cleans them up when they become obsolete.
+/// sessions/validator sets and cleans them up when they become obsolete. Requests of all tasks
+/// for the same authority are sent in batches.
 pub struct DisputeSender {
 	/// All heads we currently consider active.
 	active_heads: Vec<Hash>,
@@ -61,9 +69,12 @@ pub struct DisputeSender {
 	/// All ongoing dispute sendings this subsystem is aware of.
 	disputes: HashMap<CandidateHash, SendTask>,
 
-	/// Sender to be cloned for `SendTask`s.
+	/// Sender to be cloned for the tasks awaiting responses.
 	tx: mpsc::Sender<TaskFinish>,
 
+	/// Authorities which do not support batch requests.
+	no_batches: HashSet<AuthorityDiscoveryId>,
+
 	/// Metrics for reporting stats about sent requests.
 	metrics: Metrics,
 }
@@ -77,6 +88,7 @@ impl DisputeSender
 			active_sessions: HashMap::new(),
 			disputes: HashMap::new(),
 			tx,
+			no_batches: HashSet::new(),
 			metrics,
 		}
 	}
@@ -87,6 +99,19 @@ impl DisputeSender
 		ctx: &mut Context,
 		runtime: &mut RuntimeInfo,
 		msg: DisputeMessage,
+	) -> Result<()> {
+		let mut sends = PendingSends::new();
+		self.add_dispute(ctx, runtime, msg, &mut sends).await?;
+		self.send(ctx, sends).await
+	}
+
+	/// Create a `SendTask` for a particular new dispute, adding its requests to `sends`.
+	async fn add_dispute<Context: SubsystemContext>(
+		&mut self,
+		ctx: &mut Context,
+		runtime: &mut RuntimeInfo,
+		msg: DisputeMessage,
+		sends: &mut PendingSends,
 	) -> Result<()> {
 		let req: DisputeRequest = msg.into();
 		let candidate_hash = req.0.candidate_receipt.hash();
@@ -100,14 +125,11 @@ impl DisputeSender
 				return Ok(())
 			}
 			Entry::Vacant(vacant) => {
-				let send_task = SendTask::new(
-					ctx,
-					runtime,
-					&self.active_sessions,
-					self.tx.clone(),
-					req,
-				)
-				.await?;
+				let mut send_task = SendTask::new(req);
+				let authorities = send_task
+					.refresh_sends(ctx, runtime, &self.active_sessions)
+					.await?;
+				add_sends(sends, candidate_hash, authorities);
 				vacant.insert(send_task);
 			}
 		}
@@ -120,6 +142,7 @@ impl DisputeSender
 	/// - Get new authorities to send messages to.
 	/// - Get rid of obsolete tasks and disputes.
 	/// - Get dispute sending started in case we missed one for some reason (e.g. on node startup)
+	/// - Send all resulting requests, batching those for the same authority.
 	pub async fn update_leaves<Context: SubsystemContext>(
 		&mut self,
 		ctx: &mut Context,
@@ -147,15 +170,38 @@ impl DisputeSender
 			|candidate_hash, _| active_disputes.contains(candidate_hash)
 		);
 
-		for dispute in self.disputes.values_mut() {
+		let mut sends = PendingSends::new();
+		let collected = self.collect_sends(
+			ctx,
+			runtime,
+			have_new_sessions,
+			unknown_disputes,
+			&mut sends,
+		).await;
+		// Requests collected before any error still need to get out:
+		self.send(ctx, sends).await?;
+		collected
+	}
+
+	/// Refresh sends of existing disputes as needed and start sending for `unknown_disputes`.
+	async fn collect_sends<Context: SubsystemContext>(
+		&mut self,
+		ctx: &mut Context,
+		runtime: &mut RuntimeInfo,
+		have_new_sessions: bool,
+		unknown_disputes: Vec<(SessionIndex, CandidateHash)>,
+		sends: &mut PendingSends,
+	) -> Result<()> {
+		for (candidate_hash, dispute) in self.disputes.iter_mut() {
 			if have_new_sessions || dispute.has_failed_sends() {
-				dispute.refresh_sends(ctx, runtime, &self.active_sessions).await?;
+				let authorities = dispute.refresh_sends(ctx, runtime, &self.active_sessions).await?;
+				add_sends(sends, *candidate_hash, authorities);
 			}
 		}
 
 		// This should only be non-empty on startup, but if not - we got you covered:
 		for dispute in unknown_disputes {
-			self.start_send_for_dispute(ctx, runtime, dispute).await?
+			self.start_send_for_dispute(ctx, runtime, dispute, sends).await?
 		}
 		Ok(())
 	}
@@ -167,6 +213,10 @@ impl DisputeSender
 
 		self.metrics.on_sent_request(result.as_metrics_label());
 
+		if let TaskResult::BatchUnsupported = result {
+			self.no_batches.insert(receiver.clone());
+		}
+
 		let task = match self.disputes.get_mut(&candidate_hash) {
 			None => {
 				// Can happen when a dispute ends, with messages still in queue:
@@ -182,14 +232,15 @@ impl DisputeSender
 		task.on_finished_send(&receiver, result);
 	}
 
-	/// Call `start_sender` on all passed in disputes.
+	/// Call `add_dispute` on the passed in dispute.
 	///
-	/// Recover necessary votes for building up `DisputeMessage` and start sending for all of them.
+	/// Recover necessary votes for building up `DisputeMessage` and add its requests to `sends`.
 	async fn start_send_for_dispute<Context: SubsystemContext>(
 		&mut self,
 		ctx: &mut Context,
 		runtime: &mut RuntimeInfo,
 		dispute: (SessionIndex, CandidateHash),
+		sends: &mut PendingSends,
 	) -> Result<()> {
 		let (session_index, candidate_hash) = dispute;
 		// We need some relay chain head for context for receiving session info information:
@@ -295,7 +346,34 @@ impl DisputeSender
 		.map_err(NonFatal::InvalidDisputeFromCoordinator)?;
 
 		// Finally, get the party started:
-		self.start_sender(ctx, runtime, message).await
+		self.add_dispute(ctx, runtime, message, sends).await
+	}
+
+	/// Send the requests of `sends`, batching those for the same authority.
+	async fn send<Context: SubsystemContext>(
+		&mut self,
+		ctx: &mut Context,
+		sends: PendingSends,
+	) -> Result<()> {
+		let sends = sends
+			.into_iter()
+			.map(|(authority, candidates)| {
+				let requests = candidates
+					.iter()
+					.filter_map(|candidate_hash| self.disputes.get(candidate_hash))
+					.map(|task| task.request().clone())
+					.collect();
+				(authority, requests)
+			})
+			.collect();
+
+		let statuses = send_requests(ctx, self.tx.clone(), sends, &self.no_batches).await?;
+		for (authority, candidate_hash, handle) in statuses {
+			if let Some(task) = self.disputes.get_mut(&candidate_hash) {
+				task.on_request_sent(authority, handle);
+			}
+		}
+		Ok(())
 	}
 
 	/// Make active sessions correspond to currently active heads.
@@ -316,6 +394,17 @@ impl DisputeSender
 	}
 }
 
+/// Add the sends of the request of `candidate_hash` to `authorities`.
+fn add_sends(
+	sends: &mut PendingSends,
+	candidate_hash: CandidateHash,
+	authorities: Vec<AuthorityDiscoveryId>,
+) {
+	for authority in authorities {
+		sends.entry(authority).or_default().push(candidate_hash);
+	}
+}
+
 /// Retrieve the currently active sessions.
 ///
 /// List is all indices of all active sessions together with the head that was used for the query.
@@ -17,6 +17,7 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Arc;
 
 use futures::Future;
 use futures::FutureExt;
@@ -27,8 +28,9 @@ use futures::future::RemoteHandle;
 use polkadot_node_network_protocol::{
 	IfDisconnected,
 	request_response::{
-		OutgoingRequest, OutgoingResult, Recipient, Requests,
+		OutgoingRequest, OutgoingResult, Priority, Recipient, Requests,
 		v1::{DisputeRequest, DisputeResponse},
+		v2::{DisputeBatchRequest, DisputeBatchResponse, MAX_DISPUTES_PER_BATCH},
 	}
 };
 use polkadot_node_subsystem_util::runtime::RuntimeInfo;
@@ -61,15 +63,15 @@ pub struct SendTask {
 
 	/// Whether we have any tasks failed since the last refresh.
 	has_failed_sends: bool,
-
-	/// Sender to be cloned for tasks.
-	tx: mpsc::Sender<TaskFinish>,
 }
 
 /// Status of a particular vote/statement delivery to a particular validator.
 enum DeliveryStatus {
 	/// Request is still in flight.
-	Pending(RemoteHandle<()>),
+	///
+	/// The handle is shared with the other disputes of a batch request, the response is awaited
+	/// as long as any of them still needs it.
+	Pending(Arc<RemoteHandle<()>>),
 	/// Succeeded - no need to send request to this peer anymore.
 	Succeeded,
 }
@@ -85,7 +87,7 @@ pub struct TaskFinish {
 	pub result: TaskResult,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum TaskResult {
 	/// Task succeeded in getting the request to its peer.
 	Succeeded,
@@ -93,51 +95,47 @@ pub enum TaskResult {
 	///
 	/// It should be retried in that case.
 	Failed,
+	/// The peer does not support batch requests.
+	///
+	/// It should be retried with a request of its own in that case.
+	BatchUnsupported,
 }
 
 impl TaskResult {
 	pub fn as_metrics_label(&self) -> &'static str {
 		match self {
 			Self::Succeeded => SUCCEEDED,
-			Self::Failed => FAILED,
+			Self::Failed | Self::BatchUnsupported => FAILED,
 		}
 	}
 }
 
 impl SendTask
 {
-	/// Initiates sending a dispute message to peers.
-	pub async fn new<Context: SubsystemContext>(
-		ctx: &mut Context,
-		runtime: &mut RuntimeInfo,
-		active_sessions: &HashMap<SessionIndex,Hash>,
-		tx: mpsc::Sender<TaskFinish>,
-		request: DisputeRequest,
-	) -> Result<Self> {
-		let mut send_task = Self {
+	/// Create a task for sending a dispute message to peers.
+	///
+	/// Nothing is sent until `refresh_sends` determined the peers.
+	pub fn new(request: DisputeRequest) -> Self {
+		Self {
 			request,
 			deliveries: HashMap::new(),
 			has_failed_sends: false,
-			tx,
-		};
-		send_task.refresh_sends(
-			ctx,
-			runtime,
-			active_sessions,
-		).await?;
-		Ok(send_task)
+		}
 	}
 
 	/// Make sure we are sending to all relevant authorities.
 	///
-	/// This function is called at construction and should also be called whenever a session change
-	/// happens and on a regular basis to ensure we are retrying failed attempts.
+	/// This function needs to be called after construction and should also be called whenever a
+	/// session change happens and on a regular basis to ensure we are retrying failed attempts.
+	///
+	/// Returns: The authorities our request still needs to be sent to, which should be passed on
+	/// to [`send_requests`].
 	pub async fn refresh_sends<Context: SubsystemContext>(
 		&mut self,
 		ctx: &mut Context,
 		runtime: &mut RuntimeInfo,
 		active_sessions: &HashMap<SessionIndex, Hash>,
-	) -> Result<()> {
+	) -> Result<Vec<AuthorityDiscoveryId>> {
 		let new_authorities = self.get_relevant_validators(ctx, runtime, active_sessions).await?;
 
 		let add_authorities = new_authorities
@@ -149,17 +147,18 @@ impl SendTask
 		// Get rid of dead/irrelevant tasks/statuses:
 		self.deliveries.retain(|k, _| new_authorities.contains(k));
 
-		// Start any new tasks that are needed:
-		let new_statuses = send_requests(
-			ctx,
-			self.tx.clone(),
-			add_authorities,
-			self.request.clone(),
-		).await?;
-
-		self.deliveries.extend(new_statuses.into_iter());
 		self.has_failed_sends = false;
-		Ok(())
+		Ok(add_authorities)
+	}
+
+	/// The request we are sending.
+	pub fn request(&self) -> &DisputeRequest {
+		&self.request
+	}
+
+	/// Our request is in flight to `authority`, its response is awaited by the task of `handle`.
+	pub fn on_request_sent(&mut self, authority: AuthorityDiscoveryId, handle: Arc<RemoteHandle<()>>) {
+		self.deliveries.insert(authority, DeliveryStatus::Pending(handle));
 	}
 
 	/// Whether any sends have failed since the last refreshed.
@@ -170,7 +169,7 @@ impl SendTask
 	/// Handle a finished response waiting task.
 	pub fn on_finished_send(&mut self, authority: &AuthorityDiscoveryId, result: TaskResult) {
 		match result {
-			TaskResult::Failed => {
+			TaskResult::Failed | TaskResult::BatchUnsupported => {
 				tracing::warn!(
 					target: LOG_TARGET,
 					candidate = ?self.request.0.candidate_receipt.hash(),
@@ -247,37 +246,72 @@ impl SendTask
 }
 
 
-/// Start sending of the given message to all given authorities.
+/// Start sending the given requests to their authorities.
 ///
-/// And spawn tasks for handling the response.
-async fn send_requests<Context: SubsystemContext>(
+/// Several requests for the same authority are sent in batches of up to
+/// `MAX_DISPUTES_PER_BATCH`, unless the authority is known not to support them. Tasks are spawned
+/// for handling the responses.
+///
+/// Returns: The handles of the spawned tasks, for the authority and candidate of each request.
+pub async fn send_requests<Context: SubsystemContext>(
 	ctx: &mut Context,
 	tx: mpsc::Sender<TaskFinish>,
-	receivers: Vec<AuthorityDiscoveryId>,
-	req: DisputeRequest,
-) -> Result<HashMap<AuthorityDiscoveryId, DeliveryStatus>> {
-	let mut statuses = HashMap::with_capacity(receivers.len());
-	let mut reqs = Vec::with_capacity(receivers.len());
-
-	for receiver in receivers {
-		let (outgoing, pending_response) = OutgoingRequest::new(
-			Recipient::Authority(receiver.clone()),
-			req.clone(),
-		);
-
-		reqs.push(Requests::DisputeSending(outgoing));
-
-		let fut = wait_response_task(
-			pending_response,
-			req.0.candidate_receipt.hash(),
-			receiver.clone(),
-			tx.clone(),
-		);
+	sends: Vec<(AuthorityDiscoveryId, Vec<DisputeRequest>)>,
+	no_batches: &HashSet<AuthorityDiscoveryId>,
+) -> Result<Vec<(AuthorityDiscoveryId, CandidateHash, Arc<RemoteHandle<()>>)>> {
+	let mut statuses = Vec::new();
+	let mut reqs = Vec::with_capacity(sends.len());
+
+	for (receiver, requests) in sends {
+		let batch_size = if no_batches.contains(&receiver) { 1 } else { MAX_DISPUTES_PER_BATCH };
+		for chunk in requests.chunks(batch_size) {
+			let candidate_hashes: Vec<_> = chunk
+				.iter()
+				.map(|req| req.0.candidate_receipt.hash())
+				.collect();
+
+			let fut = if let [req] = chunk {
+				let (outgoing, pending_response) = OutgoingRequest::new_with_priority(
+					Recipient::Authority(receiver.clone()),
+					req.clone(),
+					Priority::Critical,
+				);
+				reqs.push(Requests::DisputeSending(outgoing));
+				wait_response_task(
+					pending_response,
+					candidate_hashes[0],
+					receiver.clone(),
+					tx.clone(),
+				).boxed()
+			} else {
+				let (outgoing, pending_response) = OutgoingRequest::new_with_priority(
+					Recipient::Authority(receiver.clone()),
+					DisputeBatchRequest(chunk.iter().map(|req| req.0.clone()).collect()),
+					Priority::Critical,
+				);
+				reqs.push(Requests::DisputeBatchSending(outgoing));
+				wait_batch_response_task(
+					pending_response,
+					candidate_hashes.clone(),
+					receiver.clone(),
+					tx.clone(),
+				).boxed()
+			};
+
+			let (remote, remote_handle) = fut.remote_handle();
+			ctx.spawn("dispute-sender", remote.boxed())
+				.map_err(Fatal::SpawnTask)?;
+			let remote_handle = Arc::new(remote_handle);
+			statuses.extend(
+				candidate_hashes
+					.into_iter()
+					.map(|candidate_hash| (receiver.clone(), candidate_hash, remote_handle.clone()))
+			);
+		}
+	}
 
-		let (remote, remote_handle) = fut.remote_handle();
-		ctx.spawn("dispute-sender", remote.boxed())
-			.map_err(Fatal::SpawnTask)?;
-		statuses.insert(receiver, DeliveryStatus::Pending(remote_handle));
+	if reqs.is_empty() {
+		return Ok(statuses)
 	}
 
 	let msg = NetworkBridgeMessage::SendRequests(
@@ -308,6 +342,16 @@ async fn wait_response_task(
 			);
 			TaskFinish { candidate_hash, receiver, result: TaskResult::Failed}
 		}
+		Ok(DisputeResponse::Refused(reason)) => {
+			tracing::debug!(
+				target: LOG_TARGET,
+				%candidate_hash,
+				%receiver,
+				?reason,
+				"Node refused our dispute statements."
+			);
+			TaskFinish { candidate_hash, receiver, result: TaskResult::Failed}
+		}
 		Ok(DisputeResponse::Confirmed) => {
 			tracing::trace!(
 				target: LOG_TARGET,
@@ -326,3 +370,68 @@ async fn wait_response_task(
 		);
 	}
 }
+
+/// Future to be spawned in a task for awaiting the response to a batch request.
+///
+/// Reports a result for each dispute of the batch.
+async fn wait_batch_response_task(
+	pending_response: impl Future<Output = OutgoingResult<DisputeBatchResponse>>,
+	candidate_hashes: Vec<CandidateHash>,
+	receiver: AuthorityDiscoveryId,
+	mut tx: mpsc::Sender<TaskFinish>,
+) {
+	let results: Vec<_> = match pending_response.await {
+		Err(err) => {
+			tracing::warn!(
+				target: LOG_TARGET,
+				%receiver,
+				%err,
+				"Error sending batch of dispute statements to node."
+			);
+			let result = if err.is_unsupported_protocol() {
+				TaskResult::BatchUnsupported
+			} else {
+				TaskResult::Failed
+			};
+			candidate_hashes.into_iter().map(|candidate_hash| (candidate_hash, result)).collect()
+		}
+		Ok(DisputeBatchResponse::Refused(reason)) => {
+			tracing::debug!(
+				target: LOG_TARGET,
+				%receiver,
+				?reason,
+				"Node refused our batch of dispute statements."
+			);
+			candidate_hashes.into_iter().map(|candidate_hash| (candidate_hash, TaskResult::Failed)).collect()
+		}
+		Ok(DisputeBatchResponse::Responses(responses)) => candidate_hashes
+			.into_iter()
+			.enumerate()
+			.map(|(i, candidate_hash)| match responses.get(i) {
+				Some(DisputeResponse::Confirmed) => (candidate_hash, TaskResult::Succeeded),
+				response => {
+					tracing::debug!(
+						target: LOG_TARGET,
+						%candidate_hash,
+						%receiver,
+						?response,
+						"Node did not confirm our dispute statements of a batch."
+					);
+					(candidate_hash, TaskResult::Failed)
+				}
+			})
+			.collect(),
+	};
+
+	for (candidate_hash, result) in results {
+		let msg = TaskFinish { candidate_hash, receiver: receiver.clone(), result };
+		if let Err(err) = tx.feed(msg).await {
+			tracing::debug!(
+				target: LOG_TARGET,
+				%err,
+				"Failed to notify susystem about dispute sending result."
+			);
+			return
+		}
+	}
+}
@@ -33,7 +33,11 @@ use futures_timer::Delay;
 use parity_scale_codec::{Encode, Decode};
 
 use polkadot_node_network_protocol::PeerId;
-use polkadot_node_network_protocol::request_response::v1::DisputeRequest;
+use polkadot_node_network_protocol::request_response::v1::{DisputeRequest, RefusalReason};
+use polkadot_node_network_protocol::request_response::v2::{
+	DisputeBatchRequest, DisputeBatchResponse, MAX_DISPUTES_PER_BATCH,
+};
+use sc_network::{OutboundFailure, RequestFailure};
 use sp_keyring::Sr25519Keyring;
 
 use polkadot_node_network_protocol::{IfDisconnected, request_response::{Recipient, Requests, v1::DisputeResponse}};
@@ -52,7 +56,7 @@ use crate::{DisputeDistributionSubsystem, LOG_TARGET, Metrics};
 use self::mock::{
 	ALICE_INDEX, FERDIE_INDEX, make_candidate_receipt, make_dispute_message,
 	MOCK_AUTHORITY_DISCOVERY, MOCK_SESSION_INDEX, MOCK_SESSION_INFO, MOCK_NEXT_SESSION_INDEX,
-	MOCK_NEXT_SESSION_INFO, FERDIE_DISCOVERY_KEY,
+	MOCK_NEXT_SESSION_INFO, MOCK_VALIDATORS_DISCOVERY_KEYS, FERDIE_DISCOVERY_KEY,
 };
 
 /// Useful mock providers.
@@ -63,7 +67,7 @@ fn send_dispute_sends_dispute() {
 	let test = |mut handle: TestSubsystemContextHandle<DisputeDistributionMessage>|
 		async move {
 
-			let (_, _) = handle_subsystem_startup(&mut handle, None).await;
+			let (_, _, _) = handle_subsystem_startup(&mut handle, None).await;
 
 			let relay_parent = Hash::random();
 			let candidate = make_candidate_receipt(relay_parent);
@@ -113,7 +117,7 @@ fn send_dispute_sends_dispute() {
 fn received_request_triggers_import() {
 	let test = |mut handle: TestSubsystemContextHandle<DisputeDistributionMessage>|
 		async move {
-			let (_, mut req_tx) = handle_subsystem_startup(&mut handle, None).await;
+			let (_, mut req_tx, _) = handle_subsystem_startup(&mut handle, None).await;
 
 			let relay_parent = Hash::random();
 			let candidate = make_candidate_receipt(relay_parent);
@@ -142,8 +146,8 @@ fn received_request_triggers_import() {
 
 			// Nested valid and invalid import.
 			//
-			// Nested requests from same peer should get dropped. For the invalid request even
-			// subsequent requests should get dropped.
+			// Nested requests from same peer should get refused. For the invalid request even
+			// subsequent requests should get refused.
 			nested_network_dispute_request(
 				&mut handle,
 				&mut req_tx,
@@ -160,7 +164,7 @@ fn received_request_triggers_import() {
 						ImportStatementsResult::ValidImport,
 						false,
 						move |_, req_tx, message| async move {
-							// Another request from Alice should get dropped (request already in
+							// Another request from Alice should get refused (request already in
 							// flight):
 							{
 								let rx_response = send_network_dispute_request(
@@ -169,18 +173,9 @@ fn received_request_triggers_import() {
 									message.clone(),
 								).await;
 
-								assert_matches!(
-									rx_response.await,
-									Err(err) => {
-										tracing::trace!(
-											target: LOG_TARGET,
-											?err,
-											"Request got dropped - other request already in flight"
-										);
-									}
-								);
+								assert_refused(rx_response.await, RefusalReason::Busy);
 							}
-							// Another request from Bob should get dropped (request already in
+							// Another request from Bob should get refused (request already in
 							// flight):
 							{
 								let rx_response = send_network_dispute_request(
@@ -189,16 +184,7 @@ fn received_request_triggers_import() {
 									message.clone(),
 								).await;
 
-								assert_matches!(
-									rx_response.await,
-									Err(err) => {
-										tracing::trace!(
-											target: LOG_TARGET,
-											?err,
-											"Request got dropped - other request already in flight"
-										);
-									}
-								);
+								assert_refused(rx_response.await, RefusalReason::Busy);
 							}
 						}
 					)
@@ -212,16 +198,7 @@ fn received_request_triggers_import() {
 					message.clone().into()
 				).await;
 
-				assert_matches!(
-					rx_response.await,
-					Err(err) => {
-						tracing::trace!(
-							target: LOG_TARGET,
-							?err,
-							"Request got dropped - peer is banned."
-							);
-					}
-				);
+				assert_refused(rx_response.await, RefusalReason::Invalid);
 			}
 
 			// But should work fine for Bob:
@@ -249,7 +226,7 @@ fn disputes_are_recovered_at_startup() {
 			let relay_parent = Hash::random();
 			let candidate = make_candidate_receipt(relay_parent);
 
-			let (_, _) = handle_subsystem_startup(&mut handle, Some(candidate.hash())).await;
+			let (_, _, _) = handle_subsystem_startup(&mut handle, Some(candidate.hash())).await;
 
 			let message =
 				make_dispute_message(candidate.clone(), ALICE_INDEX, FERDIE_INDEX,).await;
@@ -305,7 +282,7 @@ fn send_dispute_gets_cleaned_up() {
 	let test = |mut handle: TestSubsystemContextHandle<DisputeDistributionMessage>|
 		async move {
 
-			let (old_head, _) = handle_subsystem_startup(&mut handle, None).await;
+			let (old_head, _, _) = handle_subsystem_startup(&mut handle, None).await;
 
 			let relay_parent = Hash::random();
 			let candidate = make_candidate_receipt(relay_parent);
@@ -372,7 +349,7 @@ fn dispute_retries_and_works_across_session_boundaries() {
 	let test = |mut handle: TestSubsystemContextHandle<DisputeDistributionMessage>|
 		async move {
 
-			let (old_head, _) = handle_subsystem_startup(&mut handle, None).await;
+			let (old_head, _, _) = handle_subsystem_startup(&mut handle, None).await;
 
 			let relay_parent = Hash::random();
 			let candidate = make_candidate_receipt(relay_parent);
@@ -466,6 +443,206 @@ fn dispute_retries_and_works_across_session_boundaries() {
 	test_harness(test);
 }
 
+#[test]
+fn batch_requests_are_imported_per_dispute() {
+	let test = |mut handle: TestSubsystemContextHandle<DisputeDistributionMessage>|
+		async move {
+			let (_, _, mut batch_req_tx) = handle_subsystem_startup(&mut handle, None).await;
+
+			let mut messages = Vec::new();
+			for _ in 0..MAX_DISPUTES_PER_BATCH {
+				let candidate = make_candidate_receipt(Hash::random());
+				let message: UncheckedDisputeMessage =
+					make_dispute_message(candidate, ALICE_INDEX, FERDIE_INDEX,).await.into();
+				messages.push(message);
+			}
+			// One bad signature among them:
+			const BAD: usize = 3;
+			messages[BAD].valid_vote.signature = messages[BAD].invalid_vote.signature.clone();
+
+			let rx_response = send_network_dispute_batch_request(
+				&mut batch_req_tx,
+				MOCK_AUTHORITY_DISCOVERY.get_peer_id_by_authority(Sr25519Keyring::Alice),
+				DisputeBatchRequest(messages.clone()),
+			).await;
+
+			// Subsystem needs `SessionInfo` for checking signatures:
+			assert_matches!(
+				handle.recv().await,
+				AllMessages::RuntimeApi(RuntimeApiMessage::Request(
+					_,
+					RuntimeApiRequest::SessionInfo(session_index, tx)
+				)) => {
+					assert_eq!(session_index, MOCK_SESSION_INDEX);
+					tx.send(Ok(Some(MOCK_SESSION_INFO.clone()))).expect("Receiver should stay alive.");
+				}
+			);
+
+			// All other disputes get imported:
+			for message in messages.iter().enumerate().filter(|(i, _)| *i != BAD).map(|(_, m)| m) {
+				assert_matches!(
+					handle.recv().await,
+					AllMessages::DisputeCoordinator(
+						DisputeCoordinatorMessage::ImportStatements {
+							candidate_hash,
+							pending_confirmation,
+							..
+						}
+					) => {
+						assert_eq!(candidate_hash, message.candidate_receipt.hash());
+						pending_confirmation.send(ImportStatementsResult::ValidImport).unwrap();
+					}
+				);
+			}
+
+			let response = rx_response.await.expect("batch requests are answered");
+			// Peer gets punished for the bad signature:
+			assert_eq!(response.reputation_changes.len(), 1);
+			let decoded = DisputeBatchResponse::decode(&mut response.result.unwrap().as_slice()).unwrap();
+			let expected = (0..MAX_DISPUTES_PER_BATCH)
+				.map(|i| if i == BAD {
+					DisputeResponse::Refused(RefusalReason::Invalid)
+				} else {
+					DisputeResponse::Confirmed
+				})
+				.collect();
+			assert_eq!(decoded, DisputeBatchResponse::Responses(expected));
+
+			// Batches of too many disputes get refused right away:
+			messages.push(messages[0].clone());
+			let rx_response = send_network_dispute_batch_request(
+				&mut batch_req_tx,
+				MOCK_AUTHORITY_DISCOVERY.get_peer_id_by_authority(Sr25519Keyring::Bob),
+				DisputeBatchRequest(messages),
+			).await;
+			let response = rx_response.await.expect("refused requests are answered");
+			assert_eq!(
+				response.result,
+				Ok(DisputeBatchResponse::Refused(RefusalReason::Invalid).encode()),
+			);
+
+			conclude(&mut handle).await;
+	};
+	test_harness(test);
+}
+
+#[test]
+fn failed_sends_are_retried_in_batches() {
+	let test = |mut handle: TestSubsystemContextHandle<DisputeDistributionMessage>|
+		async move {
+
+			let (old_head, _, _) = handle_subsystem_startup(&mut handle, None).await;
+
+			let expected_receivers: HashSet<_> = MOCK_SESSION_INFO
+				.discovery_keys
+				.clone()
+				.into_iter()
+				.filter(|a| *a != *FERDIE_DISCOVERY_KEY)
+				.collect();
+
+			let mut candidate_hashes = Vec::new();
+			for need_session_info in [true, false].iter().copied() {
+				let candidate = make_candidate_receipt(Hash::random());
+				candidate_hashes.push(candidate.hash());
+				let message = make_dispute_message(candidate, ALICE_INDEX, FERDIE_INDEX,).await;
+				handle.send(
+					FromOverseer::Communication {
+						msg: DisputeDistributionMessage::SendDispute(message)
+					}
+				).await;
+				if need_session_info {
+					assert_matches!(
+						handle.recv().await,
+						AllMessages::RuntimeApi(RuntimeApiMessage::Request(
+							_,
+							RuntimeApiRequest::SessionInfo(_, tx)
+						)) => {
+							tx.send(Ok(Some(MOCK_SESSION_INFO.clone()))).expect("Receiver should stay alive.");
+						}
+					);
+				}
+				// Requests don't get confirmed:
+				check_sent_requests(&mut handle, expected_receivers.clone(), false).await;
+			}
+
+			// Give tasks a chance to finish:
+			Delay::new(Duration::from_millis(20)).await;
+
+			// Trigger retry, of both disputes in a single request per authority:
+			let old_head2 = Hash::random();
+			activate_leaf(
+				&mut handle,
+				old_head2,
+				Some(old_head),
+				MOCK_SESSION_INDEX,
+				None,
+				candidate_hashes.iter().map(|c| (MOCK_SESSION_INDEX, *c)).collect(),
+			).await;
+
+			let unsupporting = Recipient::Authority(
+				MOCK_VALIDATORS_DISCOVERY_KEYS.get(&Sr25519Keyring::Alice).unwrap().clone()
+			);
+			assert_matches!(
+				handle.recv().await,
+				AllMessages::NetworkBridge(
+					NetworkBridgeMessage::SendRequests(reqs, IfDisconnected::TryConnect)
+				) => {
+					assert_eq!(reqs.len(), expected_receivers.len());
+					for req in reqs {
+						let req = assert_matches!(req, Requests::DisputeBatchSending(req) => req);
+						let batched: HashSet<_> = req.payload.0
+							.iter()
+							.map(|m| m.candidate_receipt.hash())
+							.collect();
+						assert_eq!(batched, candidate_hashes.iter().copied().collect());
+						let response = if req.peer == unsupporting {
+							Err(RequestFailure::Network(OutboundFailure::UnsupportedProtocols))
+						} else {
+							Ok(DisputeBatchResponse::Responses(
+								vec![DisputeResponse::Confirmed, DisputeResponse::Confirmed]
+							).encode())
+						};
+						req.pending_response.send(response)
+							.expect("Subsystem should be listening for a response.");
+					}
+				}
+			);
+
+			// Give tasks a chance to finish:
+			Delay::new(Duration::from_millis(20)).await;
+
+			// Only the authority not supporting batches gets the disputes again, one by one:
+			activate_leaf(
+				&mut handle,
+				Hash::random(),
+				Some(old_head2),
+				MOCK_SESSION_INDEX,
+				None,
+				candidate_hashes.iter().map(|c| (MOCK_SESSION_INDEX, *c)).collect(),
+			).await;
+
+			assert_matches!(
+				handle.recv().await,
+				AllMessages::NetworkBridge(
+					NetworkBridgeMessage::SendRequests(reqs, IfDisconnected::TryConnect)
+				) => {
+					let mut sent = HashSet::new();
+					for req in reqs {
+						let req = assert_matches!(req, Requests::DisputeSending(req) => req);
+						assert_eq!(req.peer, unsupporting);
+						sent.insert(req.payload.0.candidate_receipt.hash());
+						req.pending_response.send(Ok(DisputeResponse::Confirmed.encode()))
+							.expect("Subsystem should be listening for a response.");
+					}
+					assert_eq!(sent, candidate_hashes.iter().copied().collect());
+				}
+			);
+
+			conclude(&mut handle).await;
+	};
+	test_harness(test);
+}
+
 async fn send_network_dispute_request(
 	req_tx: &mut mpsc::Sender<sc_network::config::IncomingRequest>,
 	peer: PeerId,
@@ -481,6 +658,29 @@ async fn send_network_dispute_request(
 	rx_response
 }
 
+async fn send_network_dispute_batch_request(
+	batch_req_tx: &mut mpsc::Sender<sc_network::config::IncomingRequest>,
+	peer: PeerId,
+	request: DisputeBatchRequest,
+) -> oneshot::Receiver<sc_network::config::OutgoingResponse> {
+	let (pending_response, rx_response) = oneshot::channel();
+	let req = sc_network::config::IncomingRequest {
+		peer,
+		payload: request.encode(),
+		pending_response,
+	};
+	batch_req_tx.feed(req).await.unwrap();
+	rx_response
+}
+
+fn assert_refused(
+	response: Result<sc_network::config::OutgoingResponse, oneshot::Canceled>,
+	reason: RefusalReason,
+) {
+	let response = response.expect("refused requests are answered");
+	assert_eq!(response.result, Ok(DisputeResponse::Refused(reason).encode()));
+}
+
 /// Send request and handle its reactions.
 ///
 /// Passed in function will be called while votes are still being imported.
@@ -705,16 +905,24 @@ async fn check_sent_requests(
 	);
 }
 
-/// Initialize subsystem and return request sender needed for sending incoming requests to the
-/// subsystem.
+/// Initialize subsystem and return request senders needed for sending incoming requests and
+/// batch requests to the subsystem.
 async fn handle_subsystem_startup(
 	handle: &mut TestSubsystemContextHandle<DisputeDistributionMessage>,
 	ongoing_dispute: Option<CandidateHash>,
-) -> (Hash, mpsc::Sender<sc_network::config::IncomingRequest>) {
+) -> (
+	Hash,
+	mpsc::Sender<sc_network::config::IncomingRequest>,
+	mpsc::Sender<sc_network::config::IncomingRequest>,
+) {
 	let (request_tx, request_rx) = mpsc::channel(5);
+	let (batch_request_tx, batch_request_rx) = mpsc::channel(5);
 	handle.send(
 		FromOverseer::Communication {
-			msg: DisputeDistributionMessage::DisputeSendingReceiver(request_rx),
+			msg: DisputeDistributionMessage::DisputeSendingReceiver {
+				single: request_rx,
+				batched: batch_request_rx,
+			},
 		}
 	).await;
 
@@ -727,7 +935,7 @@ async fn handle_subsystem_startup(
 		Some(MOCK_SESSION_INFO.clone()),
 		ongoing_dispute.into_iter().map(|c| (MOCK_SESSION_INDEX, c)).collect()
 	).await;
-	(relay_parent, request_tx)
+	(relay_parent, request_tx, batch_request_tx)
 }
 
 
@@ -0,0 +1,19 @@
+use polkadot_node_network_protocol::request_response::{
+	fuzzing::{arbitrary_payload, decode, Entropy, PayloadKind},
+	Protocol,
+};
+use honggfuzz::fuzz;
+use strum::IntoEnumIterator;
+
+fn main() {
+	loop {
+		fuzz!(|data: &[u8]| {
+			for protocol in Protocol::iter() {
+				// The raw input, as well as a payload generated from it.
+				let _ = decode(PayloadKind::Response, protocol, data);
+				let payload = arbitrary_payload(PayloadKind::Response, protocol, &mut Entropy::new(data));
+				let _ = decode(PayloadKind::Response, protocol, &payload);
+			}
+		});
+	}
+}
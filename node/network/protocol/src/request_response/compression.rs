@@ -0,0 +1,206 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compression of large responses.
+//!
+//! A compressed response is sent in an envelope of `MAGIC`, the size of the uncompressed
+//! response as a little endian `u32` and the zstd compressed response. Recipients reject
+//! envelopes claiming or decompressing to more than the maximum size of the response, so a
+//! small response can't be a decompression bomb.
+
+use std::io::Read;
+
+use parity_scale_codec::{Decode, Encode, Input, Output};
+use thiserror::Error;
+
+/// Prefix of every compressed envelope.
+pub const MAGIC: [u8; 4] = *b"pzr1";
+
+/// Size of the envelope around the compressed data.
+pub const ENVELOPE_HEADER_SIZE: usize = MAGIC.len() + 4;
+
+/// The zstd compression level, trading compression ratio for the time the responder needs.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Responses which can be sent compressed.
+pub trait CompressibleResponse: Encode + Decode {
+	/// The maximum size of the uncompressed response.
+	const MAX_SIZE: usize;
+}
+
+/// The maximum size of an envelope of a response of at most `max_size` bytes.
+///
+/// Incompressible data grows a little when compressed, this accounts for zstd's worst case (see
+/// `ZSTD_COMPRESSBOUND`) and the envelope.
+pub const fn max_compressed_size(max_size: u64) -> u64 {
+	max_size + (max_size >> 8) + 1000 + ENVELOPE_HEADER_SIZE as u64
+}
+
+/// Errors of decompressing an envelope.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecompressionError {
+	/// The data does not start with a full envelope header.
+	#[error("Compressed response lacks the envelope header")]
+	MissingHeader,
+	/// The prefix of the envelope is not `MAGIC`.
+	#[error("Compressed response has an invalid magic prefix")]
+	InvalidMagic,
+	/// The envelope claims a size larger than allowed.
+	#[error("Compressed response claims {claimed} bytes, more than the maximum of {max}")]
+	TooLarge {
+		/// The size claimed by the envelope.
+		claimed: usize,
+		/// The maximum size of the response.
+		max: usize,
+	},
+	/// The data did not decompress to the size claimed by the envelope.
+	#[error("Compressed response decompressed to a different size than the claimed {claimed} bytes")]
+	SizeMismatch {
+		/// The size claimed by the envelope.
+		claimed: usize,
+	},
+	/// The data is no valid zstd stream.
+	#[error("Compressed response is corrupt: {0}")]
+	Corrupt(String),
+}
+
+/// Put `data` into a compressed envelope.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+	let mut envelope = Vec::with_capacity(ENVELOPE_HEADER_SIZE + data.len() / 2);
+	envelope.extend_from_slice(&MAGIC);
+	envelope.extend_from_slice(&(data.len() as u32).to_le_bytes());
+	zstd::stream::copy_encode(data, &mut envelope, COMPRESSION_LEVEL)
+		.expect("Compressing from and into memory does not fail; qed");
+	envelope
+}
+
+/// Take the data out of a compressed `envelope`, if it decompresses to at most `max_size` bytes.
+pub fn decompress(envelope: &[u8], max_size: usize) -> Result<Vec<u8>, DecompressionError> {
+	if envelope.len() < ENVELOPE_HEADER_SIZE {
+		return Err(DecompressionError::MissingHeader)
+	}
+	let (magic, rest) = envelope.split_at(MAGIC.len());
+	if magic != MAGIC {
+		return Err(DecompressionError::InvalidMagic)
+	}
+	let (size, compressed) = rest.split_at(4);
+	let mut size_bytes = [0u8; 4];
+	size_bytes.copy_from_slice(size);
+	let claimed = u32::from_le_bytes(size_bytes) as usize;
+	if claimed > max_size {
+		return Err(DecompressionError::TooLarge { claimed, max: max_size })
+	}
+
+	let decoder = zstd::stream::read::Decoder::new(compressed)
+		.map_err(|e| DecompressionError::Corrupt(e.to_string()))?;
+	let mut data = Vec::with_capacity(claimed);
+	// Reading one byte more than claimed catches envelopes lying about their size.
+	decoder.take(claimed as u64 + 1).read_to_end(&mut data)
+		.map_err(|e| DecompressionError::Corrupt(e.to_string()))?;
+	if data.len() != claimed {
+		return Err(DecompressionError::SizeMismatch { claimed })
+	}
+	Ok(data)
+}
+
+/// A response which is encoded into a compressed envelope.
+#[derive(Debug, Clone)]
+pub struct Compressed<R>(pub R);
+
+impl<R: CompressibleResponse> Encode for Compressed<R> {
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		dest.write(&compress(&self.0.encode()))
+	}
+}
+
+impl<R: CompressibleResponse> Decode for Compressed<R> {
+	/// Decode a compressed envelope, which must be all of `input`.
+	fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+		let len = input.remaining_len()?
+			.ok_or("Compressed responses must be of known length")?;
+		let mut envelope = vec![0u8; len];
+		input.read(&mut envelope)?;
+		let data = decompress(&envelope, R::MAX_SIZE)
+			.map_err(|_| "Invalid compressed response")?;
+		R::decode(&mut &data[..]).map(Compressed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use polkadot_node_primitives::{BlockData, PoV};
+	use polkadot_primitives::v1::MAX_POV_SIZE;
+
+	use crate::request_response::{v1::PoVFetchingResponse, Protocol, ReqProtocolNames};
+
+	/// Incompressible bytes, the worst case for the size of the envelope.
+	fn noise(len: usize) -> Vec<u8> {
+		let mut state = 0x2545_f491_4f6c_dd1du64;
+		(0..len).map(|_| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state as u8
+		}).collect()
+	}
+
+	#[test]
+	fn near_limit_pov_round_trips() {
+		let block_data = BlockData(noise(MAX_POV_SIZE as usize - 100));
+		let response = PoVFetchingResponse::PoV(PoV { block_data });
+		assert!(response.encoded_size() <= PoVFetchingResponse::MAX_SIZE);
+
+		let compressed = Compressed(response.clone()).encode();
+		let (_, config) = Protocol::CompressedPoVFetching
+			.get_config(&ReqProtocolNames::new([0u8; 32], None, false));
+		assert!(compressed.len() as u64 <= config.max_response_size);
+
+		let decoded = Compressed::<PoVFetchingResponse>::decode(&mut &compressed[..])
+			.expect("envelope is valid");
+		assert_eq!(decoded.0.encode(), response.encode());
+	}
+
+	#[test]
+	fn compressible_data_shrinks() {
+		let data = vec![7u8; 1_000_000];
+		let envelope = compress(&data);
+		assert!(envelope.len() < data.len() / 100);
+		assert_eq!(decompress(&envelope, data.len()), Ok(data));
+	}
+
+	#[test]
+	fn decompression_bombs_are_rejected() {
+		let max = 1024;
+		let bomb = compress(&vec![0u8; 100 * max]);
+		assert!(bomb.len() < max);
+		assert_eq!(
+			decompress(&bomb, max),
+			Err(DecompressionError::TooLarge { claimed: 100 * max, max }),
+		);
+
+		// An envelope lying about its size is no better.
+		let mut lying = bomb.clone();
+		lying[MAGIC.len()..ENVELOPE_HEADER_SIZE].copy_from_slice(&(max as u32).to_le_bytes());
+		assert_eq!(decompress(&lying, max), Err(DecompressionError::SizeMismatch { claimed: max }));
+
+		let mut bad_magic = bomb;
+		bad_magic[0] ^= 0xff;
+		assert_eq!(decompress(&bad_magic, max), Err(DecompressionError::InvalidMagic));
+		assert_eq!(decompress(&MAGIC, max), Err(DecompressionError::MissingHeader));
+	}
+}
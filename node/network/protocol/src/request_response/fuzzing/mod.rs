@@ -0,0 +1,548 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generators of request and response payloads, for fuzzing their decoding.
+//!
+//! Any peer can send us arbitrary bytes as a request or response, so decoding them must never
+//! panic. The generators draw their decisions from an `Entropy`, which is either seeded for the
+//! bounded tests or the input of a fuzzer, and produce random bytes, valid encodings of the v1
+//! types or structurally mutated valid encodings. The fuzzer in `fuzzer/` of this crate uses
+//! them as well.
+
+use std::sync::Arc;
+
+use parity_scale_codec::{Compact, Decode, Encode, Error as DecodingError};
+
+use polkadot_node_primitives::{
+	AvailableData, BlockData, InvalidDisputeVote, PoV, UncheckedDisputeMessage, ValidDisputeVote,
+};
+use polkadot_primitives::v1::{
+	CandidateDescriptor, CandidateHash, CandidateReceipt, CommittedCandidateReceipt, Hash,
+	Id as ParaId, InvalidDisputeStatementKind, PersistedValidationData, ValidDisputeStatementKind,
+	ValidatorIndex,
+};
+
+use super::compression::Compressed;
+use super::request::IsRequest;
+use super::v1::{self, RefusalReason};
+use super::v2;
+use super::Protocol;
+
+/// The most random bytes in a payload, or in a variable sized field of a valid encoding.
+const MAX_RANDOM_LEN: usize = 256;
+
+/// The most mutations applied to a valid encoding.
+const MAX_MUTATIONS: usize = 4;
+
+/// Inputs which once were, or could easily become, a problem.
+///
+/// Each line is `request` or `response`, the short name of a protocol and the hex encoded
+/// payload. None of them decode.
+const CORPUS: &str = include_str!("corpus.txt");
+
+/// Whether a payload is sent as a request or as a response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PayloadKind {
+	/// A request, decoded by the responder.
+	Request,
+	/// A response, decoded by the requester.
+	Response,
+}
+
+/// The source of all decisions of the generators.
+///
+/// Once its data is used up all decisions are zero, so any data generates valid payloads.
+pub struct Entropy<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Entropy<'a> {
+	/// Draw decisions from `data`.
+	pub fn new(data: &'a [u8]) -> Self {
+		Entropy { data }
+	}
+
+	/// The next byte.
+	pub fn byte(&mut self) -> u8 {
+		match self.data.split_first() {
+			Some((byte, rest)) => {
+				self.data = rest;
+				*byte
+			}
+			None => 0,
+		}
+	}
+
+	/// The next `u32`.
+	pub fn u32(&mut self) -> u32 {
+		u32::from_le_bytes([self.byte(), self.byte(), self.byte(), self.byte()])
+	}
+
+	/// A number below `n`, or zero if `n` is zero.
+	pub fn below(&mut self, n: usize) -> usize {
+		if n == 0 {
+			0
+		} else {
+			self.u32() as usize % n
+		}
+	}
+
+	/// At most `max_len` bytes.
+	pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+		let len = self.below(max_len + 1);
+		(0..len).map(|_| self.byte()).collect()
+	}
+
+	/// A hash.
+	pub fn hash(&mut self) -> Hash {
+		let mut hash = [0u8; 32];
+		for byte in hash.iter_mut() {
+			*byte = self.byte();
+		}
+		Hash::from(hash)
+	}
+}
+
+/// `len` pseudo random bytes, for seeding an `Entropy` in tests.
+pub fn seeded_bytes(seed: u64, len: usize) -> Vec<u8> {
+	// Xorshift must not start at zero.
+	let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+	(0..len).map(|_| {
+		state ^= state << 13;
+		state ^= state >> 7;
+		state ^= state << 17;
+		(state >> 24) as u8
+	}).collect()
+}
+
+/// A payload of `kind` for `protocol`: random bytes, a valid encoding or a mutated one.
+pub fn arbitrary_payload(kind: PayloadKind, protocol: Protocol, entropy: &mut Entropy) -> Vec<u8> {
+	match entropy.below(3) {
+		0 => entropy.bytes(MAX_RANDOM_LEN),
+		1 => valid_payload(kind, protocol, entropy),
+		_ => {
+			let valid = valid_payload(kind, protocol, entropy);
+			mutate(valid, entropy)
+		}
+	}
+}
+
+/// A valid encoding of a request or response of `protocol`.
+pub fn valid_payload(kind: PayloadKind, protocol: Protocol, entropy: &mut Entropy) -> Vec<u8> {
+	match kind {
+		PayloadKind::Request => valid_request(protocol, entropy),
+		PayloadKind::Response => valid_response(protocol, entropy),
+	}
+}
+
+/// Decode `payload` into the request or response type of `protocol`, as its recipient would.
+pub fn decode(kind: PayloadKind, protocol: Protocol, payload: &[u8]) -> Result<(), DecodingError> {
+	fn decode_as<Req>(kind: PayloadKind, mut payload: &[u8]) -> Result<(), DecodingError>
+	where
+		Req: IsRequest + Decode,
+		Req::Response: Decode,
+	{
+		match kind {
+			PayloadKind::Request => Req::decode(&mut payload).map(|_| ()),
+			PayloadKind::Response => Req::Response::decode(&mut payload).map(|_| ()),
+		}
+	}
+
+	match protocol {
+		Protocol::ChunkFetching => decode_as::<v1::ChunkFetchingRequest>(kind, payload),
+		Protocol::CollationFetching => decode_as::<v1::CollationFetchingRequest>(kind, payload),
+		Protocol::PoVFetching => decode_as::<v1::PoVFetchingRequest>(kind, payload),
+		Protocol::AvailableDataFetching =>
+			decode_as::<v1::AvailableDataFetchingRequest>(kind, payload),
+		Protocol::StatementFetching => decode_as::<v1::StatementFetchingRequest>(kind, payload),
+		Protocol::DisputeSending => decode_as::<v1::DisputeRequest>(kind, payload),
+		Protocol::MultiChunkFetching => decode_as::<v1::MultiChunkFetchingRequest>(kind, payload),
+		Protocol::CompressedPoVFetching =>
+			decode_as::<v1::CompressedPoVFetchingRequest>(kind, payload),
+		Protocol::CompressedAvailableDataFetching =>
+			decode_as::<v1::CompressedAvailableDataFetchingRequest>(kind, payload),
+		Protocol::CollationFetchingV2 => decode_as::<v2::CollationFetchingRequest>(kind, payload),
+		Protocol::DisputeBatchSending => decode_as::<v2::DisputeBatchRequest>(kind, payload),
+		Protocol::AvailableDataFetchingV2 =>
+			decode_as::<v2::AvailableDataFetchingRequest>(kind, payload),
+		Protocol::StatementFetchingV2 => decode_as::<v2::StatementFetchingRequest>(kind, payload),
+	}
+}
+
+/// The checked in regression inputs.
+pub fn corpus() -> impl Iterator<Item = (PayloadKind, Protocol, Vec<u8>)> {
+	CORPUS.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| {
+			let mut fields = line.split_whitespace();
+			let kind = match fields.next() {
+				Some("request") => PayloadKind::Request,
+				Some("response") => PayloadKind::Response,
+				other => panic!("Invalid payload kind {:?} in corpus", other),
+			};
+			let protocol = fields.next()
+				.and_then(|name| name.parse().ok())
+				.unwrap_or_else(|| panic!("Invalid protocol in corpus line {:?}", line));
+			(kind, protocol, from_hex(fields.next().unwrap_or("")))
+		})
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+	assert!(hex.len() % 2 == 0, "Odd number of hex digits in corpus");
+	(0..hex.len()).step_by(2)
+		.map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Invalid hex digits in corpus"))
+		.collect()
+}
+
+/// Apply a few random mutations to a valid `encoding`.
+pub fn mutate(mut encoding: Vec<u8>, entropy: &mut Entropy) -> Vec<u8> {
+	for _ in 0..=entropy.below(MAX_MUTATIONS) {
+		let pos = entropy.below(encoding.len() + 1);
+		match entropy.below(6) {
+			// Flip some bits of a byte, e.g. of an enum variant or option tag.
+			0 => if let Some(byte) = encoding.get_mut(pos) {
+				*byte ^= entropy.byte() | 1;
+			},
+			// Cut the encoding short.
+			1 => encoding.truncate(pos),
+			// Leave garbage at the end.
+			2 => encoding.extend(entropy.bytes(MAX_RANDOM_LEN)),
+			// Insert a length prefix, possibly claiming an enormous number of items.
+			3 => {
+				let len = if entropy.byte() % 2 == 0 { u32::MAX } else { entropy.u32() };
+				insert(&mut encoding, pos, Compact(len).encode());
+			}
+			// Insert a random byte, shifting all fields after it.
+			4 => {
+				let byte = entropy.byte();
+				insert(&mut encoding, pos, vec![byte]);
+			}
+			// Repeat a piece of the encoding.
+			_ => {
+				let end = pos + entropy.below(encoding.len() - pos + 1);
+				let piece = encoding[pos..end].to_vec();
+				insert(&mut encoding, end, piece);
+			}
+		}
+	}
+	encoding
+}
+
+fn insert(encoding: &mut Vec<u8>, pos: usize, bytes: Vec<u8>) {
+	let tail = encoding.split_off(pos);
+	encoding.extend(bytes);
+	encoding.extend(tail);
+}
+
+fn valid_request(protocol: Protocol, entropy: &mut Entropy) -> Vec<u8> {
+	match protocol {
+		Protocol::ChunkFetching => v1::ChunkFetchingRequest {
+			candidate_hash: CandidateHash(entropy.hash()),
+			index: ValidatorIndex(entropy.u32()),
+		}.encode(),
+		Protocol::CollationFetching => v1::CollationFetchingRequest {
+			relay_parent: entropy.hash(),
+			para_id: ParaId::from(entropy.u32()),
+		}.encode(),
+		Protocol::PoVFetching => pov_request(entropy).encode(),
+		Protocol::AvailableDataFetching => available_data_request(entropy).encode(),
+		Protocol::StatementFetching => v1::StatementFetchingRequest {
+			relay_parent: entropy.hash(),
+			candidate_hash: CandidateHash(entropy.hash()),
+		}.encode(),
+		Protocol::DisputeSending => v1::DisputeRequest(dispute_message(entropy)).encode(),
+		Protocol::MultiChunkFetching => {
+			let candidate_hash = CandidateHash(entropy.hash());
+			// Also more indices than allowed, which are refused but must decode.
+			let count = entropy.below(2 * v1::MAX_CHUNKS_PER_REQUEST + 1);
+			v1::MultiChunkFetchingRequest {
+				candidate_hash,
+				indices: (0..count).map(|_| ValidatorIndex(entropy.u32())).collect(),
+			}.encode()
+		}
+		Protocol::CompressedPoVFetching =>
+			v1::CompressedPoVFetchingRequest(pov_request(entropy)).encode(),
+		Protocol::CompressedAvailableDataFetching =>
+			v1::CompressedAvailableDataFetchingRequest(available_data_request(entropy)).encode(),
+		Protocol::CollationFetchingV2 => v2::CollationFetchingRequest {
+			relay_parent: entropy.hash(),
+			para_id: ParaId::from(entropy.u32()),
+		}.encode(),
+		Protocol::DisputeBatchSending => {
+			// Also more disputes than allowed, which are refused but must decode.
+			let count = entropy.below(2 * v2::MAX_DISPUTES_PER_BATCH + 1);
+			v2::DisputeBatchRequest((0..count).map(|_| dispute_message(entropy)).collect()).encode()
+		}
+		Protocol::AvailableDataFetchingV2 => v2::AvailableDataFetchingRequest {
+			candidate_hash: CandidateHash(entropy.hash()),
+			segment: if entropy.byte() % 2 == 0 { Some(entropy.u32()) } else { None },
+		}.encode(),
+		Protocol::StatementFetchingV2 => {
+			let relay_parent = entropy.hash();
+			// Also more candidates than allowed, which are refused but must decode.
+			let count = entropy.below(2 * v2::MAX_STATEMENTS_PER_REQUEST + 1);
+			v2::StatementFetchingRequest {
+				relay_parent,
+				candidate_hashes: (0..count).map(|_| CandidateHash(entropy.hash())).collect(),
+			}.encode()
+		}
+	}
+}
+
+fn valid_response(protocol: Protocol, entropy: &mut Entropy) -> Vec<u8> {
+	let refusal = if entropy.byte() % 2 == 0 { RefusalReason::Busy } else { RefusalReason::Invalid };
+	// The last variant of every response is its refusal.
+	let refused = entropy.below(3) == 2;
+	let found = entropy.byte() % 2 == 0;
+	match protocol {
+		Protocol::ChunkFetching => match (refused, found) {
+			(true, _) => v1::ChunkFetchingResponse::Refused(refusal),
+			(false, true) => v1::ChunkFetchingResponse::Chunk(chunk_response(entropy)),
+			(false, false) => v1::ChunkFetchingResponse::NoSuchChunk,
+		}.encode(),
+		Protocol::CollationFetching => if refused {
+			v1::CollationFetchingResponse::Refused(refusal)
+		} else {
+			v1::CollationFetchingResponse::Collation(candidate_receipt(entropy), pov(entropy))
+		}.encode(),
+		Protocol::PoVFetching => pov_response(refused, found, refusal, entropy).encode(),
+		Protocol::AvailableDataFetching =>
+			available_data_response(refused, found, refusal, entropy).encode(),
+		Protocol::StatementFetching => if refused {
+			v1::StatementFetchingResponse::Refused(refusal)
+		} else {
+			v1::StatementFetchingResponse::Statement(committed_candidate_receipt(entropy))
+		}.encode(),
+		Protocol::DisputeSending => if refused {
+			v1::DisputeResponse::Refused(refusal)
+		} else {
+			v1::DisputeResponse::Confirmed
+		}.encode(),
+		Protocol::MultiChunkFetching => if refused {
+			v1::MultiChunkFetchingResponse::Refused(refusal)
+		} else {
+			let count = entropy.below(v1::MAX_CHUNKS_PER_REQUEST + 1);
+			v1::MultiChunkFetchingResponse::Chunks((0..count).map(|_| {
+				if entropy.byte() % 2 == 0 {
+					Some(chunk_response(entropy))
+				} else {
+					None
+				}
+			}).collect())
+		}.encode(),
+		Protocol::CompressedPoVFetching =>
+			Compressed(pov_response(refused, found, refusal, entropy)).encode(),
+		Protocol::CompressedAvailableDataFetching =>
+			Compressed(available_data_response(refused, found, refusal, entropy)).encode(),
+		Protocol::CollationFetchingV2 => if refused {
+			v2::CollationFetchingResponse::Refused(refusal)
+		} else {
+			v2::CollationFetchingResponse::Collation(v2::SignedCollation {
+				receipt: candidate_receipt(entropy),
+				pov: pov(entropy),
+				pov_hash: entropy.hash(),
+				signature: Default::default(),
+			})
+		}.encode(),
+		Protocol::DisputeBatchSending => if refused {
+			v2::DisputeBatchResponse::Refused(refusal)
+		} else {
+			let count = entropy.below(v2::MAX_DISPUTES_PER_BATCH + 1);
+			v2::DisputeBatchResponse::Responses((0..count).map(|_| {
+				if entropy.byte() % 2 == 0 {
+					v1::DisputeResponse::Confirmed
+				} else {
+					v1::DisputeResponse::Refused(refusal)
+				}
+			}).collect())
+		}.encode(),
+		Protocol::AvailableDataFetchingV2 => match (refused, found, entropy.below(3)) {
+			(true, _, _) => v2::AvailableDataFetchingResponse::Refused(refusal),
+			(false, false, _) => v2::AvailableDataFetchingResponse::NoSuchData,
+			(false, true, 0) => v2::AvailableDataFetchingResponse::AvailableData(available_data(entropy)),
+			(false, true, 1) => v2::AvailableDataFetchingResponse::Segmented { size: entropy.u32() },
+			(false, true, _) => v2::AvailableDataFetchingResponse::Segment(entropy.bytes(MAX_RANDOM_LEN)),
+		}.encode(),
+		Protocol::StatementFetchingV2 => if refused {
+			v2::StatementFetchingResponse::Refused(refusal)
+		} else {
+			let count = entropy.below(v2::MAX_STATEMENTS_PER_REQUEST + 1);
+			v2::StatementFetchingResponse::Statements((0..count).map(|_| {
+				if entropy.byte() % 2 == 0 {
+					Some(committed_candidate_receipt(entropy))
+				} else {
+					None
+				}
+			}).collect())
+		}.encode(),
+	}
+}
+
+fn dispute_message(entropy: &mut Entropy) -> UncheckedDisputeMessage {
+	UncheckedDisputeMessage {
+		candidate_receipt: candidate_receipt(entropy),
+		session_index: entropy.u32(),
+		invalid_vote: InvalidDisputeVote {
+			validator_index: ValidatorIndex(entropy.u32()),
+			signature: Default::default(),
+			kind: InvalidDisputeStatementKind::Explicit,
+		},
+		valid_vote: ValidDisputeVote {
+			validator_index: ValidatorIndex(entropy.u32()),
+			signature: Default::default(),
+			kind: ValidDisputeStatementKind::Explicit,
+		},
+	}
+}
+
+fn pov_request(entropy: &mut Entropy) -> v1::PoVFetchingRequest {
+	v1::PoVFetchingRequest { candidate_hash: CandidateHash(entropy.hash()) }
+}
+
+fn available_data_request(entropy: &mut Entropy) -> v1::AvailableDataFetchingRequest {
+	v1::AvailableDataFetchingRequest { candidate_hash: CandidateHash(entropy.hash()) }
+}
+
+fn candidate_receipt(entropy: &mut Entropy) -> CandidateReceipt {
+	CandidateReceipt {
+		descriptor: CandidateDescriptor {
+			para_id: ParaId::from(entropy.u32()),
+			relay_parent: entropy.hash(),
+			pov_hash: entropy.hash(),
+			..Default::default()
+		},
+		commitments_hash: entropy.hash(),
+	}
+}
+
+fn committed_candidate_receipt(entropy: &mut Entropy) -> CommittedCandidateReceipt {
+	let mut receipt = CommittedCandidateReceipt::default();
+	receipt.descriptor = candidate_receipt(entropy).descriptor;
+	receipt
+}
+
+fn pov(entropy: &mut Entropy) -> PoV {
+	PoV { block_data: BlockData(entropy.bytes(MAX_RANDOM_LEN)) }
+}
+
+fn chunk_response(entropy: &mut Entropy) -> v1::ChunkResponse {
+	let chunk = entropy.bytes(MAX_RANDOM_LEN);
+	let proof_len = entropy.below(4);
+	v1::ChunkResponse {
+		chunk,
+		proof: (0..proof_len).map(|_| entropy.bytes(64)).collect(),
+	}
+}
+
+fn pov_response(
+	refused: bool,
+	found: bool,
+	refusal: RefusalReason,
+	entropy: &mut Entropy,
+) -> v1::PoVFetchingResponse {
+	match (refused, found) {
+		(true, _) => v1::PoVFetchingResponse::Refused(refusal),
+		(false, true) => v1::PoVFetchingResponse::PoV(pov(entropy)),
+		(false, false) => v1::PoVFetchingResponse::NoSuchPoV,
+	}
+}
+
+fn available_data_response(
+	refused: bool,
+	found: bool,
+	refusal: RefusalReason,
+	entropy: &mut Entropy,
+) -> v1::AvailableDataFetchingResponse {
+	match (refused, found) {
+		(true, _) => v1::AvailableDataFetchingResponse::Refused(refusal),
+		(false, true) => v1::AvailableDataFetchingResponse::AvailableData(available_data(entropy)),
+		(false, false) => v1::AvailableDataFetchingResponse::NoSuchData,
+	}
+}
+
+fn available_data(entropy: &mut Entropy) -> AvailableData {
+	AvailableData {
+		pov: Arc::new(pov(entropy)),
+		validation_data: PersistedValidationData {
+			relay_parent_number: entropy.u32(),
+			relay_parent_storage_root: entropy.hash(),
+			max_pov_size: entropy.u32(),
+			..Default::default()
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use strum::IntoEnumIterator;
+
+	/// Iterations per protocol, bounded to keep the tests fast.
+	const ITERATIONS: u64 = 500;
+
+	#[test]
+	fn valid_payloads_decode() {
+		for seed in 0..ITERATIONS {
+			let data = seeded_bytes(seed, 2048);
+			let mut entropy = Entropy::new(&data);
+			for kind in [PayloadKind::Request, PayloadKind::Response].iter().copied() {
+				for protocol in Protocol::iter() {
+					let payload = valid_payload(kind, protocol, &mut entropy);
+					assert!(
+						decode(kind, protocol, &payload).is_ok(),
+						"valid {:?} of {:?} decodes, seed {}", kind, protocol, seed,
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn fuzzed_responses_decode_without_panicking() {
+		for seed in 0..ITERATIONS {
+			let data = seeded_bytes(seed, 2048);
+			let mut entropy = Entropy::new(&data);
+			for protocol in Protocol::iter() {
+				let payload = arbitrary_payload(PayloadKind::Response, protocol, &mut entropy);
+				// Either result is fine, as long as decoding returns.
+				let _ = decode(PayloadKind::Response, protocol, &payload);
+			}
+		}
+	}
+
+	#[test]
+	fn corpus_is_rejected() {
+		let mut entries = 0;
+		for (kind, protocol, payload) in corpus() {
+			assert!(
+				decode(kind, protocol, &payload).is_err(),
+				"{:?} of {:?} in the corpus is rejected: {:?}", kind, protocol, payload,
+			);
+			entries += 1;
+		}
+		assert!(entries > 0, "corpus is not empty");
+	}
+
+	#[test]
+	fn exhausted_entropy_still_generates() {
+		let mut entropy = Entropy::new(&[]);
+		assert_eq!(entropy.u32(), 0);
+		let payload = valid_payload(PayloadKind::Request, Protocol::ChunkFetching, &mut entropy);
+		assert!(decode(PayloadKind::Request, Protocol::ChunkFetching, &payload).is_ok());
+	}
+}
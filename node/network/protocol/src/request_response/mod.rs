@@ -30,21 +30,25 @@
 //! trait `IsRequest` .... A trait describing a particular request. It is used for gathering meta
 //! data, like what is the corresponding response type.
 //!
-//!  Versioned (v1 module): The actual requests and responses as sent over the network.
+//!  Versioned (v1 and v2 modules): The actual requests and responses as sent over the network.
 
-use std::{borrow::Cow, u64};
+use std::{borrow::Cow, collections::HashMap, u64};
 use std::time::Duration;
 
 use futures::channel::mpsc;
+use parity_scale_codec::Encode;
 use polkadot_primitives::v1::{MAX_CODE_SIZE, MAX_POV_SIZE};
-use strum::EnumIter;
+use strum::{EnumIter, IntoEnumIterator};
 
 pub use sc_network::config as network;
 pub use sc_network::config::RequestResponseConfig;
 
 /// All requests that can be sent to the network bridge.
 pub mod request;
-pub use request::{IncomingRequest, OutgoingRequest, Requests, Recipient, OutgoingResult, ResponseSender};
+pub use request::{
+	IncomingRequest, OutgoingRequest, Priority, Requests, Recipient, OutgoingResult, ResponseSender,
+	VersionedReceiver,
+};
 
 ///// Multiplexer for incoming requests.
 // pub mod multiplexer;
@@ -52,6 +56,20 @@ pub use request::{IncomingRequest, OutgoingRequest, Requests, Recipient, Outgoin
 /// Actual versioned requests and responses, that are sent over the wire.
 pub mod v1;
 
+/// Second versions of requests and responses, for the protocols which have one.
+pub mod v2;
+
+/// Compressed envelopes for large responses.
+pub mod compression;
+
+/// Overrides of the protocol defaults from the node configuration.
+pub mod settings;
+
+/// Generators of request and response payloads, for fuzzing their decoding.
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzzing;
+pub use settings::{InvalidSetting, ProtocolSettings, RequestResponseSettings};
+
 /// A protocol per subsystem seems to make the most sense, this way we don't need any dispatching
 /// within protocols.
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, EnumIter)]
@@ -68,6 +86,20 @@ pub enum Protocol {
 	StatementFetching,
 	/// Sending of dispute statements with application level confirmations.
 	DisputeSending,
+	/// Protocol for fetching several chunks of a candidate at once, used by availability recovery.
+	MultiChunkFetching,
+	/// `PoVFetching` with compressed responses.
+	CompressedPoVFetching,
+	/// `AvailableDataFetching` with compressed responses.
+	CompressedAvailableDataFetching,
+	/// `CollationFetching` with collations signed by the collator.
+	CollationFetchingV2,
+	/// `DisputeSending` with the votes of several disputes per request.
+	DisputeBatchSending,
+	/// `AvailableDataFetching` with large available data sent in segments.
+	AvailableDataFetchingV2,
+	/// `StatementFetching` of several statements per request.
+	StatementFetchingV2,
 }
 
 
@@ -75,6 +107,32 @@ pub enum Protocol {
 /// 50Meg bytes per second:
 const MIN_BANDWIDTH_BYTES: u64  = 50 * 1024 * 1024;
 
+/// The maximum size of a single erasure chunk response.
+///
+/// Chunks are a fraction of the available data, which is dominated by the PoV.
+pub const MAX_CHUNK_RESPONSE_SIZE: u64 = MAX_POV_SIZE as u64 / 10;
+
+/// The maximum size of a multi chunk response, fitting `v1::MAX_CHUNKS_PER_REQUEST` chunks of
+/// `MAX_CHUNK_RESPONSE_SIZE`.
+///
+/// 1000 accounts for the overhead of the response, which is a handful of bytes per chunk.
+pub const MAX_MULTI_CHUNK_RESPONSE_SIZE: u64 =
+	v1::MAX_CHUNKS_PER_REQUEST as u64 * MAX_CHUNK_RESPONSE_SIZE + 1000;
+
+/// The maximum size of a large statement response.
+///
+/// Statement size is dominated by the code size, + 1000 to account for protocol overhead (should
+/// be way less).
+pub const MAX_STATEMENT_RESPONSE_SIZE: u64 = MAX_CODE_SIZE as u64 + 1000;
+
+/// The maximum size of a PoV response.
+pub const MAX_POV_RESPONSE_SIZE: u64 = MAX_POV_SIZE as u64;
+
+/// The maximum size of an available data response.
+///
+/// Available data size is dominated by the PoV size.
+pub const MAX_AVAILABLE_DATA_RESPONSE_SIZE: u64 = MAX_POV_SIZE as u64 + 1000;
+
 /// Default request timeout in seconds.
 ///
 /// When decreasing this value, take into account that the very first request might need to open a
@@ -103,24 +161,101 @@ const STATEMENTS_TIMEOUT: Duration = Duration::from_secs(1);
 /// to have 3 slow nodes connected, to delay transfer for others by `STATEMENTS_TIMEOUT`.
 pub const MAX_PARALLEL_STATEMENT_REQUESTS: u32 = 3;
 
+/// The names of the request-response protocols on a particular chain.
+///
+/// The names are prefixed with the genesis hash of the chain, and its fork id if any, so that
+/// nodes of different chains running the same code don't waste resources on each other's
+/// requests. During the transition to these names, the legacy names of
+/// `Protocol::get_protocol_name_static` are registered as well and used for outgoing requests,
+/// so that nodes not knowing the new names can still be talked to.
+#[derive(Clone, Debug)]
+pub struct ReqProtocolNames {
+	names: HashMap<Protocol, Cow<'static, str>>,
+	legacy_names: bool,
+}
+
+impl ReqProtocolNames {
+	/// The names of the protocols on the chain of `genesis_hash` and `fork_id`, also using the
+	/// legacy names if `legacy_names` is set.
+	pub fn new<Hash: AsRef<[u8]>>(
+		genesis_hash: Hash,
+		fork_id: Option<&str>,
+		legacy_names: bool,
+	) -> Self {
+		let genesis_hash: String = genesis_hash.as_ref().iter()
+			.map(|b| format!("{:02x}", b))
+			.collect();
+		let prefix = match fork_id {
+			Some(fork_id) => format!("/{}/{}", genesis_hash, fork_id),
+			None => format!("/{}", genesis_hash),
+		};
+		let names = Protocol::iter()
+			.map(|p| {
+				let legacy = p.get_protocol_name_static();
+				let suffix = legacy.strip_prefix("/polkadot").unwrap_or(legacy);
+				(p, format!("{}{}", prefix, suffix).into())
+			})
+			.collect();
+		Self { names, legacy_names }
+	}
+
+	/// The chain specific name of `protocol`.
+	pub fn get_name(&self, protocol: Protocol) -> Cow<'static, str> {
+		self.names.get(&protocol)
+			.expect("Names of all protocols are created in `new`; qed")
+			.clone()
+	}
+
+	/// The name to send requests of `protocol` with.
+	///
+	/// This is the legacy name as long as the legacy names are used, as it is the one every
+	/// node knows.
+	pub fn get_outgoing_name(&self, protocol: Protocol) -> Cow<'static, str> {
+		if self.legacy_names {
+			protocol.get_protocol_name_static().into()
+		} else {
+			self.get_name(protocol)
+		}
+	}
+
+	/// Whether the legacy names are registered and used, in addition to the chain specific ones.
+	pub fn legacy_names(&self) -> bool {
+		self.legacy_names
+	}
+}
+
 impl Protocol {
 	/// Get a configuration for a given Request response protocol.
 	///
 	/// Returns a receiver for messages received on this protocol and the requested
-	/// `ProtocolConfig`.
+	/// `ProtocolConfig`, which is named according to `names`.
 	pub fn get_config(
 		self,
+		names: &ReqProtocolNames,
+	) -> (
+		mpsc::Receiver<network::IncomingRequest>,
+		RequestResponseConfig,
+	) {
+		self.get_config_with(names, ProtocolSettings::default())
+	}
+
+	/// Get a configuration for a given Request response protocol, with the defaults overridden
+	/// by `settings`.
+	pub fn get_config_with(
+		self,
+		names: &ReqProtocolNames,
+		settings: ProtocolSettings,
 	) -> (
 		mpsc::Receiver<network::IncomingRequest>,
 		RequestResponseConfig,
 	) {
-		let p_name = self.into_protocol_name();
-		let (tx, rx) = mpsc::channel(self.get_channel_size());
-		let cfg = match self {
+		let p_name = names.get_name(self);
+		let (tx, rx) = mpsc::channel(settings.channel_size.unwrap_or_else(|| self.get_channel_size()));
+		let mut cfg = match self {
 			Protocol::ChunkFetching => RequestResponseConfig {
 				name: p_name,
 				max_request_size: 1_000,
-				max_response_size: MAX_POV_SIZE as u64 / 10,
+				max_response_size: MAX_CHUNK_RESPONSE_SIZE,
 				// We are connected to all validators:
 				request_timeout: DEFAULT_REQUEST_TIMEOUT_CONNECTED,
 				inbound_queue: Some(tx),
@@ -136,24 +271,21 @@ impl Protocol {
 			Protocol::PoVFetching => RequestResponseConfig {
 				name: p_name,
 				max_request_size: 1_000,
-				max_response_size: MAX_POV_SIZE as u64,
+				max_response_size: MAX_POV_RESPONSE_SIZE,
 				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
 				inbound_queue: Some(tx),
 			},
 			Protocol::AvailableDataFetching => RequestResponseConfig {
 				name: p_name,
 				max_request_size: 1_000,
-				// Available data size is dominated by the PoV size.
-				max_response_size: MAX_POV_SIZE as u64 + 1000,
+				max_response_size: MAX_AVAILABLE_DATA_RESPONSE_SIZE,
 				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
 				inbound_queue: Some(tx),
 			},
 			Protocol::StatementFetching => RequestResponseConfig {
 				name: p_name,
 				max_request_size: 1_000,
-				// Available data size is dominated code size.
-				// + 1000 to account for protocol overhead (should be way less).
-				max_response_size: MAX_CODE_SIZE as u64 + 1000,
+				max_response_size: MAX_STATEMENT_RESPONSE_SIZE,
 				// We need statement fetching to be fast and will try our best at the responding
 				// side to answer requests within that timeout, assuming a bandwidth of 500Mbit/s
 				// - which is the recommended minimum bandwidth for nodes on Kusama as of April
@@ -177,12 +309,76 @@ impl Protocol {
 				request_timeout: Duration::from_secs(12),
 				inbound_queue: Some(tx),
 			},
+			Protocol::MultiChunkFetching => RequestResponseConfig {
+				name: p_name,
+				max_request_size: 1_000,
+				max_response_size: MAX_MULTI_CHUNK_RESPONSE_SIZE,
+				// As much data as a PoV:
+				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
+				inbound_queue: Some(tx),
+			},
+			// Incompressible responses are a little larger than uncompressed ones.
+			Protocol::CompressedPoVFetching => RequestResponseConfig {
+				name: p_name,
+				max_request_size: 1_000,
+				max_response_size: compression::max_compressed_size(MAX_POV_RESPONSE_SIZE),
+				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
+				inbound_queue: Some(tx),
+			},
+			Protocol::CompressedAvailableDataFetching => RequestResponseConfig {
+				name: p_name,
+				max_request_size: 1_000,
+				max_response_size: compression::max_compressed_size(MAX_AVAILABLE_DATA_RESPONSE_SIZE),
+				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
+				inbound_queue: Some(tx),
+			},
+			Protocol::CollationFetchingV2 => RequestResponseConfig {
+				name: p_name,
+				max_request_size: 1_000,
+				// The pov hash and signature are well within the overhead.
+				max_response_size: MAX_POV_SIZE as u64 + 1000,
+				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
+				inbound_queue: Some(tx),
+			},
+			Protocol::DisputeBatchSending => RequestResponseConfig {
+				name: p_name,
+				// As much as `DisputeSending` per dispute in the batch, plus the length prefix.
+				max_request_size: v2::MAX_DISPUTES_PER_BATCH as u64 * 1_000 + 100,
+				// A confirmation or refusal per dispute.
+				max_response_size: 100 + v2::MAX_DISPUTES_PER_BATCH as u64 * 2,
+				// Same as for `DisputeSending`.
+				request_timeout: Duration::from_secs(12),
+				inbound_queue: Some(tx),
+			},
+			Protocol::AvailableDataFetchingV2 => RequestResponseConfig {
+				name: p_name,
+				max_request_size: 1_000,
+				// Inline data or a single segment, + 1000 for the protocol overhead.
+				max_response_size: std::cmp::max(
+					v2::MAX_INLINE_AVAILABLE_DATA_SIZE,
+					v2::AVAILABLE_DATA_SEGMENT_SIZE,
+				) as u64 + 1000,
+				request_timeout: POV_REQUEST_TIMEOUT_CONNECTED,
+				inbound_queue: Some(tx),
+			},
+			Protocol::StatementFetchingV2 => RequestResponseConfig {
+				name: p_name,
+				max_request_size: 1_000,
+				// Responders only send as many statements as fit into a `StatementFetching`
+				// response, so the same timeout applies.
+				max_response_size: MAX_STATEMENT_RESPONSE_SIZE,
+				request_timeout: STATEMENTS_TIMEOUT,
+				inbound_queue: Some(tx),
+			},
 		};
+		if let Some(request_timeout) = settings.request_timeout {
+			cfg.request_timeout = request_timeout;
+		}
 		(rx, cfg)
 	}
 
-	// Channel sizes for the supported protocols.
-	fn get_channel_size(self) -> usize {
+	/// The default channel size for the incoming requests of this protocol.
+	pub fn get_channel_size(self) -> usize {
 		match self {
 			// Hundreds of validators will start requesting their chunks once they see a candidate
 			// awaiting availability on chain. Given that they will see that block at different
@@ -221,15 +417,73 @@ impl Protocol {
 			// average, so something in the ballpark of 100 should be fine. Nodes will retry on
 			// failure, so having a good value here is mostly about performance tuning.
 			Protocol::DisputeSending => 100,
+			// Every request is worth up to `v1::MAX_CHUNKS_PER_REQUEST` chunk requests.
+			Protocol::MultiChunkFetching => 10,
+			// Same as for the uncompressed protocols.
+			Protocol::CompressedPoVFetching => 10,
+			Protocol::CompressedAvailableDataFetching => 100,
+			Protocol::CollationFetchingV2 => 10,
+			// Every request is worth up to `v2::MAX_DISPUTES_PER_BATCH` dispute requests.
+			Protocol::DisputeBatchSending => 20,
+			// A requester of large data sends a request per segment.
+			Protocol::AvailableDataFetchingV2 => 200,
+			// Every request is worth up to `v2::MAX_STATEMENTS_PER_REQUEST` statement requests.
+			Protocol::StatementFetchingV2 => std::cmp::max(
+				Protocol::StatementFetching.get_channel_size() / v2::MAX_STATEMENTS_PER_REQUEST,
+				1,
+			),
 		}
 	}
 
-	/// Get the protocol name of this protocol, as understood by substrate networking.
-	pub fn into_protocol_name(self) -> Cow<'static, str> {
-		self.get_protocol_name_static().into()
+	/// The encoded response refusing a request of this protocol for `reason`.
+	///
+	/// Useful for refusing requests which were not decoded.
+	pub fn encode_refusal(self, reason: v1::RefusalReason) -> Vec<u8> {
+		match self {
+			Protocol::ChunkFetching => v1::ChunkFetchingResponse::Refused(reason).encode(),
+			Protocol::CollationFetching => v1::CollationFetchingResponse::Refused(reason).encode(),
+			Protocol::PoVFetching => v1::PoVFetchingResponse::Refused(reason).encode(),
+			Protocol::AvailableDataFetching =>
+				v1::AvailableDataFetchingResponse::Refused(reason).encode(),
+			Protocol::StatementFetching => v1::StatementFetchingResponse::Refused(reason).encode(),
+			Protocol::DisputeSending => v1::DisputeResponse::Refused(reason).encode(),
+			Protocol::MultiChunkFetching =>
+				v1::MultiChunkFetchingResponse::Refused(reason).encode(),
+			Protocol::CompressedPoVFetching =>
+				compression::Compressed(v1::PoVFetchingResponse::Refused(reason)).encode(),
+			Protocol::CompressedAvailableDataFetching =>
+				compression::Compressed(v1::AvailableDataFetchingResponse::Refused(reason)).encode(),
+			Protocol::CollationFetchingV2 => v2::CollationFetchingResponse::Refused(reason).encode(),
+			Protocol::DisputeBatchSending => v2::DisputeBatchResponse::Refused(reason).encode(),
+			Protocol::AvailableDataFetchingV2 =>
+				v2::AvailableDataFetchingResponse::Refused(reason).encode(),
+			Protocol::StatementFetchingV2 => v2::StatementFetchingResponse::Refused(reason).encode(),
+		}
+	}
+
+	/// The short name of this protocol, as used in the node configuration.
+	pub const fn short_name(self) -> &'static str {
+		match self {
+			Protocol::ChunkFetching => "chunk",
+			Protocol::CollationFetching => "collation",
+			Protocol::PoVFetching => "pov",
+			Protocol::AvailableDataFetching => "available-data",
+			Protocol::StatementFetching => "statement",
+			Protocol::DisputeSending => "dispute",
+			Protocol::MultiChunkFetching => "multi-chunk",
+			Protocol::CompressedPoVFetching => "compressed-pov",
+			Protocol::CompressedAvailableDataFetching => "compressed-available-data",
+			Protocol::CollationFetchingV2 => "collation-v2",
+			Protocol::DisputeBatchSending => "dispute-batch",
+			Protocol::AvailableDataFetchingV2 => "available-data-v2",
+			Protocol::StatementFetchingV2 => "statement-v2",
+		}
 	}
 
-	/// Get the protocol name associated with each peer set as static str.
+	/// Get the legacy protocol name of this protocol, which is the same on all chains.
+	///
+	/// See `ReqProtocolNames` for the names used on a chain. This name is also used for labeling
+	/// metrics.
 	pub const fn get_protocol_name_static(self) -> &'static str {
 		match self {
 			Protocol::ChunkFetching => "/polkadot/req_chunk/1",
@@ -238,6 +492,78 @@ impl Protocol {
 			Protocol::AvailableDataFetching => "/polkadot/req_available_data/1",
 			Protocol::StatementFetching => "/polkadot/req_statement/1",
 			Protocol::DisputeSending => "/polkadot/send_dispute/1",
+			Protocol::MultiChunkFetching => "/polkadot/req_multi_chunk/1",
+			Protocol::CompressedPoVFetching => "/polkadot/req_pov/2",
+			Protocol::CompressedAvailableDataFetching => "/polkadot/req_available_data/2",
+			Protocol::CollationFetchingV2 => "/polkadot/req_collation/2",
+			Protocol::DisputeBatchSending => "/polkadot/send_dispute/2",
+			// The second version is taken by `CompressedAvailableDataFetching`.
+			Protocol::AvailableDataFetchingV2 => "/polkadot/req_available_data/3",
+			Protocol::StatementFetchingV2 => "/polkadot/req_statement/2",
 		}
 	}
 }
+
+impl std::str::FromStr for Protocol {
+	type Err = String;
+
+	/// Parse the short name of a protocol.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Protocol::iter()
+			.find(|p| p.short_name() == s)
+			.ok_or_else(|| format!(
+				"Unknown request-response protocol `{}`, expected one of: {}",
+				s,
+				Protocol::iter().map(Protocol::short_name).collect::<Vec<_>>().join(", "),
+			))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::collections::HashSet;
+
+	fn config_names(names: &ReqProtocolNames) -> HashSet<Cow<'static, str>> {
+		Protocol::iter().map(|p| p.get_config(names).1.name).collect()
+	}
+
+	#[test]
+	fn names_of_different_chains_are_disjoint() {
+		let polkadot = ReqProtocolNames::new([1u8; 32], None, false);
+		let fork = ReqProtocolNames::new([2u8; 32], None, false);
+		let forked_polkadot = ReqProtocolNames::new([1u8; 32], Some("fork"), false);
+
+		let polkadot_names = config_names(&polkadot);
+		assert_eq!(polkadot_names.len(), Protocol::iter().count());
+		assert!(polkadot_names.is_disjoint(&config_names(&fork)));
+		assert!(polkadot_names.is_disjoint(&config_names(&forked_polkadot)));
+
+		assert_eq!(
+			polkadot.get_name(Protocol::ChunkFetching),
+			format!("/{}/req_chunk/1", "01".repeat(32)),
+		);
+		assert_eq!(
+			forked_polkadot.get_name(Protocol::DisputeSending),
+			format!("/{}/fork/send_dispute/1", "01".repeat(32)),
+		);
+	}
+
+	#[test]
+	fn legacy_names_are_used_for_outgoing_requests_during_transition() {
+		let transition = ReqProtocolNames::new([1u8; 32], None, true);
+		assert_eq!(transition.get_outgoing_name(Protocol::PoVFetching), "/polkadot/req_pov/1");
+		// Configs always carry the chain specific name.
+		assert_eq!(
+			Protocol::PoVFetching.get_config(&transition).1.name,
+			transition.get_name(Protocol::PoVFetching),
+		);
+
+		let done = ReqProtocolNames::new([1u8; 32], None, false);
+		assert_eq!(
+			done.get_outgoing_name(Protocol::PoVFetching),
+			done.get_name(Protocol::PoVFetching),
+		);
+	}
+}
@@ -15,9 +15,12 @@
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::marker::PhantomData;
+use std::pin::Pin;
 
-use futures::channel::oneshot;
-use futures::prelude::Future;
+use futures::channel::{mpsc, oneshot};
+use futures::prelude::{Future, Stream};
+use futures::task::{Context, Poll};
+use futures::StreamExt;
 
 use thiserror::Error;
 use parity_scale_codec::{Decode, Encode, Error as DecodingError};
@@ -29,7 +32,7 @@ use polkadot_primitives::v1::AuthorityDiscoveryId;
 
 use crate::UnifiedReputationChange;
 
-use super::{v1, Protocol};
+use super::{v1, v2, Protocol};
 
 /// Used by the network to send us a response to a request.
 pub type ResponseSender = oneshot::Sender<Result<Vec<u8>, network::RequestFailure>>;
@@ -58,6 +61,20 @@ pub enum Requests {
 	StatementFetching(OutgoingRequest<v1::StatementFetchingRequest>),
 	/// Requests for notifying about an ongoing dispute.
 	DisputeSending(OutgoingRequest<v1::DisputeRequest>),
+	/// Request several availability chunks of a candidate from a node.
+	MultiChunkFetching(OutgoingRequest<v1::MultiChunkFetchingRequest>),
+	/// Fetch a PoV, with a compressed response.
+	CompressedPoVFetching(OutgoingRequest<v1::CompressedPoVFetchingRequest>),
+	/// Request full available data, with a compressed response.
+	CompressedAvailableDataFetching(OutgoingRequest<v1::CompressedAvailableDataFetchingRequest>),
+	/// Fetch a collation signed by the collator which previously announced it.
+	CollationFetchingV2(OutgoingRequest<v2::CollationFetchingRequest>),
+	/// Requests for notifying about several ongoing disputes at once.
+	DisputeBatchSending(OutgoingRequest<v2::DisputeBatchRequest>),
+	/// Fetch available data, or a segment of it.
+	AvailableDataFetchingV2(OutgoingRequest<v2::AvailableDataFetchingRequest>),
+	/// Fetch the data of several large statements of the same relay parent.
+	StatementFetchingV2(OutgoingRequest<v2::StatementFetchingRequest>),
 }
 
 impl Requests {
@@ -70,6 +87,13 @@ impl Requests {
 			Self::AvailableDataFetching(_) => Protocol::AvailableDataFetching,
 			Self::StatementFetching(_) => Protocol::StatementFetching,
 			Self::DisputeSending(_) => Protocol::DisputeSending,
+			Self::MultiChunkFetching(_) => Protocol::MultiChunkFetching,
+			Self::CompressedPoVFetching(_) => Protocol::CompressedPoVFetching,
+			Self::CompressedAvailableDataFetching(_) => Protocol::CompressedAvailableDataFetching,
+			Self::CollationFetchingV2(_) => Protocol::CollationFetchingV2,
+			Self::DisputeBatchSending(_) => Protocol::DisputeBatchSending,
+			Self::AvailableDataFetchingV2(_) => Protocol::AvailableDataFetchingV2,
+			Self::StatementFetchingV2(_) => Protocol::StatementFetchingV2,
 		}
 	}
 
@@ -88,10 +112,54 @@ impl Requests {
 			Self::AvailableDataFetching(r) => r.encode_request(),
 			Self::StatementFetching(r) => r.encode_request(),
 			Self::DisputeSending(r) => r.encode_request(),
+			Self::MultiChunkFetching(r) => r.encode_request(),
+			Self::CompressedPoVFetching(r) => r.encode_request(),
+			Self::CompressedAvailableDataFetching(r) => r.encode_request(),
+			Self::CollationFetchingV2(r) => r.encode_request(),
+			Self::DisputeBatchSending(r) => r.encode_request(),
+			Self::AvailableDataFetchingV2(r) => r.encode_request(),
+			Self::StatementFetchingV2(r) => r.encode_request(),
+		}
+	}
+
+	/// The priority the network bridge sends this request with.
+	pub fn priority(&self) -> Priority {
+		match self {
+			Self::ChunkFetching(r) => r.priority,
+			Self::CollationFetching(r) => r.priority,
+			Self::PoVFetching(r) => r.priority,
+			Self::AvailableDataFetching(r) => r.priority,
+			Self::StatementFetching(r) => r.priority,
+			Self::DisputeSending(r) => r.priority,
+			Self::MultiChunkFetching(r) => r.priority,
+			Self::CompressedPoVFetching(r) => r.priority,
+			Self::CompressedAvailableDataFetching(r) => r.priority,
+			Self::CollationFetchingV2(r) => r.priority,
+			Self::DisputeBatchSending(r) => r.priority,
+			Self::AvailableDataFetchingV2(r) => r.priority,
+			Self::StatementFetchingV2(r) => r.priority,
 		}
 	}
 }
 
+/// The lane an outgoing request is sent in by the network bridge.
+///
+/// Critical requests are sent before bulk requests, but bulk requests still get their turn every
+/// now and then, so they are never starved.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum Priority {
+	/// Latency critical requests, such as disputes and statements.
+	Critical,
+	/// Requests for large amounts of data, such as chunks and PoVs.
+	Bulk,
+}
+
+impl Default for Priority {
+	fn default() -> Self {
+		Priority::Bulk
+	}
+}
+
 /// Potential recipients of an outgoing request.
 #[derive(Debug, Eq, Hash, PartialEq, Clone)]
 pub enum Recipient {
@@ -119,6 +187,8 @@ pub struct OutgoingRequest<Req> {
 	pub payload: Req,
 	/// Sender which is used by networking to get us back a response.
 	pub pending_response: ResponseSender,
+	/// The lane the request is sent in.
+	pub priority: Priority,
 }
 
 /// Any error that can occur when sending a request.
@@ -157,7 +227,7 @@ where
 	Req: IsRequest + Encode,
 	Req::Response: Decode,
 {
-	/// Create a new `OutgoingRequest`.
+	/// Create a new `OutgoingRequest` of bulk priority.
 	///
 	/// It will contain a sender that is used by the networking for sending back responses. The
 	/// connected receiver is returned as the second element in the returned tuple.
@@ -167,12 +237,25 @@ where
 	) -> (
 		Self,
 		impl Future<Output = OutgoingResult<Req::Response>>,
+	) {
+		Self::new_with_priority(peer, payload, Priority::Bulk)
+	}
+
+	/// Create a new `OutgoingRequest`, sent in the lane of `priority`.
+	pub fn new_with_priority(
+		peer: Recipient,
+		payload: Req,
+		priority: Priority,
+	) -> (
+		Self,
+		impl Future<Output = OutgoingResult<Req::Response>>,
 	) {
 		let (tx, rx) = oneshot::channel();
 		let r = Self {
 			peer,
 			payload,
 			pending_response: tx,
+			priority,
 		};
 		(r, receive_response::<Req>(rx))
 	}
@@ -186,16 +269,29 @@ where
 			peer,
 			payload,
 			pending_response,
+			priority,
 		} = self;
 		let encoded = OutgoingRequest {
 			peer,
 			payload: payload.encode(),
 			pending_response,
+			priority,
 		};
 		(Req::PROTOCOL, encoded)
 	}
 }
 
+impl RequestError {
+	/// Whether the recipient does not know the protocol of the request, e.g. because it runs a
+	/// version only knowing older versions of the protocol.
+	pub fn is_unsupported_protocol(&self) -> bool {
+		matches!(
+			self,
+			Self::NetworkError(network::RequestFailure::Network(network::OutboundFailure::UnsupportedProtocols))
+		)
+	}
+}
+
 impl From<DecodingError> for RequestError {
 	fn from(err: DecodingError) -> Self {
 		Self::InvalidResponse(err)
@@ -279,6 +375,17 @@ where
 
 		self.pending_response.send(response).map_err(|_| ())
 	}
+
+	/// Refuse the request for `reason`, so the requester knows whether to retry.
+	pub fn send_refusal(self, reason: v1::RefusalReason) -> Result<(), ()> {
+		self.pending_response
+			.send(netconfig::OutgoingResponse {
+				result: Ok(Req::PROTOCOL.encode_refusal(reason)),
+				reputation_changes: Vec::new(),
+				sent_feedback: None,
+			})
+			.map_err(|_| ())
+	}
 }
 
 /// Typed variant of [`netconfig::OutgoingResponse`].
@@ -322,8 +429,8 @@ where
 
 	/// Try building from raw substrate request.
 	///
-	/// This function will fail if the request cannot be decoded and will apply passed in
-	/// reputation changes in that case.
+	/// This function will fail if the request cannot be decoded, refuse it as invalid and apply
+	/// passed in reputation changes in that case.
 	///
 	/// Params:
 	///		- The raw request to decode
@@ -345,7 +452,7 @@ where
 					.map(|r| r.into_base_rep())
 					.collect();
 				let response = sc_network::config::OutgoingResponse {
-					result: Err(()),
+					result: Ok(Req::PROTOCOL.encode_refusal(v1::RefusalReason::Invalid)),
 					reputation_changes,
 					sent_feedback: None,
 				};
@@ -373,6 +480,65 @@ where
 		-> Result<(), ()> {
 		self.pending_response.send_outgoing_response(resp)
 	}
+
+	/// Refuse the request for `reason`, instead of dropping it.
+	///
+	/// Calls [`OutgoingResponseSender::send_refusal`].
+	pub fn respond_refusal(self, reason: v1::RefusalReason) -> Result<(), ()> {
+		self.pending_response.send_refusal(reason)
+	}
+}
+
+/// The receivers of several versions of a protocol, whose raw requests are handled by the same
+/// subsystem instead of being multiplexed.
+///
+/// Requests are yielded along with the protocol they were received on, so they can be decoded and
+/// answered according to their version. The receivers are polled in turns, so no version starves
+/// the others. The stream ends once all receivers ended.
+#[derive(Debug)]
+pub struct VersionedReceiver {
+	receivers: Vec<(Protocol, mpsc::Receiver<netconfig::IncomingRequest>)>,
+	/// Index of the receiver to poll first.
+	next_poll: usize,
+}
+
+impl VersionedReceiver {
+	/// Receive the requests of all `receivers`, each of the protocol it is paired with.
+	pub fn new(receivers: Vec<(Protocol, mpsc::Receiver<netconfig::IncomingRequest>)>) -> Self {
+		VersionedReceiver { receivers, next_poll: 0 }
+	}
+}
+
+impl Stream for VersionedReceiver {
+	type Item = (Protocol, netconfig::IncomingRequest);
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = &mut *self;
+		let mut index = this.next_poll;
+		for _ in 0..this.receivers.len() {
+			if index >= this.receivers.len() {
+				index = 0;
+			}
+			let (protocol, receiver) = &mut this.receivers[index];
+			match receiver.poll_next_unpin(cx) {
+				Poll::Ready(Some(request)) => {
+					this.next_poll = index + 1;
+					return Poll::Ready(Some((*protocol, request)))
+				}
+				// The receivers after `index` move down by one, so the next one is at `index`.
+				Poll::Ready(None) => {
+					this.receivers.remove(index);
+				}
+				Poll::Pending => index += 1,
+			}
+		}
+		this.next_poll = index;
+		if this.receivers.is_empty() {
+			Poll::Ready(None)
+		} else {
+			Poll::Pending
+		}
+	}
 }
 
 /// Future for actually receiving a typed response for an OutgoingRequest.
@@ -386,3 +552,42 @@ where
 	let raw = rec.await??;
 	Ok(Decode::decode(&mut raw.as_ref())?)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::executor::block_on;
+
+	fn incoming(payload: Vec<u8>) -> netconfig::IncomingRequest {
+		let (pending_response, _) = oneshot::channel();
+		netconfig::IncomingRequest { peer: PeerId::random(), payload, pending_response }
+	}
+
+	#[test]
+	fn versioned_receivers_yield_requests_with_their_protocol() {
+		let (mut v1_tx, v1_rx) = mpsc::channel(10);
+		let (mut v2_tx, v2_rx) = mpsc::channel(10);
+		let mut receiver = VersionedReceiver::new(vec![
+			(Protocol::StatementFetching, v1_rx),
+			(Protocol::StatementFetchingV2, v2_rx),
+		]);
+		for i in 0..2 {
+			v1_tx.try_send(incoming(vec![1, i])).unwrap();
+		}
+		v2_tx.try_send(incoming(vec![2, 0])).unwrap();
+		drop(v1_tx);
+		drop(v2_tx);
+
+		let mut received = Vec::new();
+		while let Some((protocol, request)) = block_on(receiver.next()) {
+			received.push((protocol, request.payload));
+		}
+		// Both versions take turns, and the stream ends with both receivers.
+		assert_eq!(received, vec![
+			(Protocol::StatementFetching, vec![1, 0]),
+			(Protocol::StatementFetchingV2, vec![2, 0]),
+			(Protocol::StatementFetching, vec![1, 1]),
+		]);
+	}
+}
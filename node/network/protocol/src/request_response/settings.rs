@@ -0,0 +1,216 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per protocol overrides of request timeouts and incoming request channel sizes.
+//!
+//! The defaults of `Protocol::get_config` suit most nodes, but operators of nodes on slow links
+//! or with many peers may want to tune them. Overrides are validated when they are set, so a
+//! `RequestResponseSettings` only ever holds values within sane bounds.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use super::{Protocol, ReqProtocolNames};
+
+/// Requests timing out sooner than this could hardly ever succeed.
+pub const MIN_REQUEST_TIMEOUT: Duration = Duration::from_millis(100);
+/// Requests taking longer than this are of no use to any subsystem.
+pub const MAX_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// A channel needs room for at least one request.
+pub const MIN_CHANNEL_SIZE: usize = 1;
+/// Requests beyond this would be queued for longer than any timeout.
+pub const MAX_CHANNEL_SIZE: usize = 10_000;
+
+/// An override which was rejected.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidSetting {
+	/// The request timeout is out of bounds.
+	#[error("Request timeout of {timeout:?} for {protocol:?} is not between {min:?} and {max:?}", min = MIN_REQUEST_TIMEOUT, max = MAX_REQUEST_TIMEOUT)]
+	RequestTimeout {
+		/// The protocol the timeout was meant for.
+		protocol: Protocol,
+		/// The rejected timeout.
+		timeout: Duration,
+	},
+	/// The channel size is out of bounds.
+	#[error("Channel size of {size} for {protocol:?} is not between {min} and {max}", min = MIN_CHANNEL_SIZE, max = MAX_CHANNEL_SIZE)]
+	ChannelSize {
+		/// The protocol the channel size was meant for.
+		protocol: Protocol,
+		/// The rejected channel size.
+		size: usize,
+	},
+}
+
+/// Overrides of the defaults of a single protocol, `None` keeps the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProtocolSettings {
+	/// Timeout of outgoing requests.
+	pub request_timeout: Option<Duration>,
+	/// Size of the channel for incoming requests.
+	pub channel_size: Option<usize>,
+}
+
+/// Overrides of the defaults of all protocols, as given in the node configuration.
+#[derive(Clone, Debug)]
+pub struct RequestResponseSettings {
+	protocols: HashMap<Protocol, ProtocolSettings>,
+	fork_id: Option<String>,
+	legacy_names: bool,
+}
+
+impl Default for RequestResponseSettings {
+	fn default() -> Self {
+		RequestResponseSettings {
+			protocols: HashMap::new(),
+			fork_id: None,
+			// Until all nodes know the chain specific names.
+			legacy_names: true,
+		}
+	}
+}
+
+impl RequestResponseSettings {
+	/// Set the fork id, which is part of the protocol names of the chain.
+	pub fn set_fork_id(&mut self, fork_id: Option<String>) {
+		self.fork_id = fork_id;
+	}
+
+	/// Set whether the legacy protocol names are registered and used as well.
+	pub fn set_legacy_names(&mut self, legacy_names: bool) {
+		self.legacy_names = legacy_names;
+	}
+
+	/// The protocol names on the chain of `genesis_hash`.
+	pub fn protocol_names<Hash: AsRef<[u8]>>(&self, genesis_hash: Hash) -> ReqProtocolNames {
+		ReqProtocolNames::new(genesis_hash, self.fork_id.as_deref(), self.legacy_names)
+	}
+
+	/// Override the request timeout of `protocol`.
+	pub fn set_request_timeout(
+		&mut self,
+		protocol: Protocol,
+		timeout: Duration,
+	) -> Result<(), InvalidSetting> {
+		if timeout < MIN_REQUEST_TIMEOUT || timeout > MAX_REQUEST_TIMEOUT {
+			return Err(InvalidSetting::RequestTimeout { protocol, timeout })
+		}
+		self.protocols.entry(protocol).or_default().request_timeout = Some(timeout);
+		Ok(())
+	}
+
+	/// Override the size of the channel for incoming requests of `protocol`.
+	pub fn set_channel_size(
+		&mut self,
+		protocol: Protocol,
+		size: usize,
+	) -> Result<(), InvalidSetting> {
+		if size < MIN_CHANNEL_SIZE || size > MAX_CHANNEL_SIZE {
+			return Err(InvalidSetting::ChannelSize { protocol, size })
+		}
+		self.protocols.entry(protocol).or_default().channel_size = Some(size);
+		Ok(())
+	}
+
+	/// The overrides of `protocol`.
+	pub fn get(&self, protocol: Protocol) -> ProtocolSettings {
+		self.protocols.get(&protocol).copied().unwrap_or_default()
+	}
+
+	/// All protocols with overrides, for logging them.
+	pub fn overrides(&self) -> impl Iterator<Item = (Protocol, ProtocolSettings)> + '_ {
+		self.protocols.iter().map(|(p, s)| (*p, *s))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::channel::oneshot;
+	use sc_network::PeerId;
+
+	use crate::request_response::network::{IncomingRequest, RequestResponseConfig};
+
+	/// How many requests fit into the incoming channel of `config`.
+	fn fitting_requests(config: &RequestResponseConfig) -> usize {
+		let mut tx = config.inbound_queue.clone().expect("requests are served");
+		let mut fitting = 0;
+		loop {
+			let (pending_response, _) = oneshot::channel();
+			let request = IncomingRequest { peer: PeerId::random(), payload: Vec::new(), pending_response };
+			if tx.try_send(request).is_err() {
+				break fitting
+			}
+			fitting += 1;
+		}
+	}
+
+	#[test]
+	fn overrides_end_up_in_the_config() {
+		let mut settings = RequestResponseSettings::default();
+		settings.set_request_timeout(Protocol::ChunkFetching, Duration::from_secs(3)).unwrap();
+		settings.set_channel_size(Protocol::ChunkFetching, 42).unwrap();
+		settings.set_channel_size(Protocol::PoVFetching, 7).unwrap();
+
+		let names = settings.protocol_names([0u8; 32]);
+		let (_rx, chunk) = Protocol::ChunkFetching
+			.get_config_with(&names, settings.get(Protocol::ChunkFetching));
+		assert_eq!(chunk.request_timeout, Duration::from_secs(3));
+		// The channel has room for one more request per sender, the config's and our clone.
+		assert_eq!(fitting_requests(&chunk), 42 + 2);
+
+		let (_rx, pov) = Protocol::PoVFetching
+			.get_config_with(&names, settings.get(Protocol::PoVFetching));
+		let (_, default_pov) = Protocol::PoVFetching.get_config(&names);
+		assert_eq!(pov.request_timeout, default_pov.request_timeout);
+		assert_eq!(fitting_requests(&pov), 7 + 2);
+		assert_eq!(settings.overrides().count(), 2);
+	}
+
+	#[test]
+	fn nonsensical_values_are_rejected() {
+		let mut settings = RequestResponseSettings::default();
+		assert_eq!(
+			settings.set_request_timeout(Protocol::DisputeSending, Duration::from_millis(0)),
+			Err(InvalidSetting::RequestTimeout {
+				protocol: Protocol::DisputeSending,
+				timeout: Duration::from_millis(0),
+			}),
+		);
+		assert!(settings.set_request_timeout(Protocol::DisputeSending, Duration::from_secs(3600)).is_err());
+		assert_eq!(
+			settings.set_channel_size(Protocol::StatementFetching, 0),
+			Err(InvalidSetting::ChannelSize { protocol: Protocol::StatementFetching, size: 0 }),
+		);
+		assert!(settings.set_channel_size(Protocol::StatementFetching, MAX_CHANNEL_SIZE + 1).is_err());
+
+		// Nothing was overridden.
+		assert_eq!(settings.overrides().count(), 0);
+		assert_eq!(settings.get(Protocol::DisputeSending), ProtocolSettings::default());
+	}
+
+	#[test]
+	fn protocols_parse_from_their_short_names() {
+		use strum::IntoEnumIterator;
+		for protocol in Protocol::iter() {
+			assert_eq!(protocol.short_name().parse::<Protocol>(), Ok(protocol));
+		}
+		assert!("chunks".parse::<Protocol>().is_err());
+	}
+}
@@ -22,8 +22,34 @@ use polkadot_primitives::v1::{CandidateHash, CandidateReceipt, CommittedCandidat
 use polkadot_primitives::v1::Id as ParaId;
 use polkadot_node_primitives::{AvailableData, DisputeMessage, ErasureChunk, PoV, UncheckedDisputeMessage};
 
+use super::compression::{CompressibleResponse, Compressed};
 use super::request::IsRequest;
-use super::Protocol;
+use super::{MAX_AVAILABLE_DATA_RESPONSE_SIZE, MAX_POV_RESPONSE_SIZE, Protocol};
+
+/// Why a request was refused by its recipient.
+///
+/// Every response type has a `Refused` variant, so that requesters can tell whether to retry.
+#[derive(Debug, Copy, Clone, Encode, Decode, PartialEq, Eq)]
+pub enum RefusalReason {
+	/// The recipient is busy or the requester exceeded its rate limit, the request may succeed if
+	/// retried later.
+	#[codec(index = 0)]
+	Busy,
+	/// The request is invalid or can't be served, e.g. because of an unknown relay parent, it
+	/// should not be retried.
+	#[codec(index = 1)]
+	Invalid,
+}
+
+impl RefusalReason {
+	/// Whether the refused request may succeed if retried later.
+	pub fn should_retry(self) -> bool {
+		match self {
+			RefusalReason::Busy => true,
+			RefusalReason::Invalid => false,
+		}
+	}
+}
 
 /// Request an availability chunk.
 #[derive(Debug, Copy, Clone, Encode, Decode)]
@@ -43,6 +69,9 @@ pub enum ChunkFetchingResponse {
 	/// Node was not in possession of the requested chunk.
 	#[codec(index = 1)]
 	NoSuchChunk,
+	/// Node refused to serve the request.
+	#[codec(index = 2)]
+	Refused(RefusalReason),
 }
 
 impl From<Option<ChunkResponse>> for ChunkFetchingResponse {
@@ -90,6 +119,45 @@ impl IsRequest for ChunkFetchingRequest {
 	const PROTOCOL: Protocol = Protocol::ChunkFetching;
 }
 
+/// The most chunks a single `MultiChunkFetchingRequest` may ask for.
+pub const MAX_CHUNKS_PER_REQUEST: usize = 10;
+
+/// Request several availability chunks of the same candidate at once.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct MultiChunkFetchingRequest {
+	/// Hash of candidate we want chunks for.
+	pub candidate_hash: CandidateHash,
+	/// The indices of the chunks to fetch, at most `MAX_CHUNKS_PER_REQUEST`.
+	pub indices: Vec<ValidatorIndex>,
+}
+
+impl MultiChunkFetchingRequest {
+	/// The single chunk requests combined in this request, in order.
+	///
+	/// Useful for recombining the chunks of the response with `ChunkResponse::recombine_into_chunk`.
+	pub fn chunk_requests(&self) -> impl Iterator<Item = ChunkFetchingRequest> + '_ {
+		let candidate_hash = self.candidate_hash;
+		self.indices.iter().map(move |&index| ChunkFetchingRequest { candidate_hash, index })
+	}
+}
+
+/// Receive several requested erasure chunks.
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum MultiChunkFetchingResponse {
+	/// An entry per requested index, in the order of the request. `None` if the node was not in
+	/// possession of that chunk.
+	#[codec(index = 0)]
+	Chunks(Vec<Option<ChunkResponse>>),
+	/// Node refused to serve the request.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
+}
+
+impl IsRequest for MultiChunkFetchingRequest {
+	type Response = MultiChunkFetchingResponse;
+	const PROTOCOL: Protocol = Protocol::MultiChunkFetching;
+}
+
 /// Request the advertised collation at that relay-parent.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct CollationFetchingRequest {
@@ -105,6 +173,9 @@ pub enum CollationFetchingResponse {
 	/// Deliver requested collation.
 	#[codec(index = 0)]
 	Collation(CandidateReceipt, PoV),
+	/// Collator refused to serve the request.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
 }
 
 impl IsRequest for CollationFetchingRequest {
@@ -128,6 +199,9 @@ pub enum PoVFetchingResponse {
 	/// PoV was not found in store.
 	#[codec(index = 1)]
 	NoSuchPoV,
+	/// Node refused to serve the request.
+	#[codec(index = 2)]
+	Refused(RefusalReason),
 }
 
 impl IsRequest for PoVFetchingRequest {
@@ -135,6 +209,19 @@ impl IsRequest for PoVFetchingRequest {
 	const PROTOCOL: Protocol = Protocol::PoVFetching;
 }
 
+impl CompressibleResponse for PoVFetchingResponse {
+	const MAX_SIZE: usize = MAX_POV_RESPONSE_SIZE as usize;
+}
+
+/// A `PoVFetchingRequest`, answered with a compressed response.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct CompressedPoVFetchingRequest(pub PoVFetchingRequest);
+
+impl IsRequest for CompressedPoVFetchingRequest {
+	type Response = Compressed<PoVFetchingResponse>;
+	const PROTOCOL: Protocol = Protocol::CompressedPoVFetching;
+}
+
 /// Request the entire available data for a candidate.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct AvailableDataFetchingRequest {
@@ -151,6 +238,9 @@ pub enum AvailableDataFetchingResponse {
 	/// Node was not in possession of the requested data.
 	#[codec(index = 1)]
 	NoSuchData,
+	/// Node refused to serve the request.
+	#[codec(index = 2)]
+	Refused(RefusalReason),
 }
 
 impl From<Option<AvailableData>> for AvailableDataFetchingResponse {
@@ -167,6 +257,19 @@ impl IsRequest for AvailableDataFetchingRequest {
 	const PROTOCOL: Protocol = Protocol::AvailableDataFetching;
 }
 
+impl CompressibleResponse for AvailableDataFetchingResponse {
+	const MAX_SIZE: usize = MAX_AVAILABLE_DATA_RESPONSE_SIZE as usize;
+}
+
+/// An `AvailableDataFetchingRequest`, answered with a compressed response.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct CompressedAvailableDataFetchingRequest(pub AvailableDataFetchingRequest);
+
+impl IsRequest for CompressedAvailableDataFetchingRequest {
+	type Response = Compressed<AvailableDataFetchingResponse>;
+	const PROTOCOL: Protocol = Protocol::CompressedAvailableDataFetching;
+}
+
 /// Request for fetching a large statement via request/response.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct StatementFetchingRequest {
@@ -186,6 +289,9 @@ pub enum StatementFetchingResponse {
 	/// Data missing to reconstruct the full signed statement.
 	#[codec(index = 0)]
 	Statement(CommittedCandidateReceipt),
+	/// Node refused to serve the request.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
 }
 
 impl IsRequest for StatementFetchingRequest {
@@ -210,10 +316,109 @@ impl From<DisputeMessage> for DisputeRequest {
 pub enum DisputeResponse {
 	/// Recipient successfully processed the dispute request.
 	#[codec(index = 0)]
-	Confirmed
+	Confirmed,
+	/// Recipient refused to process the dispute request.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
 }
 
 impl IsRequest for DisputeRequest {
 	type Response = DisputeResponse;
 	const PROTOCOL: Protocol = Protocol::DisputeSending;
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn old_response_encodings_still_decode() {
+		let chunk = ChunkResponse { chunk: vec![1, 2], proof: vec![vec![3]] };
+		assert!(matches!(
+			ChunkFetchingResponse::decode(&mut &[0u8, 8, 1, 2, 4, 4, 3][..]),
+			Ok(ChunkFetchingResponse::Chunk(c)) if c.chunk == chunk.chunk && c.proof == chunk.proof
+		));
+		assert!(matches!(
+			ChunkFetchingResponse::decode(&mut &[1u8][..]),
+			Ok(ChunkFetchingResponse::NoSuchChunk)
+		));
+		assert!(matches!(
+			PoVFetchingResponse::decode(&mut &[1u8][..]),
+			Ok(PoVFetchingResponse::NoSuchPoV)
+		));
+		assert!(matches!(
+			AvailableDataFetchingResponse::decode(&mut &[1u8][..]),
+			Ok(AvailableDataFetchingResponse::NoSuchData)
+		));
+		assert_eq!(DisputeResponse::decode(&mut &[0u8][..]), Ok(DisputeResponse::Confirmed));
+	}
+
+	#[test]
+	fn refusals_are_appended() {
+		for reason in [RefusalReason::Busy, RefusalReason::Invalid].iter().copied() {
+			let reason_byte = reason.encode()[0];
+			assert_eq!(ChunkFetchingResponse::Refused(reason).encode(), vec![2, reason_byte]);
+			assert_eq!(CollationFetchingResponse::Refused(reason).encode(), vec![1, reason_byte]);
+			assert_eq!(PoVFetchingResponse::Refused(reason).encode(), vec![2, reason_byte]);
+			assert_eq!(AvailableDataFetchingResponse::Refused(reason).encode(), vec![2, reason_byte]);
+			assert_eq!(StatementFetchingResponse::Refused(reason).encode(), vec![1, reason_byte]);
+			assert_eq!(DisputeResponse::Refused(reason).encode(), vec![1, reason_byte]);
+			assert_eq!(MultiChunkFetchingResponse::Refused(reason).encode(), vec![1, reason_byte]);
+		}
+		assert_eq!(RefusalReason::Busy.encode(), vec![0]);
+		assert_eq!(RefusalReason::Invalid.encode(), vec![1]);
+	}
+
+	#[test]
+	fn multi_chunk_responses_fit_the_size_limits() {
+		use crate::request_response::{
+			MAX_CHUNK_RESPONSE_SIZE, MAX_MULTI_CHUNK_RESPONSE_SIZE, ReqProtocolNames,
+		};
+		let (_, config) = Protocol::MultiChunkFetching
+			.get_config(&ReqProtocolNames::new([0u8; 32], None, false));
+
+		// The largest chunk a single chunk response may carry.
+		let mut chunk = ChunkResponse { chunk: vec![0; MAX_CHUNK_RESPONSE_SIZE as usize], proof: Vec::new() };
+		while ChunkFetchingResponse::Chunk(chunk.clone()).encoded_size() as u64 > MAX_CHUNK_RESPONSE_SIZE {
+			chunk.chunk.pop();
+		}
+		let response = MultiChunkFetchingResponse::Chunks(vec![Some(chunk); MAX_CHUNKS_PER_REQUEST]);
+		assert!(response.encoded_size() as u64 <= MAX_MULTI_CHUNK_RESPONSE_SIZE);
+		assert_eq!(config.max_response_size, MAX_MULTI_CHUNK_RESPONSE_SIZE);
+
+		let request = MultiChunkFetchingRequest {
+			candidate_hash: CandidateHash(Hash::repeat_byte(1)),
+			indices: vec![ValidatorIndex(u32::MAX); MAX_CHUNKS_PER_REQUEST],
+		};
+		assert!(request.encoded_size() as u64 <= config.max_request_size);
+	}
+
+	#[test]
+	fn multi_chunk_requests_round_trip() {
+		let request = MultiChunkFetchingRequest {
+			candidate_hash: CandidateHash(Hash::repeat_byte(1)),
+			indices: vec![ValidatorIndex(3), ValidatorIndex(0), ValidatorIndex(7)],
+		};
+		let decoded = MultiChunkFetchingRequest::decode(&mut &request.encode()[..]).unwrap();
+		assert_eq!(decoded.candidate_hash, request.candidate_hash);
+		assert_eq!(decoded.indices, request.indices);
+		assert_eq!(
+			decoded.chunk_requests().map(|r| r.index).collect::<Vec<_>>(),
+			request.indices,
+		);
+
+		let chunk = ChunkResponse { chunk: vec![1, 2], proof: vec![vec![3]] };
+		let response = MultiChunkFetchingResponse::Chunks(vec![Some(chunk.clone()), None, Some(chunk)]);
+		match MultiChunkFetchingResponse::decode(&mut &response.encode()[..]).unwrap() {
+			MultiChunkFetchingResponse::Chunks(chunks) => {
+				assert_eq!(chunks.len(), 3);
+				assert!(chunks[1].is_none());
+				for chunk in [&chunks[0], &chunks[2]].iter() {
+					let chunk = chunk.as_ref().expect("chunk was sent");
+					assert_eq!((&chunk.chunk[..], &chunk.proof[..]), (&[1, 2][..], &[vec![3]][..]));
+				}
+			}
+			MultiChunkFetchingResponse::Refused(_) => panic!("chunks were sent"),
+		}
+	}
+}
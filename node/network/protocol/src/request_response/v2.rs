@@ -0,0 +1,586 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Requests and responses of the second version of a protocol, for the protocols which have one.
+
+use parity_scale_codec::{Decode, Encode};
+use thiserror::Error;
+
+use polkadot_primitives::v1::{
+	CandidateHash, CandidateReceipt, CollatorSignature, CommittedCandidateReceipt, Hash,
+};
+use polkadot_primitives::v1::Id as ParaId;
+use polkadot_node_primitives::{AvailableData, PoV, UncheckedDisputeMessage};
+
+use super::request::IsRequest;
+use super::v1::{DisputeResponse, RefusalReason};
+use super::{Protocol, MAX_AVAILABLE_DATA_RESPONSE_SIZE, MAX_STATEMENT_RESPONSE_SIZE};
+
+/// Request the advertised collation at that relay-parent, answered with a signed collation.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct CollationFetchingRequest {
+	/// Relay parent we want a collation for.
+	pub relay_parent: Hash,
+	/// The `ParaId` of the collation.
+	pub para_id: ParaId,
+}
+
+impl From<super::v1::CollationFetchingRequest> for CollationFetchingRequest {
+	fn from(req: super::v1::CollationFetchingRequest) -> Self {
+		CollationFetchingRequest { relay_parent: req.relay_parent, para_id: req.para_id }
+	}
+}
+
+/// A collation, signed by the collator which built it.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct SignedCollation {
+	/// The receipt of the collation's candidate.
+	pub receipt: CandidateReceipt,
+	/// The proof of validity of the candidate.
+	pub pov: PoV,
+	/// The hash of `pov`, as signed by the collator.
+	pub pov_hash: Hash,
+	/// The signature of the collator over `collation_signature_payload`.
+	pub signature: CollatorSignature,
+}
+
+/// Responses as sent by collators.
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum CollationFetchingResponse {
+	/// Deliver requested collation.
+	#[codec(index = 0)]
+	Collation(SignedCollation),
+	/// Collator refused to serve the request.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
+}
+
+impl IsRequest for CollationFetchingRequest {
+	type Response = CollationFetchingResponse;
+	const PROTOCOL: Protocol = Protocol::CollationFetchingV2;
+}
+
+/// Get the payload that should be signed by the collator in a `SignedCollation`.
+///
+/// The payload binds the PoV to the relay parent and para it was requested for, so a collation
+/// can't be replayed for another request.
+pub fn collation_signature_payload(relay_parent: &Hash, para_id: &ParaId, pov_hash: &Hash) -> Vec<u8> {
+	(b"COLL", relay_parent, para_id, pov_hash).encode()
+}
+
+/// The most disputes a single `DisputeBatchRequest` may carry.
+pub const MAX_DISPUTES_PER_BATCH: usize = 10;
+
+/// Votes of several disputes, each as in a `v1::DisputeRequest`, at most
+/// `MAX_DISPUTES_PER_BATCH`.
+#[derive(Clone, Encode, Decode, Debug)]
+pub struct DisputeBatchRequest(pub Vec<UncheckedDisputeMessage>);
+
+/// Possible responses to a `DisputeBatchRequest`.
+#[derive(Encode, Decode, Debug, PartialEq, Eq)]
+pub enum DisputeBatchResponse {
+	/// The response to every dispute of the batch, in the order of the request.
+	#[codec(index = 0)]
+	Responses(Vec<DisputeResponse>),
+	/// Recipient refused to process the whole batch.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
+}
+
+impl IsRequest for DisputeBatchRequest {
+	type Response = DisputeBatchResponse;
+	const PROTOCOL: Protocol = Protocol::DisputeBatchSending;
+}
+
+/// The most candidates a single `StatementFetchingRequest` may ask for.
+pub const MAX_STATEMENTS_PER_REQUEST: usize = 4;
+
+/// Request the data of the large statements of several candidates of the same relay parent, at
+/// most `MAX_STATEMENTS_PER_REQUEST`.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct StatementFetchingRequest {
+	/// Relay parent of the candidates.
+	pub relay_parent: Hash,
+	/// Hashes of the candidates the `CommittedCandidateReceipt`s are requested for.
+	pub candidate_hashes: Vec<CandidateHash>,
+}
+
+/// Respond with the data of several large statements.
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum StatementFetchingResponse {
+	/// The data of the requested candidates in the order of the request, `None` for candidates
+	/// the responder has no statement for.
+	///
+	/// Responses are no larger than a `v1` response, so they only contain the statements of the
+	/// leading candidates that fit. The remaining candidates need to be requested again.
+	#[codec(index = 0)]
+	Statements(Vec<Option<CommittedCandidateReceipt>>),
+	/// Node refused to serve the request.
+	#[codec(index = 1)]
+	Refused(RefusalReason),
+}
+
+impl StatementFetchingResponse {
+	/// The response with the leading `statements` which fit into `MAX_STATEMENT_RESPONSE_SIZE`.
+	pub fn fitting(mut statements: Vec<Option<CommittedCandidateReceipt>>) -> Self {
+		// The variant index and the length prefix, which only shrinks with fewer statements.
+		let mut size = 1 + parity_scale_codec::Compact(statements.len() as u32).encoded_size();
+		let fitting = statements.iter()
+			.take_while(|statement| {
+				size += statement.encoded_size();
+				size as u64 <= MAX_STATEMENT_RESPONSE_SIZE
+			})
+			.count();
+		statements.truncate(fitting);
+		StatementFetchingResponse::Statements(statements)
+	}
+}
+
+impl IsRequest for StatementFetchingRequest {
+	type Response = StatementFetchingResponse;
+	const PROTOCOL: Protocol = Protocol::StatementFetchingV2;
+}
+
+/// Available data of an encoded size up to this is sent in a single response.
+pub const MAX_INLINE_AVAILABLE_DATA_SIZE: usize = 1024 * 1024;
+
+/// The size of the segments larger available data is sent in, all but the last segment have
+/// exactly this size.
+pub const AVAILABLE_DATA_SEGMENT_SIZE: usize = 1024 * 1024;
+
+/// Request the entire available data for a candidate, or a segment of it.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct AvailableDataFetchingRequest {
+	/// The candidate hash to get the available data for.
+	pub candidate_hash: CandidateHash,
+	/// The index of the segment to get, `None` for the available data itself if it is small
+	/// enough to be sent inline.
+	pub segment: Option<u32>,
+}
+
+/// Receive a requested available data, or a segment of it.
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum AvailableDataFetchingResponse {
+	/// The requested data, small enough to be sent inline.
+	#[codec(index = 0)]
+	AvailableData(AvailableData),
+	/// The data is too large to be sent inline and needs to be fetched in segments.
+	#[codec(index = 1)]
+	Segmented {
+		/// The size of the encoded available data.
+		size: u32,
+	},
+	/// The requested segment of the encoded available data.
+	#[codec(index = 2)]
+	Segment(Vec<u8>),
+	/// Node was not in possession of the requested data.
+	#[codec(index = 3)]
+	NoSuchData,
+	/// Node refused to serve the request.
+	#[codec(index = 4)]
+	Refused(RefusalReason),
+}
+
+impl AvailableDataFetchingResponse {
+	/// The response to a request of `segment` given the `data` we have.
+	///
+	/// Data too large to be sent inline is only announced, so the requester fetches it segment
+	/// by segment. Responders serving many segments of the same data should rather keep its
+	/// encoding and answer with `segment`.
+	pub fn new(data: Option<AvailableData>, segment: Option<u32>) -> Self {
+		let data = match data {
+			Some(data) => data,
+			None => return AvailableDataFetchingResponse::NoSuchData,
+		};
+		match segment {
+			None => {
+				let size = data.encoded_size();
+				if size <= MAX_INLINE_AVAILABLE_DATA_SIZE {
+					AvailableDataFetchingResponse::AvailableData(data)
+				} else {
+					AvailableDataFetchingResponse::Segmented { size: size as u32 }
+				}
+			}
+			Some(index) => Self::segment(&data.encode(), index),
+		}
+	}
+
+	/// The response to a request of the segment `index` of the `encoded` available data.
+	pub fn segment(encoded: &[u8], index: u32) -> Self {
+		let start = index as usize * AVAILABLE_DATA_SEGMENT_SIZE;
+		if start >= encoded.len() {
+			return AvailableDataFetchingResponse::Refused(RefusalReason::Invalid)
+		}
+		let end = std::cmp::min(start + AVAILABLE_DATA_SEGMENT_SIZE, encoded.len());
+		AvailableDataFetchingResponse::Segment(encoded[start..end].to_vec())
+	}
+}
+
+impl IsRequest for AvailableDataFetchingRequest {
+	type Response = AvailableDataFetchingResponse;
+	const PROTOCOL: Protocol = Protocol::AvailableDataFetchingV2;
+}
+
+/// Segments not adding up to valid available data.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SegmentError {
+	/// No valid available data has the announced size.
+	#[error("Announced size of {0} bytes is out of bounds")]
+	InvalidSize(u32),
+	/// A segment was larger or smaller than it should be.
+	#[error("Segment {index} has {len} bytes instead of {expected}")]
+	InvalidLength {
+		/// The index of the segment.
+		index: u32,
+		/// The length of the segment.
+		len: usize,
+		/// The length the segment should have had.
+		expected: usize,
+	},
+	/// The segments don't decode to available data, or not all of them were received.
+	#[error("Segments don't add up to available data")]
+	InvalidData,
+}
+
+/// Reassembles available data from the segments of a `Segmented` response.
+///
+/// The segments are only checked to add up to the announced size and to decode, the data still
+/// needs to be checked against the erasure root of the candidate.
+#[derive(Debug)]
+pub struct AvailableDataSegments {
+	size: usize,
+	encoded: Vec<u8>,
+}
+
+impl AvailableDataSegments {
+	/// Start reassembling available data of the encoded `size` announced in a `Segmented`
+	/// response.
+	pub fn new(size: u32) -> Result<Self, SegmentError> {
+		// Smaller data would have been sent inline.
+		let in_bounds = size as usize > MAX_INLINE_AVAILABLE_DATA_SIZE
+			&& size as u64 <= MAX_AVAILABLE_DATA_RESPONSE_SIZE;
+		if !in_bounds {
+			return Err(SegmentError::InvalidSize(size))
+		}
+		Ok(AvailableDataSegments { size: size as usize, encoded: Vec::with_capacity(size as usize) })
+	}
+
+	/// The index of the segment to fetch next, `None` once all of them were added.
+	pub fn next_segment(&self) -> Option<u32> {
+		if self.encoded.len() < self.size {
+			Some((self.encoded.len() / AVAILABLE_DATA_SEGMENT_SIZE) as u32)
+		} else {
+			None
+		}
+	}
+
+	/// Add the segment of the index returned by `next_segment`.
+	pub fn push(&mut self, segment: Vec<u8>) -> Result<(), SegmentError> {
+		let index = self.next_segment().ok_or(SegmentError::InvalidData)?;
+		let expected = std::cmp::min(AVAILABLE_DATA_SEGMENT_SIZE, self.size - self.encoded.len());
+		if segment.len() != expected {
+			return Err(SegmentError::InvalidLength { index, len: segment.len(), expected })
+		}
+		self.encoded.extend_from_slice(&segment);
+		Ok(())
+	}
+
+	/// Decode the reassembled available data, which has to consume all of the segments.
+	pub fn finish(self) -> Result<AvailableData, SegmentError> {
+		if self.encoded.len() != self.size {
+			return Err(SegmentError::InvalidData)
+		}
+		let mut input = &self.encoded[..];
+		let data = AvailableData::decode(&mut input).map_err(|_| SegmentError::InvalidData)?;
+		if !input.is_empty() {
+			return Err(SegmentError::InvalidData)
+		}
+		Ok(data)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use polkadot_node_primitives::BlockData;
+	use polkadot_primitives::v1::{CandidateDescriptor, ValidationCode, MAX_CODE_SIZE};
+
+	use crate::request_response::ReqProtocolNames;
+
+	fn signed_collation() -> SignedCollation {
+		let pov = PoV { block_data: BlockData(vec![1, 2, 3]) };
+		let mut receipt = CandidateReceipt::default();
+		receipt.descriptor = CandidateDescriptor { pov_hash: pov.hash(), ..Default::default() };
+		SignedCollation {
+			pov_hash: pov.hash(),
+			receipt,
+			pov,
+			signature: Default::default(),
+		}
+	}
+
+	#[test]
+	fn signed_collations_round_trip() {
+		let collation = signed_collation();
+		let encoded = CollationFetchingResponse::Collation(collation.clone()).encode();
+		match CollationFetchingResponse::decode(&mut &encoded[..]).unwrap() {
+			CollationFetchingResponse::Collation(decoded) => {
+				assert_eq!(decoded.receipt, collation.receipt);
+				assert_eq!(decoded.pov, collation.pov);
+				assert_eq!(decoded.pov_hash, collation.pov_hash);
+				assert_eq!(decoded.signature, collation.signature);
+			}
+			CollationFetchingResponse::Refused(_) => panic!("a collation was sent"),
+		}
+		assert_eq!(CollationFetchingResponse::Refused(RefusalReason::Busy).encode(), vec![1, 0]);
+	}
+
+	#[test]
+	fn requests_are_encoded_like_v1_requests() {
+		let v1_request = super::super::v1::CollationFetchingRequest {
+			relay_parent: Hash::repeat_byte(1),
+			para_id: 7.into(),
+		};
+		assert_eq!(CollationFetchingRequest::from(v1_request.clone()).encode(), v1_request.encode());
+	}
+
+	#[test]
+	fn payload_binds_the_request() {
+		let relay_parent = Hash::repeat_byte(1);
+		let pov_hash = Hash::repeat_byte(2);
+		let payload = collation_signature_payload(&relay_parent, &7.into(), &pov_hash);
+		assert_ne!(payload, collation_signature_payload(&Hash::repeat_byte(3), &7.into(), &pov_hash));
+		assert_ne!(payload, collation_signature_payload(&relay_parent, &8.into(), &pov_hash));
+		assert_ne!(payload, collation_signature_payload(&relay_parent, &7.into(), &relay_parent));
+	}
+
+	#[test]
+	fn signed_collations_fit_the_size_limit() {
+		let (_, config) = Protocol::CollationFetchingV2
+			.get_config(&ReqProtocolNames::new([0u8; 32], None, false));
+		let (_, v1_config) = Protocol::CollationFetching
+			.get_config(&ReqProtocolNames::new([0u8; 32], None, false));
+		let collation = signed_collation();
+		let v1_response = super::super::v1::CollationFetchingResponse::Collation(
+			collation.receipt.clone(),
+			collation.pov.clone(),
+		);
+		let overhead = CollationFetchingResponse::Collation(collation).encoded_size()
+			- v1_response.encoded_size();
+		assert!(v1_config.max_response_size + overhead as u64 <= config.max_response_size);
+	}
+
+	#[test]
+	fn full_batches_fit_the_size_limits() {
+		let names = ReqProtocolNames::new([0u8; 32], None, false);
+		let (_, config) = Protocol::DisputeBatchSending.get_config(&names);
+		let (_, v1_config) = Protocol::DisputeSending.get_config(&names);
+		assert!(config.max_request_size >= v1_config.max_request_size * MAX_DISPUTES_PER_BATCH as u64);
+
+		let response = DisputeBatchResponse::Responses(
+			(0..MAX_DISPUTES_PER_BATCH).map(|_| DisputeResponse::Refused(RefusalReason::Invalid)).collect()
+		);
+		assert!(response.encoded_size() as u64 <= config.max_response_size);
+	}
+
+	#[test]
+	fn statements_round_trip() {
+		let mut receipt = CommittedCandidateReceipt::default();
+		receipt.descriptor.relay_parent = Hash::repeat_byte(1);
+		let request = StatementFetchingRequest {
+			relay_parent: Hash::repeat_byte(1),
+			candidate_hashes: vec![receipt.hash(), CandidateHash(Hash::repeat_byte(2))],
+		};
+		let decoded = StatementFetchingRequest::decode(&mut &request.encode()[..]).unwrap();
+		assert_eq!(decoded.candidate_hashes, request.candidate_hashes);
+
+		// Statements of unknown candidates are missing, the others are in the order of the request.
+		let response = StatementFetchingResponse::Statements(vec![Some(receipt.clone()), None]);
+		match StatementFetchingResponse::decode(&mut &response.encode()[..]).unwrap() {
+			StatementFetchingResponse::Statements(statements) =>
+				assert_eq!(statements, vec![Some(receipt), None]),
+			StatementFetchingResponse::Refused(_) => panic!("statements were sent"),
+		}
+		assert_eq!(StatementFetchingResponse::Refused(RefusalReason::Busy).encode(), vec![1, 0]);
+	}
+
+	fn statements_of(response: StatementFetchingResponse) -> Vec<Option<CommittedCandidateReceipt>> {
+		match response {
+			StatementFetchingResponse::Statements(statements) => statements,
+			StatementFetchingResponse::Refused(_) => panic!("statements were sent"),
+		}
+	}
+
+	/// A receipt with `size` bytes of validation code.
+	fn receipt_of_size(size: usize, byte: u8) -> CommittedCandidateReceipt {
+		let mut receipt = CommittedCandidateReceipt::default();
+		receipt.descriptor.relay_parent = Hash::repeat_byte(byte);
+		receipt.commitments.new_validation_code = Some(ValidationCode(vec![byte; size]));
+		receipt
+	}
+
+	#[test]
+	fn full_statement_responses_fit_the_size_limit() {
+		let names = ReqProtocolNames::new([0u8; 32], None, false);
+		let (_, config) = Protocol::StatementFetchingV2.get_config(&names);
+		let (_, v1_config) = Protocol::StatementFetching.get_config(&names);
+		// Batches are answered within the limits of a `v1` response.
+		assert_eq!(config.max_response_size, v1_config.max_response_size);
+		assert_eq!(config.request_timeout, v1_config.request_timeout);
+
+		// Statements as large as possible are sent one per response.
+		let largest = receipt_of_size(MAX_CODE_SIZE as usize, 1);
+		let response = StatementFetchingResponse::fitting(
+			vec![Some(largest.clone()); MAX_STATEMENTS_PER_REQUEST],
+		);
+		assert!(response.encoded_size() as u64 <= config.max_response_size);
+		assert_eq!(statements_of(response), vec![Some(largest)]);
+
+		// Small ones are all sent at once, as are missing ones.
+		let mut statements = vec![Some(receipt_of_size(1000, 2)); MAX_STATEMENTS_PER_REQUEST - 1];
+		statements.push(None);
+		assert_eq!(statements_of(StatementFetchingResponse::fitting(statements.clone())), statements);
+	}
+
+	#[test]
+	fn statements_filling_the_response_are_kept() {
+		let first = Some(receipt_of_size(1000, 3));
+		let pair = |second_size: usize| vec![first.clone(), Some(receipt_of_size(second_size, 4))];
+		let encoded_size = |size: usize| StatementFetchingResponse::Statements(pair(size)).encoded_size();
+
+		// The overhead only depends on the size through the length prefix of the code.
+		let mut size = MAX_STATEMENT_RESPONSE_SIZE as usize - encoded_size(0);
+		size -= encoded_size(size) - MAX_STATEMENT_RESPONSE_SIZE as usize;
+		assert_eq!(encoded_size(size) as u64, MAX_STATEMENT_RESPONSE_SIZE);
+
+		assert_eq!(statements_of(StatementFetchingResponse::fitting(pair(size))), pair(size));
+		assert_eq!(
+			statements_of(StatementFetchingResponse::fitting(pair(size + 1))),
+			vec![first.clone()],
+		);
+	}
+
+	#[test]
+	fn statement_requests_fit_the_size_limit() {
+		let names = ReqProtocolNames::new([0u8; 32], None, false);
+		let (_, config) = Protocol::StatementFetchingV2.get_config(&names);
+		let request = StatementFetchingRequest {
+			relay_parent: Hash::repeat_byte(1),
+			candidate_hashes: vec![CandidateHash::default(); MAX_STATEMENTS_PER_REQUEST],
+		};
+		assert!(request.encoded_size() as u64 <= config.max_request_size);
+	}
+
+	fn available_data(pov_size: usize) -> AvailableData {
+		AvailableData {
+			pov: std::sync::Arc::new(PoV { block_data: BlockData(vec![7; pov_size]) }),
+			validation_data: Default::default(),
+		}
+	}
+
+	fn segments_of(data: &AvailableData) -> (AvailableDataSegments, Vec<Vec<u8>>) {
+		let size = match AvailableDataFetchingResponse::new(Some(data.clone()), None) {
+			AvailableDataFetchingResponse::Segmented { size } => size,
+			r => panic!("large data is segmented, got {:?}", r),
+		};
+		let mut segments = Vec::new();
+		while let AvailableDataFetchingResponse::Segment(segment) =
+			AvailableDataFetchingResponse::new(Some(data.clone()), Some(segments.len() as u32))
+		{
+			segments.push(segment);
+		}
+		(AvailableDataSegments::new(size).unwrap(), segments)
+	}
+
+	#[test]
+	fn small_data_is_sent_inline() {
+		let data = available_data(1000);
+		assert!(matches!(
+			AvailableDataFetchingResponse::new(Some(data.clone()), None),
+			AvailableDataFetchingResponse::AvailableData(d) if d == data
+		));
+		assert!(matches!(
+			AvailableDataFetchingResponse::new(None, None),
+			AvailableDataFetchingResponse::NoSuchData
+		));
+	}
+
+	#[test]
+	fn segments_are_reassembled() {
+		let data = available_data(2 * AVAILABLE_DATA_SEGMENT_SIZE + 10);
+		let (mut reassembled, segments) = segments_of(&data);
+		assert_eq!(segments.len(), 3);
+		for (index, segment) in segments.into_iter().enumerate() {
+			assert_eq!(reassembled.next_segment(), Some(index as u32));
+			reassembled.push(segment).unwrap();
+		}
+		assert_eq!(reassembled.next_segment(), None);
+		assert_eq!(reassembled.finish(), Ok(data));
+	}
+
+	#[test]
+	fn invalid_segments_are_rejected() {
+		let data = available_data(2 * AVAILABLE_DATA_SEGMENT_SIZE + 10);
+
+		// A truncated segment.
+		let (mut reassembled, mut segments) = segments_of(&data);
+		segments[1].pop();
+		reassembled.push(segments[0].clone()).unwrap();
+		assert_eq!(
+			reassembled.push(segments[1].clone()),
+			Err(SegmentError::InvalidLength {
+				index: 1,
+				len: AVAILABLE_DATA_SEGMENT_SIZE - 1,
+				expected: AVAILABLE_DATA_SEGMENT_SIZE,
+			}),
+		);
+
+		// Missing segments.
+		let (mut reassembled, segments) = segments_of(&data);
+		reassembled.push(segments[0].clone()).unwrap();
+		assert_eq!(reassembled.finish(), Err(SegmentError::InvalidData));
+
+		// Segments which don't decode.
+		let (mut reassembled, segments) = segments_of(&data);
+		for segment in segments {
+			reassembled.push(vec![0xff; segment.len()]).unwrap();
+		}
+		assert_eq!(reassembled.finish(), Err(SegmentError::InvalidData));
+
+		// Sizes which would have been sent inline or exceed any available data.
+		assert!(AvailableDataSegments::new(100).is_err());
+		assert!(AvailableDataSegments::new(MAX_AVAILABLE_DATA_RESPONSE_SIZE as u32 + 1).is_err());
+
+		// Segments beyond the end are refused.
+		assert!(matches!(
+			AvailableDataFetchingResponse::new(Some(data), Some(3)),
+			AvailableDataFetchingResponse::Refused(RefusalReason::Invalid)
+		));
+	}
+
+	#[test]
+	fn full_segments_fit_the_size_limit() {
+		let (_, config) = Protocol::AvailableDataFetchingV2
+			.get_config(&ReqProtocolNames::new([0u8; 32], None, false));
+		let segment = AvailableDataFetchingResponse::Segment(vec![0; AVAILABLE_DATA_SEGMENT_SIZE]);
+		assert!(segment.encoded_size() as u64 <= config.max_response_size);
+		let inline = available_data(MAX_INLINE_AVAILABLE_DATA_SIZE - 100);
+		assert!(inline.encoded_size() <= MAX_INLINE_AVAILABLE_DATA_SIZE);
+		let inline = AvailableDataFetchingResponse::AvailableData(inline);
+		assert!(inline.encoded_size() as u64 <= config.max_response_size);
+	}
+}
@@ -48,6 +48,7 @@ use polkadot_node_network_protocol::{
 	peer_set::{
 		IsAuthority, PeerSet
 	},
+	request_response::Requests,
 	v1::{
 		self as protocol_v1, StatementMetadata
 	}
@@ -66,7 +67,7 @@ pub use error::{Error, NonFatal, Fatal, Result};
 
 /// Background task logic for requesting of large statements.
 mod requester;
-use requester::{RequesterMessage, fetch};
+use requester::{PendingRequests, RequesterMessage, fetch};
 
 /// Background task logic for responding for large statements.
 mod responder;
@@ -1612,9 +1613,26 @@ impl StatementDistribution {
 		let (req_sender, mut req_receiver) = mpsc::channel(1);
 		// Sender/Receiver for getting news from our responder task.
 		let (res_sender, mut res_receiver) = mpsc::channel(1);
+		// Requests of our statement fetching tasks, to be sent in batches.
+		let mut pending_requests = PendingRequests::default();
 
 		loop {
-			let message = MuxedMessage::receive(&mut ctx, &mut req_receiver, &mut res_receiver).await;
+			// Collect requests as long as there are messages ready, so that requests of tasks
+			// started together get sent together:
+			let ready = if pending_requests.is_empty() {
+				None
+			} else {
+				MuxedMessage::receive(&mut ctx, &mut req_receiver, &mut res_receiver).now_or_never()
+			};
+			let message = match ready {
+				Some(message) => message,
+				None => {
+					if !pending_requests.is_empty() {
+						send_pending_requests(&mut ctx, &mut pending_requests, &req_sender).await?;
+					}
+					MuxedMessage::receive(&mut ctx, &mut req_receiver, &mut res_receiver).await
+				}
+			};
 			match message {
 				MuxedMessage::Subsystem(result) => {
 					let result = self.handle_subsystem_message(
@@ -1644,6 +1662,7 @@ impl StatementDistribution {
 						&mut peers,
 						&mut active_heads,
 						&req_sender,
+						&mut pending_requests,
 						result.ok_or(Fatal::RequesterReceiverFinished)?
 					)
 					.await;
@@ -1714,6 +1733,7 @@ impl StatementDistribution {
 		peers: &mut HashMap<PeerId, PeerData>,
 		active_heads: &mut HashMap<Hash, ActiveHeadData>,
 		req_sender: &mpsc::Sender<RequesterMessage>,
+		pending_requests: &mut PendingRequests,
 		message: RequesterMessage,
 	) -> NonFatalResult<()> {
 		match message {
@@ -1772,6 +1792,8 @@ impl StatementDistribution {
 					}
 				}
 			}
+			RequesterMessage::SendRequest(Requests::StatementFetching(req)) =>
+				pending_requests.push(req),
 			RequesterMessage::SendRequest(req) => {
 				ctx.send_message(
 					AllMessages::NetworkBridge(
@@ -1824,6 +1846,8 @@ impl StatementDistribution {
 			}
 			RequesterMessage::ReportPeer(peer, rep) =>
 				report_peer(ctx, peer, rep).await,
+			RequesterMessage::BatchUnsupported(peer) =>
+				pending_requests.note_batch_unsupported(peer),
 		}
 		Ok(())
 	}
@@ -1959,6 +1983,24 @@ impl StatementDistribution {
 	}
 }
 
+/// Send all pending statement fetching requests, batched where possible.
+async fn send_pending_requests(
+	ctx: &mut (impl SubsystemContext + overseer::SubsystemContext),
+	pending_requests: &mut PendingRequests,
+	req_sender: &mpsc::Sender<RequesterMessage>,
+) -> std::result::Result<(), Fatal> {
+	let (requests, tasks) = pending_requests.batch(req_sender);
+	for task in tasks {
+		ctx.spawn("large-statement-batch", task).map_err(Fatal::SpawnTask)?;
+	}
+	ctx.send_message(
+		AllMessages::NetworkBridge(
+			NetworkBridgeMessage::SendRequests(requests, IfDisconnected::ImmediateError)
+		)
+	).await;
+	Ok(())
+}
+
 /// Check whether a peer knows about a candidate from us.
 ///
 /// If not, it is deemed illegal for it to request corresponding data from us.
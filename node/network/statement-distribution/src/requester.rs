@@ -14,17 +14,21 @@
 
 //! Large statement requesting background task logic.
 
+use std::collections::HashSet;
 use std::time::Duration;
 
-use futures::{SinkExt, channel::{mpsc, oneshot}};
+use futures::{FutureExt, SinkExt, channel::{mpsc, oneshot}, future::BoxFuture};
+use indexmap::IndexMap;
+use parity_scale_codec::Encode;
 
 use polkadot_node_network_protocol::{
 	PeerId, UnifiedReputationChange,
 	request_response::{
-		OutgoingRequest, Recipient, Requests,
+		OutgoingRequest, OutgoingResult, Priority, Recipient, Requests,
 		v1::{
 			StatementFetchingRequest, StatementFetchingResponse
-		}
+		},
+		v2,
 	}};
 use polkadot_node_subsystem_util::TimeoutExt;
 use polkadot_primitives::v1::{CandidateHash, CommittedCandidateReceipt, Hash};
@@ -63,8 +67,166 @@ pub enum RequesterMessage {
 	ReportPeer(PeerId, UnifiedReputationChange),
 	/// Ask subsystem to send a request for us.
 	SendRequest(Requests),
+	/// The peer does not support fetching several statements per request, so requests to it
+	/// should no longer be batched.
+	BatchUnsupported(PeerId),
+}
+
+/// Statement fetching requests of the fetch tasks, which are not sent yet.
+///
+/// Requests to the same peer in the context of the same relay parent are sent as one `v2` request
+/// for several candidates, whose response is then split up for the tasks.
+#[derive(Default)]
+pub struct PendingRequests {
+	requests: Vec<OutgoingRequest<StatementFetchingRequest>>,
+	/// Peers which don't support `v2` requests, so they get sent all requests one by one.
+	no_batches: HashSet<PeerId>,
+}
+
+impl PendingRequests {
+	/// Add a request to be sent with the next batch.
+	pub fn push(&mut self, request: OutgoingRequest<StatementFetchingRequest>) {
+		self.requests.push(request)
+	}
+
+	/// Don't batch requests to `peer` any longer.
+	pub fn note_batch_unsupported(&mut self, peer: PeerId) {
+		self.no_batches.insert(peer);
+	}
+
+	/// Whether there are no requests to be sent.
+	pub fn is_empty(&self) -> bool {
+		self.requests.is_empty()
+	}
+
+	/// Take all pending requests, batching requests for the same peer and relay parent.
+	///
+	/// Returns the requests to send, along with the tasks answering the batched requests from the
+	/// response of their batch. Responses are passed on encoded as `v1` responses, so for the fetch
+	/// tasks it makes no difference whether their request was batched.
+	pub fn batch(
+		&mut self,
+		sender: &mpsc::Sender<RequesterMessage>,
+	) -> (Vec<Requests>, Vec<BoxFuture<'static, ()>>) {
+		let mut requests = Vec::new();
+		let mut tasks = Vec::new();
+		let mut batches: IndexMap<(PeerId, Hash), Vec<_>> = IndexMap::new();
+		for request in self.requests.drain(..) {
+			match &request.peer {
+				Recipient::Peer(peer) if !self.no_batches.contains(peer) =>
+					batches
+						.entry((*peer, request.payload.relay_parent))
+						.or_default()
+						.push(request),
+				_ => requests.push(Requests::StatementFetching(request)),
+			}
+		}
+
+		for ((peer, relay_parent), mut batch) in batches {
+			while !batch.is_empty() {
+				let rest = batch.split_off(
+					std::cmp::min(batch.len(), v2::MAX_STATEMENTS_PER_REQUEST)
+				);
+				let originals = std::mem::replace(&mut batch, rest);
+				if originals.len() == 1 {
+					requests.extend(originals.into_iter().map(Requests::StatementFetching));
+					continue
+				}
+
+				let (outgoing, response) = OutgoingRequest::new_with_priority(
+					Recipient::Peer(peer),
+					v2::StatementFetchingRequest {
+						relay_parent,
+						candidate_hashes: originals.iter().map(|r| r.payload.candidate_hash).collect(),
+					},
+					originals[0].priority,
+				);
+				requests.push(Requests::StatementFetchingV2(outgoing));
+				tasks.push(split_response(peer, originals, response, sender.clone()).boxed());
+			}
+		}
+		(requests, tasks)
+	}
+}
+
+/// Answer the `originals` batched into one `v2` request, once its `response` arrived.
+///
+/// Candidates the peer does not know about are answered as refused, just like a `v1` request for
+/// them would be. Candidates whose statements did not fit into the response are sent again on
+/// their own, as are all originals if the peer does not support `v2` requests. Any other failure
+/// fails all of them.
+async fn split_response(
+	peer: PeerId,
+	mut originals: Vec<OutgoingRequest<StatementFetchingRequest>>,
+	response: impl std::future::Future<Output = OutgoingResult<v2::StatementFetchingResponse>>,
+	mut sender: mpsc::Sender<RequesterMessage>,
+) {
+	match response.await {
+		Ok(v2::StatementFetchingResponse::Statements(statements))
+			if !statements.is_empty() && statements.len() <= originals.len() =>
+		{
+			let left_out = originals.split_off(statements.len());
+			for (original, statement) in originals.into_iter().zip(statements) {
+				let response = match statement {
+					Some(statement) => Ok(StatementFetchingResponse::Statement(statement).encode()),
+					None => Err(sc_network::RequestFailure::Refused),
+				};
+				let _ = original.pending_response.send(response);
+			}
+			send_one_by_one(left_out, &mut sender).await;
+		}
+		Ok(v2::StatementFetchingResponse::Statements(statements)) => {
+			tracing::debug!(
+				target: LOG_TARGET,
+				?peer,
+				expected = originals.len(),
+				received = statements.len(),
+				"Peer sent a wrong number of statements"
+			);
+		}
+		Ok(v2::StatementFetchingResponse::Refused(reason)) => {
+			for original in originals {
+				let _ = original.pending_response.send(
+					Ok(StatementFetchingResponse::Refused(reason).encode())
+				);
+			}
+		}
+		Err(err) if err.is_unsupported_protocol() => {
+			tracing::debug!(
+				target: LOG_TARGET,
+				?peer,
+				"Peer does not support batched statement requests - sending them one by one."
+			);
+			if sender.feed(RequesterMessage::BatchUnsupported(peer)).await.is_err() {
+				return
+			}
+			send_one_by_one(originals, &mut sender).await;
+		}
+		// Dropping the originals fails their requests as well.
+		Err(err) => {
+			tracing::debug!(
+				target: LOG_TARGET,
+				?peer,
+				?err,
+				"Batched statement request failed"
+			);
+		}
+	}
 }
 
+/// Send the `originals` of a batch again as `v1` requests.
+async fn send_one_by_one(
+	originals: Vec<OutgoingRequest<StatementFetchingRequest>>,
+	sender: &mut mpsc::Sender<RequesterMessage>,
+) {
+	for original in originals {
+		if sender.feed(
+			RequesterMessage::SendRequest(Requests::StatementFetching(original))
+		).await.is_err() {
+			return
+		}
+	}
+}
 
 /// A fetching task, taking care of fetching large statements via request/response.
 ///
@@ -102,9 +264,10 @@ pub async fn fetch(
 			let _span = span.child("try-peer")
 				.with_peer_id(&peer);
 
-			let (outgoing, pending_response) = OutgoingRequest::new(
+			let (outgoing, pending_response) = OutgoingRequest::new_with_priority(
 				Recipient::Peer(peer),
 				req.clone(),
+				Priority::Critical,
 			);
 			if let Err(err) = sender.feed(
 				RequesterMessage::SendRequest(Requests::StatementFetching(outgoing))
@@ -159,6 +322,15 @@ pub async fn fetch(
 					// We are done now.
 					return
 				},
+				Ok(StatementFetchingResponse::Refused(reason)) => {
+					tracing::debug!(
+						target: LOG_TARGET,
+						?reason,
+						"Peer refused to serve the statement - trying next peer."
+					);
+
+					metrics.on_received_response(false);
+				}
 				Err(err) => {
 					tracing::debug!(
 						target: LOG_TARGET,
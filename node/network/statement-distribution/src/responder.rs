@@ -19,10 +19,8 @@ use futures::{SinkExt, StreamExt, channel::{mpsc, oneshot}, stream::FuturesUnord
 use polkadot_node_network_protocol::{
 	PeerId, UnifiedReputationChange as Rep,
 	request_response::{
-		IncomingRequest, MAX_PARALLEL_STATEMENT_REQUESTS, request::OutgoingResponse,
-		v1::{
-			StatementFetchingRequest, StatementFetchingResponse
-		},
+		IncomingRequest, MAX_PARALLEL_STATEMENT_REQUESTS, Protocol, VersionedReceiver,
+		request::OutgoingResponse, v1::{self, RefusalReason}, v2,
 	},
 };
 use polkadot_primitives::v1::{CandidateHash, CommittedCandidateReceipt, Hash};
@@ -48,8 +46,11 @@ pub enum ResponderMessage {
 /// A fetch task does not know about a particular `Statement` instead it just tries fetching a
 /// `CommittedCandidateReceipt` from peers, whether this can be used to re-assemble one ore
 /// many `SignedFullStatement`s needs to be verified by the caller.
+///
+/// Requests of both versions of the protocol are served: A `v2` request asks for several
+/// candidates at once and gets a response with an entry for each of them.
 pub async fn respond(
-	mut receiver: mpsc::Receiver<sc_network::config::IncomingRequest>,
+	mut receiver: VersionedReceiver,
 	mut sender: mpsc::Sender<ResponderMessage>,
 ) {
 	let mut pending_out = FuturesUnordered::new();
@@ -72,7 +73,7 @@ pub async fn respond(
 			pending_out.next().await;
 		}
 
-		let raw = match receiver.next().await {
+		let (protocol, raw) = match receiver.next().await {
 			None => {
 				tracing::debug!(
 					target: LOG_TARGET,
@@ -83,57 +84,114 @@ pub async fn respond(
 			Some(v) => v,
 		};
 
-		let req =
-			match IncomingRequest::<StatementFetchingRequest>::try_from_raw(
-				raw,
-				vec![COST_INVALID_REQUEST],
-			) {
-			Err(err) => {
-				tracing::debug!(
-					target: LOG_TARGET,
-					?err,
-					"Decoding request failed"
-				);
-				continue
+		let (pending_sent_tx, pending_sent_rx) = oneshot::channel();
+		let sent = match protocol {
+			Protocol::StatementFetching => {
+				let req =
+					match IncomingRequest::<v1::StatementFetchingRequest>::try_from_raw(
+						raw,
+						vec![COST_INVALID_REQUEST],
+					) {
+					Err(err) => {
+						tracing::debug!(
+							target: LOG_TARGET,
+							?err,
+							"Decoding request failed"
+						);
+						continue
+					}
+					Ok(payload) => payload,
+				};
+
+				let receipt = match get_data(
+					&mut sender,
+					req.peer,
+					req.payload.relay_parent,
+					req.payload.candidate_hash,
+				).await {
+					Err(()) => return,
+					Ok(receipt) => receipt,
+				};
+				let response = match receipt {
+					None => {
+						tracing::debug!(
+							target: LOG_TARGET,
+							"Requested data not found."
+						);
+						Err(())
+					}
+					Some(v) => Ok(v1::StatementFetchingResponse::Statement(v)),
+				};
+				req.send_outgoing_response(OutgoingResponse {
+					result: response,
+					reputation_changes: Vec::new(),
+					sent_feedback: Some(pending_sent_tx),
+				})
 			}
-			Ok(payload) => payload,
-		};
+			Protocol::StatementFetchingV2 => {
+				let req =
+					match IncomingRequest::<v2::StatementFetchingRequest>::try_from_raw(
+						raw,
+						vec![COST_INVALID_REQUEST],
+					) {
+					Err(err) => {
+						tracing::debug!(
+							target: LOG_TARGET,
+							?err,
+							"Decoding request failed"
+						);
+						continue
+					}
+					Ok(payload) => payload,
+				};
+
+				if req.payload.candidate_hashes.len() > v2::MAX_STATEMENTS_PER_REQUEST {
+					tracing::debug!(
+						target: LOG_TARGET,
+						peer = ?req.peer,
+						candidates = req.payload.candidate_hashes.len(),
+						"Refusing request for too many candidates"
+					);
+					if let Err(_) = req.respond_refusal(RefusalReason::Invalid) {
+						tracing::debug!(
+							target: LOG_TARGET,
+							"Sending refusal failed"
+						);
+					}
+					continue
+				}
 
-		let (tx, rx) = oneshot::channel();
-		if let Err(err) = sender.feed(
-			ResponderMessage::GetData {
-				requesting_peer: req.peer,
-				relay_parent: req.payload.relay_parent,
-				candidate_hash: req.payload.candidate_hash,
-				tx,
+				// Unknown candidates don't fail the whole request, their entries are just empty.
+				let mut statements = Vec::with_capacity(req.payload.candidate_hashes.len());
+				for candidate_hash in req.payload.candidate_hashes.iter() {
+					match get_data(
+						&mut sender,
+						req.peer,
+						req.payload.relay_parent,
+						*candidate_hash,
+					).await {
+						Err(()) => return,
+						Ok(receipt) => statements.push(receipt),
+					}
+				}
+				// Statements which don't fit are left out, the requester asks for them again.
+				req.send_outgoing_response(OutgoingResponse {
+					result: Ok(v2::StatementFetchingResponse::fitting(statements)),
+					reputation_changes: Vec::new(),
+					sent_feedback: Some(pending_sent_tx),
+				})
 			}
-		).await {
-			tracing::debug!(
-				target: LOG_TARGET,
-				?err,
-				"Shutting down responder"
-			);
-			return
-		}
-		let response = match rx.await {
-			Err(err) => {
-				tracing::debug!(
+			_ => {
+				tracing::warn!(
 					target: LOG_TARGET,
-					?err,
-					"Requested data not found."
+					?protocol,
+					"Received request of unexpected protocol"
 				);
-				Err(())
+				continue
 			}
-			Ok(v) => Ok(StatementFetchingResponse::Statement(v)),
-		};
-		let (pending_sent_tx, pending_sent_rx) = oneshot::channel();
-		let response = OutgoingResponse {
-			result: response,
-			reputation_changes: Vec::new(),
-			sent_feedback: Some(pending_sent_tx),
 		};
 		pending_out.push(pending_sent_rx);
-		if let Err(_) = req.send_outgoing_response(response) {
+		if let Err(_) = sent {
 			tracing::debug!(
 				target: LOG_TARGET,
 				"Sending response failed"
@@ -141,3 +199,31 @@ pub async fn respond(
 		}
 	}
 }
+
+/// Get the receipt of `candidate_hash` from the subsystem, if it has one.
+///
+/// Returns `Err` if the subsystem is shut down.
+async fn get_data(
+	sender: &mut mpsc::Sender<ResponderMessage>,
+	requesting_peer: PeerId,
+	relay_parent: Hash,
+	candidate_hash: CandidateHash,
+) -> Result<Option<CommittedCandidateReceipt>, ()> {
+	let (tx, rx) = oneshot::channel();
+	if let Err(err) = sender.feed(
+		ResponderMessage::GetData {
+			requesting_peer,
+			relay_parent,
+			candidate_hash,
+			tx,
+		}
+	).await {
+		tracing::debug!(
+			target: LOG_TARGET,
+			?err,
+			"Shutting down responder"
+		);
+		return Err(())
+	}
+	Ok(rx.await.ok())
+}
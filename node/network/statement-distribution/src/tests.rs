@@ -34,12 +34,16 @@ use polkadot_subsystem::{
 	jaeger, ActivatedLeaf, messages::{RuntimeApiMessage, RuntimeApiRequest}, LeafStatus,
 };
 use polkadot_node_network_protocol::request_response::{
-	Requests,
+	OutgoingRequest, Priority, Protocol, Requests, VersionedReceiver,
+	request::RequestError,
 	v1::{
+		RefusalReason,
 		StatementFetchingRequest,
 		StatementFetchingResponse,
 	},
+	v2,
 };
+use requester::PendingRequests;
 
 #[test]
 fn active_head_accepts_only_2_seconded_per_validator() {
@@ -821,10 +825,14 @@ fn receiving_large_statement_from_one_sends_to_another_and_to_candidate_backing(
 	};
 
 	let (mut tx_reqs, rx_reqs) = mpsc::channel(1);
+	let (mut tx_reqs_v2, rx_reqs_v2) = mpsc::channel(1);
 
 	let test_fut = async move {
 		handle.send(FromOverseer::Communication {
-			msg: StatementDistributionMessage::StatementFetchingReceiver(rx_reqs)
+			msg: StatementDistributionMessage::StatementFetchingReceiver(VersionedReceiver::new(vec![
+				(Protocol::StatementFetching, rx_reqs),
+				(Protocol::StatementFetchingV2, rx_reqs_v2),
+			]))
 		}).await;
 
 		// register our active heads.
@@ -1222,10 +1230,47 @@ fn receiving_large_statement_from_one_sends_to_another_and_to_candidate_backing(
 			pending_response,
 		};
 		tx_reqs.send(req).await.unwrap();
-		let StatementFetchingResponse::Statement(committed) =
-			Decode::decode(&mut response_rx.await.unwrap().result.unwrap().as_ref()).unwrap();
+		let committed = assert_matches!(
+			StatementFetchingResponse::decode(&mut response_rx.await.unwrap().result.unwrap().as_ref()),
+			Ok(StatementFetchingResponse::Statement(committed)) => committed
+		);
 		assert_eq!(committed, candidate);
 
+		// A request for several candidates gets an empty entry for the unknown one:
+		let (pending_response, response_rx) = oneshot::channel();
+		let inner_req = v2::StatementFetchingRequest {
+			relay_parent: metadata.relay_parent,
+			candidate_hashes: vec![metadata.candidate_hash, CandidateHash(Hash::repeat_byte(42))],
+		};
+		let req = sc_network::config::IncomingRequest {
+			peer: peer_b,
+			payload: inner_req.encode(),
+			pending_response,
+		};
+		tx_reqs_v2.send(req).await.unwrap();
+		let statements = assert_matches!(
+			v2::StatementFetchingResponse::decode(&mut response_rx.await.unwrap().result.unwrap().as_ref()),
+			Ok(v2::StatementFetchingResponse::Statements(statements)) => statements
+		);
+		assert_eq!(statements, vec![Some(candidate.clone()), None]);
+
+		// Requests for too many candidates get refused:
+		let (pending_response, response_rx) = oneshot::channel();
+		let inner_req = v2::StatementFetchingRequest {
+			relay_parent: metadata.relay_parent,
+			candidate_hashes: vec![metadata.candidate_hash; v2::MAX_STATEMENTS_PER_REQUEST + 1],
+		};
+		let req = sc_network::config::IncomingRequest {
+			peer: peer_b,
+			payload: inner_req.encode(),
+			pending_response,
+		};
+		tx_reqs_v2.send(req).await.unwrap();
+		assert_matches!(
+			v2::StatementFetchingResponse::decode(&mut response_rx.await.unwrap().result.unwrap().as_ref()),
+			Ok(v2::StatementFetchingResponse::Refused(RefusalReason::Invalid))
+		);
+
 		handle.send(FromOverseer::Signal(OverseerSignal::Conclude)).await;
 	};
 
@@ -1296,7 +1341,9 @@ fn share_prioritizes_backing_group() {
 
 	let test_fut = async move {
 		handle.send(FromOverseer::Communication {
-			msg: StatementDistributionMessage::StatementFetchingReceiver(rx_reqs)
+			msg: StatementDistributionMessage::StatementFetchingReceiver(
+				VersionedReceiver::new(vec![(Protocol::StatementFetching, rx_reqs)])
+			)
 		}).await;
 
 		// register our active heads.
@@ -1494,8 +1541,10 @@ fn share_prioritizes_backing_group() {
 			pending_response,
 		};
 		tx_reqs.send(req).await.unwrap();
-		let StatementFetchingResponse::Statement(committed) =
-			Decode::decode(&mut response_rx.await.unwrap().result.unwrap().as_ref()).unwrap();
+		let committed = assert_matches!(
+			StatementFetchingResponse::decode(&mut response_rx.await.unwrap().result.unwrap().as_ref()),
+			Ok(StatementFetchingResponse::Statement(committed)) => committed
+		);
 		assert_eq!(committed, candidate);
 
 		handle.send(FromOverseer::Signal(OverseerSignal::Conclude)).await;
@@ -1552,7 +1601,9 @@ fn peer_cant_flood_with_large_statements() {
 
 	let test_fut = async move {
 		handle.send(FromOverseer::Communication {
-			msg: StatementDistributionMessage::StatementFetchingReceiver(rx_reqs)
+			msg: StatementDistributionMessage::StatementFetchingReceiver(
+				VersionedReceiver::new(vec![(Protocol::StatementFetching, rx_reqs)])
+			)
 		}).await;
 
 		// register our active heads.
@@ -1685,6 +1736,192 @@ fn peer_cant_flood_with_large_statements() {
 	executor::block_on(future::join(test_fut, bg));
 }
 
+fn statement_request(
+	peer: PeerId,
+	relay_parent: Hash,
+	candidate_hash: CandidateHash,
+) -> (
+	OutgoingRequest<StatementFetchingRequest>,
+	impl Future<Output = Result<StatementFetchingResponse, RequestError>>,
+) {
+	OutgoingRequest::new_with_priority(
+		Recipient::Peer(peer),
+		StatementFetchingRequest { relay_parent, candidate_hash },
+		Priority::Critical,
+	)
+}
+
+#[test]
+fn pending_requests_are_batched_per_peer_and_relay_parent() {
+	let peer_a = PeerId::random();
+	let peer_b = PeerId::random();
+	let hash_a = Hash::repeat_byte(1);
+	let hash_b = Hash::repeat_byte(2);
+	let candidates: Vec<_> = (0..6).map(|i| CandidateHash(Hash::repeat_byte(10 + i))).collect();
+	let (tx, _rx) = mpsc::channel(1);
+
+	let mut pending = PendingRequests::default();
+	for candidate_hash in &candidates[..5] {
+		pending.push(statement_request(peer_a, hash_a, *candidate_hash).0);
+	}
+	pending.push(statement_request(peer_a, hash_b, candidates[5]).0);
+	pending.push(statement_request(peer_b, hash_a, candidates[0]).0);
+
+	let (requests, tasks) = pending.batch(&tx);
+	assert!(pending.is_empty());
+	assert_eq!(tasks.len(), 1);
+	assert_eq!(requests.len(), 4);
+	assert_matches!(
+		&requests[0],
+		Requests::StatementFetchingV2(req) => {
+			assert_eq!(req.peer, Recipient::Peer(peer_a));
+			assert_eq!(req.payload.relay_parent, hash_a);
+			assert_eq!(req.payload.candidate_hashes, candidates[..v2::MAX_STATEMENTS_PER_REQUEST]);
+		}
+	);
+	// Batches of a single request are sent as they are:
+	let singles: Vec<_> = requests[1..].iter()
+		.map(|req| assert_matches!(
+			req,
+			Requests::StatementFetching(req) =>
+				(req.peer.clone(), req.payload.relay_parent, req.payload.candidate_hash)
+		))
+		.collect();
+	assert_eq!(singles, vec![
+		(Recipient::Peer(peer_a), hash_a, candidates[4]),
+		(Recipient::Peer(peer_a), hash_b, candidates[5]),
+		(Recipient::Peer(peer_b), hash_a, candidates[0]),
+	]);
+
+	// No more batches for peers without support:
+	pending.note_batch_unsupported(peer_a);
+	pending.push(statement_request(peer_a, hash_a, candidates[0]).0);
+	pending.push(statement_request(peer_a, hash_a, candidates[1]).0);
+	let (requests, tasks) = pending.batch(&tx);
+	assert!(tasks.is_empty());
+	assert_eq!(requests.len(), 2);
+	assert!(requests.iter().all(|req| matches!(req, Requests::StatementFetching(_))));
+}
+
+#[test]
+fn batched_responses_are_split_up() {
+	let peer = PeerId::random();
+	let relay_parent = Hash::repeat_byte(1);
+	let known = {
+		let mut c = CommittedCandidateReceipt::default();
+		c.descriptor.para_id = 1.into();
+		c
+	};
+	let unknown = CandidateHash(Hash::repeat_byte(42));
+	let (tx, _rx) = mpsc::channel(1);
+
+	let mut pending = PendingRequests::default();
+	let (known_req, known_response) = statement_request(peer, relay_parent, known.hash());
+	let (unknown_req, unknown_response) = statement_request(peer, relay_parent, unknown);
+	pending.push(known_req);
+	pending.push(unknown_req);
+
+	let (mut requests, tasks) = pending.batch(&tx);
+	let batched = assert_matches!(
+		requests.pop(),
+		Some(Requests::StatementFetchingV2(req)) => req
+	);
+	batched.pending_response.send(Ok(
+		v2::StatementFetchingResponse::Statements(vec![Some(known.clone()), None]).encode()
+	)).unwrap();
+
+	executor::block_on(async move {
+		future::join_all(tasks).await;
+		assert_matches!(
+			known_response.await,
+			Ok(StatementFetchingResponse::Statement(c)) if c == known
+		);
+		// Unknown candidates are refused, just like `v1` requests for them:
+		assert_matches!(
+			unknown_response.await,
+			Err(RequestError::NetworkError(sc_network::RequestFailure::Refused))
+		);
+	});
+}
+
+#[test]
+fn statements_left_out_of_responses_are_requested_again() {
+	let peer = PeerId::random();
+	let relay_parent = Hash::repeat_byte(1);
+	let (tx, mut rx) = mpsc::channel(10);
+	let first = {
+		let mut c = CommittedCandidateReceipt::default();
+		c.descriptor.para_id = 1.into();
+		c
+	};
+	let left_out: Vec<_> = (0..2).map(|i| CandidateHash(Hash::repeat_byte(i))).collect();
+
+	let mut pending = PendingRequests::default();
+	let (first_req, first_response) = statement_request(peer, relay_parent, first.hash());
+	pending.push(first_req);
+	for candidate_hash in &left_out {
+		pending.push(statement_request(peer, relay_parent, *candidate_hash).0);
+	}
+
+	let (mut requests, tasks) = pending.batch(&tx);
+	let batched = assert_matches!(
+		requests.pop(),
+		Some(Requests::StatementFetchingV2(req)) => req
+	);
+	batched.pending_response.send(Ok(
+		v2::StatementFetchingResponse::Statements(vec![Some(first.clone())]).encode()
+	)).unwrap();
+
+	executor::block_on(async move {
+		future::join_all(tasks).await;
+		assert_matches!(
+			first_response.await,
+			Ok(StatementFetchingResponse::Statement(c)) if c == first
+		);
+	});
+	for candidate_hash in left_out {
+		assert_matches!(
+			rx.try_next(),
+			Ok(Some(RequesterMessage::SendRequest(Requests::StatementFetching(req))))
+				if req.payload.candidate_hash == candidate_hash
+		);
+	}
+}
+
+#[test]
+fn unsupported_batches_are_sent_one_by_one() {
+	let peer = PeerId::random();
+	let relay_parent = Hash::repeat_byte(1);
+	let (tx, mut rx) = mpsc::channel(10);
+
+	let mut pending = PendingRequests::default();
+	for i in 0..2 {
+		pending.push(statement_request(peer, relay_parent, CandidateHash(Hash::repeat_byte(i))).0);
+	}
+
+	let (mut requests, tasks) = pending.batch(&tx);
+	let batched = assert_matches!(
+		requests.pop(),
+		Some(Requests::StatementFetchingV2(req)) => req
+	);
+	batched.pending_response.send(Err(sc_network::RequestFailure::Network(
+		sc_network::OutboundFailure::UnsupportedProtocols,
+	))).unwrap();
+	executor::block_on(future::join_all(tasks));
+
+	assert_matches!(
+		rx.try_next(),
+		Ok(Some(RequesterMessage::BatchUnsupported(p))) if p == peer
+	);
+	for i in 0..2 {
+		assert_matches!(
+			rx.try_next(),
+			Ok(Some(RequesterMessage::SendRequest(Requests::StatementFetching(req))))
+				if req.payload.candidate_hash == CandidateHash(Hash::repeat_byte(i))
+		);
+	}
+}
+
 fn make_session_info(validators: Vec<Pair>, groups: Vec<Vec<u32>>) -> SessionInfo {
 
 	let validator_groups: Vec<Vec<ValidatorIndex>> = groups
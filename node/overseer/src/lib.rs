@@ -868,7 +868,7 @@ where
 // more types to the proc macro logic.
 
 use polkadot_node_network_protocol::{
-	request_response::{request::IncomingRequest, v1 as req_res_v1},
+	request_response::{request::IncomingRequest, v1 as req_res_v1, v2 as req_res_v2},
 };
 
 impl From<IncomingRequest<req_res_v1::PoVFetchingRequest>> for AllMessages {
@@ -881,13 +881,38 @@ impl From<IncomingRequest<req_res_v1::ChunkFetchingRequest>> for AllMessages {
 		From::<AvailabilityDistributionMessage>::from(From::from(req))
 	}
 }
+impl From<IncomingRequest<req_res_v1::CompressedPoVFetchingRequest>> for AllMessages {
+	fn from(req: IncomingRequest<req_res_v1::CompressedPoVFetchingRequest>) -> Self {
+		From::<AvailabilityDistributionMessage>::from(From::from(req))
+	}
+}
+impl From<IncomingRequest<req_res_v1::MultiChunkFetchingRequest>> for AllMessages {
+	fn from(req: IncomingRequest<req_res_v1::MultiChunkFetchingRequest>) -> Self {
+		From::<AvailabilityDistributionMessage>::from(From::from(req))
+	}
+}
 impl From<IncomingRequest<req_res_v1::CollationFetchingRequest>> for AllMessages {
 	fn from(req: IncomingRequest<req_res_v1::CollationFetchingRequest>) -> Self {
 		From::<CollatorProtocolMessage>::from(From::from(req))
 	}
 }
+impl From<IncomingRequest<req_res_v2::CollationFetchingRequest>> for AllMessages {
+	fn from(req: IncomingRequest<req_res_v2::CollationFetchingRequest>) -> Self {
+		From::<CollatorProtocolMessage>::from(From::from(req))
+	}
+}
 impl From<IncomingRequest<req_res_v1::AvailableDataFetchingRequest>> for AllMessages {
 	fn from(req: IncomingRequest<req_res_v1::AvailableDataFetchingRequest>) -> Self {
 		From::<AvailabilityRecoveryMessage>::from(From::from(req))
 	}
 }
+impl From<IncomingRequest<req_res_v1::CompressedAvailableDataFetchingRequest>> for AllMessages {
+	fn from(req: IncomingRequest<req_res_v1::CompressedAvailableDataFetchingRequest>) -> Self {
+		From::<AvailabilityRecoveryMessage>::from(From::from(req))
+	}
+}
+impl From<IncomingRequest<req_res_v2::AvailableDataFetchingRequest>> for AllMessages {
+	fn from(req: IncomingRequest<req_res_v2::AvailableDataFetchingRequest>) -> Self {
+		From::<AvailabilityRecoveryMessage>::from(From::from(req))
+	}
+}
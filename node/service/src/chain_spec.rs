@@ -27,8 +27,9 @@ use kusama_runtime::constants::currency::UNITS as KSM;
 use pallet_im_online::sr25519::AuthorityId as ImOnlineId;
 use pallet_staking::Forcing;
 use polkadot::constants::currency::UNITS as DOT;
-use polkadot_primitives::v1::{AccountId, AccountPublic, AssignmentId, ValidatorId};
+use polkadot_primitives::v1::{AccountId, AccountPublic, AssignmentId, Balance, ValidatorId};
 use polkadot_runtime as polkadot;
+use polkadot_runtime_common::claims::{EthereumAddress, StatementKind};
 
 #[cfg(feature = "rococo-native")]
 use rococo_runtime as rococo;
@@ -1599,6 +1600,80 @@ pub fn polkadot_development_config() -> Result<PolkadotChainSpec, String> {
 	))
 }
 
+/// Builder of Polkadot development chain specs with a custom genesis.
+///
+/// Starts from the genesis of `polkadot_development_config`, with Alice as the only validator and
+/// the dev accounts endowed.
+#[derive(Default, Clone)]
+pub struct PolkadotDevGenesisBuilder {
+	endowed: Vec<(AccountId, Balance)>,
+	council: Vec<AccountId>,
+	technical_committee: Vec<AccountId>,
+	claims: Vec<(EthereumAddress, Balance, Option<AccountId>, Option<StatementKind>)>,
+}
+
+impl PolkadotDevGenesisBuilder {
+	/// Give `account` a free balance of `amount`, replacing any endowment it already has.
+	pub fn endow(mut self, account: AccountId, amount: Balance) -> Self {
+		self.endowed.push((account, amount));
+		self
+	}
+
+	/// Set the members of the council.
+	pub fn council(mut self, members: Vec<AccountId>) -> Self {
+		self.council = members;
+		self
+	}
+
+	/// Set the members of the technical committee.
+	pub fn technical_committee(mut self, members: Vec<AccountId>) -> Self {
+		self.technical_committee = members;
+		self
+	}
+
+	/// Add claims of DOTs by Ethereum addresses.
+	pub fn claims(
+		mut self,
+		entries: Vec<(EthereumAddress, Balance, Option<AccountId>, Option<StatementKind>)>,
+	) -> Self {
+		self.claims.extend(entries);
+		self
+	}
+
+	/// The genesis config with `wasm_binary` as the runtime code.
+	pub fn genesis(&self, wasm_binary: &[u8]) -> polkadot::GenesisConfig {
+		let mut genesis = polkadot_development_config_genesis(wasm_binary);
+		for (account, amount) in &self.endowed {
+			let balances = &mut genesis.balances.balances;
+			match balances.iter_mut().find(|(who, _)| who == account) {
+				Some(endowment) => endowment.1 = *amount,
+				None => balances.push((account.clone(), *amount)),
+			}
+		}
+		genesis.council.members = self.council.clone();
+		genesis.technical_committee.members = self.technical_committee.clone();
+		genesis.claims.claims = self.claims.clone();
+		genesis
+	}
+
+	/// The development chain spec with the built genesis.
+	pub fn build(self) -> Result<PolkadotChainSpec, String> {
+		let wasm_binary = polkadot::WASM_BINARY.ok_or("Polkadot development wasm not available")?;
+
+		Ok(PolkadotChainSpec::from_genesis(
+			"Development",
+			"dev",
+			ChainType::Development,
+			move || self.genesis(wasm_binary),
+			vec![],
+			None,
+			Some(DEFAULT_PROTOCOL_ID),
+			None,
+			Default::default(),
+		))
+	}
+}
+
 /// Kusama development config (single validator Alice)
 #[cfg(feature = "kusama-native")]
 pub fn kusama_development_config() -> Result<KusamaChainSpec, String> {
@@ -1805,3 +1880,56 @@ pub fn rococo_local_testnet_config() -> Result<RococoChainSpec, String> {
 		Default::default(),
 	))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::BuildStorage;
+
+	#[test]
+	fn dev_genesis_builder_sets_balances_and_collectives() {
+		let alice = get_account_id_from_seed::<sr25519::Public>("Alice");
+		let bob = get_account_id_from_seed::<sr25519::Public>("Bob");
+		let outsider = get_account_id_from_seed::<sr25519::Public>("Outsider");
+		let address = EthereumAddress::default();
+		let mut council = vec![alice.clone(), bob.clone()];
+		council.sort();
+
+		let storage = PolkadotDevGenesisBuilder::default()
+			.endow(bob.clone(), 42 * DOT)
+			.endow(outsider.clone(), 7 * DOT)
+			.council(council.clone())
+			.technical_committee(vec![bob.clone()])
+			.claims(vec![(address, 5 * DOT, None, None)])
+			.genesis(&[])
+			.build_storage()
+			.expect("the built genesis is valid");
+
+		sp_io::TestExternalities::new(storage).execute_with(|| {
+			assert_eq!(polkadot::Balances::free_balance(&alice), 1_000_000 * DOT);
+			assert_eq!(polkadot::Balances::free_balance(&bob), 42 * DOT);
+			assert_eq!(polkadot::Balances::free_balance(&outsider), 7 * DOT);
+			assert_eq!(polkadot::Council::members(), council);
+			assert_eq!(polkadot::TechnicalCommittee::members(), vec![bob]);
+			assert_eq!(polkadot::Claims::claims(&address), Some(5 * DOT));
+		});
+	}
+
+	#[test]
+	fn dev_genesis_builder_chain_spec_carries_runtime_and_balances() {
+		let bob = get_account_id_from_seed::<sr25519::Public>("Bob");
+		let spec = PolkadotDevGenesisBuilder::default()
+			.endow(bob.clone(), 42 * DOT)
+			.build()
+			.expect("the Polkadot wasm is built");
+		assert_eq!(spec.id(), "dev");
+		assert_eq!(spec.chain_type(), ChainType::Development);
+
+		let storage = spec.build_storage().expect("the built genesis is valid");
+		let code = storage.top.get(sp_core::storage::well_known_keys::CODE).cloned();
+		assert_eq!(code, polkadot::WASM_BINARY.map(|wasm| wasm.to_vec()));
+		sp_io::TestExternalities::new(storage).execute_with(|| {
+			assert_eq!(polkadot::Balances::free_balance(&bob), 42 * DOT);
+		});
+	}
+}
@@ -37,7 +37,7 @@ pub use self::overseer::{
 #[cfg(feature = "full-node")]
 use {
 	tracing::info,
-	polkadot_network_bridge::RequestMultiplexer,
+	polkadot_network_bridge::{BufferConfig, RateLimitConfig, RequestMultiplexer},
 	polkadot_node_core_av_store::Config as AvailabilityConfig,
 	polkadot_node_core_av_store::Error as AvailabilityError,
 	polkadot_node_core_approval_voting::Config as ApprovalVotingConfig,
@@ -57,6 +57,7 @@ pub use {
 	sc_client_api::AuxStore,
 	polkadot_primitives::v1::ParachainHost,
 	polkadot_overseer::{Overseer, Handle},
+	polkadot_network_bridge::{InvalidSetting, RequestProtocol, RequestResponseSettings},
 };
 pub use sp_core::traits::SpawnNamed;
 
@@ -541,6 +542,7 @@ pub fn new_full<RuntimeApi, Executor, OverseerGenerator>(
 	jaeger_agent: Option<std::net::SocketAddr>,
 	telemetry_worker_handle: Option<TelemetryWorkerHandle>,
 	program_path: Option<std::path::PathBuf>,
+	request_response_settings: RequestResponseSettings,
 	overseer_gen: OverseerGenerator,
 ) -> Result<NewFull<Arc<FullClient<RuntimeApi, Executor>>>, Error>
 	where
@@ -607,7 +609,17 @@ pub fn new_full<RuntimeApi, Executor, OverseerGenerator>(
 		&config, task_manager.spawn_handle(), backend.clone(), import_setup.1.shared_authority_set().clone(),
 	));
 	let request_multiplexer = {
-		let (multiplexer, configs) = RequestMultiplexer::new();
+		let genesis_hash = client.block_hash(0)
+			.ok()
+			.flatten()
+			.expect("Genesis block exists; qed");
+		let (multiplexer, configs) = RequestMultiplexer::new(
+			prometheus_registry.as_ref(),
+			genesis_hash,
+			request_response_settings,
+			BufferConfig::default(),
+			RateLimitConfig::default(),
+		)?;
 		config.network.request_response_protocols.extend(configs);
 		multiplexer
 	};
@@ -1185,6 +1197,7 @@ pub fn build_full(
 	disable_beefy: bool,
 	jaeger_agent: Option<std::net::SocketAddr>,
 	telemetry_worker_handle: Option<TelemetryWorkerHandle>,
+	request_response_settings: RequestResponseSettings,
 	overseer_gen: impl OverseerGen,
 ) -> Result<NewFull<Client>, Error> {
 	#[cfg(feature = "rococo-native")]
@@ -1197,6 +1210,7 @@ pub fn build_full(
 			jaeger_agent,
 			telemetry_worker_handle,
 			None,
+			request_response_settings,
 			overseer_gen,
 		).map(|full| full.with_client(Client::Rococo))
 	}
@@ -1211,12 +1225,19 @@ pub fn build_full(
 			jaeger_agent,
 			telemetry_worker_handle,
 			None,
+			request_response_settings,
 			overseer_gen,
 		).map(|full| full.with_client(Client::Kusama))
 	}
 
 	#[cfg(feature = "westend-native")]
 	if config.chain_spec.is_westend() {
+		if westend_runtime::FAST_RUNTIME {
+			tracing::info!(
+				"The native Westend runtime was built with `fast-runtime`: \
+				governance and staking periods are shortened.",
+			);
+		}
 		return new_full::<westend_runtime::RuntimeApi, WestendExecutor, _>(
 			config,
 			is_collator,
@@ -1225,6 +1246,7 @@ pub fn build_full(
 			jaeger_agent,
 			telemetry_worker_handle,
 			None,
+			request_response_settings,
 			overseer_gen,
 		).map(|full| full.with_client(Client::Westend))
 	}
@@ -1237,6 +1259,7 @@ pub fn build_full(
 		jaeger_agent,
 		telemetry_worker_handle,
 		None,
+		request_response_settings,
 		overseer_gen,
 	).map(|full| full.with_client(Client::Polkadot))
 }
@@ -28,7 +28,7 @@ use thiserror::Error;
 
 pub use sc_network::IfDisconnected;
 
-use polkadot_node_network_protocol::{PeerId, UnifiedReputationChange, peer_set::PeerSet, request_response::{request::IncomingRequest, v1 as req_res_v1, Requests}, v1 as protocol_v1};
+use polkadot_node_network_protocol::{PeerId, UnifiedReputationChange, peer_set::PeerSet, request_response::{request::IncomingRequest, v1 as req_res_v1, v2 as req_res_v2, Requests, VersionedReceiver}, v1 as protocol_v1};
 use polkadot_node_primitives::{AvailableData, BabeEpoch, BlockWeight, CandidateVotes, CollationGenerationConfig, DisputeMessage, ErasureChunk, PoV, SignedDisputeStatement, SignedFullStatement, ValidationResult, approval::{BlockApprovalMeta, IndirectAssignmentCert, IndirectSignedApprovalVote}};
 use polkadot_primitives::v1::{
 	AuthorityDiscoveryId, BackedCandidate, BlockNumber, CandidateDescriptor, CandidateEvent,
@@ -159,6 +159,8 @@ pub enum CollatorProtocolMessage {
 	NetworkBridgeUpdateV1(NetworkBridgeEvent<protocol_v1::CollatorProtocolMessage>),
 	/// Incoming network request for a collation.
 	CollationFetchingRequest(IncomingRequest<req_res_v1::CollationFetchingRequest>),
+	/// Incoming network request for a collation, to be answered with a signed collation.
+	CollationFetchingRequestV2(IncomingRequest<req_res_v2::CollationFetchingRequest>),
 	/// We recommended a particular candidate to be seconded, but it was invalid; penalize the collator.
 	///
 	/// The hash is the relay parent.
@@ -291,8 +293,13 @@ pub enum DisputeDistributionMessage {
 	/// validators.
 	SendDispute(DisputeMessage),
 
-	/// Get receiver for receiving incoming network requests for dispute sending.
-	DisputeSendingReceiver(mpsc::Receiver<sc_network::config::IncomingRequest>),
+	/// Get receivers for receiving incoming network requests for dispute sending.
+	DisputeSendingReceiver {
+		/// Requests carrying the votes of a single dispute.
+		single: mpsc::Receiver<sc_network::config::IncomingRequest>,
+		/// Requests carrying the votes of several disputes.
+		batched: mpsc::Receiver<sc_network::config::IncomingRequest>,
+	},
 }
 
 /// Messages received by the network bridge subsystem.
@@ -375,8 +382,12 @@ impl NetworkBridgeMessage {
 pub enum AvailabilityDistributionMessage {
 	/// Incoming network request for an availability chunk.
 	ChunkFetchingRequest(IncomingRequest<req_res_v1::ChunkFetchingRequest>),
+	/// Incoming network request for several availability chunks of a candidate.
+	MultiChunkFetchingRequest(IncomingRequest<req_res_v1::MultiChunkFetchingRequest>),
 	/// Incoming network request for a seconded PoV.
 	PoVFetchingRequest(IncomingRequest<req_res_v1::PoVFetchingRequest>),
+	/// Incoming network request for a seconded PoV, to be answered compressed.
+	CompressedPoVFetchingRequest(IncomingRequest<req_res_v1::CompressedPoVFetchingRequest>),
 	/// Instruct availability distribution to fetch a remote PoV.
 	///
 	/// NOTE: The result of this fetch is not yet locally validated and could be bogus.
@@ -409,6 +420,14 @@ pub enum AvailabilityRecoveryMessage {
 	/// Incoming network request for available data.
 	#[from]
 	AvailableDataFetchingRequest(IncomingRequest<req_res_v1::AvailableDataFetchingRequest>),
+	/// Incoming network request for available data, to be answered compressed.
+	#[from]
+	CompressedAvailableDataFetchingRequest(
+		IncomingRequest<req_res_v1::CompressedAvailableDataFetchingRequest>,
+	),
+	/// Incoming network request for available data, to be answered in segments if large.
+	#[from]
+	AvailableDataFetchingRequestV2(IncomingRequest<req_res_v2::AvailableDataFetchingRequest>),
 }
 
 /// Bitfield distribution message.
@@ -660,8 +679,9 @@ pub enum StatementDistributionMessage {
 	/// Event from the network bridge.
 	#[from]
 	NetworkBridgeUpdateV1(NetworkBridgeEvent<protocol_v1::StatementDistributionMessage>),
-	/// Get receiver for receiving incoming network requests for statement fetching.
-	StatementFetchingReceiver(mpsc::Receiver<sc_network::config::IncomingRequest>),
+	/// Get receiver for receiving incoming network requests for statement fetching, of all
+	/// versions.
+	StatementFetchingReceiver(VersionedReceiver),
 }
 
 /// This data becomes intrinsics or extrinsics which should be included in a future relay chain block.
@@ -850,8 +870,23 @@ impl From<IncomingRequest<req_res_v1::ChunkFetchingRequest>> for AvailabilityDis
 		Self::ChunkFetchingRequest(req)
 	}
 }
+impl From<IncomingRequest<req_res_v1::CompressedPoVFetchingRequest>> for AvailabilityDistributionMessage {
+	fn from(req: IncomingRequest<req_res_v1::CompressedPoVFetchingRequest>) -> Self {
+		Self::CompressedPoVFetchingRequest(req)
+	}
+}
+impl From<IncomingRequest<req_res_v1::MultiChunkFetchingRequest>> for AvailabilityDistributionMessage {
+	fn from(req: IncomingRequest<req_res_v1::MultiChunkFetchingRequest>) -> Self {
+		Self::MultiChunkFetchingRequest(req)
+	}
+}
 impl From<IncomingRequest<req_res_v1::CollationFetchingRequest>> for CollatorProtocolMessage {
 	fn from(req: IncomingRequest<req_res_v1::CollationFetchingRequest>) -> Self {
 		Self::CollationFetchingRequest(req)
 	}
 }
+impl From<IncomingRequest<req_res_v2::CollationFetchingRequest>> for CollatorProtocolMessage {
+	fn from(req: IncomingRequest<req_res_v2::CollationFetchingRequest>) -> Self {
+		Self::CollationFetchingRequestV2(req)
+	}
+}
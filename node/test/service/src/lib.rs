@@ -86,6 +86,7 @@ pub fn new_full(
 		None,
 		None,
 		worker_program_path,
+		Default::default(),
 		polkadot_service::RealOverseerGen,
 	)
 }
@@ -373,14 +374,11 @@ pub fn construct_extrinsic(
 		.map(|c| c / 2)
 		.unwrap_or(2) as u64;
 	let tip = 0;
-	let extra: SignedExtra = (
-		frame_system::CheckSpecVersion::<Runtime>::new(),
-		frame_system::CheckTxVersion::<Runtime>::new(),
-		frame_system::CheckGenesis::<Runtime>::new(),
-		frame_system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
-		frame_system::CheckNonce::<Runtime>::from(nonce),
-		frame_system::CheckWeight::<Runtime>::new(),
-		pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+	let extra: SignedExtra = polkadot_runtime_common::construct_signed_extras!(
+		Runtime,
+		era: generic::Era::mortal(period, current_block),
+		nonce: nonce,
+		tip: tip,
 	);
 	let raw_payload = SignedPayload::from_raw(
 		function.clone(),
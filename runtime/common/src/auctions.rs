@@ -18,7 +18,7 @@
 //! auctioning mechanism and for reserving balance as part of the "payment". Unreserving the balance
 //! happens elsewhere.
 
-use sp_std::{prelude::*, mem::swap};
+use sp_std::{prelude::*, mem::swap, convert::TryFrom};
 use sp_runtime::traits::{CheckedSub, Zero, One, Saturating};
 use frame_support::{
 	ensure, dispatch::DispatchResult,
@@ -26,7 +26,7 @@ use frame_support::{
 	weights::{Weight},
 };
 use primitives::v1::Id as ParaId;
-use crate::slot_range::SlotRange;
+use crate::slot_range::{SlotRange, SlotRangeError, WinnersTable};
 use crate::traits::{Leaser, LeaseError, Auctioneer, Registrar, AuctionStatus};
 use parity_scale_codec::Decode;
 pub use pallet::*;
@@ -55,8 +55,7 @@ pub type AuctionIndex = u32;
 
 type LeasePeriodOf<T> = <<T as Config>::Leaser as Leaser>::LeasePeriod;
 // Winning data type. This encodes the top bidders of each range together with their bid.
-type WinningData<T> =
-	[Option<(<T as frame_system::Config>::AccountId, ParaId, BalanceOf<T>)>; SlotRange::SLOT_RANGE_COUNT];
+type WinningData<T> = WinnersTable<<T as frame_system::Config>::AccountId, BalanceOf<T>>;
 // Winners data type. This encodes each of the final winners of a parachain auction, the parachain
 // index assigned to them, their winning bid and the range that they won.
 type WinnersData<T> = Vec<(<T as frame_system::Config>::AccountId, ParaId, BalanceOf<T>, SlotRange)>;
@@ -151,6 +150,12 @@ pub mod pallet {
 		AuctionEnded,
 		/// The para is already leased out for part of this range.
 		AlreadyLeasedOut,
+		/// The range bid on begins before the first lease period of the auction.
+		SlotRangeOutOfBounds,
+		/// The last lease period of the range bid on is before its first lease period.
+		SlotRangeInverted,
+		/// The range bid on extends beyond the lease periods being auctioned.
+		SlotRangeTooLong,
 	}
 
 	/// Number of auctions started so far.
@@ -209,7 +214,7 @@ pub mod pallet {
 					weight = weight.saturating_add(T::DbWeight::get().writes(1));
 					let winning_data = offset.checked_sub(&One::one())
 							.and_then(Winning::<T>::get)
-							.unwrap_or([Self::EMPTY; SlotRange::SLOT_RANGE_COUNT]);
+							.unwrap_or_default();
 					Winning::<T>::insert(offset, winning_data);
 				}
 			}
@@ -293,6 +298,16 @@ pub mod pallet {
 	}
 }
 
+impl<T: Config> From<SlotRangeError> for Error<T> {
+	fn from(e: SlotRangeError) -> Self {
+		match e {
+			SlotRangeError::OutOfBounds => Error::<T>::SlotRangeOutOfBounds,
+			SlotRangeError::Inverted => Error::<T>::SlotRangeInverted,
+			SlotRangeError::TooLong => Error::<T>::SlotRangeTooLong,
+		}
+	}
+}
+
 impl<T: Config> Auctioneer for Pallet<T> {
 	type AccountId = T::AccountId;
 	type BlockNumber = T::BlockNumber;
@@ -354,9 +369,6 @@ impl<T: Config> Auctioneer for Pallet<T> {
 }
 
 impl<T: Config> Pallet<T> {
-	// A trick to allow me to initialize large arrays with nothing in them.
-	const EMPTY: Option<(<T as frame_system::Config>::AccountId, ParaId, BalanceOf<T>)> = None;
-
 	/// Create a new auction.
 	///
 	/// This can only happen when there isn't already an auction in progress. Accepts the `duration`
@@ -419,17 +431,16 @@ impl<T: Config> Pallet<T> {
 		ensure!(!T::Leaser::already_leased(para, first_slot, last_slot), Error::<T>::AlreadyLeasedOut);
 
 		// Our range.
-		let range = SlotRange::new_bounded(first_lease_period, first_slot, last_slot)?;
-		// Range as an array index.
-		let range_index = range as u8 as usize;
+		let range = SlotRange::new_checked(first_lease_period, first_slot, last_slot)
+			.map_err(Error::<T>::from)?;
 
 		// The current winning ranges.
 		let mut current_winning = Winning::<T>::get(offset)
 			.or_else(|| offset.checked_sub(&One::one()).and_then(Winning::<T>::get))
-			.unwrap_or([Self::EMPTY; SlotRange::SLOT_RANGE_COUNT]);
+			.unwrap_or_default();
 
 		// If this bid beat the previous winner of our range.
-		if current_winning[range_index].as_ref().map_or(true, |last| amount > last.2) {
+		if current_winning[range].as_ref().map_or(true, |last| amount > last.2) {
 			// Ok; we are the new winner of this range - reserve the additional amount and record.
 
 			// Get the amount already held on deposit if this is a renewal bid (i.e. there's
@@ -459,11 +470,10 @@ impl<T: Config> Pallet<T> {
 			// Return any funds reserved for the previous winner if we are not in the ending period
 			// and they no longer have any active bids.
 			let mut outgoing_winner = Some((bidder.clone(), para, amount));
-			swap(&mut current_winning[range_index], &mut outgoing_winner);
+			swap(&mut current_winning[range], &mut outgoing_winner);
 			if let Some((who, para, _amount)) = outgoing_winner {
-				if auction_status.is_starting() && current_winning.iter()
-					.filter_map(Option::as_ref)
-					.all(|&(ref other, other_para, _)| other != &who || other_para != para)
+				if auction_status.is_starting() && current_winning.occupied()
+					.all(|(_, &(ref other, other_para, _))| other != &who || other_para != para)
 				{
 					// Previous bidder is no longer winning any ranges: unreserve their funds.
 					if let Some(amount) = ReservedAmounts::<T>::take(&(who.clone(), para)) {
@@ -506,7 +516,7 @@ impl<T: Config> Pallet<T> {
 
 					let auction_counter = AuctionCounter::<T>::get();
 					Self::deposit_event(Event::<T>::WinningOffset(auction_counter, offset));
-					let res = Winning::<T>::get(offset).unwrap_or([Self::EMPTY; SlotRange::SLOT_RANGE_COUNT]);
+					let res = Winning::<T>::get(offset).unwrap_or_default();
 					// This `remove_all` statement should remove at most `EndingPeriod` / `SampleLength` items,
 					// which should be bounded and sensibly configured in the runtime.
 					Winning::<T>::remove_all(None);
@@ -573,16 +583,16 @@ impl<T: Config> Pallet<T> {
 			let mut best_winners_ending_at:
 				[(Vec<SlotRange>, BalanceOf<T>); SlotRange::LEASE_PERIODS_PER_SLOT] = Default::default();
 			let best_bid = |range: SlotRange| {
-				winning[range as u8 as usize].as_ref()
+				winning[range].as_ref()
 					.map(|(_, _, amount)| *amount * (range.len() as u32).into())
 			};
 			for i in 0..SlotRange::LEASE_PERIODS_PER_SLOT {
-				let r = SlotRange::new_bounded(0, 0, i as u32).expect("`i < 4`; qed");
+				let r = SlotRange::try_from((0, i as u32)).expect("`i < LPPS`; qed");
 				if let Some(bid) = best_bid(r) {
 					best_winners_ending_at[i] = (vec![r], bid);
 				}
 				for j in 0..i {
-					let r = SlotRange::new_bounded(0, j as u32 + 1, i as u32)
+					let r = SlotRange::try_from((j as u32 + 1, i as u32))
 						.expect("`i < LPPS`; `j < i`; `j + 1 < LPPS`; qed");
 					if let Some(mut bid) = best_bid(r) {
 						bid += best_winners_ending_at[j].1;
@@ -603,7 +613,7 @@ impl<T: Config> Pallet<T> {
 
 		winning_ranges.into_iter().map(|range| {
 			let mut final_winner = Default::default();
-			swap(&mut final_winner, winning[range as u8 as usize].as_mut()
+			swap(&mut final_winner, winning[range].as_mut()
 				.expect("none values are filtered out in previous logic; qed"));
 			let (bidder, para, amount) = final_winner;
 			(bidder, para, amount, range)
@@ -878,12 +888,41 @@ mod tests {
 			assert_eq!(Balances::reserved_balance(1), 5);
 			assert_eq!(Balances::free_balance(1), 5);
 			assert_eq!(
-				Auctions::winning(0).unwrap()[SlotRange::ZeroThree as u8 as usize],
+				Auctions::winning(0).unwrap()[SlotRange::ZeroThree],
 				Some((1, 0.into(), 5))
 			);
 		});
 	}
 
+	#[test]
+	fn bidding_on_invalid_range_fails() {
+		new_test_ext().execute_with(|| {
+			run_to_block(1);
+			assert_ok!(Auctions::new_auction(Origin::signed(6), 5, 1));
+
+			// Range begins before the first lease period of the auction.
+			assert_noop!(
+				Auctions::bid(Origin::signed(1), 0.into(), 1, 0, 2, 5),
+				Error::<Test>::SlotRangeOutOfBounds,
+			);
+			// Range ends before it begins.
+			assert_noop!(
+				Auctions::bid(Origin::signed(1), 0.into(), 1, 3, 2, 5),
+				Error::<Test>::SlotRangeInverted,
+			);
+			// Range extends beyond the lease periods being auctioned.
+			let last_slot = 1 + SlotRange::LEASE_PERIODS_PER_SLOT as u32;
+			assert_noop!(
+				Auctions::bid(Origin::signed(1), 0.into(), 1, 1, last_slot, 5),
+				Error::<Test>::SlotRangeTooLong,
+			);
+			assert_noop!(
+				Auctions::bid(Origin::signed(1), 0.into(), 1, last_slot, last_slot, 5),
+				Error::<Test>::SlotRangeTooLong,
+			);
+		});
+	}
+
 	#[test]
 	fn under_bidding_works() {
 		new_test_ext().execute_with(|| {
@@ -911,7 +950,7 @@ mod tests {
 			assert_eq!(Balances::reserved_balance(2), 6);
 			assert_eq!(Balances::free_balance(2), 14);
 			assert_eq!(
-				Auctions::winning(0).unwrap()[SlotRange::ZeroThree as u8 as usize],
+				Auctions::winning(0).unwrap()[SlotRange::ZeroThree],
 				Some((2, 0.into(), 6))
 			);
 		});
@@ -1196,8 +1235,8 @@ mod tests {
 
 	#[test]
 	fn incomplete_calculate_winners_works() {
-		let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-		winning[SlotRange::ThreeThree as u8 as usize] = Some((1, 0.into(), 1));
+		let mut winning = WinnersTable::new();
+		winning[SlotRange::ThreeThree] = Some((1, 0.into(), 1));
 
 		let winners = vec![
 			(1, 0.into(), 1, SlotRange::ThreeThree)
@@ -1208,8 +1247,8 @@ mod tests {
 
 	#[test]
 	fn first_incomplete_calculate_winners_works() {
-		let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-		winning[0] = Some((1, 0.into(), 1));
+		let mut winning = WinnersTable::new();
+		winning[SlotRange::ZeroZero] = Some((1, 0.into(), 1));
 
 		let winners = vec![
 			(1, 0.into(), 1, SlotRange::ZeroZero)
@@ -1220,12 +1259,12 @@ mod tests {
 
 	#[test]
 	fn calculate_winners_works() {
-		let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-		winning[SlotRange::ZeroZero as u8 as usize] = Some((2, 0.into(), 2));
-		winning[SlotRange::ZeroThree as u8 as usize] = Some((1, 100.into(), 1));
-		winning[SlotRange::OneOne as u8 as usize] = Some((3, 1.into(), 1));
-		winning[SlotRange::TwoTwo as u8 as usize] = Some((1, 2.into(), 53));
-		winning[SlotRange::ThreeThree as u8 as usize] = Some((5, 3.into(), 1));
+		let mut winning = WinnersTable::new();
+		winning[SlotRange::ZeroZero] = Some((2, 0.into(), 2));
+		winning[SlotRange::ZeroThree] = Some((1, 100.into(), 1));
+		winning[SlotRange::OneOne] = Some((3, 1.into(), 1));
+		winning[SlotRange::TwoTwo] = Some((1, 2.into(), 53));
+		winning[SlotRange::ThreeThree] = Some((5, 3.into(), 1));
 
 		let winners = vec![
 			(2, 0.into(), 2, SlotRange::ZeroZero),
@@ -1235,7 +1274,7 @@ mod tests {
 		];
 		assert_eq!(Auctions::calculate_winners(winning.clone()), winners);
 
-		winning[SlotRange::ZeroOne as u8 as usize] = Some((4, 10.into(), 3));
+		winning[SlotRange::ZeroOne] = Some((4, 10.into(), 3));
 		let winners = vec![
 			(4, 10.into(), 3, SlotRange::ZeroOne),
 			(1, 2.into(), 53, SlotRange::TwoTwo),
@@ -1243,7 +1282,7 @@ mod tests {
 		];
 		assert_eq!(Auctions::calculate_winners(winning.clone()), winners);
 
-		winning[SlotRange::ZeroThree as u8 as usize] = Some((1, 100.into(), 100));
+		winning[SlotRange::ZeroThree] = Some((1, 100.into(), 100));
 		let winners = vec![
 			(1, 100.into(), 100, SlotRange::ZeroThree),
 		];
@@ -1288,9 +1327,9 @@ mod tests {
 			assert_ok!(Auctions::bid(Origin::signed(2), para_2, 1, 3, 4, 20));
 
 			assert_eq!(Auctions::auction_status(System::block_number()), AuctionStatus::<u32>::StartingPeriod);
-			let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-			winning[SlotRange::ZeroThree as u8 as usize] = Some((1, para_1, 10));
-			winning[SlotRange::TwoThree as u8 as usize] = Some((2, para_2, 20));
+			let mut winning = WinnersTable::new();
+			winning[SlotRange::ZeroThree] = Some((1, para_1, 10));
+			winning[SlotRange::TwoThree] = Some((2, para_2, 20));
 			assert_eq!(Auctions::winning(0), Some(winning));
 
 			run_to_block(9);
@@ -1307,7 +1346,7 @@ mod tests {
 
 			run_to_block(12);
 			assert_eq!(Auctions::auction_status(System::block_number()), AuctionStatus::<u32>::EndingPeriod(2, 0));
-			winning[SlotRange::TwoThree as u8 as usize] = Some((3, para_3, 30));
+			winning[SlotRange::TwoThree] = Some((3, para_3, 30));
 			assert_eq!(Auctions::winning(2), Some(winning));
 		});
 	}
@@ -1377,9 +1416,9 @@ mod tests {
 			assert_ok!(Auctions::bid(Origin::signed(2), para_2, 1, 13, 14, 20));
 
 			assert_eq!(Auctions::auction_status(System::block_number()), AuctionStatus::<u32>::StartingPeriod);
-			let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-			winning[SlotRange::ZeroThree as u8 as usize] = Some((1, para_1, 10));
-			winning[SlotRange::TwoThree as u8 as usize] = Some((2, para_2, 20));
+			let mut winning = WinnersTable::new();
+			winning[SlotRange::ZeroThree] = Some((1, para_1, 10));
+			winning[SlotRange::TwoThree] = Some((2, para_2, 20));
 			assert_eq!(Auctions::winning(0), Some(winning));
 
 			run_to_block(9);
@@ -1391,7 +1430,7 @@ mod tests {
 
 			// New bids update the current winning
 			assert_ok!(Auctions::bid(Origin::signed(3), para_3, 1, 14, 14, 30));
-			winning[SlotRange::ThreeThree as u8 as usize] = Some((3, para_3, 30));
+			winning[SlotRange::ThreeThree] = Some((3, para_3, 30));
 			assert_eq!(Auctions::winning(0), Some(winning));
 
 			run_to_block(20);
@@ -1400,7 +1439,7 @@ mod tests {
 			run_to_block(25);
 			// Overbid mid sample
 			assert_ok!(Auctions::bid(Origin::signed(3), para_3, 1, 13, 14, 30));
-			winning[SlotRange::TwoThree as u8 as usize] = Some((3, para_3, 30));
+			winning[SlotRange::TwoThree] = Some((3, para_3, 30));
 			assert_eq!(Auctions::winning(1), Some(winning));
 
 			run_to_block(30);
@@ -1527,7 +1566,7 @@ mod benchmarking {
 			let bidder = account("bidder", n, 0);
 			CurrencyOf::<T>::make_free_balance_be(&bidder, BalanceOf::<T>::max_value());
 
-			let slot_range = SlotRange::n((n - 1) as u8).unwrap();
+			let slot_range = SlotRange::from_index((n - 1) as usize).unwrap();
 			let (start, end) = slot_range.as_pair();
 
 			assert!(Auctions::<T>::bid(
@@ -63,8 +63,8 @@ use sp_runtime::{
 		AccountIdConversion, Hash, Saturating, Zero, One, CheckedAdd, Verify, IdentifyAccount,
 	},
 };
-use crate::traits::{Registrar, Auctioneer};
-use crate::slot_range::SlotRange;
+use crate::traits::{BlockNumberProvider, Registrar, Auctioneer};
+use crate::slot_range::{SlotRange, SlotRangeError};
 use parity_scale_codec::{Encode, Decode};
 use sp_std::vec::Vec;
 use primitives::v1::Id as ParaId;
@@ -190,6 +190,9 @@ pub mod pallet {
 		/// The maximum length for the memo attached to a crowdloan contribution.
 		type MaxMemoLength: Get<u8>;
 
+		/// The source of the relay chain block number, which crowdloans end at.
+		type BlockNumberProvider: BlockNumberProvider<BlockNumber = Self::BlockNumber>;
+
 		/// Weight Information for the Extrinsics in the Pallet
 		type WeightInfo: WeightInfo;
 	}
@@ -343,12 +346,14 @@ pub mod pallet {
 		) -> DispatchResult {
 			let depositor = ensure_signed(origin)?;
 
-			ensure!(first_period <= last_period, Error::<T>::LastPeriodBeforeFirstPeriod);
-			let last_period_limit = first_period
+			// The range must fit in a single slot beginning at `first_period`.
+			SlotRange::new_checked(first_period, first_period, last_period)
+				.map_err(Error::<T>::from)?;
+			let last_slot_period = first_period
 				.checked_add(&((SlotRange::LEASE_PERIODS_PER_SLOT as u32) - 1).into())
 				.ok_or(Error::<T>::FirstPeriodTooFarInFuture)?;
-			ensure!(last_period <= last_period_limit, Error::<T>::LastPeriodTooFarInFuture);
-			ensure!(end > <frame_system::Pallet<T>>::block_number(), Error::<T>::CannotEndInPast);
+			ensure!(last_period <= last_slot_period, Error::<T>::LastPeriodTooFarInFuture);
+			ensure!(end > T::BlockNumberProvider::current_block_number(), Error::<T>::CannotEndInPast);
 			let last_possible_win_date = (first_period.saturating_add(One::one())).saturating_mul(T::Auctioneer::lease_period());
 			ensure!(end <= last_possible_win_date, Error::<T>::EndTooFarInFuture);
 			ensure!(first_period >= T::Auctioneer::lease_period_index(), Error::<T>::FirstPeriodInPast);
@@ -405,7 +410,7 @@ pub mod pallet {
 			ensure!(fund.raised <= fund.cap, Error::<T>::CapExceeded);
 
 			// Make sure crowdloan has not ended
-			let now = <frame_system::Pallet<T>>::block_number();
+			let now = T::BlockNumberProvider::current_block_number();
 			ensure!(now < fund.end, Error::<T>::ContributionPeriodOver);
 
 			// Make sure crowdloan is in a valid lease period
@@ -494,7 +499,7 @@ pub mod pallet {
 			ensure_signed(origin)?;
 
 			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidParaId)?;
-			let now = frame_system::Pallet::<T>::block_number();
+			let now = T::BlockNumberProvider::current_block_number();
 			let fund_account = Self::fund_account_id(index);
 			Self::ensure_crowdloan_ended(now, &fund_account, &fund)?;
 
@@ -525,7 +530,7 @@ pub mod pallet {
 			ensure_signed(origin)?;
 
 			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidParaId)?;
-			let now = frame_system::Pallet::<T>::block_number();
+			let now = T::BlockNumberProvider::current_block_number();
 			let fund_account = Self::fund_account_id(index);
 			Self::ensure_crowdloan_ended(now, &fund_account, &fund)?;
 
@@ -566,7 +571,7 @@ pub mod pallet {
 			let who = ensure_signed(origin)?;
 
 			let fund = Self::funds(index).ok_or(Error::<T>::InvalidParaId)?;
-			let now = frame_system::Pallet::<T>::block_number();
+			let now = T::BlockNumberProvider::current_block_number();
 
 			// Only allow dissolution when the raised funds goes to zero,
 			// and the caller is the fund creator or we are past the end date.
@@ -653,6 +658,17 @@ pub mod pallet {
 	}
 }
 
+impl<T: Config> From<SlotRangeError> for Error<T> {
+	fn from(e: SlotRangeError) -> Self {
+		match e {
+			// Cannot happen since the range is relative to its own first period.
+			SlotRangeError::OutOfBounds => Error::<T>::FirstPeriodInPast,
+			SlotRangeError::Inverted => Error::<T>::LastPeriodBeforeFirstPeriod,
+			SlotRangeError::TooLong => Error::<T>::LastPeriodTooFarInFuture,
+		}
+	}
+}
+
 impl<T: Config> Pallet<T> {
 	/// The account ID of the fund pot.
 	///
@@ -764,7 +780,7 @@ mod tests {
 		testing::Header, traits::{BlakeTwo256, IdentityLookup}, DispatchResult,
 	};
 	use crate::{
-		mock::TestRegistrar,
+		mock::{MockBlockNumberProvider, TestRegistrar},
 		traits::{OnSwap, AuctionStatus},
 		crowdloan,
 	};
@@ -936,7 +952,7 @@ mod tests {
 		}
 
 		fn lease_period_index() -> u64 {
-			System::block_number() / Self::lease_period()
+			MockBlockNumberProvider::<Test>::current_block_number() / Self::lease_period()
 		}
 
 		fn lease_period() -> u64 {
@@ -965,6 +981,7 @@ mod tests {
 		type Registrar = TestRegistrar<Test>;
 		type Auctioneer = TestAuctioneer;
 		type MaxMemoLength = MaxMemoLength;
+		type BlockNumberProvider = MockBlockNumberProvider<Test>;
 		type WeightInfo = crate::crowdloan::TestWeightInfo;
 	}
 
@@ -1332,6 +1349,30 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn crowdloan_follows_block_number_provider() {
+		new_test_ext().execute_with(|| {
+			let para = new_para();
+
+			assert_ok!(Crowdloan::create(Origin::signed(1), para, 1000, 1, 1, 30, None));
+			// Jump to the last block of the crowdloan without sealing any blocks.
+			MockBlockNumberProvider::<Test>::set(29);
+			assert_ok!(Crowdloan::contribute(Origin::signed(2), para, 100, None));
+			assert_noop!(Crowdloan::withdraw(Origin::signed(2), 2, para), Error::<Test>::FundNotEnded);
+
+			// Jump across the lease period boundary.
+			MockBlockNumberProvider::<Test>::set(40);
+			assert_eq!(TestAuctioneer::lease_period_index(), 2);
+			assert_eq!(System::block_number(), 0);
+			assert_noop!(
+				Crowdloan::contribute(Origin::signed(3), para, 50, None),
+				Error::<Test>::ContributionPeriodOver,
+			);
+			assert_ok!(Crowdloan::withdraw(Origin::signed(2), 2, para));
+			assert_eq!(Balances::free_balance(2), 2000);
+		});
+	}
+
 	#[test]
 	fn withdraw_cannot_be_griefed() {
 		new_test_ext().execute_with(|| {
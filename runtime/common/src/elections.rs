@@ -18,34 +18,138 @@
 
 use frame_support::{
 	parameter_types,
+	traits::Get,
 	weights::{DispatchClass, Weight},
 };
 use sp_runtime::{
 	traits::{Zero, Dispatchable},
-	FixedU128, FixedPointNumber, Perbill,
+	FixedU128, FixedPointNumber, Perbill, RuntimeDebug,
 };
 use pallet_transaction_payment::OnChargeTransaction;
+use pallet_election_provider_multi_phase::BenchmarkingConfig;
+use sp_npos_elections::ElectionScore;
 use frame_support::weights::{DispatchInfo, Pays};
 use super::{BlockExecutionWeight, BlockLength, BlockWeights};
 
 parameter_types! {
+	/// The weight of the normal class's maximum extrinsic weight which off-chain solutions leave
+	/// unused, for the overhead of the submit extrinsic beyond the solution itself.
+	///
+	/// The base weight of the extrinsic is already excluded from `max_extrinsic`, but the
+	/// benchmarked weight of a solution is only an estimate, so we can't risk filling the rest. We
+	/// keep the base block execution weight free, as a margin known to comfortably exceed that
+	/// error.
+	pub OffchainSolutionWeightMargin: Weight = BlockExecutionWeight::get();
+
 	/// A limit for off-chain phragmen unsigned solution submission.
 	///
-	/// We want to keep it as high as possible, but can't risk having it reject,
-	/// so we always subtract the base block execution weight.
-	pub OffchainSolutionWeightLimit: Weight = BlockWeights::get()
-		.get(DispatchClass::Normal)
-		.max_extrinsic
-		.expect("Normal extrinsics have weight limit configured by default; qed")
-		.saturating_sub(BlockExecutionWeight::get());
+	/// See `offchain_solution_weight_limit`.
+	pub OffchainSolutionWeightLimit: Weight = offchain_solution_weight_limit(
+		BlockWeights::get()
+			.get(DispatchClass::Normal)
+			.max_extrinsic
+			.expect("Normal extrinsics have weight limit configured by default; qed"),
+		OffchainSolutionWeightMargin::get(),
+	);
 
 	/// A limit for off-chain phragmen unsigned solution length.
 	///
-	/// We allow up to 90% of the block's size to be consumed by the solution.
-	pub OffchainSolutionLengthLimit: u32 = Perbill::from_rational(90_u32, 100) *
-		*BlockLength::get()
-		.max
-		.get(DispatchClass::Normal);
+	/// See `offchain_solution_length_limit`.
+	pub OffchainSolutionLengthLimit: u32 = offchain_solution_length_limit(
+		*BlockLength::get().max.get(DispatchClass::Normal),
+	);
+
+	/// The amount by which an unsigned solution must improve on the queued one to replace it.
+	///
+	/// Every unsigned solution takes most of a block, and validators all mine at once, so we only
+	/// want the ones which help: a solution less than 0.05% better than the queued one is mostly
+	/// the miner's rounding and isn't worth the block.
+	pub BetterUnsignedThreshold: Perbill = Perbill::from_rational(5u32, 10_000);
+
+	/// The amount by which a signed solution must improve on the best one submitted so far.
+	///
+	/// Signed submitters put down a deposit which covers the cost of checking their solution, so
+	/// any strictly better solution is welcome.
+	pub BetterSignedThreshold: Perbill = Perbill::zero();
+}
+
+/// Whether a solution scoring `candidate` improves on one scoring `best` by more than `threshold`,
+/// which the election provider requires of a solution before accepting it.
+///
+/// Miners can use this to avoid submitting solutions which would be rejected.
+pub fn is_score_better_by_threshold(
+	candidate: ElectionScore,
+	best: ElectionScore,
+	threshold: Perbill,
+) -> bool {
+	sp_npos_elections::is_score_better(candidate, best, threshold)
+}
+
+/// The share of a block's weight which may be spent taking voters into the election snapshot.
+pub const VOTER_SNAPSHOT_BLOCK_RATIO: Perbill = Perbill::from_percent(50);
+
+/// The weight of taking a single voter into the election snapshot, according to the benchmarked
+/// `get_npos_voters` weight of `W`.
+///
+/// This is the cost of one more nominator, including the reads of its bond, ledger and
+/// nominations.
+pub fn voter_snapshot_weight<W: pallet_staking::WeightInfo>() -> Weight {
+	W::get_npos_voters(0, 1, 0).saturating_sub(W::get_npos_voters(0, 0, 0))
+}
+
+/// The number of voters which can be taken into the election snapshot of runtime `T` in a single
+/// block.
+///
+/// See `max_voters_for_snapshot` and `voter_snapshot_weight`. The election provider can't be
+/// configured with this bound yet: it takes no bound on its snapshot, and staking requires it to
+/// read the voters from staking directly. Runtimes pin it in their tests instead, so that
+/// benchmark regressions shrinking it show up there.
+pub struct VoterSnapshotPerBlock<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: pallet_staking::Config> Get<u32> for VoterSnapshotPerBlock<T> {
+	fn get() -> u32 {
+		max_voters_for_snapshot(
+			VOTER_SNAPSHOT_BLOCK_RATIO,
+			voter_snapshot_weight::<<T as pallet_staking::Config>::WeightInfo>(),
+		)
+	}
+}
+
+/// The number of voters which can be taken into the election snapshot in a block, spending at
+/// most `target_block_weight_fraction` of the block's weight at `avg_voter_weight` per voter.
+pub fn max_voters_for_snapshot(
+	target_block_weight_fraction: Perbill,
+	avg_voter_weight: Weight,
+) -> u32 {
+	let allotted = target_block_weight_fraction * BlockWeights::get().max_block;
+	(allotted / avg_voter_weight.max(1)).min(u32::max_value() as Weight) as u32
+}
+
+/// The maximum weight of an off-chain solution, given the maximum weight of a normal extrinsic.
+///
+/// We want to keep it as high as possible, but can't risk having it reject, so we subtract
+/// `margin` for the overhead of the submit extrinsic.
+pub fn offchain_solution_weight_limit(normal_max_extrinsic: Weight, margin: Weight) -> Weight {
+	normal_max_extrinsic.saturating_sub(margin)
+}
+
+/// The share of the normal dispatch class's block length which an unsigned solution extrinsic
+/// may consume.
+pub const OFFCHAIN_SOLUTION_LENGTH_RATIO: Perbill = Perbill::from_percent(90);
+
+/// The length reserved for the parts of an unsigned solution extrinsic other than the solution
+/// itself: the extrinsic and call prefixes, the score, the witness and the round.
+///
+/// Those take less than a hundred bytes; the rest is a safety margin.
+pub const SOLUTION_ENVELOPE_LENGTH: u32 = 1024;
+
+/// The maximum length of an off-chain solution, given the maximum length of the normal dispatch
+/// class.
+///
+/// We allow `OFFCHAIN_SOLUTION_LENGTH_RATIO` of the normal class to be consumed by the solution
+/// extrinsic, of which `SOLUTION_ENVELOPE_LENGTH` is kept for everything but the solution.
+pub fn offchain_solution_length_limit(normal_max_length: u32) -> u32 {
+	(OFFCHAIN_SOLUTION_LENGTH_RATIO * normal_max_length).saturating_sub(SOLUTION_ENVELOPE_LENGTH)
 }
 
 pub fn fee_for_submit_call<T>(
@@ -67,10 +171,24 @@ where
 	))
 }
 
+/// The on-chain election, used when no better solution is available.
+pub type OnChainSeqPhragmen<T> = frame_election_provider_support::onchain::OnChainSequentialPhragmen<
+	pallet_election_provider_multi_phase::OnChainConfig<T>,
+>;
+
+/// Bounds for an on-chain election, such as the emergency election of `emergency`.
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub struct FallbackConfig {
+	/// The maximum number of voters taken into account.
+	pub max_voters: u32,
+	/// The maximum number of targets taken into account.
+	pub max_targets: u32,
+}
+
 /// The numbers configured here should always be more than the the maximum limits of staking pallet
 /// to ensure election snapshot will not run out of memory.
 pub struct BenchmarkConfig;
-impl pallet_election_provider_multi_phase::BenchmarkingConfig for BenchmarkConfig {
+impl BenchmarkingConfig for BenchmarkConfig {
 	const VOTERS: [u32; 2] = [5_000, 10_000];
 	const TARGETS: [u32; 2] = [1_000, 2_000];
 	const ACTIVE_VOTERS: [u32; 2] = [1000, 4_000];
@@ -79,3 +197,420 @@ impl pallet_election_provider_multi_phase::BenchmarkingConfig for BenchmarkConfi
 	const MINER_MAXIMUM_VOTERS: u32 = 15_000;
 	const MAXIMUM_TARGETS: u32 = 2000;
 }
+
+/// The weight of checking and applying an unsigned solution with `voters` voters, of which
+/// `active_voters` back `degree` winners out of `targets`, according to the benchmarked
+/// `submit_unsigned` weight of `W`.
+pub fn miner_solution_weight<W: pallet_election_provider_multi_phase::WeightInfo>(
+	voters: u32,
+	targets: u32,
+	active_voters: u32,
+	degree: u32,
+) -> Weight {
+	W::submit_unsigned(voters, targets, active_voters, degree)
+}
+
+/// Something that can tell the miner the weight of the solution it is about to submit.
+pub trait MinerWeight {
+	/// The weight of a solution for the given election size. See [`miner_solution_weight`].
+	fn solution_weight(voters: u32, targets: u32, active_voters: u32, degree: u32) -> Weight;
+}
+
+/// A [`MinerWeight`] based on the election provider weights of runtime `T`.
+pub struct BenchmarkedMinerWeight<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: pallet_election_provider_multi_phase::Config> MinerWeight for BenchmarkedMinerWeight<T> {
+	fn solution_weight(voters: u32, targets: u32, active_voters: u32, degree: u32) -> Weight {
+		miner_solution_weight::<T::WeightInfo>(voters, targets, active_voters, degree)
+	}
+}
+
+/// The miner weight according to the default election provider weights.
+impl MinerWeight for () {
+	fn solution_weight(voters: u32, targets: u32, active_voters: u32, degree: u32) -> Weight {
+		miner_solution_weight::<()>(voters, targets, active_voters, degree)
+	}
+}
+
+/// Assert that the miner weight of runtime `T` matches `benchmarked`, the `submit_unsigned`
+/// formula of its election provider weights, over a grid of election sizes.
+///
+/// This catches drift between the weights the miner trims its solutions to and the benchmarks.
+/// Meant to be called from the tests of each runtime, with the formula of its weights.
+#[cfg(feature = "std")]
+pub fn assert_miner_weight_matches<T: pallet_election_provider_multi_phase::Config>(
+	benchmarked: impl Fn(u32, u32, u32, u32) -> Weight,
+) {
+	for &voters in &[1_000u32, 10_000, 25_000] {
+		for &targets in &[500u32, 2_000] {
+			for &active_voters in &[voters / 2, voters] {
+				for &degree in &[100u32, 400, 800] {
+					assert_eq!(
+						BenchmarkedMinerWeight::<T>::solution_weight(voters, targets, active_voters, degree),
+						benchmarked(voters, targets, active_voters, degree),
+						"weight drifted for ({}, {}, {}, {})",
+						voters, targets, active_voters, degree,
+					);
+				}
+			}
+		}
+	}
+}
+
+/// The length of the solution entry of a single voter: its compact index, and the index of and
+/// share of its stake backing each target it votes for.
+fn voter_solution_length(votes: u32) -> u32 {
+	const VOTER_INDEX_LENGTH: u32 = 4;
+	const TARGET_INDEX_LENGTH: u32 = 2;
+	const ACCURACY_LENGTH: u32 = 2;
+	VOTER_INDEX_LENGTH.saturating_add(votes.saturating_mul(TARGET_INDEX_LENGTH + ACCURACY_LENGTH))
+}
+
+/// A bound which an election's solution would violate.
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum SolutionBoundError {
+	/// More validators are desired than there are targets to elect.
+	TooFewTargets { targets: u32, desired: u32 },
+	/// The solution would weigh more than `OffchainSolutionWeightLimit`.
+	Weight { weight: Weight, limit: Weight },
+	/// The solution would be longer than `OffchainSolutionLengthLimit`.
+	Length { length: u32, limit: u32 },
+}
+
+/// Check that a solution electing `active_validators` of `n_targets`, backed by `n_voters` voters
+/// who each vote for up to `max_votes` targets, fits within the off-chain solution limits.
+///
+/// The solution's weight is the one `M` tells the miner.
+pub fn solution_fits<M: MinerWeight>(
+	n_voters: u32,
+	n_targets: u32,
+	active_validators: u32,
+	max_votes: u32,
+) -> Result<(), SolutionBoundError> {
+	if active_validators > n_targets {
+		return Err(SolutionBoundError::TooFewTargets {
+			targets: n_targets,
+			desired: active_validators,
+		});
+	}
+	let weight = M::solution_weight(n_voters, n_targets, n_voters, active_validators);
+	let limit = OffchainSolutionWeightLimit::get();
+	if weight > limit {
+		return Err(SolutionBoundError::Weight { weight, limit });
+	}
+	let length = n_voters.saturating_mul(voter_solution_length(max_votes));
+	let limit = OffchainSolutionLengthLimit::get();
+	if length > limit {
+		return Err(SolutionBoundError::Length { length, limit });
+	}
+	Ok(())
+}
+
+/// Assert that a solution to the largest election the election provider is benchmarked for fits
+/// within the off-chain solution limits of `$runtime`, given its `MAX_NOMINATIONS`.
+///
+/// Panics naming the violated bound otherwise.
+#[macro_export]
+macro_rules! assert_election_bounds {
+	($runtime:ty) => {{
+		use pallet_election_provider_multi_phase::BenchmarkingConfig;
+		use $crate::elections::BenchmarkConfig;
+
+		let result = $crate::elections::solution_fits::<
+			$crate::elections::BenchmarkedMinerWeight<$runtime>,
+		>(
+			BenchmarkConfig::MINER_MAXIMUM_VOTERS,
+			BenchmarkConfig::MAXIMUM_TARGETS,
+			BenchmarkConfig::DESIRED_TARGETS[1],
+			<$runtime as pallet_staking::Config>::MAX_NOMINATIONS,
+		);
+		if let Err(bound) = result {
+			panic!("a full solution for {} violates a bound: {:?}", stringify!($runtime), bound);
+		}
+	}};
+}
+
+/// Helpers for governance to compute the election result to submit with
+/// `set_emergency_election_result` when the election provider is in its emergency phase.
+///
+/// The voters and targets come from the election provider's snapshot, which can be fetched with
+/// the `state_getStorage` RPC.
+///
+/// The Polkadot runtime's tests check that the call built here decodes as the runtime's
+/// `set_emergency_election_result` call.
+#[cfg(feature = "std")]
+pub mod emergency {
+	use super::*;
+	use parity_scale_codec::Encode;
+	use sp_npos_elections::{
+		assignment_ratio_to_staked_normalized, seq_phragmen, to_supports, to_without_backing,
+		EvaluateSupport, IdentifierT, Supports, VoteWeight,
+	};
+
+	/// The number of rounds of balancing run on the result of seq-phragmen.
+	const BALANCING_ITERATIONS: usize = 10;
+
+	/// Why no emergency election result could be computed.
+	#[derive(Debug, Eq, PartialEq)]
+	pub enum EmergencyError {
+		/// The election itself failed.
+		Election(sp_npos_elections::Error),
+		/// A different number of winners than desired was elected. \[elected, desired\]
+		WrongWinnerCount(u32, u32),
+		/// More winners were desired than the chain accepts. \[desired, max\]
+		TooManyWinners(u32, u32),
+		/// Some winner has no backing at all.
+		UnbackedWinner,
+	}
+
+	impl From<sp_npos_elections::Error> for EmergencyError {
+		fn from(error: sp_npos_elections::Error) -> Self {
+			EmergencyError::Election(error)
+		}
+	}
+
+	/// Elect `desired_targets` of `targets` with seq-phragmen, returning the supports of the
+	/// winners and their score.
+	///
+	/// Only the `bounds.max_voters` voters with the most stake and the first `bounds.max_targets`
+	/// targets are taken into account. Fails unless exactly `desired_targets` winners, all backed,
+	/// are elected, and if `desired_targets` exceeds `max_winners`.
+	pub fn emergency_supports<AccountId: IdentifierT>(
+		mut voters: Vec<(AccountId, VoteWeight, Vec<AccountId>)>,
+		mut targets: Vec<AccountId>,
+		desired_targets: u32,
+		max_winners: u32,
+		bounds: FallbackConfig,
+	) -> Result<(Supports<AccountId>, ElectionScore), EmergencyError> {
+		if desired_targets > max_winners {
+			return Err(EmergencyError::TooManyWinners(desired_targets, max_winners));
+		}
+		voters.sort_by(|a, b| b.1.cmp(&a.1));
+		voters.truncate(bounds.max_voters as usize);
+		targets.truncate(bounds.max_targets as usize);
+
+		let stakes = voters.iter()
+			.map(|(who, stake, _)| (who.clone(), *stake))
+			.collect::<std::collections::BTreeMap<_, _>>();
+		let stake_of = |who: &AccountId| stakes.get(who).cloned().unwrap_or_default();
+
+		let result = seq_phragmen::<_, Perbill>(
+			desired_targets as usize,
+			targets,
+			voters,
+			Some((BALANCING_ITERATIONS, 0)),
+		)?;
+		let winners = to_without_backing(result.winners);
+		let staked = assignment_ratio_to_staked_normalized(result.assignments, stake_of)?;
+		let supports = to_supports(&winners, &staked)?;
+
+		if supports.len() as u32 != desired_targets {
+			return Err(EmergencyError::WrongWinnerCount(supports.len() as u32, desired_targets));
+		}
+		if supports.iter().any(|(_, support)| support.total == 0) {
+			return Err(EmergencyError::UnbackedWinner);
+		}
+		let score = supports.evaluate();
+		Ok((supports, score))
+	}
+
+	/// The SCALE-encoded call submitting `supports` as the emergency election result.
+	pub fn emergency_call<T>(supports: Supports<T::AccountId>) -> Vec<u8> where
+		T: pallet_election_provider_multi_phase::Config,
+		<T as frame_system::Config>::Call: From<pallet_election_provider_multi_phase::Call<T>>,
+	{
+		let call: <T as frame_system::Config>::Call =
+			pallet_election_provider_multi_phase::Call::<T>::set_emergency_election_result(supports)
+				.into();
+		call.encode()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn normal_max_length() -> u32 {
+		*BlockLength::get().max.get(DispatchClass::Normal)
+	}
+
+	#[test]
+	fn solution_length_limit_follows_block_length() {
+		// 90% of the normal class's 75% of a 5 MiB block, less the envelope.
+		const EXPECTED_LIMIT: u32 = 5 * 1024 * 1024 / 4 * 3 / 10 * 9 - 1024;
+		assert_eq!(EXPECTED_LIMIT, 3_537_920);
+		assert_eq!(OffchainSolutionLengthLimit::get(), EXPECTED_LIMIT);
+		for length in &[SOLUTION_ENVELOPE_LENGTH, 64 * 1024, normal_max_length(), u32::max_value()] {
+			let limit = offchain_solution_length_limit(*length);
+			assert!(limit.saturating_add(SOLUTION_ENVELOPE_LENGTH) <= *length);
+		}
+	}
+
+	#[test]
+	fn max_length_solution_fits_in_normal_class() {
+		let solution_extrinsic = OffchainSolutionLengthLimit::get() + SOLUTION_ENVELOPE_LENGTH;
+		assert!(solution_extrinsic <= normal_max_length());
+		// The limit isn't needlessly tight either.
+		assert!(OffchainSolutionLengthLimit::get() >= Perbill::from_percent(85) * normal_max_length());
+	}
+
+	#[test]
+	fn max_weight_solution_is_admissible_in_normal_class() {
+		let weights = BlockWeights::get();
+		let normal = weights.get(DispatchClass::Normal);
+		let solution = OffchainSolutionWeightLimit::get();
+
+		assert_eq!(
+			solution,
+			normal.max_extrinsic.unwrap() - OffchainSolutionWeightMargin::get(),
+		);
+		// The submission, with the base weight of an extrinsic, must fit into the normal class of
+		// a block which is otherwise only initialized.
+		let submission = normal.base_extrinsic + solution;
+		let initialization = crate::AVERAGE_ON_INITIALIZE_RATIO * crate::MAXIMUM_BLOCK_WEIGHT;
+		assert!(submission + initialization <= normal.max_total.unwrap());
+		assert!(solution + OffchainSolutionWeightMargin::get() <= normal.max_extrinsic.unwrap());
+		// The margin is small next to the solution.
+		assert!(OffchainSolutionWeightMargin::get() < Perbill::from_percent(1) * solution);
+	}
+
+	#[test]
+	fn voter_snapshot_fits_allotted_weight() {
+		let allotted = VOTER_SNAPSHOT_BLOCK_RATIO * BlockWeights::get().max_block;
+		let weight = voter_snapshot_weight::<()>();
+		let voters = max_voters_for_snapshot(VOTER_SNAPSHOT_BLOCK_RATIO, weight) as Weight;
+		assert!(voters * weight <= allotted);
+		assert!((voters + 1) * weight > allotted);
+
+		assert_eq!(max_voters_for_snapshot(Perbill::zero(), weight), 0);
+		assert_eq!(max_voters_for_snapshot(Perbill::one(), 0), u32::max_value());
+		assert_eq!(
+			max_voters_for_snapshot(Perbill::one(), BlockWeights::get().max_block / 10),
+			10,
+		);
+	}
+
+	#[test]
+	fn voter_snapshot_weight_follows_benchmarks() {
+		use pallet_staking::WeightInfo;
+		// Taking nominators into the snapshot costs the same for each of them.
+		let weight = voter_snapshot_weight::<()>();
+		assert!(weight > 0);
+		assert_eq!(
+			<() as WeightInfo>::get_npos_voters(0, 1_000, 0)
+				- <() as WeightInfo>::get_npos_voters(0, 0, 0),
+			1_000 * weight,
+		);
+		// Benchmarks should cover at least one block's worth of voters.
+		assert!(
+			BenchmarkConfig::SNAPSHOT_MAXIMUM_VOTERS >=
+				max_voters_for_snapshot(VOTER_SNAPSHOT_BLOCK_RATIO, weight)
+		);
+	}
+
+	#[test]
+	fn score_must_improve_by_threshold() {
+		let threshold = BetterUnsignedThreshold::get();
+		let best = [1_000_000, 2_000_000, 3_000_000];
+		// 0.05% of the minimal stake of the best solution.
+		let margin = threshold * best[0];
+		assert_eq!(margin, 500);
+
+		let just_under = [best[0] + margin, best[1], best[2]];
+		let just_over = [best[0] + margin + 1, best[1], best[2]];
+		assert!(!is_score_better_by_threshold(just_under, best, threshold));
+		assert!(is_score_better_by_threshold(just_over, best, threshold));
+		assert!(!is_score_better_by_threshold(best, best, threshold));
+		// A worse solution is never better, whatever the threshold.
+		assert!(!is_score_better_by_threshold(best, just_over, Perbill::zero()));
+
+		// Signed solutions only need to be strictly better.
+		let slightly_better = [best[0] + 1, best[1], best[2]];
+		assert!(is_score_better_by_threshold(slightly_better, best, BetterSignedThreshold::get()));
+		assert!(!is_score_better_by_threshold(slightly_better, best, threshold));
+	}
+
+	fn fixture() -> (Vec<(u64, u64, Vec<u64>)>, Vec<u64>) {
+		let targets = vec![10, 20, 30, 40];
+		let voters = vec![
+			(1, 100, vec![10, 20]),
+			(2, 50, vec![20, 30]),
+			(3, 70, vec![30]),
+			(4, 5, vec![40]),
+			// Voting for themselves.
+			(10, 10, vec![10]),
+			(20, 10, vec![20]),
+			(30, 10, vec![30]),
+			(40, 10, vec![40]),
+		];
+		(voters, targets)
+	}
+
+	#[test]
+	fn emergency_supports_works() {
+		use emergency::*;
+
+		let (voters, targets) = fixture();
+		let total_stake: u128 = voters.iter().map(|(_, stake, _)| *stake as u128).sum();
+		let bounds = FallbackConfig { max_voters: 100, max_targets: 100 };
+		let (supports, score) = emergency_supports(voters, targets, 3, 3, bounds).unwrap();
+
+		let mut winners = supports.iter().map(|(who, _)| *who).collect::<Vec<_>>();
+		winners.sort();
+		assert_eq!(winners, vec![10, 20, 30]);
+		assert!(supports.iter().all(|(_, support)| support.total > 0));
+		// Nothing but the voters' stake backs the winners.
+		assert!(supports.iter().map(|(_, support)| support.total).sum::<u128>() <= total_stake);
+		assert!(score[0] > 0);
+		assert_eq!(score[1], supports.iter().map(|(_, support)| support.total).sum::<u128>());
+	}
+
+	#[test]
+	fn emergency_supports_is_bounded() {
+		use emergency::*;
+
+		let (voters, targets) = fixture();
+		let unbounded = FallbackConfig { max_voters: 100, max_targets: 100 };
+		assert_eq!(
+			emergency_supports(voters.clone(), targets.clone(), 4, 3, unbounded),
+			Err(EmergencyError::TooManyWinners(4, 3)),
+		);
+		// Only the top voter and the first two targets are considered.
+		let bounds = FallbackConfig { max_voters: 1, max_targets: 2 };
+		let (supports, _) = emergency_supports(voters.clone(), targets.clone(), 2, 2, bounds).unwrap();
+		assert_eq!(supports.iter().map(|(_, support)| support.total).sum::<u128>(), 100);
+		assert_eq!(
+			emergency_supports(voters, targets, 3, 3, bounds),
+			Err(EmergencyError::WrongWinnerCount(2, 3)),
+		);
+	}
+
+	#[test]
+	fn solution_fits_works() {
+		assert_eq!(
+			solution_fits::<()>(
+				BenchmarkConfig::MINER_MAXIMUM_VOTERS,
+				BenchmarkConfig::MAXIMUM_TARGETS,
+				BenchmarkConfig::DESIRED_TARGETS[1],
+				24,
+			),
+			Ok(()),
+		);
+
+		assert_eq!(
+			solution_fits::<()>(1_000, 10, 11, 16),
+			Err(SolutionBoundError::TooFewTargets { targets: 10, desired: 11 }),
+		);
+		assert!(matches!(
+			solution_fits::<()>(10_000_000, 1_000, 100, 16),
+			Err(SolutionBoundError::Weight { .. }),
+		));
+		assert_eq!(
+			solution_fits::<()>(10_000, 1_000, 100, 1_000),
+			Err(SolutionBoundError::Length {
+				length: 10_000 * 4_004,
+				limit: OffchainSolutionLengthLimit::get(),
+			}),
+		);
+	}
+}
@@ -0,0 +1,185 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulation of the fee multiplier over sequences of blocks, to answer questions such as how
+//! long blocks must stay full until fees double.
+//!
+//! The blocks are simulated in their own externalities, so this is only available with `std`.
+
+use crate::{normal_dispatch_limit, simulate_blocks};
+use pallet_transaction_payment::Multiplier;
+use sp_runtime::{Perquintill, traits::Convert};
+use sp_std::prelude::*;
+
+/// The fee multiplier after each of `blocks` blocks filled to `fullness` of the normal dispatch
+/// limit of `R`, starting from `initial` and updating with `Update`.
+///
+/// `Update` is the `FeeMultiplierUpdate` of the runtime, e.g. `SlowAdjustingFeeUpdate<R>`. The
+/// `n`th item is the multiplier after `n + 1` blocks, so `initial` is not included. The blocks
+/// are simulated like those of [`crate::multiplier_after_blocks`].
+pub fn simulate_multiplier<R, Update>(
+	initial: Multiplier,
+	fullness: Perquintill,
+	blocks: u32,
+) -> Vec<Multiplier>
+where
+	R: frame_system::Config,
+	Update: Convert<Multiplier, Multiplier>,
+{
+	let mut multipliers = Vec::with_capacity(blocks as usize);
+	let block_weight = fullness * normal_dispatch_limit::<R>();
+	simulate_blocks::<R, Update>(initial, block_weight, blocks, |multiplier| {
+		multipliers.push(multiplier);
+		true
+	});
+	multipliers
+}
+
+/// The number of blocks filled to `fullness` after which the multiplier of `Update` first reaches
+/// `target` from `initial`, if it does within `max_blocks`.
+pub fn blocks_until_multiplier<R, Update>(
+	initial: Multiplier,
+	target: Multiplier,
+	fullness: Perquintill,
+	max_blocks: u32,
+) -> Option<u32>
+where
+	R: frame_system::Config,
+	Update: Convert<Multiplier, Multiplier>,
+{
+	if initial >= target {
+		return Some(0)
+	}
+	let mut blocks = 0;
+	let block_weight = fullness * normal_dispatch_limit::<R>();
+	let last = simulate_blocks::<R, Update>(initial, block_weight, max_blocks, |multiplier| {
+		blocks += 1;
+		multiplier < target
+	});
+	if last >= target { Some(blocks) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		FastAdjustingFeeUpdate, MinimumMultiplier, SlowAdjustingFeeUpdate, TargetBlockFullness,
+	};
+	use frame_support::{parameter_types, traits::Get};
+	use sp_core::H256;
+	use sp_runtime::{
+		testing::Header,
+		traits::{BlakeTwo256, IdentityLookup, One},
+		FixedPointNumber,
+	};
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
+	type Block = frame_system::mocking::MockBlock<Runtime>;
+
+	frame_support::construct_runtime!(
+		pub enum Runtime where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>}
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub BlockWeights: frame_system::limits::BlockWeights =
+			frame_system::limits::BlockWeights::simple_max(1024);
+	}
+
+	impl frame_system::Config for Runtime {
+		type BaseCallFilter = frame_support::traits::AllowAll;
+		type BlockWeights = BlockWeights;
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Call = Call;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	fn twice_target() -> Perquintill {
+		Perquintill::from_parts(TargetBlockFullness::get().deconstruct() * 2)
+	}
+
+	#[test]
+	fn simulation_grows_above_target_and_shrinks_below() {
+		let one = Multiplier::one();
+		let above = simulate_multiplier::<Runtime, SlowAdjustingFeeUpdate<Runtime>>(one, twice_target(), 10);
+		assert_eq!(above.len(), 10);
+		assert!(above.windows(2).all(|w| w[1] > w[0]));
+		assert!(above[0] > one);
+
+		let empty = simulate_multiplier::<Runtime, SlowAdjustingFeeUpdate<Runtime>>(one, Perquintill::zero(), 10);
+		assert!(empty.windows(2).all(|w| w[1] < w[0]));
+
+		assert!(simulate_multiplier::<Runtime, SlowAdjustingFeeUpdate<Runtime>>(one, twice_target(), 0).is_empty());
+	}
+
+	#[test]
+	fn fast_multiplier_reaches_one_from_minimum_at_twice_target() {
+		// 18_421 blocks, or about 31 hours of 6 second blocks.
+		let blocks = blocks_until_multiplier::<Runtime, FastAdjustingFeeUpdate<Runtime>>(
+			MinimumMultiplier::get(),
+			Multiplier::one(),
+			twice_target(),
+			20_000,
+		).expect("the multiplier reaches one at twice the target fullness");
+		assert!((18_400..18_450).contains(&blocks), "took {} blocks", blocks);
+	}
+
+	#[test]
+	fn multiplier_doubles_at_twice_target() {
+		let two = Multiplier::saturating_from_integer(2);
+		// 925 blocks, or about an hour and a half of 6 second blocks.
+		let fast = blocks_until_multiplier::<Runtime, FastAdjustingFeeUpdate<Runtime>>(
+			Multiplier::one(),
+			two,
+			twice_target(),
+			1_000,
+		).expect("fees double at twice the target fullness");
+		assert!((920..930).contains(&fast), "took {} blocks", fast);
+
+		// 92_420 blocks, or about 154 hours of 6 second blocks.
+		let slow = blocks_until_multiplier::<Runtime, SlowAdjustingFeeUpdate<Runtime>>(
+			Multiplier::one(),
+			two,
+			twice_target(),
+			100_000,
+		).expect("fees double at twice the target fullness");
+		assert!((92_000..93_000).contains(&slow), "took {} blocks", slow);
+	}
+}
@@ -16,62 +16,259 @@
 
 //! Auxillary struct/enums for polkadot runtime.
 
-use frame_support::traits::{OnUnbalanced, Imbalance, Currency};
+use sp_std::prelude::*;
+use frame_support::traits::{OnUnbalanced, Imbalance, Currency, CurrencyToVote, Get};
+use sp_runtime::{Perbill, PerThing, helpers_128bit::multiply_by_rational, traits::AtLeast32BitUnsigned};
 use crate::NegativeImbalance;
 
 /// Logic for the author to get a portion of fees.
-pub struct ToAuthor<R>(sp_std::marker::PhantomData<R>);
-impl<R> OnUnbalanced<NegativeImbalance<R>> for ToAuthor<R>
+///
+/// If no author can be found for the current block, the imbalance goes to `Fallback` instead.
+pub struct ToAuthor<R, Fallback = pallet_treasury::Pallet<R>>(
+	sp_std::marker::PhantomData<(R, Fallback)>,
+);
+impl<R, Fallback> OnUnbalanced<NegativeImbalance<R>> for ToAuthor<R, Fallback>
 where
 	R: pallet_balances::Config + pallet_authorship::Config,
+	Fallback: OnUnbalanced<NegativeImbalance<R>>,
 	<R as frame_system::Config>::AccountId: From<primitives::v1::AccountId>,
 	<R as frame_system::Config>::AccountId: Into<primitives::v1::AccountId>,
 	<R as frame_system::Config>::Event: From<pallet_balances::Event<R>>,
 {
 	fn on_nonzero_unbalanced(amount: NegativeImbalance<R>) {
-		let numeric_amount = amount.peek();
+		// `pallet_authorship` gives the default account as the author if none is found.
 		let author = <pallet_authorship::Pallet<R>>::author();
-		<pallet_balances::Pallet<R>>::resolve_creating(&<pallet_authorship::Pallet<R>>::author(), amount);
-		<frame_system::Pallet<R>>::deposit_event(pallet_balances::Event::Deposit(author, numeric_amount));
+		if author == Default::default() {
+			return Fallback::on_unbalanced(amount)
+		}
+		deposit::<R>(author, amount);
 	}
 }
 
-pub struct DealWithFees<R>(sp_std::marker::PhantomData<R>);
-impl<R> OnUnbalanced<NegativeImbalance<R>> for DealWithFees<R>
+/// Credits the imbalance to the default account, which is what `ToAuthor` used to do in blocks
+/// without a known author. For runtimes without a treasury to fall back to.
+pub struct ToDefaultAccount<R>(sp_std::marker::PhantomData<R>);
+impl<R> OnUnbalanced<NegativeImbalance<R>> for ToDefaultAccount<R>
 where
-	R: pallet_balances::Config + pallet_treasury::Config + pallet_authorship::Config,
-	pallet_treasury::Pallet<R>: OnUnbalanced<NegativeImbalance<R>>,
-	<R as frame_system::Config>::AccountId: From<primitives::v1::AccountId>,
-	<R as frame_system::Config>::AccountId: Into<primitives::v1::AccountId>,
+	R: pallet_balances::Config,
+	<R as frame_system::Config>::Event: From<pallet_balances::Event<R>>,
+{
+	fn on_nonzero_unbalanced(amount: NegativeImbalance<R>) {
+		deposit::<R>(Default::default(), amount);
+	}
+}
+
+/// Credit `amount` to `who`, creating the account if needed.
+fn deposit<R>(who: R::AccountId, amount: NegativeImbalance<R>)
+where
+	R: pallet_balances::Config,
 	<R as frame_system::Config>::Event: From<pallet_balances::Event<R>>,
+{
+	let numeric_amount = amount.peek();
+	<pallet_balances::Pallet<R>>::resolve_creating(&who, amount);
+	<frame_system::Pallet<R>>::deposit_event(pallet_balances::Event::Deposit(who, numeric_amount));
+}
+
+/// Splits transaction fees between a treasury and the block author, with `TreasuryShare` of the
+/// fees (rounded down) going to `Treasury` and the rest to `Author`. Tips are routed separately by
+/// `Tips`, entirely to `Author` by default.
+///
+/// Before the split, `BurnShare` of the fees (rounded down) is burnt, reducing total issuance.
+/// It defaults to burning nothing.
+pub struct SplitFees<Treasury, Author, TreasuryShare, BurnShare = (), Tips = TipsToAuthor>(
+	sp_std::marker::PhantomData<(Treasury, Author, TreasuryShare, BurnShare, Tips)>,
+);
+impl<Treasury, Author, TreasuryShare, BurnShare, Tips>
+	SplitFees<Treasury, Author, TreasuryShare, BurnShare, Tips>
+where
+	TreasuryShare: Get<Perbill>,
+	BurnShare: Get<Perbill>,
+{
+	/// The fraction of fees that is burnt, and the fraction of the rest that goes to the treasury.
+	pub fn shares() -> (Perbill, Perbill) {
+		(BurnShare::get(), TreasuryShare::get())
+	}
+}
+impl<R, Treasury, Author, TreasuryShare, BurnShare, Tips> OnUnbalanced<NegativeImbalance<R>>
+	for SplitFees<Treasury, Author, TreasuryShare, BurnShare, Tips>
+where
+	R: pallet_balances::Config,
+	Treasury: OnUnbalanced<NegativeImbalance<R>>,
+	Author: OnUnbalanced<NegativeImbalance<R>>,
+	TreasuryShare: Get<Perbill>,
+	BurnShare: Get<Perbill>,
+	Tips: TipRoute,
 {
 	fn on_unbalanceds<B>(mut fees_then_tips: impl Iterator<Item=NegativeImbalance<R>>) {
-		if let Some(fees) = fees_then_tips.next() {
-			// for fees, 80% to treasury, 20% to author
-			let mut split = fees.ration(80, 20);
-			if let Some(tips) = fees_then_tips.next() {
-				// for tips, if any, 100% to author
-				tips.merge_into(&mut split.1);
+		let (fees, tips) = (fees_then_tips.next(), fees_then_tips.next());
+		if let Some(fees) = fees {
+			let to_burn = BurnShare::get().mul_floor(fees.peek());
+			let (burnt, fees) = fees.split(to_burn);
+			// dropping a negative imbalance reduces the total issuance.
+			drop(burnt);
+			SplitImbalance::<(Author, Treasury), AuthorAndTreasury<TreasuryShare>>::on_unbalanced(fees);
+		}
+		if let Some(tips) = tips {
+			Tips::route::<_, Treasury, Author>(tips);
+		}
+	}
+}
+
+/// Where `SplitFees` sends tips, given its treasury and author.
+pub trait TipRoute {
+	/// Hand `tips` to their target.
+	fn route<I, Treasury: OnUnbalanced<I>, Author: OnUnbalanced<I>>(tips: I);
+}
+
+/// Tips go entirely to the block author.
+pub struct TipsToAuthor;
+impl TipRoute for TipsToAuthor {
+	fn route<I, Treasury: OnUnbalanced<I>, Author: OnUnbalanced<I>>(tips: I) {
+		Author::on_unbalanced(tips);
+	}
+}
+
+/// Tips go entirely to the treasury.
+pub struct TipsToTreasury;
+impl TipRoute for TipsToTreasury {
+	fn route<I, Treasury: OnUnbalanced<I>, Author: OnUnbalanced<I>>(tips: I) {
+		Treasury::on_unbalanced(tips);
+	}
+}
+
+/// The ratios of fees given to the author and the treasury, out of `TreasuryShare` for the latter.
+pub struct AuthorAndTreasury<TreasuryShare>(sp_std::marker::PhantomData<TreasuryShare>);
+impl<TreasuryShare: Get<Perbill>> Get<[Perbill; 2]> for AuthorAndTreasury<TreasuryShare> {
+	fn get() -> [Perbill; 2] {
+		let to_treasury = TreasuryShare::get();
+		[to_treasury.left_from_one(), to_treasury]
+	}
+}
+
+/// Split `imbalance` into one part per ratio of `ratios`, in the same order.
+///
+/// Every part but the first gets its ratio of the imbalance, rounded down. The first part gets
+/// whatever remains, so nothing is lost to rounding; its own ratio is only implied.
+pub fn split_by_ratios<B, I>(imbalance: I, ratios: &[Perbill]) -> Vec<I>
+where
+	B: AtLeast32BitUnsigned + Copy,
+	I: Imbalance<B>,
+{
+	let total = imbalance.peek();
+	let mut rest = imbalance;
+	let mut parts = Vec::with_capacity(ratios.len());
+	for ratio in ratios.iter().skip(1) {
+		let (part, remainder) = rest.split(ratio.mul_floor(total));
+		parts.push(part);
+		rest = remainder;
+	}
+	parts.insert(0, rest);
+	parts
+}
+
+/// A tuple of `OnUnbalanced` targets to split an imbalance between.
+pub trait ImbalanceTargets<B, I> {
+	/// One ratio per target.
+	type Ratios: AsRef<[Perbill]>;
+
+	/// Split `imbalance` with [`split_by_ratios`] and hand each part to its target.
+	fn distribute(imbalance: I, ratios: Self::Ratios);
+}
+
+macro_rules! impl_imbalance_targets {
+	($count:expr; $($target:ident),+) => {
+		impl<B, I, $($target),+> ImbalanceTargets<B, I> for ($($target,)+)
+		where
+			B: AtLeast32BitUnsigned + Copy,
+			I: Imbalance<B>,
+			$($target: OnUnbalanced<I>,)+
+		{
+			type Ratios = [Perbill; $count];
+
+			fn distribute(imbalance: I, ratios: Self::Ratios) {
+				let mut parts = split_by_ratios(imbalance, ratios.as_ref()).into_iter();
+				$(
+					$target::on_unbalanced(parts.next().expect("one part per ratio; qed"));
+				)+
 			}
-			use pallet_treasury::Pallet as Treasury;
-			<Treasury<R> as OnUnbalanced<_>>::on_unbalanced(split.0);
-			<ToAuthor<R> as OnUnbalanced<_>>::on_unbalanced(split.1);
 		}
+	};
+}
+
+impl_imbalance_targets!(2; A0, A1);
+impl_imbalance_targets!(3; A0, A1, A2);
+impl_imbalance_targets!(4; A0, A1, A2, A3);
+
+/// Splits an imbalance between the `OnUnbalanced` targets of the tuple `Targets`, according to
+/// the ratios given by `Ratios`. The first target also receives anything lost to rounding by the
+/// others, see [`split_by_ratios`].
+///
+/// For example, to send slashes half to the treasury and a quarter each to two other pots:
+///
+/// ```ignore
+/// parameter_types! {
+/// 	pub SlashRatios: [Perbill; 3] = [
+/// 		Perbill::from_percent(50),
+/// 		Perbill::from_percent(25),
+/// 		Perbill::from_percent(25),
+/// 	];
+/// }
+///
+/// type Slash = SplitImbalance<(Treasury, FirstPot, SecondPot), SlashRatios>;
+/// ```
+pub struct SplitImbalance<Targets, Ratios>(sp_std::marker::PhantomData<(Targets, Ratios)>);
+impl<R, Targets, Ratios> OnUnbalanced<NegativeImbalance<R>> for SplitImbalance<Targets, Ratios>
+where
+	R: pallet_balances::Config,
+	Targets: ImbalanceTargets<R::Balance, NegativeImbalance<R>>,
+	Ratios: Get<Targets::Ratios>,
+{
+	fn on_nonzero_unbalanced(amount: NegativeImbalance<R>) {
+		Targets::distribute(amount, Ratios::get());
 	}
 }
 
+/// A `CurrencyToVote` for `u128` balances which scales stakes to the whole `u64` range relative to
+/// the total issuance, rather than by a power of two as `U128CurrencyToVote` does.
+///
+/// A vote is its stake's share of the issuance times `u64::MAX`, rounded down. Its error is thus
+/// less than one, so the relative error of the vote of any stake holding at least 2^-32 of the
+/// issuance is below 2^-32. Stakes above the issuance saturate to `u64::MAX`.
+///
+/// Runtimes switch to it through `crate::CurrencyToVote`, which staking and the elections must
+/// share.
+pub struct SaturatingCurrencyToVote;
+impl CurrencyToVote<u128> for SaturatingCurrencyToVote {
+	fn to_vote(value: u128, issuance: u128) -> u64 {
+		let issuance = issuance.max(1);
+		if value >= issuance {
+			return u64::max_value();
+		}
+		// `value < issuance`, so the vote is below `u64::MAX`.
+		multiply_by_rational(value, u64::max_value().into(), issuance)
+			.map(|vote| vote as u64)
+			.unwrap_or(u64::max_value())
+	}
+
+	fn to_currency(value: u128, issuance: u128) -> u128 {
+		multiply_by_rational(value, issuance, u64::max_value().into()).unwrap_or(u128::max_value())
+	}
+}
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use frame_system::limits;
-	use frame_support::{parameter_types, PalletId, weights::DispatchClass};
-	use frame_support::traits::FindAuthor;
+	use frame_support::{
+		assert_ok, parameter_types, PalletId, traits::FindAuthor,
+		weights::{DispatchClass, DispatchInfo, IdentityFee, PostDispatchInfo},
+	};
+	use std::cell::RefCell;
+	use parity_scale_codec::Encode;
 	use sp_core::H256;
 	use sp_runtime::{
 		testing::Header,
-		traits::{BlakeTwo256, IdentityLookup},
-		Perbill,
+		traits::{BlakeTwo256, IdentityLookup, SignedExtension},
 	};
 	use primitives::v1::AccountId;
 
@@ -88,6 +285,7 @@ mod tests {
 			Authorship: pallet_authorship::{Pallet, Call, Storage, Inherent},
 			Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 			Treasury: pallet_treasury::{Pallet, Call, Storage, Config, Event<T>},
+			TransactionPayment: pallet_transaction_payment::{Pallet, Storage},
 		}
 	);
 
@@ -166,12 +364,27 @@ mod tests {
 		type WeightInfo = ();
 	}
 
+	parameter_types! {
+		pub const TransactionByteFee: u64 = 1;
+	}
+
+	impl pallet_transaction_payment::Config for Test {
+		type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<Balances, DealWithFees>;
+		type TransactionByteFee = TransactionByteFee;
+		type WeightToFee = IdentityFee<u64>;
+		type FeeMultiplierUpdate = ();
+	}
+
+	thread_local! {
+		static HAS_AUTHOR: RefCell<bool> = RefCell::new(true);
+	}
+
 	pub struct OneAuthor;
 	impl FindAuthor<AccountId> for OneAuthor {
 		fn find_author<'a, I>(_: I) -> Option<AccountId>
 			where I: 'a,
 		{
-			Some(Default::default())
+			HAS_AUTHOR.with(|h| *h.borrow()).then(author)
 		}
 	}
 	impl pallet_authorship::Config for Test {
@@ -188,6 +401,14 @@ mod tests {
 		t.into()
 	}
 
+	parameter_types! {
+		pub const TreasuryShare: Perbill = Perbill::from_percent(80);
+		pub const ThirdToTreasury: Perbill = Perbill::from_percent(33);
+		pub const TenthBurnt: Perbill = Perbill::from_percent(10);
+	}
+
+	type DealWithFees = SplitFees<Treasury, ToAuthor<Test>, TreasuryShare>;
+
 	#[test]
 	fn test_fees_and_tip_split() {
 		new_test_ext().execute_with(|| {
@@ -195,14 +416,279 @@ mod tests {
 			let tip = Balances::issue(20);
 
 			assert_eq!(Balances::free_balance(Treasury::account_id()), 0);
-			assert_eq!(Balances::free_balance(AccountId::default()), 0);
+			assert_eq!(Balances::free_balance(author()), 0);
 
 			DealWithFees::on_unbalanceds(vec![fee, tip].into_iter());
 
 			// Author gets 100% of tip and 20% of fee = 22
-			assert_eq!(Balances::free_balance(AccountId::default()), 22);
+			assert_eq!(Balances::free_balance(author()), 22);
 			// Treasury gets 80% of fee
 			assert_eq!(Balances::free_balance(Treasury::account_id()), 8);
 		});
 	}
+
+	#[test]
+	fn treasury_share_is_rounded_down() {
+		new_test_ext().execute_with(|| {
+			DealWithFees::on_unbalanceds(vec![Balances::issue(11)].into_iter());
+
+			// 80% of 11 is 8.8, the remainder goes to the author.
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 8);
+			assert_eq!(Balances::free_balance(author()), 3);
+
+			let fee = Balances::issue(7);
+			let tip = Balances::issue(5);
+			SplitFees::<Treasury, ToAuthor<Test>, ThirdToTreasury>::on_unbalanceds(
+				vec![fee, tip].into_iter(),
+			);
+
+			// 33% of 7 is 2.31.
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 8 + 2);
+			assert_eq!(Balances::free_balance(author()), 3 + 5 + 5);
+			assert_eq!(Balances::total_issuance(), 11 + 7 + 5);
+		});
+	}
+
+	#[test]
+	fn burn_share_reduces_issuance() {
+		type BurnAndSplit = SplitFees<Treasury, ToAuthor<Test>, TreasuryShare, TenthBurnt>;
+		assert_eq!(BurnAndSplit::shares(), (Perbill::from_percent(10), Perbill::from_percent(80)));
+		assert_eq!(DealWithFees::shares(), (Perbill::zero(), Perbill::from_percent(80)));
+
+		new_test_ext().execute_with(|| {
+			let (burn_share, _) = BurnAndSplit::shares();
+			for i in 0u32..100 {
+				let random = sp_io::hashing::blake2_256(&i.to_le_bytes());
+				let amount = u64::from_le_bytes([
+					random[0], random[1], random[2], random[3], random[4], random[5], random[6], random[7],
+				]) % 1_000_000_000;
+				let issuance = Balances::total_issuance();
+				let received = Balances::free_balance(Treasury::account_id()) +
+					Balances::free_balance(author());
+
+				BurnAndSplit::on_unbalanceds(vec![Balances::issue(amount)].into_iter());
+
+				let burnt = burn_share.mul_floor(amount);
+				assert_eq!(Balances::total_issuance(), issuance + amount - burnt);
+				assert_eq!(
+					Balances::free_balance(Treasury::account_id()) +
+						Balances::free_balance(author()),
+					received + amount - burnt,
+				);
+			}
+		});
+	}
+
+	#[test]
+	fn fees_go_to_fallback_without_author() {
+		HAS_AUTHOR.with(|h| *h.borrow_mut() = false);
+		new_test_ext().execute_with(|| {
+			ToAuthor::<Test>::on_unbalanced(Balances::issue(10));
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 10);
+			assert_eq!(Balances::free_balance(author()), 0);
+			assert_eq!(Balances::total_issuance(), 10);
+
+			// Runtimes without a treasury credit the default account, as before.
+			ToAuthor::<Test, ToDefaultAccount<Test>>::on_unbalanced(Balances::issue(5));
+			assert_eq!(Balances::free_balance(AccountId::default()), 5);
+			assert_eq!(Balances::total_issuance(), 15);
+		});
+		HAS_AUTHOR.with(|h| *h.borrow_mut() = true);
+	}
+
+	pub struct ToAlice;
+	impl OnUnbalanced<NegativeImbalance<Test>> for ToAlice {
+		fn on_nonzero_unbalanced(amount: NegativeImbalance<Test>) {
+			Balances::resolve_creating(&alice(), amount);
+		}
+	}
+
+	fn alice() -> AccountId {
+		[1u8; 32].into()
+	}
+
+	/// The author of every block, unless `HAS_AUTHOR` is unset.
+	fn author() -> AccountId {
+		[2u8; 32].into()
+	}
+
+	parameter_types! {
+		pub SlashRatios: [Perbill; 3] = [
+			Perbill::from_percent(50),
+			Perbill::from_percent(25),
+			Perbill::from_percent(25),
+		];
+	}
+
+	#[test]
+	fn split_by_ratios_loses_nothing_to_rounding() {
+		let ratios: &[&[Perbill]] = &[
+			&[Perbill::one()],
+			&[Perbill::from_percent(50), Perbill::from_percent(50)],
+			&[Perbill::from_percent(34), Perbill::from_percent(33), Perbill::from_percent(33)],
+			&[Perbill::from_parts(1), Perbill::from_parts(999_999_999)],
+			&[Perbill::zero(), Perbill::one()],
+			&[Perbill::one(), Perbill::zero(), Perbill::zero(), Perbill::zero()],
+		];
+		new_test_ext().execute_with(|| {
+			for ratios in ratios {
+				for amount in (0..=100).chain(vec![999, 1_000_001, u32::max_value() as u64]) {
+					let parts = split_by_ratios(Balances::issue(amount), ratios);
+					assert_eq!(parts.len(), ratios.len());
+
+					let amounts = parts.iter().map(|part| part.peek()).collect::<Vec<_>>();
+					for (ratio, part) in ratios.iter().zip(&amounts).skip(1) {
+						assert_eq!(*part, ratio.mul_floor(amount));
+					}
+					assert_eq!(amounts.iter().sum::<u64>(), amount);
+				}
+			}
+		});
+	}
+
+	#[test]
+	fn split_imbalance_distributes_to_all_targets() {
+		type Slash = SplitImbalance<(Treasury, ToAuthor<Test>, ToAlice), SlashRatios>;
+		new_test_ext().execute_with(|| {
+			Slash::on_unbalanced(Balances::issue(101));
+
+			// 25% of 101 is 25.25, the rounding goes to the treasury.
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 51);
+			assert_eq!(Balances::free_balance(author()), 25);
+			assert_eq!(Balances::free_balance(alice()), 25);
+			assert_eq!(Balances::total_issuance(), 101);
+
+			Slash::on_unbalanced(Balances::issue(3));
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 51 + 3);
+			assert_eq!(Balances::free_balance(author()), 25);
+			assert_eq!(Balances::free_balance(alice()), 25);
+		});
+	}
+
+	#[test]
+	fn split_imbalance_handles_zero() {
+		type Slash = SplitImbalance<(Treasury, ToAuthor<Test>, ToAlice), SlashRatios>;
+		new_test_ext().execute_with(|| {
+			let parts = split_by_ratios(Balances::issue(0), &SlashRatios::get());
+			assert!(parts.iter().all(|part| part.peek() == 0));
+			drop(parts);
+
+			Slash::on_unbalanced(Balances::issue(0));
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 0);
+			assert_eq!(Balances::free_balance(author()), 0);
+			assert_eq!(Balances::free_balance(alice()), 0);
+			assert_eq!(Balances::total_issuance(), 0);
+		});
+	}
+
+	/// A deterministic stream of pseudo-random numbers.
+	fn random(seed: u32) -> impl FnMut() -> u128 {
+		let mut counter = 0u32;
+		move || {
+			counter += 1;
+			let hash = sp_io::hashing::blake2_256(&(seed, counter).encode());
+			let mut bytes = [0u8; 16];
+			bytes.copy_from_slice(&hash[..16]);
+			u128::from_le_bytes(bytes)
+		}
+	}
+
+	#[test]
+	fn saturating_currency_to_vote_is_precise() {
+		use sp_core::U256;
+		let max = U256::from(u64::max_value());
+		let mut random = random(0);
+
+		for _ in 0..1_000 {
+			// between roughly 10^18 and 10^27.
+			let issuance = 1_000_000_000_000_000_000 + random() % 1_000_000_000_000_000_000_000_000_000;
+			let a = 1 + random() % (issuance / 2);
+			let b = 1 + random() % (issuance / 2);
+			let vote_a = SaturatingCurrencyToVote::to_vote(a, issuance);
+			let vote_b = SaturatingCurrencyToVote::to_vote(b, issuance);
+
+			// each vote is its exact share of `u64::MAX`, rounded down.
+			for &(stake, vote) in &[(a, vote_a), (b, vote_b)] {
+				let exact = U256::from(stake) * max;
+				assert!(U256::from(vote) * U256::from(issuance) <= exact);
+				assert!(exact < U256::from(vote + 1) * U256::from(issuance));
+			}
+
+			// so the ratio of votes is that of stakes, up to the rounding.
+			let cross_a = U256::from(vote_a) * U256::from(b);
+			let cross_b = U256::from(vote_b) * U256::from(a);
+			let error = if cross_a > cross_b { cross_a - cross_b } else { cross_b - cross_a };
+			assert!(error < U256::from(a.max(b)), "{} / {} of {}", a, b, issuance);
+
+			// and converting back loses at most a vote's worth of currency.
+			let back = SaturatingCurrencyToVote::to_currency(vote_a.into(), issuance);
+			assert!(back <= a);
+			assert!(a - back <= issuance / u128::from(u64::max_value()) + 1);
+		}
+	}
+
+	#[test]
+	fn saturating_currency_to_vote_saturates() {
+		let to_vote = SaturatingCurrencyToVote::to_vote;
+		let max = u64::max_value();
+		assert_eq!(to_vote(0, 0), 0);
+		assert_eq!(to_vote(10, 0), max);
+		assert_eq!(to_vote(10, 10), max);
+		assert_eq!(to_vote(u128::max_value(), 10), max);
+		assert_eq!(to_vote(5, 10), max / 2);
+		assert_eq!(to_vote(u128::max_value() / 2, u128::max_value()), max / 2);
+
+		assert_eq!(SaturatingCurrencyToVote::to_currency(0, 10), 0);
+		assert_eq!(SaturatingCurrencyToVote::to_currency(max.into(), 10), 10);
+		assert_eq!(
+			SaturatingCurrencyToVote::to_currency(u128::max_value(), u128::max_value()),
+			u128::max_value(),
+		);
+	}
+
+	#[test]
+	fn tipped_extrinsic_fees_and_tip_are_routed() {
+		use pallet_transaction_payment::ChargeTransactionPayment;
+		new_test_ext().execute_with(|| {
+			Balances::make_free_balance_be(&alice(), 1_000);
+			let call = Call::System(frame_system::Call::remark(vec![]));
+			let info = DispatchInfo { weight: 10, ..Default::default() };
+			let len = 10;
+
+			let pre = ChargeTransactionPayment::<Test>::from(30)
+				.pre_dispatch(&alice(), &call, &info, len)
+				.unwrap();
+			assert_ok!(ChargeTransactionPayment::<Test>::post_dispatch(
+				pre,
+				&info,
+				&PostDispatchInfo::default(),
+				len,
+				&Ok(()),
+			));
+
+			// base fee of 100, length fee of 10 and weight fee of 10, plus the tip.
+			assert_eq!(Balances::free_balance(alice()), 1_000 - 120 - 30);
+			// Treasury gets 80% of the fee
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 96);
+			// Author gets the rest of the fee, and exactly the tip
+			assert_eq!(Balances::free_balance(author()), 24 + 30);
+		});
+	}
+
+	#[test]
+	fn tips_can_go_to_treasury() {
+		type TipsToTheTreasury = SplitFees<Treasury, ToAuthor<Test>, TreasuryShare, (), TipsToTreasury>;
+		new_test_ext().execute_with(|| {
+			let fee = Balances::issue(10);
+			let tip = Balances::issue(20);
+
+			TipsToTheTreasury::on_unbalanceds(vec![fee, tip].into_iter());
+
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 8 + 20);
+			assert_eq!(Balances::free_balance(author()), 2);
+		});
+	}
 }
@@ -34,12 +34,16 @@ use frame_support::{
 use frame_system::EnsureRoot;
 use runtime_parachains::{
 	ParaLifecycle, Origin as ParaOrigin,
-	paras, configuration, shared,
+	paras, configuration, shared, dmp,
+	runtime_api_impl::v1::dmq_contents,
 };
+use xcm::{opaque::VersionedXcm, v0::{SendXcm, MultiLocation, Junction, Xcm, Error as XcmError}};
+use parity_scale_codec::Encode;
 use frame_support_test::TestRandomness;
 use crate::{
-	auctions, crowdloan, slots, paras_registrar,
-	slot_range::SlotRange,
+	auctions, crowdloan, slots, paras_registrar, xcm_sender,
+	mock::TestSendXcm,
+	slot_range::{SlotRange, WinnersTable},
 	traits::{
 		Registrar as RegistrarT, Auctioneer, AuctionStatus,
 	},
@@ -66,6 +70,7 @@ frame_support::construct_runtime!(
 		// Parachains Runtime
 		Configuration: configuration::{Pallet, Call, Storage, Config<T>},
 		Paras: paras::{Pallet, Origin, Call, Storage, Event, Config},
+		Dmp: dmp::{Pallet, Call, Storage},
 
 		// Para Onboarding Pallets
 		Registrar: paras_registrar::{Pallet, Call, Storage, Event<T>},
@@ -168,6 +173,21 @@ impl paras::Config for Test {
 	type Event = Event;
 }
 
+impl dmp::Config for Test { }
+
+parameter_types! {
+	pub const BaseDeliveryFee: u128 = 100;
+	pub const ByteDeliveryFee: u128 = 2;
+}
+
+const MAX_DOWNWARD_MESSAGE_SIZE: u32 = 1024;
+
+type DeliveryPrice = xcm_sender::SizeBasedPrice<BaseDeliveryFee, ByteDeliveryFee>;
+type FreeXcmRouter = TestSendXcm<xcm_sender::ChildParachainRouter<Test>>;
+type PricedXcmRouter = TestSendXcm<xcm_sender::ChildParachainRouter<Test, DeliveryPrice>>;
+/// Only sends to paras which are registered with `Paras`.
+type RegisteredXcmRouter = TestSendXcm<xcm_sender::ChildParachainRouter<Test, (), (), (), Paras>>;
+
 parameter_types! {
 	pub const ParaDeposit: Balance = 500;
 	pub const DataDepositPerByte: Balance = 1;
@@ -208,6 +228,7 @@ impl slots::Config for Test {
 	type Currency = Balances;
 	type Registrar = Registrar;
 	type LeasePeriod = LeasePeriod;
+	type BlockNumberProvider = crate::traits::RelayChainBlockNumberProvider<Test>;
 	type WeightInfo = crate::slots::TestWeightInfo;
 }
 
@@ -228,6 +249,7 @@ impl crowdloan::Config for Test {
 	type Registrar = Registrar;
 	type Auctioneer = Auctions;
 	type MaxMemoLength = MaxMemoLength;
+	type BlockNumberProvider = crate::traits::RelayChainBlockNumberProvider<Test>;
 	type WeightInfo = crate::crowdloan::TestWeightInfo;
 }
 
@@ -238,6 +260,7 @@ pub fn new_test_ext() -> TestExternalities {
 		config: configuration::HostConfiguration {
 			max_code_size: 2 * 1024 * 1024, // 2 MB
 			max_head_data_size: 1 * 1024 * 1024, // 1 MB
+			max_downward_message_size: MAX_DOWNWARD_MESSAGE_SIZE,
 			..Default::default()
 		},
 	}.assimilate_storage(&mut t).unwrap();
@@ -294,6 +317,18 @@ fn last_event() -> Event {
 	System::events().pop().expect("Event expected").event
 }
 
+fn para_location(id: u32) -> MultiLocation {
+	MultiLocation::X1(Junction::Parachain(id))
+}
+
+fn xcm_message(size: usize) -> Xcm {
+	Xcm::Transact {
+		origin_type: xcm::v0::OriginKind::Native,
+		require_weight_at_most: 0,
+		call: vec![0u8; size].into(),
+	}
+}
+
 #[test]
 fn basic_end_to_end_works() {
 	new_test_ext().execute_with(|| {
@@ -324,6 +359,8 @@ fn basic_end_to_end_works() {
 		// Paras should be onboarding
 		assert_eq!(Paras::lifecycle(ParaId::from(para_1)), Some(ParaLifecycle::Onboarding));
 		assert_eq!(Paras::lifecycle(ParaId::from(para_2)), Some(ParaLifecycle::Onboarding));
+		// and can't receive messages yet
+		assert!(RegisteredXcmRouter::send_xcm(para_location(para_1), xcm_message(10)).is_err());
 
 		// Start a new auction in the future
 		let duration = 99u32;
@@ -334,6 +371,8 @@ fn basic_end_to_end_works() {
 		run_to_session(2);
 		assert_eq!(Paras::lifecycle(ParaId::from(para_1)), Some(ParaLifecycle::Parathread));
 		assert_eq!(Paras::lifecycle(ParaId::from(para_2)), Some(ParaLifecycle::Parathread));
+		assert_ok!(RegisteredXcmRouter::send_xcm(para_location(para_1), xcm_message(10)));
+		assert_eq!(RegisteredXcmRouter::sent_to(), vec![para_location(para_1)]);
 
 		// Para 1 will bid directly for slot 1, 2
 		// Open a crowdloan for Para 2 for slot 3, 4
@@ -869,9 +908,9 @@ fn crowdloan_ending_period_bid() {
 		run_to_block(100);
 
 		assert_eq!(Auctions::auction_status(100), AuctionStatus::<u32>::EndingPeriod(0, 0));
-		let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-		winning[SlotRange::ZeroOne as u8 as usize] = Some((2, ParaId::from(2001), 900));
-		winning[SlotRange::ZeroThree as u8 as usize] = Some((crowdloan_account, ParaId::from(2000), total));
+		let mut winning = WinnersTable::new();
+		winning[SlotRange::ZeroOne] = Some((2, ParaId::from(2001), 900));
+		winning[SlotRange::ZeroThree] = Some((crowdloan_account, ParaId::from(2000), total));
 
 		assert_eq!(Auctions::winning(0), Some(winning));
 
@@ -882,9 +921,9 @@ fn crowdloan_ending_period_bid() {
 
 		// Data propagates correctly
 		run_to_block(102);
-		let mut winning = [None; SlotRange::SLOT_RANGE_COUNT];
-		winning[SlotRange::ZeroOne as u8 as usize] = Some((2, ParaId::from(2001), 900));
-		winning[SlotRange::ZeroThree as u8 as usize] = Some((crowdloan_account, ParaId::from(2000), total + 900));
+		let mut winning = WinnersTable::new();
+		winning[SlotRange::ZeroOne] = Some((2, ParaId::from(2001), 900));
+		winning[SlotRange::ZeroThree] = Some((crowdloan_account, ParaId::from(2000), total + 900));
 		assert_eq!(Auctions::winning(2), Some(winning));
 	})
 }
@@ -1243,3 +1282,66 @@ fn cant_bid_on_existing_lease_periods() {
 		);
 	});
 }
+
+#[test]
+fn xcm_sends_are_validated_for_size() {
+	new_test_ext().execute_with(|| {
+		let max = MAX_DOWNWARD_MESSAGE_SIZE as usize;
+		let encoded = |msg: &Xcm| VersionedXcm::from(msg.clone()).encode();
+		// The largest message which still fits.
+		let fits = (0..max)
+			.map(xcm_message)
+			.take_while(|msg| encoded(msg).len() <= max)
+			.last()
+			.unwrap();
+
+		assert_eq!(
+			FreeXcmRouter::send_xcm(para_location(1), xcm_message(max)),
+			Err(XcmError::ExceedsMaxMessageSize),
+		);
+		assert_ok!(FreeXcmRouter::send_xcm(para_location(1), fits.clone()));
+
+		assert_eq!(FreeXcmRouter::sent(), vec![
+			(para_location(1), fits.clone(), sp_io::hashing::blake2_256(&encoded(&fits))),
+		]);
+		assert_eq!(dmq_contents::<Test>(1.into()).len(), 1);
+	});
+}
+
+#[test]
+fn xcm_sends_are_priced() {
+	new_test_ext().execute_with(|| {
+		let (ticket, required) = xcm_sender::ChildParachainRouter::<Test, DeliveryPrice>::validate(
+			para_location(1),
+			xcm_message(10),
+		).unwrap();
+		assert_eq!(ticket.price, 100 + 2 * ticket.blob.len() as u128);
+		assert_eq!(required, vec![xcm_sender::native_asset(ticket.price)]);
+
+		// Sending through `SendXcm` pays nothing, so only free deliveries go through.
+		assert_eq!(
+			PricedXcmRouter::send_xcm(para_location(1), xcm_message(10)),
+			Err(XcmError::TooExpensive),
+		);
+		assert_ok!(FreeXcmRouter::send_xcm(para_location(2), xcm_message(10)));
+		assert_eq!(FreeXcmRouter::sent_to(), vec![para_location(2)]);
+		assert!(dmq_contents::<Test>(1.into()).is_empty());
+	});
+}
+
+#[test]
+fn xcm_send_failures_can_be_injected() {
+	new_test_ext().execute_with(|| {
+		FreeXcmRouter::fail_nth_send(2, XcmError::Undefined);
+
+		assert_ok!(FreeXcmRouter::send_xcm(para_location(1), xcm_message(10)));
+		assert_eq!(
+			FreeXcmRouter::send_xcm(para_location(2), xcm_message(10)),
+			Err(XcmError::Undefined),
+		);
+		assert_ok!(FreeXcmRouter::send_xcm(para_location(3), xcm_message(10)));
+
+		assert_eq!(FreeXcmRouter::sent_to(), vec![para_location(1), para_location(3)]);
+		assert!(dmq_contents::<Test>(2.into()).is_empty());
+	});
+}
@@ -24,13 +24,19 @@ pub mod auctions;
 pub mod crowdloan;
 pub mod purchase;
 pub mod impls;
+pub mod migrations;
 pub mod mmr;
 pub mod paras_sudo_wrapper;
 pub mod paras_registrar;
+pub mod paused_calls;
 pub mod slot_range;
+pub mod spend_origins;
 pub mod traits;
 pub mod xcm_sender;
 pub mod elections;
+#[cfg(feature = "std")]
+pub mod fee_simulation;
+pub mod weight_to_fee;
 
 #[cfg(test)]
 mod mock;
@@ -38,15 +44,14 @@ mod mock;
 mod integration_tests;
 
 use beefy_primitives::crypto::AuthorityId as BeefyId;
-use primitives::v1::{AccountId, AssignmentId, BlockNumber, ValidatorId};
-use sp_runtime::{Perquintill, Perbill, FixedPointNumber};
+use primitives::v1::{AccountId, AssignmentId, Balance, BlockNumber, ValidatorId};
+use sp_runtime::{Perquintill, Perbill, FixedPointNumber, RuntimeDebug};
 use frame_system::limits;
 use frame_support::{
-	parameter_types, traits::{Currency, OneSessionHandler},
-	weights::{Weight, constants::WEIGHT_PER_SECOND, DispatchClass},
+	parameter_types, traits::{Currency, Get, OneSessionHandler},
+	weights::{Weight, constants::WEIGHT_PER_SECOND, DispatchClass, WeightToFeePolynomial},
 };
-use pallet_transaction_payment::{TargetedFeeAdjustment, Multiplier};
-use static_assertions::const_assert;
+use pallet_transaction_payment::{TargetedFeeAdjustment, Multiplier, OnChargeTransaction};
 pub use frame_support::weights::constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight};
 
 #[cfg(feature = "std")]
@@ -55,13 +60,72 @@ pub use pallet_staking::StakerStatus;
 pub use sp_runtime::BuildStorage;
 pub use pallet_timestamp::Call as TimestampCall;
 pub use pallet_balances::Call as BalancesCall;
-pub use elections::{OffchainSolutionLengthLimit, OffchainSolutionWeightLimit};
+pub use elections::{
+	OffchainSolutionLengthLimit, OffchainSolutionWeightLimit, BetterUnsignedThreshold,
+};
 
 /// Implementations of some helper traits passed into runtime modules as associated types.
 pub use impls::ToAuthor;
+pub use mmr::verify as mmr_verify;
 
 pub type NegativeImbalance<T> = <pallet_balances::Pallet<T> as Currency<<T as frame_system::Config>::AccountId>>::NegativeImbalance;
 
+/// Select `$prod` or, if the calling crate was built with the `fast-runtime` feature, `$fast`.
+///
+/// The feature is checked in the crate invoking the macro, so each runtime opts in on its own.
+/// Both values are always type-checked and the two-argument form can be used for `const`
+/// parameters.
+///
+/// With a third argument naming an environment variable, a `fast-runtime` build uses the value
+/// of that variable at compile time when it is set and parses, falling back to `$fast`
+/// otherwise. Production builds ignore the variable. This form is not `const`.
+#[macro_export]
+macro_rules! prod_or_fast {
+	($prod:expr, $fast:expr $(,)?) => {
+		if cfg!(feature = "fast-runtime") { $fast } else { $prod }
+	};
+	($prod:expr, $fast:expr, $env:expr $(,)?) => {
+		if cfg!(feature = "fast-runtime") {
+			core::option_env!($env).and_then(|value| value.parse().ok()).unwrap_or($fast)
+		} else {
+			$prod
+		}
+	};
+}
+
+/// Construct the `SignedExtra` tuple shared by the relay chain runtimes for `$runtime`, checking
+/// mortality against `era`, with the sender's `nonce` and paying `tip`.
+///
+/// Runtimes with the claims pallet also validate attestations, which is requested by appending
+/// `prevalidate_claims`. The calling crate must depend on `frame_system` and
+/// `pallet_transaction_payment`.
+#[macro_export]
+macro_rules! construct_signed_extras {
+	($runtime:ty, era: $era:expr, nonce: $nonce:expr, tip: $tip:expr $(,)?) => {
+		(
+			frame_system::CheckSpecVersion::<$runtime>::new(),
+			frame_system::CheckTxVersion::<$runtime>::new(),
+			frame_system::CheckGenesis::<$runtime>::new(),
+			frame_system::CheckMortality::<$runtime>::from($era),
+			frame_system::CheckNonce::<$runtime>::from($nonce),
+			frame_system::CheckWeight::<$runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<$runtime>::from($tip),
+		)
+	};
+	($runtime:ty, era: $era:expr, nonce: $nonce:expr, tip: $tip:expr, prevalidate_claims $(,)?) => {
+		(
+			frame_system::CheckSpecVersion::<$runtime>::new(),
+			frame_system::CheckTxVersion::<$runtime>::new(),
+			frame_system::CheckGenesis::<$runtime>::new(),
+			frame_system::CheckMortality::<$runtime>::from($era),
+			frame_system::CheckNonce::<$runtime>::from($nonce),
+			frame_system::CheckWeight::<$runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<$runtime>::from($tip),
+			$crate::claims::PrevalidateAttests::<$runtime>::new(),
+		)
+	};
+}
+
 /// We assume that an on-initialize consumes 1% of the weight on average, hence a single extrinsic
 /// will not be allowed to consume more than `AvailableBlockRatio - 1%`.
 pub const AVERAGE_ON_INITIALIZE_RATIO: Perbill = Perbill::from_percent(1);
@@ -71,7 +135,39 @@ const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 /// We allow for 2 seconds of compute with a 6 second average block time.
 pub const MAXIMUM_BLOCK_WEIGHT: Weight = 2 * WEIGHT_PER_SECOND;
 
-const_assert!(NORMAL_DISPATCH_RATIO.deconstruct() >= AVERAGE_ON_INITIALIZE_RATIO.deconstruct());
+/// Block weights allowing for `max_weight` of compute, of which `Normal` extrinsics may use
+/// `normal_ratio` and the rest is reserved for `Operational` ones. `on_initialize_ratio` is the
+/// expected average share of `on_initialize`.
+///
+/// Panics if `on_initialize_ratio` exceeds `normal_ratio`, or if the weights are otherwise invalid.
+pub fn block_weights_for(
+	max_weight: Weight,
+	normal_ratio: Perbill,
+	on_initialize_ratio: Perbill,
+) -> limits::BlockWeights {
+	assert!(
+		normal_ratio >= on_initialize_ratio,
+		"the normal dispatch ratio {:?} cannot be below the on-initialize ratio {:?}",
+		normal_ratio,
+		on_initialize_ratio,
+	);
+	limits::BlockWeights::builder()
+		.base_block(BlockExecutionWeight::get())
+		.for_class(DispatchClass::all(), |weights| {
+			weights.base_extrinsic = ExtrinsicBaseWeight::get();
+		})
+		.for_class(DispatchClass::Normal, |weights| {
+			weights.max_total = Some(normal_ratio * max_weight);
+		})
+		.for_class(DispatchClass::Operational, |weights| {
+			weights.max_total = Some(max_weight);
+			// Operational transactions have an extra reserved space, so that they
+			// are included even if block reached `max_weight`.
+			weights.reserved = Some(max_weight - normal_ratio * max_weight);
+		})
+		.avg_block_initialization(on_initialize_ratio)
+		.build_or_panic()
+}
 
 // Common constants used in all runtimes.
 parameter_types! {
@@ -82,117 +178,344 @@ parameter_types! {
 	/// The adjustment variable of the runtime. Higher values will cause `TargetBlockFullness` to
 	/// change the fees more rapidly.
 	pub AdjustmentVariable: Multiplier = Multiplier::saturating_from_rational(3, 100_000);
+	/// The adjustment variable of `FastAdjustingFeeUpdate`, letting fees react to congestion within
+	/// minutes rather than hours. Meant for test networks.
+	pub FastAdjustmentVariable: Multiplier = Multiplier::saturating_from_rational(3, 1_000);
 	/// Minimum amount of the multiplier. This value cannot be too low. A test case should ensure
-	/// that combined with `AdjustmentVariable`, we can recover from the minimum.
+	/// that combined with `AdjustmentVariable` and `FastAdjustmentVariable`, we can recover from the
+	/// minimum.
 	/// See `multiplier_can_grow_from_zero`.
 	pub MinimumMultiplier: Multiplier = Multiplier::saturating_from_rational(1, 1_000_000u128);
 	/// Maximum length of block. Up to 5MB.
 	pub BlockLength: limits::BlockLength =
 		limits::BlockLength::max_with_normal_ratio(5 * 1024 * 1024, NORMAL_DISPATCH_RATIO);
 	/// Block weights base values and limits.
-	pub BlockWeights: limits::BlockWeights = limits::BlockWeights::builder()
-		.base_block(BlockExecutionWeight::get())
-		.for_class(DispatchClass::all(), |weights| {
-			weights.base_extrinsic = ExtrinsicBaseWeight::get();
-		})
-		.for_class(DispatchClass::Normal, |weights| {
-			weights.max_total = Some(NORMAL_DISPATCH_RATIO * MAXIMUM_BLOCK_WEIGHT);
-		})
-		.for_class(DispatchClass::Operational, |weights| {
-			weights.max_total = Some(MAXIMUM_BLOCK_WEIGHT);
-			// Operational transactions have an extra reserved space, so that they
-			// are included even if block reached `MAXIMUM_BLOCK_WEIGHT`.
-			weights.reserved = Some(
-				MAXIMUM_BLOCK_WEIGHT - NORMAL_DISPATCH_RATIO * MAXIMUM_BLOCK_WEIGHT,
-			);
-		})
-		.avg_block_initialization(AVERAGE_ON_INITIALIZE_RATIO)
-		.build_or_panic();
+	pub BlockWeights: limits::BlockWeights =
+		block_weights_for(MAXIMUM_BLOCK_WEIGHT, NORMAL_DISPATCH_RATIO, AVERAGE_ON_INITIALIZE_RATIO);
 }
 
+/// A fee multiplier update targeting blocks filled to `Target` of their normal dispatch limit,
+/// adjusting with `Variable` and never going below `Minimum`.
+///
+/// Runtimes supplying their own parameters should check them with `assert_multiplier_recovers`.
+pub type FeeAdjustment<R, Target, Variable, Minimum> = TargetedFeeAdjustment<
+	R,
+	Target,
+	Variable,
+	Minimum
+>;
+
 /// Parameterized slow adjusting fee updated based on
 /// https://w3f-research.readthedocs.io/en/latest/polkadot/Token%20Economics.html#-2.-slow-adjusting-mechanism
-pub type SlowAdjustingFeeUpdate<R> = TargetedFeeAdjustment<
+pub type SlowAdjustingFeeUpdate<R> = FeeAdjustment<
 	R,
 	TargetBlockFullness,
 	AdjustmentVariable,
 	MinimumMultiplier
 >;
 
+/// Like `SlowAdjustingFeeUpdate`, but adjusting fees far more quickly. See
+/// `FastAdjustmentVariable`.
+pub type FastAdjustingFeeUpdate<R> = FeeAdjustment<
+	R,
+	TargetBlockFullness,
+	FastAdjustmentVariable,
+	MinimumMultiplier
+>;
+
+/// The parts of the inclusion fee of a normal dispatch, as `pallet_transaction_payment` would
+/// charge it.
+#[derive(Clone, Copy, Default, Eq, PartialEq, RuntimeDebug)]
+pub struct FeeBreakdown {
+	/// The fee of the base extrinsic weight.
+	pub base_fee: Balance,
+	/// The fee of the encoded length of the call.
+	pub len_fee: Balance,
+	/// The fee of the weight of the call, scaled by the fee multiplier.
+	pub adjusted_weight_fee: Balance,
+}
+
+impl FeeBreakdown {
+	/// The full inclusion fee.
+	pub fn total(&self) -> Balance {
+		self.base_fee.saturating_add(self.len_fee).saturating_add(self.adjusted_weight_fee)
+	}
+}
+
+/// Estimate the fee of a normal dispatch of `weight` and encoded length `call_len` in runtime `R`,
+/// with `current_multiplier` being the fee multiplier of the block it is included in.
+pub fn estimate_fee_parts<R>(
+	call_len: u32,
+	weight: Weight,
+	current_multiplier: Multiplier,
+) -> FeeBreakdown
+where
+	R: pallet_transaction_payment::Config,
+	<R as pallet_transaction_payment::Config>::OnChargeTransaction:
+		OnChargeTransaction<R, Balance = Balance>,
+{
+	let block_weights = R::BlockWeights::get();
+	let weight_to_fee = |weight: Weight| R::WeightToFee::calc(&weight.min(block_weights.max_block));
+	FeeBreakdown {
+		base_fee: weight_to_fee(block_weights.get(DispatchClass::Normal).base_extrinsic),
+		len_fee: R::TransactionByteFee::get().saturating_mul(call_len.into()),
+		adjusted_weight_fee: current_multiplier.saturating_mul_int(weight_to_fee(weight)),
+	}
+}
+
+/// Predict the fee multiplier after `n` blocks each filled to `fullness` of the normal dispatch
+/// limit of `R`, starting from `current` and updating with `Update`.
+///
+/// The blocks are simulated in their own externalities, so this is only available with `std`.
+#[cfg(feature = "std")]
+pub fn multiplier_after_blocks<R, Update>(current: Multiplier, fullness: Perbill, n: u32) -> Multiplier
+where
+	R: frame_system::Config,
+	Update: sp_runtime::traits::Convert<Multiplier, Multiplier>,
+{
+	simulate_blocks::<R, Update>(current, fullness * normal_dispatch_limit::<R>(), n, |_| true)
+}
+
+/// The weight limit of normal dispatches in a block of `R`.
+#[cfg(feature = "std")]
+fn normal_dispatch_limit<R: frame_system::Config>() -> Weight {
+	let block_weights = R::BlockWeights::get();
+	block_weights.get(DispatchClass::Normal).max_total.unwrap_or(block_weights.max_block)
+}
+
+/// Simulate up to `n` blocks of `R` of `block_weight` each, updating the fee multiplier from
+/// `current` with `Update`, and return the last multiplier.
+///
+/// `on_block` is given the multiplier after each block, and stops the simulation by returning
+/// `false`.
+#[cfg(feature = "std")]
+pub(crate) fn simulate_blocks<R, Update>(
+	current: Multiplier,
+	block_weight: Weight,
+	n: u32,
+	mut on_block: impl FnMut(Multiplier) -> bool,
+) -> Multiplier
+where
+	R: frame_system::Config,
+	Update: sp_runtime::traits::Convert<Multiplier, Multiplier>,
+{
+	sp_io::TestExternalities::new_empty().execute_with(|| {
+		let mut multiplier = current;
+		for _ in 0..n {
+			<frame_system::Pallet<R>>::set_block_consumed_resources(block_weight, 0);
+			multiplier = Update::convert(multiplier);
+			if !on_block(multiplier) {
+				break;
+			}
+		}
+		multiplier
+	})
+}
+
+/// Assert that the multiplier of `FeeAdjustment<R, Target, Variable, Minimum>` grows from
+/// `Minimum` once blocks of `R` are filled just above `Target`.
+///
+/// If the minimum is too small for `Variable` to move it, fees could never recover from it. Meant
+/// to be called from the tests of each runtime, with the parameters it uses.
+#[cfg(feature = "std")]
+pub fn assert_multiplier_recovers<R, Target, Variable, Minimum>()
+where
+	R: frame_system::Config,
+	Target: Get<Perquintill>,
+	Variable: Get<Multiplier>,
+	Minimum: Get<Multiplier>,
+{
+	use sp_runtime::traits::Convert;
+
+	let minimum = Minimum::get();
+	// the weight is 1/100th bigger than target.
+	let block_weight = Target::get() * normal_dispatch_limit::<R>() * 101 / 100;
+	let next = sp_io::TestExternalities::new_empty().execute_with(|| {
+		<frame_system::Pallet<R>>::set_block_consumed_resources(block_weight, 0);
+		FeeAdjustment::<R, Target, Variable, Minimum>::convert(minimum)
+	});
+	assert!(
+		next > minimum,
+		"the multiplier cannot recover from its minimum {:?}, it only becomes {:?}",
+		minimum,
+		next,
+	);
+}
+
 /// The type used for currency conversion.
 ///
-/// This must only be used as long as the balance type is u128.
+/// This must only be used as long as the balance type is u128. `impls::SaturatingCurrencyToVote`
+/// is a more precise alternative.
 pub type CurrencyToVote = frame_support::traits::U128CurrencyToVote;
 static_assertions::assert_eq_size!(primitives::v1::Balance, u128);
 
-/// A placeholder since there is currently no provided session key handler for parachain validator
-/// keys.
-pub struct ParachainSessionKeyPlaceholder<T>(sp_std::marker::PhantomData<T>);
-impl<T> sp_runtime::BoundToRuntimeAppPublic for ParachainSessionKeyPlaceholder<T> {
+/// Receives the parachain validator keys of the validators of each session.
+pub trait ParachainSessionKeySink<AccountId> {
+	/// The validators and their keys of the genesis session.
+	fn on_genesis_session(validators: &[(AccountId, ValidatorId)]);
+
+	/// The validators and their keys of a new session, and the queued ones of the next session.
+	///
+	/// `changed` is true if the validator set or any of their keys may have changed since the
+	/// previous session.
+	fn on_new_session(
+		changed: bool,
+		validators: &[(AccountId, ValidatorId)],
+		queued: &[(AccountId, ValidatorId)],
+	);
+}
+
+/// Drop the keys, for runtimes without parachains.
+impl<AccountId> ParachainSessionKeySink<AccountId> for () {
+	fn on_genesis_session(_: &[(AccountId, ValidatorId)]) {}
+
+	fn on_new_session(
+		_: bool,
+		_: &[(AccountId, ValidatorId)],
+		_: &[(AccountId, ValidatorId)],
+	) {}
+}
+
+fn with_key<AccountId, Key: Clone>(entry: &(AccountId, Key)) -> (&AccountId, Key) {
+	(&entry.0, entry.1.clone())
+}
+
+/// Hand the keys over to the parachains runtime modules.
+impl<T> ParachainSessionKeySink<T::AccountId> for runtime_parachains::initializer::Pallet<T> where
+	T: pallet_session::Config + runtime_parachains::initializer::Config,
+{
+	fn on_genesis_session(validators: &[(T::AccountId, ValidatorId)]) {
+		<Self as OneSessionHandler<T::AccountId>>::on_genesis_session(validators.iter().map(with_key))
+	}
+
+	fn on_new_session(
+		changed: bool,
+		validators: &[(T::AccountId, ValidatorId)],
+		queued: &[(T::AccountId, ValidatorId)],
+	) {
+		<Self as OneSessionHandler<T::AccountId>>::on_new_session(
+			changed,
+			validators.iter().map(with_key),
+			queued.iter().map(with_key),
+		)
+	}
+}
+
+/// The session key handler for parachain validator keys, which forwards the keys of each session
+/// to `Sink`.
+pub struct ParachainSessionKeyHandler<T, Sink = ()>(sp_std::marker::PhantomData<(T, Sink)>);
+impl<T, Sink> sp_runtime::BoundToRuntimeAppPublic for ParachainSessionKeyHandler<T, Sink> {
 	type Public = ValidatorId;
 }
 
-impl<T: pallet_session::Config> OneSessionHandler<T::AccountId> for ParachainSessionKeyPlaceholder<T>
+impl<T, Sink> OneSessionHandler<T::AccountId> for ParachainSessionKeyHandler<T, Sink> where
+	T: pallet_session::Config,
+	Sink: ParachainSessionKeySink<T::AccountId>,
 {
 	type Key = ValidatorId;
 
-	fn on_genesis_session<'a, I: 'a>(_validators: I) where
+	fn on_genesis_session<'a, I: 'a>(validators: I) where
 		I: Iterator<Item = (&'a T::AccountId, ValidatorId)>,
 		T::AccountId: 'a
 	{
-
+		let validators = validators.map(|(who, key)| (who.clone(), key)).collect::<Vec<_>>();
+		Sink::on_genesis_session(&validators);
 	}
 
-	fn on_new_session<'a, I: 'a>(_changed: bool, _v: I, _q: I) where
+	fn on_new_session<'a, I: 'a>(changed: bool, validators: I, queued: I) where
 		I: Iterator<Item = (&'a T::AccountId, ValidatorId)>,
 		T::AccountId: 'a
 	{
-
+		let validators = validators.map(|(who, key)| (who.clone(), key)).collect::<Vec<_>>();
+		let queued = queued.map(|(who, key)| (who.clone(), key)).collect::<Vec<_>>();
+		Sink::on_new_session(changed, &validators, &queued);
 	}
 
 	fn on_disabled(_: usize) { }
 }
 
-/// A placeholder since there is currently no provided session key handler for parachain validator
-/// keys.
-pub struct AssignmentSessionKeyPlaceholder<T>(sp_std::marker::PhantomData<T>);
-impl<T> sp_runtime::BoundToRuntimeAppPublic for AssignmentSessionKeyPlaceholder<T> {
+/// A placeholder which drops parachain validator keys.
+#[deprecated(note = "use `ParachainSessionKeyHandler` with the sink of the keys instead")]
+pub type ParachainSessionKeyPlaceholder<T> = ParachainSessionKeyHandler<T>;
+
+/// Notified when a validator is disabled, so that its approval assignments can be excluded.
+pub trait OnAssignmentKeyDisabled<ValidatorId> {
+	/// The validator at `index` of the current session was disabled. `validator` is its id and
+	/// assignment key, or `None` if the session has no validator at `index`.
+	fn on_disabled(index: u32, validator: Option<(ValidatorId, AssignmentId)>);
+}
+
+/// Ignore disabled validators.
+impl<ValidatorId> OnAssignmentKeyDisabled<ValidatorId> for () {
+	fn on_disabled(_: u32, _: Option<(ValidatorId, AssignmentId)>) {}
+}
+
+frame_support::generate_storage_alias!(
+	AssignmentSessionKeyHandler, CurrentAssignmentKeys => Value<Vec<AssignmentId>>
+);
+
+/// The session key handler for approval assignment keys, which tells `OnDisabled` about the
+/// disabled validators.
+///
+/// The assignment keys of the current session are recorded in the order of the session's
+/// validators, as `pallet_session` forgets them once they are rotated out.
+pub struct AssignmentSessionKeyHandler<T, OnDisabled = ()>(
+	sp_std::marker::PhantomData<(T, OnDisabled)>,
+);
+impl<T, OnDisabled> sp_runtime::BoundToRuntimeAppPublic for AssignmentSessionKeyHandler<T, OnDisabled> {
 	type Public = AssignmentId;
 }
 
-impl<T: pallet_session::Config> OneSessionHandler<T::AccountId> for AssignmentSessionKeyPlaceholder<T>
+impl<T, OnDisabled> OneSessionHandler<T::AccountId> for AssignmentSessionKeyHandler<T, OnDisabled> where
+	T: pallet_session::Config,
+	OnDisabled: OnAssignmentKeyDisabled<T::ValidatorId>,
 {
 	type Key = AssignmentId;
 
-	fn on_genesis_session<'a, I: 'a>(_validators: I) where
+	fn on_genesis_session<'a, I: 'a>(validators: I) where
 		I: Iterator<Item = (&'a T::AccountId, AssignmentId)>,
 		T::AccountId: 'a
 	{
-
+		CurrentAssignmentKeys::put(validators.map(|(_, key)| key).collect::<Vec<_>>());
 	}
 
-	fn on_new_session<'a, I: 'a>(_changed: bool, _v: I, _q: I) where
+	fn on_new_session<'a, I: 'a>(_changed: bool, validators: I, _queued: I) where
 		I: Iterator<Item = (&'a T::AccountId, AssignmentId)>,
 		T::AccountId: 'a
 	{
-
+		CurrentAssignmentKeys::put(validators.map(|(_, key)| key).collect::<Vec<_>>());
 	}
 
-	fn on_disabled(_: usize) { }
+	fn on_disabled(index: usize) {
+		let validator = pallet_session::Pallet::<T>::validators().get(index).cloned();
+		let key = CurrentAssignmentKeys::get().unwrap_or_default().get(index).cloned();
+		OnDisabled::on_disabled(index as u32, validator.zip(key));
+	}
 }
 
+/// A placeholder which ignores approval assignment keys.
+#[deprecated(note = "use `AssignmentSessionKeyHandler` with a handler of disabled validators instead")]
+pub type AssignmentSessionKeyPlaceholder<T> = AssignmentSessionKeyHandler<T>;
+
 /// Generates a `BeefyId` from the given `AccountId`. The resulting `BeefyId` is
 /// a dummy value and this is a utility function meant to be used when migration
 /// session keys.
+///
+/// The id is the public key of a secret key derived from the account, so it is a valid compressed
+/// secp256k1 point and unique per account. The secret key is the `blake2_256` hash of `b"beef"`
+/// followed by the account, rehashed until it is a valid scalar. It is trivially known, so the id
+/// must never be used to sign anything.
 pub fn dummy_beefy_id_from_account_id(a: AccountId) -> BeefyId {
-	let mut id = BeefyId::default();
-	let id_raw: &mut [u8] = id.as_mut();
-
-	// NOTE: AccountId is 32 bytes, whereas BeefyId is 33 bytes.
-	id_raw[1..].copy_from_slice(a.as_ref());
-	id_raw[0..4].copy_from_slice(b"beef");
-
-	id
+	let mut seed = sp_io::hashing::blake2_256(&[&b"beef"[..], a.as_ref()].concat());
+	let secret = loop {
+		match secp256k1::SecretKey::parse(&seed) {
+			Ok(secret) => break secret,
+			// the hash is zero or not below the order of the curve, with negligible probability.
+			Err(_) => seed = sp_io::hashing::blake2_256(&seed),
+		}
+	};
+	let public = secp256k1::PublicKey::from_secret_key(&secret).serialize_compressed();
+	sp_core::ecdsa::Public::from_raw(public).into()
 }
 
 #[cfg(test)]
@@ -202,7 +525,7 @@ mod multiplier_tests {
 	use sp_core::H256;
 	use sp_runtime::{
 		testing::Header,
-		traits::{BlakeTwo256, IdentityLookup, Convert, One},
+		traits::{BlakeTwo256, IdentityLookup, Convert},
 		Perbill,
 	};
 
@@ -264,38 +587,43 @@ mod multiplier_tests {
 	}
 
 	#[test]
-	fn multiplier_can_grow_from_zero() {
+	fn multiplier_after_blocks_works() {
 		let minimum_multiplier = MinimumMultiplier::get();
-		let target = TargetBlockFullness::get() *
-			BlockWeights::get().get(DispatchClass::Normal).max_total.unwrap();
-		// if the min is too small, then this will not change, and we are doomed forever.
-		// the weight is 1/100th bigger than target.
-		run_with_system_weight(target * 101 / 100, || {
-			let next = SlowAdjustingFeeUpdate::<Runtime>::convert(minimum_multiplier);
-			assert!(next > minimum_multiplier, "{:?} !>= {:?}", next, minimum_multiplier);
-		})
+		let full = Perbill::from_percent(50);
+		assert_eq!(
+			multiplier_after_blocks::<Runtime, SlowAdjustingFeeUpdate<Runtime>>(minimum_multiplier, full, 0),
+			minimum_multiplier,
+		);
+
+		let mut multiplier = minimum_multiplier;
+		for _ in 0..10 {
+			run_with_system_weight(full * 1024, || {
+				multiplier = SlowAdjustingFeeUpdate::<Runtime>::convert(multiplier);
+			});
+		}
+		assert_eq!(
+			multiplier_after_blocks::<Runtime, SlowAdjustingFeeUpdate<Runtime>>(minimum_multiplier, full, 10),
+			multiplier,
+		);
+		assert!(multiplier > minimum_multiplier);
 	}
 
 	#[test]
-	#[ignore]
-	fn multiplier_growth_simulator() {
-		// assume the multiplier is initially set to its minimum. We update it with values twice the
-		//target (target is 25%, thus 50%) and we see at which point it reaches 1.
-		let mut multiplier = MinimumMultiplier::get();
-		let block_weight = TargetBlockFullness::get()
-			* BlockWeights::get().get(DispatchClass::Normal).max_total.unwrap()
-			* 2;
-		let mut blocks = 0;
-		while multiplier <= Multiplier::one() {
-			run_with_system_weight(block_weight, || {
-				let next = SlowAdjustingFeeUpdate::<Runtime>::convert(multiplier);
-				// ensure that it is growing as well.
-				assert!(next > multiplier, "{:?} !>= {:?}", next, multiplier);
-				multiplier = next;
-			});
-			blocks += 1;
-			println!("block = {} multiplier {:?}", blocks, multiplier);
-		}
+	fn multiplier_can_grow_from_zero() {
+		type Target = TargetBlockFullness;
+		assert_multiplier_recovers::<Runtime, Target, AdjustmentVariable, MinimumMultiplier>();
+		assert_multiplier_recovers::<Runtime, Target, FastAdjustmentVariable, MinimumMultiplier>();
+	}
+
+	parameter_types! {
+		pub const TooSmallMinimum: Multiplier = Multiplier::from_inner(1);
+	}
+
+	#[test]
+	#[should_panic(expected = "cannot recover from its minimum")]
+	fn multiplier_cannot_recover_from_too_small_minimum() {
+		type Target = TargetBlockFullness;
+		assert_multiplier_recovers::<Runtime, Target, AdjustmentVariable, TooSmallMinimum>();
 	}
 
 	#[test]
@@ -308,4 +636,342 @@ mod multiplier_tests {
 
 		assert_ne!(beefy_id1, beefy_id2);
 	}
+
+	#[test]
+	fn dummy_beefy_ids_are_valid_keys() {
+		use sp_core::crypto::Public;
+		let ids = (0..=255u8)
+			.map(|i| dummy_beefy_id_from_account_id(AccountId::new([i; 32])))
+			.collect::<Vec<_>>();
+		for id in &ids {
+			let format = Some(secp256k1::PublicKeyFormat::Compressed);
+			assert!(secp256k1::PublicKey::parse_slice(id.as_slice(), format).is_ok());
+			assert_eq!(mmr::UncompressBeefyEcdsaKeys::convert(id.clone()).len(), 65);
+		}
+		let unique = ids.iter().map(|id| id.to_raw_vec()).collect::<std::collections::BTreeSet<_>>();
+		assert_eq!(unique.len(), ids.len());
+		// derivation is deterministic.
+		assert_eq!(dummy_beefy_id_from_account_id(AccountId::new([7; 32])), ids[7]);
+	}
+
+	#[test]
+	fn block_weights_profiles_are_correct() {
+		let weights = super::BlockWeights::get();
+		assert_eq!(weights.max_block, MAXIMUM_BLOCK_WEIGHT);
+		assert_eq!(weights.get(DispatchClass::Normal).max_total, Some(MAXIMUM_BLOCK_WEIGHT / 4 * 3));
+		assert_eq!(weights.get(DispatchClass::Operational).reserved, Some(MAXIMUM_BLOCK_WEIGHT / 4));
+
+		let weights = block_weights_for(
+			WEIGHT_PER_SECOND / 2,
+			Perbill::from_percent(50),
+			AVERAGE_ON_INITIALIZE_RATIO,
+		);
+		assert_eq!(weights.max_block, WEIGHT_PER_SECOND / 2);
+		assert_eq!(weights.get(DispatchClass::Normal).max_total, Some(WEIGHT_PER_SECOND / 4));
+		assert_eq!(weights.get(DispatchClass::Operational).reserved, Some(WEIGHT_PER_SECOND / 4));
+	}
+
+	#[test]
+	#[should_panic(expected = "cannot be below the on-initialize ratio")]
+	fn block_weights_reject_on_initialize_above_normal() {
+		block_weights_for(MAXIMUM_BLOCK_WEIGHT, Perbill::from_percent(1), Perbill::from_percent(2));
+	}
+
+	#[test]
+	#[should_panic(expected = "The panic is expected if runtime weights are not correct")]
+	fn block_weights_reject_block_below_base_weight() {
+		block_weights_for(
+			BlockExecutionWeight::get() / 2,
+			NORMAL_DISPATCH_RATIO,
+			AVERAGE_ON_INITIALIZE_RATIO,
+		);
+	}
+}
+
+#[cfg(test)]
+mod session_key_tests {
+	use super::*;
+	use frame_support::traits::OnInitialize;
+	use sp_core::H256;
+	use sp_runtime::{
+		impl_opaque_keys,
+		testing::Header,
+		traits::{BlakeTwo256, ConvertInto, IdentityLookup, OpaqueKeys},
+	};
+	use sp_staking::SessionIndex;
+	use std::cell::RefCell;
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
+
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Session: pallet_session::{Pallet, Call, Storage, Event, Config<T>},
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+	}
+
+	impl frame_system::Config for Test {
+		type BaseCallFilter = frame_support::traits::AllowAll;
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Call = Call;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	#[derive(Debug, PartialEq)]
+	enum Received {
+		Genesis(Vec<(u64, ValidatorId)>),
+		NewSession {
+			changed: bool,
+			validators: Vec<(u64, ValidatorId)>,
+			queued: Vec<(u64, ValidatorId)>,
+		},
+	}
+
+	thread_local! {
+		static RECEIVED: RefCell<Vec<Received>> = RefCell::new(Vec::new());
+		static NEXT_VALIDATORS: RefCell<Vec<u64>> = RefCell::new(vec![1, 2]);
+		static DISABLED: RefCell<Vec<(u32, Option<(u64, AssignmentId)>)>> = RefCell::new(Vec::new());
+	}
+
+	/// Records the keys it receives.
+	pub struct RecordingSink;
+	impl ParachainSessionKeySink<u64> for RecordingSink {
+		fn on_genesis_session(validators: &[(u64, ValidatorId)]) {
+			RECEIVED.with(|r| r.borrow_mut().push(Received::Genesis(validators.to_vec())));
+		}
+
+		fn on_new_session(
+			changed: bool,
+			validators: &[(u64, ValidatorId)],
+			queued: &[(u64, ValidatorId)],
+		) {
+			let received = Received::NewSession {
+				changed,
+				validators: validators.to_vec(),
+				queued: queued.to_vec(),
+			};
+			RECEIVED.with(|r| r.borrow_mut().push(received));
+		}
+	}
+
+	/// Records the disabled validators.
+	pub struct RecordingOnDisabled;
+	impl OnAssignmentKeyDisabled<u64> for RecordingOnDisabled {
+		fn on_disabled(index: u32, validator: Option<(u64, AssignmentId)>) {
+			DISABLED.with(|d| d.borrow_mut().push((index, validator)));
+		}
+	}
+
+	type AssignmentKeys = AssignmentSessionKeyHandler<Test, RecordingOnDisabled>;
+
+	impl_opaque_keys! {
+		pub struct SessionKeys {
+			pub para_validator: ParachainSessionKeyHandler<Test, RecordingSink>,
+			pub para_assignment: AssignmentKeys,
+		}
+	}
+
+	pub struct TestSessionManager;
+	impl pallet_session::SessionManager<u64> for TestSessionManager {
+		fn new_session(_: SessionIndex) -> Option<Vec<u64>> {
+			Some(NEXT_VALIDATORS.with(|v| v.borrow().clone()))
+		}
+		fn end_session(_: SessionIndex) {}
+		fn start_session(_: SessionIndex) {}
+	}
+
+	parameter_types! {
+		pub const Period: u64 = 10;
+		pub const Offset: u64 = 0;
+		pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(33);
+	}
+
+	impl pallet_session::Config for Test {
+		type Event = Event;
+		type ValidatorId = u64;
+		type ValidatorIdOf = ConvertInto;
+		type ShouldEndSession = pallet_session::PeriodicSessions<Period, Offset>;
+		type NextSessionRotation = pallet_session::PeriodicSessions<Period, Offset>;
+		type SessionManager = TestSessionManager;
+		type SessionHandler = <SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
+		type Keys = SessionKeys;
+		type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+		type WeightInfo = ();
+	}
+
+	fn key(n: u8) -> ValidatorId {
+		sp_core::sr25519::Public::from_raw([n; 32]).into()
+	}
+
+	fn assignment_key(n: u8) -> AssignmentId {
+		sp_core::sr25519::Public::from_raw([n; 32]).into()
+	}
+
+	fn session_keys(n: u8) -> SessionKeys {
+		SessionKeys { para_validator: key(n), para_assignment: assignment_key(n) }
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_session::GenesisConfig::<Test> {
+			keys: (1..=3).map(|who| (who, who, session_keys(who as u8))).collect(),
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	fn run_to_block(n: u64) {
+		for number in System::block_number() + 1..=n {
+			System::set_block_number(number);
+			Session::on_initialize(number);
+		}
+	}
+
+	fn take_received() -> Vec<Received> {
+		RECEIVED.with(|r| r.borrow_mut().drain(..).collect())
+	}
+
+	#[test]
+	fn keys_are_forwarded_at_genesis_and_rotation() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(take_received(), vec![Received::Genesis(vec![(1, key(1)), (2, key(2))])]);
+
+			NEXT_VALIDATORS.with(|v| *v.borrow_mut() = vec![2, 3]);
+			run_to_block(10);
+			assert_eq!(take_received(), vec![Received::NewSession {
+				changed: false,
+				validators: vec![(1, key(1)), (2, key(2))],
+				queued: vec![(2, key(2)), (3, key(3))],
+			}]);
+
+			run_to_block(20);
+			assert_eq!(take_received(), vec![Received::NewSession {
+				changed: true,
+				validators: vec![(2, key(2)), (3, key(3))],
+				queued: vec![(2, key(2)), (3, key(3))],
+			}]);
+		});
+	}
+
+	#[test]
+	fn new_keys_are_forwarded_once_queued() {
+		new_test_ext().execute_with(|| {
+			run_to_block(10);
+			take_received();
+
+			assert!(Session::set_keys(Origin::signed(2), session_keys(22), vec![]).is_ok());
+			run_to_block(20);
+			match &take_received()[..] {
+				[Received::NewSession { validators, queued, .. }] => {
+					assert_eq!(validators, &vec![(1, key(1)), (2, key(2))]);
+					assert_eq!(queued, &vec![(1, key(1)), (2, key(22))]);
+				},
+				received => panic!("unexpected keys {:?}", received),
+			}
+		});
+	}
+
+	fn take_disabled() -> Vec<(u32, Option<(u64, AssignmentId)>)> {
+		DISABLED.with(|d| d.borrow_mut().drain(..).collect())
+	}
+
+	#[test]
+	fn disabled_validators_are_forwarded() {
+		new_test_ext().execute_with(|| {
+			let disabled = |who: u64, n: u8| Some((who, assignment_key(n)));
+			Session::disable_index(1);
+			Session::disable_index(0);
+			assert_eq!(take_disabled(), vec![(1, disabled(2, 2)), (0, disabled(1, 1))]);
+
+			// the validators of the current session are disabled, not the queued ones.
+			NEXT_VALIDATORS.with(|v| *v.borrow_mut() = vec![2, 3]);
+			run_to_block(10);
+			Session::disable_index(1);
+			assert_eq!(take_disabled(), vec![(1, disabled(2, 2))]);
+			run_to_block(20);
+			Session::disable_index(0);
+			Session::disable_index(1);
+			assert_eq!(take_disabled(), vec![(0, disabled(2, 2)), (1, disabled(3, 3))]);
+
+			// the recorded key is the one of the current session, even once a new one is queued.
+			assert!(Session::set_keys(Origin::signed(3), session_keys(33), vec![]).is_ok());
+			run_to_block(30);
+			Session::disable_index(1);
+			assert_eq!(take_disabled(), vec![(1, disabled(3, 3))]);
+			run_to_block(40);
+			Session::disable_index(1);
+			assert_eq!(take_disabled(), vec![(1, disabled(3, 33))]);
+
+			// an index without a validator has none.
+			<AssignmentKeys as OneSessionHandler<u64>>::on_disabled(2);
+			assert_eq!(take_disabled(), vec![(2, None)]);
+		});
+	}
+
+	#[test]
+	fn assignment_key_type_is_unchanged() {
+		use sp_runtime::BoundToRuntimeAppPublic;
+		type Public = <AssignmentKeys as BoundToRuntimeAppPublic>::Public;
+		assert_eq!(
+			<Public as sp_runtime::RuntimeAppPublic>::ID,
+			primitives::v1::ASSIGNMENT_KEY_TYPE_ID,
+		);
+		assert_eq!(
+			SessionKeys::key_ids(),
+			&[primitives::v1::PARACHAIN_KEY_TYPE_ID, primitives::v1::ASSIGNMENT_KEY_TYPE_ID],
+		);
+	}
+}
+
+#[cfg(test)]
+mod prod_or_fast_tests {
+	use frame_support::{parameter_types, traits::Get};
+
+	const FAST: bool = cfg!(feature = "fast-runtime");
+
+	parameter_types! {
+		pub const ConstPeriod: u32 = prod_or_fast!(7 * 14_400, 10);
+		pub OverriddenPeriod: u32 = prod_or_fast!(8 * 14_400, 20, "POLKADOT_PROD_OR_FAST_UNSET");
+	}
+
+	#[test]
+	fn selects_value_for_compiled_mode() {
+		const SELECTED: u32 = prod_or_fast!(100, 1);
+		assert_eq!(SELECTED, if FAST { 1 } else { 100 });
+		assert_eq!(ConstPeriod::get(), if FAST { 10 } else { 7 * 14_400 });
+	}
+
+	#[test]
+	fn unset_override_falls_back_to_fast_value() {
+		assert_eq!(OverriddenPeriod::get(), if FAST { 20 } else { 8 * 14_400 });
+		let period: u64 = prod_or_fast!(3, 4, "POLKADOT_PROD_OR_FAST_UNSET");
+		assert_eq!(period, if FAST { 4 } else { 3 });
+	}
 }
@@ -0,0 +1,257 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pallet recording which storage migrations have run, so that each runs at most once.
+//!
+//! Migrations are keyed by the name of the pallet they migrate and their own name. A migration
+//! run through `Pallet::run_once`, or wrapped in `RunOnce` for the executive, is skipped once it
+//! has been recorded.
+
+use frame_support::pallet_prelude::*;
+use frame_support::traits::OnRuntimeUpgrade;
+use frame_system::pallet_prelude::*;
+use parity_scale_codec::Codec;
+use sp_std::{marker::PhantomData, prelude::*};
+pub use pallet::*;
+
+/// The key of an executed migration: the name of the pallet it migrates and its own name.
+pub type MigrationId = (Vec<u8>, Vec<u8>);
+
+sp_api::decl_runtime_apis! {
+	/// The storage migrations which were executed.
+	pub trait MigrationsApi<BlockNumber: Codec> {
+		/// The executed migrations and the block number they were executed at.
+		fn executed_migrations() -> Vec<(MigrationId, BlockNumber)>;
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	#[pallet::metadata(T::BlockNumber = "BlockNumber")]
+	pub enum Event<T: Config> {
+		/// A migration was executed. [pallet, migration, block_number]
+		MigrationExecuted(Vec<u8>, Vec<u8>, T::BlockNumber),
+	}
+
+	/// The executed migrations, with the block number they were executed at.
+	#[pallet::storage]
+	pub(super) type Executed<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat, MigrationId,
+		T::BlockNumber,
+		OptionQuery,
+	>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Whether the migration `name` of `pallet` was executed.
+	pub fn is_executed(pallet: &[u8], name: &[u8]) -> bool {
+		Executed::<T>::contains_key(&(pallet.to_vec(), name.to_vec()))
+	}
+
+	/// Run `migration` unless the migration `name` of `pallet` was executed before, and record it.
+	///
+	/// Returns the weight of `migration` and of the bookkeeping, or of only checking the record if
+	/// it is skipped.
+	pub fn run_once(pallet: &[u8], name: &[u8], migration: impl FnOnce() -> Weight) -> Weight {
+		let key = (pallet.to_vec(), name.to_vec());
+		if Executed::<T>::contains_key(&key) {
+			log::info!(
+				target: "runtime::migrations",
+				"skipping migration {} of {}, which was already executed",
+				sp_std::str::from_utf8(name).unwrap_or("<unknown>"),
+				sp_std::str::from_utf8(pallet).unwrap_or("<unknown>"),
+			);
+			return T::DbWeight::get().reads(1)
+		}
+
+		let weight = migration();
+		let now = frame_system::Pallet::<T>::block_number();
+		Executed::<T>::insert(&key, now);
+		Self::deposit_event(Event::MigrationExecuted(key.0, key.1, now));
+		weight.saturating_add(T::DbWeight::get().reads_writes(1, 2))
+	}
+
+	/// All executed migrations, for `MigrationsApi`.
+	pub fn executed_migrations() -> Vec<(MigrationId, T::BlockNumber)> {
+		Executed::<T>::iter().collect()
+	}
+}
+
+/// Runs the migration `Migration` of the pallet named `PalletName` as `Name` at most once.
+pub struct RunOnce<T, PalletName, Name, Migration>(PhantomData<(T, PalletName, Name, Migration)>);
+impl<T, PalletName, Name, Migration> OnRuntimeUpgrade for RunOnce<T, PalletName, Name, Migration> where
+	T: Config,
+	PalletName: Get<&'static [u8]>,
+	Name: Get<&'static [u8]>,
+	Migration: OnRuntimeUpgrade,
+{
+	fn on_runtime_upgrade() -> Weight {
+		Pallet::<T>::run_once(PalletName::get(), Name::get(), Migration::on_runtime_upgrade)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sp_core::H256;
+	use sp_runtime::{
+		traits::{BlakeTwo256, IdentityLookup},
+		testing::Header,
+	};
+	use frame_support::{parameter_types, storage::unhashed};
+	use crate::migrations;
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
+
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Migrations: migrations::{Pallet, Call, Storage, Event<T>},
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u32 = 250;
+	}
+	impl frame_system::Config for Test {
+		type BaseCallFilter = frame_support::traits::AllowAll;
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Call = Call;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<u64>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	impl Config for Test {
+		type Event = Event;
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+
+	const COUNTER: &[u8] = b":test_migration_runs:";
+
+	fn runs() -> u32 {
+		unhashed::get_or_default(COUNTER)
+	}
+
+	/// Counts how many times it runs.
+	struct CountRuns;
+	impl OnRuntimeUpgrade for CountRuns {
+		fn on_runtime_upgrade() -> Weight {
+			unhashed::put(COUNTER, &(runs() + 1));
+			100
+		}
+	}
+
+	parameter_types! {
+		pub const PalletA: &'static [u8] = b"PalletA";
+		pub const PalletB: &'static [u8] = b"PalletB";
+		pub const CountRunsName: &'static [u8] = b"count_runs";
+	}
+
+	#[test]
+	fn second_runtime_upgrade_is_a_noop() {
+		new_test_ext().execute_with(|| {
+			type Migration = RunOnce<Test, PalletA, CountRunsName, CountRuns>;
+			assert!(!Migrations::is_executed(b"PalletA", b"count_runs"));
+			assert_eq!(Migration::on_runtime_upgrade(), 100);
+			assert_eq!(runs(), 1);
+			assert!(Migrations::is_executed(b"PalletA", b"count_runs"));
+			assert_eq!(
+				System::events().pop().expect("an event was deposited").event,
+				Event::Migrations(migrations::Event::MigrationExecuted(
+					b"PalletA".to_vec(),
+					b"count_runs".to_vec(),
+					1,
+				)),
+			);
+
+			System::set_block_number(2);
+			assert_eq!(Migration::on_runtime_upgrade(), 0);
+			assert_eq!(runs(), 1);
+			assert_eq!(System::events().len(), 1);
+		});
+	}
+
+	#[test]
+	fn migrations_are_tracked_per_pallet() {
+		new_test_ext().execute_with(|| {
+			RunOnce::<Test, PalletA, CountRunsName, CountRuns>::on_runtime_upgrade();
+			// The same name in another pallet is another migration.
+			RunOnce::<Test, PalletB, CountRunsName, CountRuns>::on_runtime_upgrade();
+			assert_eq!(runs(), 2);
+
+			// A closure with the same key is skipped too.
+			Migrations::run_once(b"PalletB", b"count_runs", || panic!("migration ran twice"));
+
+			let mut executed = Migrations::executed_migrations();
+			executed.sort();
+			assert_eq!(executed, vec![
+				((b"PalletA".to_vec(), b"count_runs".to_vec()), 1),
+				((b"PalletB".to_vec(), b"count_runs".to_vec()), 1),
+			]);
+		});
+	}
+}
@@ -17,12 +17,16 @@
 //! A pallet responsible for creating Merkle Mountain Range (MMR) leaf for current block.
 
 use beefy_primitives::ValidatorSetId;
-use sp_core::H256;
+use sp_core::{offchain::StorageKind, H256};
 use sp_runtime::traits::Convert;
 use sp_std::prelude::*;
-use frame_support::RuntimeDebug;
-use pallet_mmr::primitives::LeafDataProvider;
+use frame_support::{traits::Get, RuntimeDebug};
+use pallet_mmr::primitives::{
+	DataOrHash, EncodableOpaqueLeaf, Error as MmrError, FullLeaf, LeafDataProvider, LeafIndex,
+	Proof as MmrProof,
+};
 use parity_scale_codec::{Encode, Decode};
+use primitives::v1::{HeadData, Id as ParaId};
 use runtime_parachains::paras;
 pub use pallet::*;
 
@@ -45,6 +49,8 @@ impl<T> pallet_mmr::primitives::OnNewRoot<beefy_primitives::MmrRootHash> for Dep
 }
 
 /// Convert BEEFY secp256k1 public keys into uncompressed form
+///
+/// Keys which are not valid secp256k1 points are all converted to an empty leaf.
 pub struct UncompressBeefyEcdsaKeys;
 impl Convert<beefy_primitives::crypto::AuthorityId, Vec<u8>> for UncompressBeefyEcdsaKeys {
 	fn convert(a: beefy_primitives::crypto::AuthorityId) -> Vec<u8> {
@@ -60,17 +66,192 @@ impl Convert<beefy_primitives::crypto::AuthorityId, Vec<u8>> for UncompressBeefy
 	}
 }
 
+/// Keep BEEFY secp256k1 public keys in their compressed (33 bytes) form.
+///
+/// Unlike [`UncompressBeefyEcdsaKeys`] this never fails, so every key, even an invalid one, gets a
+/// distinct leaf.
+pub struct CompressedBeefyEcdsaKeys;
+impl Convert<beefy_primitives::crypto::AuthorityId, Vec<u8>> for CompressedBeefyEcdsaKeys {
+	fn convert(a: beefy_primitives::crypto::AuthorityId) -> Vec<u8> {
+		use sp_core::crypto::Public;
+		a.as_slice().to_vec()
+	}
+}
+
 /// A leaf that gets added every block to the MMR constructed by [pallet_mmr].
 #[derive(RuntimeDebug, PartialEq, Eq, Clone, Encode, Decode)]
 pub struct MmrLeaf<BlockNumber, Hash, MerkleRoot> {
 	/// Current block parent number and hash.
 	pub parent_number_and_hash: (BlockNumber, Hash),
-	/// A merkle root of all registered parachain heads.
+	/// A merkle root of all registered parachain heads, see [`para_heads_merkle_root`].
 	pub parachain_heads: MerkleRoot,
 	/// A merkle root of the next BEEFY authority set.
 	pub beefy_next_authority_set: BeefyNextAuthoritySet<MerkleRoot>,
 }
 
+/// The version of an MMR leaf, as a major (upper 3 bits) and minor (lower 5 bits) number.
+///
+/// A new minor version only appends fields to the leaf, so clients of an older minor version of
+/// the same major version can still decode the fields they know. A new major version may change
+/// the leaf in any way.
+///
+/// Major version 0 is the original leaf format, [`MmrLeaf`], which has no version byte at all.
+#[derive(RuntimeDebug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub struct MmrLeafVersion(u8);
+
+impl MmrLeafVersion {
+	/// Create a version from its `major` and `minor` numbers, which are truncated to 3 and 5 bits.
+	pub const fn new(major: u8, minor: u8) -> Self {
+		Self((major & 0b111) << 5 | (minor & 0b1_1111))
+	}
+
+	/// The major number of the version.
+	pub fn major(&self) -> u8 {
+		self.0 >> 5
+	}
+
+	/// The minor number of the version.
+	pub fn minor(&self) -> u8 {
+		self.0 & 0b1_1111
+	}
+}
+
+/// The extended (major version 1) format of the MMR leaf.
+///
+/// It encodes as the version byte, followed by the fields of [`MmrLeaf`], followed by the
+/// length-prefixed `leaf_extra`.
+///
+/// Leaves of major version 1 also commit to the parachain heads and the BEEFY authorities with
+/// the binary merkle trees of [`para_heads_merkle_root`] and [`beefy_authorities_merkle_root`],
+/// which can be proven with [`para_head_proof`]. Those of major version 0 commit to them with
+/// ordered trie roots, as they always did.
+#[derive(RuntimeDebug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct MmrLeafV1<BlockNumber, Hash, MerkleRoot> {
+	/// The version of the leaf, with major number 1.
+	pub version: MmrLeafVersion,
+	/// The commitments of the original leaf format.
+	pub leaf: MmrLeaf<BlockNumber, Hash, MerkleRoot>,
+	/// Encoded commitments added after the original leaf format.
+	///
+	/// Empty until there are any, so that they can be added without a new major version.
+	pub leaf_extra: Vec<u8>,
+}
+
+/// An MMR leaf in any of the formats the runtime has emitted.
+///
+/// The MMR hashes the raw encoding of each leaf, and the original format is encoded exactly as
+/// an [`MmrLeaf`] was before leaves were versioned. So the hashes of leaves added before a version
+/// bump, and hence the MMR nodes above them, never change: their original bytes prove them
+/// against any later root, once a proof against that root is generated. Proofs themselves are
+/// tied to the root (and leaf count) they were generated against.
+///
+/// There is no discriminant in the encoding. A leaf decodes as [`MmrLeafV1`] if its first byte is
+/// of major version 1 and the rest decodes as such without leftover bytes, or as [`MmrLeaf`]
+/// otherwise. An extended leaf is always longer than an original one, so this is unambiguous as
+/// long as the block number and hashes have a fixed size, which they do on all our runtimes.
+#[derive(RuntimeDebug, PartialEq, Eq, Clone)]
+pub enum VersionedLeaf<BlockNumber, Hash, MerkleRoot> {
+	/// The original leaf format, without a version byte.
+	V0(MmrLeaf<BlockNumber, Hash, MerkleRoot>),
+	/// The extended leaf format.
+	V1(MmrLeafV1<BlockNumber, Hash, MerkleRoot>),
+}
+
+impl<BlockNumber, Hash, MerkleRoot> MmrLeaf<BlockNumber, Hash, MerkleRoot> {
+	/// Convert the leaf into the format of `version`.
+	pub fn into_versioned(
+		self,
+		version: MmrLeafVersion,
+	) -> VersionedLeaf<BlockNumber, Hash, MerkleRoot> {
+		if version.major() == 0 {
+			VersionedLeaf::V0(self)
+		} else {
+			VersionedLeaf::V1(MmrLeafV1 { version, leaf: self, leaf_extra: Vec::new() })
+		}
+	}
+}
+
+impl<BlockNumber, Hash, MerkleRoot> VersionedLeaf<BlockNumber, Hash, MerkleRoot> {
+	/// The version of the leaf.
+	pub fn version(&self) -> MmrLeafVersion {
+		match self {
+			Self::V0(_) => MmrLeafVersion::new(0, 0),
+			Self::V1(leaf) => leaf.version,
+		}
+	}
+
+	/// The commitments of the original leaf format, which every version has.
+	pub fn leaf(&self) -> &MmrLeaf<BlockNumber, Hash, MerkleRoot> {
+		match self {
+			Self::V0(leaf) => leaf,
+			Self::V1(leaf) => &leaf.leaf,
+		}
+	}
+
+	/// Mutable access to the commitments of the original leaf format.
+	pub fn leaf_mut(&mut self) -> &mut MmrLeaf<BlockNumber, Hash, MerkleRoot> {
+		match self {
+			Self::V0(leaf) => leaf,
+			Self::V1(leaf) => &mut leaf.leaf,
+		}
+	}
+
+	/// Convert the leaf into the original format, dropping any extended commitments.
+	pub fn into_leaf(self) -> MmrLeaf<BlockNumber, Hash, MerkleRoot> {
+		match self {
+			Self::V0(leaf) => leaf,
+			Self::V1(leaf) => leaf.leaf,
+		}
+	}
+}
+
+impl<BlockNumber, Hash, MerkleRoot> From<MmrLeaf<BlockNumber, Hash, MerkleRoot>>
+	for VersionedLeaf<BlockNumber, Hash, MerkleRoot>
+{
+	fn from(leaf: MmrLeaf<BlockNumber, Hash, MerkleRoot>) -> Self {
+		Self::V0(leaf)
+	}
+}
+
+impl<BlockNumber: Encode, Hash: Encode, MerkleRoot: Encode> Encode
+	for VersionedLeaf<BlockNumber, Hash, MerkleRoot>
+{
+	fn encode_to<T: parity_scale_codec::Output + ?Sized>(&self, dest: &mut T) {
+		match self {
+			Self::V0(leaf) => leaf.encode_to(dest),
+			Self::V1(leaf) => leaf.encode_to(dest),
+		}
+	}
+}
+
+impl<BlockNumber: Decode, Hash: Decode, MerkleRoot: Decode> Decode
+	for VersionedLeaf<BlockNumber, Hash, MerkleRoot>
+{
+	fn decode<I: parity_scale_codec::Input>(
+		input: &mut I,
+	) -> Result<Self, parity_scale_codec::Error> {
+		let len = input.remaining_len()?
+			.ok_or("The length of a versioned MMR leaf must be known")?;
+		let mut bytes = vec![0u8; len];
+		input.read(&mut bytes)?;
+
+		if bytes.first().map_or(false, |first| MmrLeafVersion(*first).major() == 1) {
+			let mut rest = &bytes[..];
+			if let Ok(leaf) = MmrLeafV1::decode(&mut rest) {
+				if rest.is_empty() {
+					return Ok(Self::V1(leaf));
+				}
+			}
+		}
+		let mut rest = &bytes[..];
+		let leaf = MmrLeaf::decode(&mut rest)?;
+		if !rest.is_empty() {
+			return Err("Unexpected bytes after an MMR leaf".into());
+		}
+		Ok(Self::V0(leaf))
+	}
+}
+
 /// Details of the next BEEFY authority set.
 #[derive(RuntimeDebug, Default, PartialEq, Eq, Clone, Encode, Decode)]
 pub struct BeefyNextAuthoritySet<MerkleRoot> {
@@ -86,7 +267,7 @@ pub struct BeefyNextAuthoritySet<MerkleRoot> {
 	/// of signatures. We put set length here, so that these clients can verify the minimal
 	/// number of required signatures.
 	pub len: u32,
-	/// Merkle Root Hash build from BEEFY AuthorityIds.
+	/// Merkle Root Hash build from BEEFY AuthorityIds, see [`beefy_authorities_merkle_root`].
 	///
 	/// This is used by Light Clients to confirm that the commitments are signed by the correct
 	/// validator set. Light Clients using interactive protocol, might verify only subset of
@@ -98,18 +279,33 @@ type MerkleRootOf<T> = <T as pallet_mmr::Config>::Hash;
 
 /// A type that is able to return current list of parachain heads that end up in the MMR leaf.
 pub trait ParachainHeadsProvider {
-	/// Return a list of encoded parachain heads.
+	/// Return the heads of all parachains, sorted by `ParaId`.
+	fn heads() -> Vec<(ParaId, HeadData)>;
+
+	/// Return a list of encoded parachain heads, as committed to by leaves of major version 0.
 	fn encoded_heads() -> Vec<Vec<u8>>;
 }
 
 /// A default implementation for runtimes without parachains.
 impl ParachainHeadsProvider for () {
+	fn heads() -> Vec<(ParaId, HeadData)> {
+		Default::default()
+	}
+
 	fn encoded_heads() -> Vec<Vec<u8>> {
 		Default::default()
 	}
 }
 
 impl<T: Config + paras::Config> ParachainHeadsProvider for paras::Pallet<T> {
+	fn heads() -> Vec<(ParaId, HeadData)> {
+		// `parachains` is sorted by `ParaId`.
+		paras::Pallet::<T>::parachains()
+			.into_iter()
+			.filter_map(|id| paras::Pallet::<T>::para_head(id).map(|head| (id, head)))
+			.collect()
+	}
+
 	fn encoded_heads() -> Vec<Vec<u8>> {
 		paras::Pallet::<T>::parachains()
 			.into_iter()
@@ -119,9 +315,601 @@ impl<T: Config + paras::Config> ParachainHeadsProvider for paras::Pallet<T> {
 	}
 }
 
+/// A proof that a parachain had some head in a given MMR leaf.
+///
+/// The heads of a leaf are the leaves of a binary merkle tree in order of `ParaId`, see
+/// [`para_heads_merkle_root`].
+#[derive(RuntimeDebug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct ParaHeadProof {
+	/// The head of the parachain.
+	pub head: HeadData,
+	/// The position of the head among all parachain heads.
+	pub index: u32,
+	/// The number of parachain heads.
+	pub count: u32,
+	/// The hashes of the siblings on the path from the head up to the root, bottom first.
+	pub proof: Vec<H256>,
+}
+
+/// An error in generating a [`ParaHeadProof`].
+#[derive(RuntimeDebug, PartialEq, Eq, Clone, Encode, Decode)]
+pub enum ParaHeadProofError {
+	/// The heads of the requested leaf are not available at this block. Proofs for a leaf must be
+	/// generated at the parent of the block which adds it.
+	LeafNotAvailable,
+	/// The parachain has no head in the leaf.
+	UnknownPara,
+	/// The leaves are of major version 0, whose parachain heads cannot be proven.
+	UnsupportedLeafVersion,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to prove the head of a single parachain against an MMR leaf.
+	pub trait ParaHeadProofApi {
+		/// Generate a proof of the head of `para_id` in the MMR leaf `leaf_index`.
+		fn generate_para_head_proof(
+			para_id: ParaId,
+			leaf_index: u64,
+		) -> Result<ParaHeadProof, ParaHeadProofError>;
+	}
+}
+
+fn para_head_leaf_hash(para_id: ParaId, head: &HeadData) -> H256 {
+	sp_io::hashing::keccak_256(&(para_id, head).encode()).into()
+}
+
+fn merkle_parent(left: &H256, right: &H256) -> H256 {
+	let mut concat = [0u8; 64];
+	concat[..32].copy_from_slice(left.as_bytes());
+	concat[32..].copy_from_slice(right.as_bytes());
+	sp_io::hashing::keccak_256(&concat).into()
+}
+
+/// The next layer up of a binary merkle tree. A node without a sibling is carried up unchanged.
+fn merkle_layer_up(layer: &[H256]) -> Vec<H256> {
+	layer
+		.chunks(2)
+		.map(|pair| match pair {
+			[left, right] => merkle_parent(left, right),
+			[single] => *single,
+			_ => unreachable!("chunks are of one or two nodes; qed"),
+		})
+		.collect()
+}
+
+/// The root of the binary merkle tree over `heads`, which must be sorted by `ParaId`.
+///
+/// The leaves are the keccak-256 hashes of the SCALE-encoded `(ParaId, HeadData)` pairs, and each
+/// node the hash of its two children concatenated. The root of no heads is zero.
+pub fn para_heads_merkle_root(heads: &[(ParaId, HeadData)]) -> H256 {
+	merkle_root(heads.iter().map(|(id, head)| para_head_leaf_hash(*id, head)).collect())
+}
+
+/// The root of the binary merkle tree over the BEEFY authority `keys`, in the form given by
+/// [`Config::BeefyAuthorityToMerkleLeaf`].
+///
+/// The leaves are the keccak-256 hashes of the keys, and the tree is built like the one of
+/// [`para_heads_merkle_root`].
+pub fn beefy_authorities_merkle_root(keys: &[Vec<u8>]) -> H256 {
+	merkle_root(keys.iter().map(|key| sp_io::hashing::keccak_256(key).into()).collect())
+}
+
+fn merkle_root(mut layer: Vec<H256>) -> H256 {
+	if layer.is_empty() {
+		return H256::zero();
+	}
+	while layer.len() > 1 {
+		layer = merkle_layer_up(&layer);
+	}
+	layer[0]
+}
+
+/// Generate a proof of the head of `para_id` among `heads`, which must be sorted by `ParaId`.
+pub fn para_head_proof(
+	heads: &[(ParaId, HeadData)],
+	para_id: ParaId,
+) -> Result<ParaHeadProof, ParaHeadProofError> {
+	let index = heads
+		.binary_search_by_key(&para_id, |(id, _)| *id)
+		.map_err(|_| ParaHeadProofError::UnknownPara)?;
+	let mut layer = heads
+		.iter()
+		.map(|(id, head)| para_head_leaf_hash(*id, head))
+		.collect::<Vec<_>>();
+	let mut position = index;
+	let mut proof = Vec::new();
+	while layer.len() > 1 {
+		if let Some(sibling) = layer.get(position ^ 1) {
+			proof.push(*sibling);
+		}
+		layer = merkle_layer_up(&layer);
+		position /= 2;
+	}
+	Ok(ParaHeadProof {
+		head: heads[index].1.clone(),
+		index: index as u32,
+		count: heads.len() as u32,
+		proof,
+	})
+}
+
+/// Verify that `proof` shows the head of `para_id` to be part of the parachain heads with merkle
+/// `root`, as found in an MMR leaf.
+pub fn verify_para_head_proof(root: H256, para_id: ParaId, proof: &ParaHeadProof) -> bool {
+	if proof.index >= proof.count {
+		return false;
+	}
+	let mut hash = para_head_leaf_hash(para_id, &proof.head);
+	let mut position = proof.index;
+	let mut width = proof.count;
+	let mut siblings = proof.proof.iter();
+	while width > 1 {
+		if position ^ 1 < width {
+			let sibling = match siblings.next() {
+				Some(sibling) => sibling,
+				None => return false,
+			};
+			hash = if position % 2 == 0 {
+				merkle_parent(&hash, sibling)
+			} else {
+				merkle_parent(sibling, &hash)
+			};
+		}
+		position /= 2;
+		width = (width + 1) / 2;
+	}
+	siblings.next().is_none() && hash == root
+}
+
+/// The maximum number of leaves which can be proven at once with [`generate_batch_proof`].
+pub const MAX_BATCH_PROOF_LEAVES: u32 = 256;
+
+/// A proof of several MMR leaves against the same root.
+///
+/// Unlike one [`MmrProof`] per leaf, every node is included at most once, and none that can be
+/// computed from the proven leaves. The `items` are, for each peak of the MMR from left to right:
+/// - the peak itself, if none of the proven leaves is below it, or else
+/// - the siblings which are not computed from the proven leaves on their paths up to the peak,
+///   layer by layer from the leaves up, and left to right within a layer.
+///
+/// The root is then the peaks bagged from right to left, like `pallet_mmr` does.
+#[derive(RuntimeDebug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct BatchProof<Hash> {
+	/// The indices of the proven leaves, in ascending order.
+	pub leaf_indices: Vec<LeafIndex>,
+	/// The number of leaves of the MMR the proof is against.
+	pub leaf_count: LeafIndex,
+	/// The hashes of the nodes needed to compute the root.
+	pub items: Vec<Hash>,
+}
+
+/// An error in generating or verifying a [`BatchProof`].
+#[derive(RuntimeDebug, PartialEq, Eq, Encode, Decode)]
+pub enum BatchProofError {
+	/// No leaves were given.
+	NoLeaves,
+	/// More than [`MAX_BATCH_PROOF_LEAVES`] leaves were given.
+	TooManyLeaves { requested: u32, max: u32 },
+	/// The number of leaves does not match the number of leaf indices of the proof.
+	LeafCountMismatch,
+	/// A leaf or node is missing, or the proof does not prove the leaves.
+	Mmr(MmrError),
+	/// Nodes the proof needs were pruned from the offchain database, see [`ProofError::Pruned`].
+	Pruned,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to prove several MMR leaves at once.
+	pub trait MmrBatchProofApi<Hash: parity_scale_codec::Codec> {
+		/// Generate a proof of the leaves at `leaf_indices`.
+		fn generate_batch_proof(
+			leaf_indices: Vec<LeafIndex>,
+		) -> Result<(Vec<EncodableOpaqueLeaf>, BatchProof<Hash>), BatchProofError>;
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to prove the next BEEFY authority set, as committed to in the latest MMR leaf.
+	pub trait BeefyNextAuthoritySetApi<Hash: parity_scale_codec::Codec> {
+		/// Generate a proof of the latest MMR leaf, which holds the next BEEFY authority set.
+		fn next_authority_set_proof() -> Result<(EncodableOpaqueLeaf, MmrProof<Hash>), MmrError>;
+	}
+}
+
+/// Generate a proof of the latest MMR leaf of runtime `T`, against the current root.
+pub fn latest_leaf_proof<T: pallet_mmr::Config>() -> Result<
+	(<T::LeafData as LeafDataProvider>::LeafData, MmrProof<<T as pallet_mmr::Config>::Hash>),
+	MmrError,
+> {
+	let latest = pallet_mmr::Pallet::<T>::mmr_leaves()
+		.checked_sub(1)
+		.ok_or(MmrError::GenerateProof)?;
+	generate_proof::<T>(latest).map_err(Into::into)
+}
+
+/// Generate a proof of the MMR leaves of runtime `T` at `leaf_indices`, against the current root.
+///
+/// The leaves are returned, and proven, in ascending order of their indices, without duplicates.
+pub fn generate_batch_proof<T: pallet_mmr::Config>(
+	mut leaf_indices: Vec<LeafIndex>,
+) -> Result<(Vec<LeafOf<T>>, BatchProof<<T as pallet_mmr::Config>::Hash>), BatchProofError> {
+	let requested = leaf_indices.len() as u32;
+	if requested == 0 {
+		return Err(BatchProofError::NoLeaves);
+	}
+	if requested > MAX_BATCH_PROOF_LEAVES {
+		return Err(BatchProofError::TooManyLeaves { requested, max: MAX_BATCH_PROOF_LEAVES });
+	}
+	leaf_indices.sort_unstable();
+	leaf_indices.dedup();
+
+	let leaf_count = pallet_mmr::Pallet::<T>::mmr_leaves();
+	let mut leaves = Vec::with_capacity(leaf_indices.len());
+	let mut leaf_hashes = Vec::with_capacity(leaf_indices.len());
+	for &leaf_index in &leaf_indices {
+		if leaf_index >= leaf_count {
+			return Err(BatchProofError::Mmr(MmrError::LeafNotFound));
+		}
+		let node = offchain_node::<T>(mmr_size(leaf_index))
+			.ok_or(BatchProofError::Mmr(MmrError::LeafNotFound))?;
+		leaf_hashes.push((leaf_index, node.hash()));
+		match node {
+			DataOrHash::Data(leaf) => leaves.push(leaf),
+			DataOrHash::Hash(_) => return Err(BatchProofError::Mmr(MmrError::LeafNotFound)),
+		}
+	}
+
+	let mut items = Vec::new();
+	let root = batch_proof_root::<<T as pallet_mmr::Config>::Hashing>(leaf_count, leaf_hashes, |height, index| {
+		let hash = offchain_node_hash::<T>(node_position(height, index))?;
+		items.push(hash);
+		Some(hash)
+	});
+	if root != Some(pallet_mmr::Pallet::<T>::mmr_root_hash()) {
+		if leaf_indices[0] < pruned_leaves::<T>() {
+			return Err(BatchProofError::Pruned);
+		}
+		log::error!(target: "runtime::mmr", "MMR nodes of leaves {:?} are missing", leaf_indices);
+		return Err(BatchProofError::Mmr(MmrError::GenerateProof));
+	}
+	Ok((leaves, BatchProof { leaf_indices, leaf_count, items }))
+}
+
+/// Verify `proof` of `leaves` against the MMR `root`, without access to the chain state.
+///
+/// The leaves must be in the order of the leaf indices of the proof.
+pub fn verify_batch_proof<H, L>(
+	root: H::Output,
+	leaves: Vec<L>,
+	proof: BatchProof<H::Output>,
+) -> Result<(), BatchProofError>
+where
+	H: sp_runtime::traits::Hash,
+	L: FullLeaf,
+{
+	if leaves.is_empty() {
+		return Err(BatchProofError::NoLeaves);
+	}
+	if leaves.len() != proof.leaf_indices.len() {
+		return Err(BatchProofError::LeafCountMismatch);
+	}
+	if proof.leaf_indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+		return Err(BatchProofError::Mmr(MmrError::Verify));
+	}
+
+	let leaf_hashes = proof.leaf_indices
+		.into_iter()
+		.zip(leaves)
+		.map(|(index, leaf)| (index, DataOrHash::<H, L>::Data(leaf).hash()))
+		.collect();
+	let mut items = proof.items.into_iter();
+	let computed = batch_proof_root::<H>(proof.leaf_count, leaf_hashes, |_, _| items.next());
+	if computed != Some(root) || items.next().is_some() {
+		return Err(BatchProofError::Mmr(MmrError::Verify));
+	}
+	Ok(())
+}
+
+/// The position of the MMR node at `height`, which is the `index`th node of that height.
+fn node_position(height: u32, index: u64) -> u64 {
+	// a node comes right after the last leaf below it and the nodes above that leaf.
+	let last_leaf = ((index + 1) << height) - 1;
+	mmr_size(last_leaf) + u64::from(height)
+}
+
+/// Hash the MMR node of the nodes `left` and `right`, as `pallet_mmr` does.
+fn merge_nodes<H: sp_runtime::traits::Hash>(left: &H::Output, right: &H::Output) -> H::Output {
+	let mut concat = left.as_ref().to_vec();
+	concat.extend_from_slice(right.as_ref());
+	H::hash(&concat)
+}
+
+/// The root of the MMR of `leaf_count` leaves, from the hashes of some of its leaves, given as
+/// their indices (ascending) and hashes, and the other nodes a [`BatchProof`] needs.
+///
+/// `item(height, index)` is called for each of these other nodes, in the order of the items of a
+/// [`BatchProof`], and returns its hash. Returns `None` if `item` does, or if a leaf is not part
+/// of the MMR.
+fn batch_proof_root<H: sp_runtime::traits::Hash>(
+	leaf_count: LeafIndex,
+	leaf_hashes: Vec<(LeafIndex, H::Output)>,
+	mut item: impl FnMut(u32, u64) -> Option<H::Output>,
+) -> Option<H::Output> {
+	let mut leaf_hashes = leaf_hashes.into_iter().peekable();
+	let mut peaks = Vec::new();
+	let mut first_leaf = 0;
+	for (_, peak_height) in mmr_peaks(leaf_count) {
+		let end = first_leaf + (1 << peak_height);
+		// the proven nodes of the current layer below the peak, as their indices and hashes.
+		let mut layer = Vec::new();
+		while leaf_hashes.peek().map_or(false, |(index, _)| *index < end) {
+			layer.extend(leaf_hashes.next());
+		}
+		if layer.is_empty() {
+			peaks.push(item(peak_height, first_leaf >> peak_height)?);
+		} else {
+			for height in 0..peak_height {
+				let mut nodes = layer.into_iter().peekable();
+				let mut parents = Vec::new();
+				while let Some((index, hash)) = nodes.next() {
+					let parent = if index % 2 == 0 {
+						let right = match nodes.peek() {
+							Some((next, _)) if *next == index + 1 => nodes.next()?.1,
+							_ => item(height, index + 1)?,
+						};
+						merge_nodes::<H>(&hash, &right)
+					} else {
+						merge_nodes::<H>(&item(height, index - 1)?, &hash)
+					};
+					parents.push((index / 2, parent));
+				}
+				layer = parents;
+			}
+			peaks.push(layer[0].1);
+		}
+		first_leaf = end;
+	}
+	if leaf_hashes.next().is_some() {
+		return None;
+	}
+
+	let mut peaks = peaks.into_iter().rev();
+	let mut root = peaks.next()?;
+	for left in peaks {
+		root = merge_nodes::<H>(&root, &left);
+	}
+	Some(root)
+}
+
+/// Stateless verification of the MMR leaves of our relay chains, for bridges and light clients.
+///
+/// This fixes the hashing choices of the runtimes: MMR nodes are hashed with keccak-256, leaves
+/// are hashed as their raw encoded bytes, and the parachain heads in a leaf of major version 1 are
+/// committed to with [`para_heads_merkle_root`](super::para_heads_merkle_root).
+pub mod verify {
+	use super::*;
+	use pallet_mmr::primitives::OpaqueLeaf;
+	use primitives::v1::BlockNumber;
+	use sp_runtime::traits::Keccak256;
+
+	/// An MMR leaf of a relay chain, in any of its formats.
+	pub type Leaf = VersionedLeaf<BlockNumber, H256, H256>;
+
+	/// An error in verifying a proof.
+	#[derive(RuntimeDebug, PartialEq, Eq)]
+	pub enum VerifyError {
+		/// The leaf is not proven against the MMR root.
+		Leaf,
+		/// The proven leaf is not a relay chain MMR leaf of major version 1 or later.
+		LeafFormat,
+		/// The head is not proven against the parachain heads of the leaf.
+		ParaHead,
+	}
+
+	/// Verify that `proof` shows the leaf with encoding `leaf` to be part of the MMR with `root`.
+	pub fn verify_leaf_proof(
+		root: H256,
+		leaf: &[u8],
+		proof: MmrProof<H256>,
+	) -> Result<(), VerifyError> {
+		let leaf = DataOrHash::Data(OpaqueLeaf(leaf.to_vec()));
+		pallet_mmr::verify_leaf_proof::<Keccak256, _>(root, leaf, proof)
+			.map_err(|_| VerifyError::Leaf)
+	}
+
+	/// Verify that `head` is the head of `para_id` in the MMR leaf with encoding `leaf`, which
+	/// `leaf_proof` shows to be part of the MMR with `root`.
+	///
+	/// `para_head_proof` is the proof of the head among the parachain heads of the leaf, as given
+	/// by the [`ParaHeadProofApi`](super::ParaHeadProofApi).
+	pub fn verify_para_head(
+		root: H256,
+		leaf: &[u8],
+		leaf_proof: MmrProof<H256>,
+		para_head_proof: &ParaHeadProof,
+		para_id: ParaId,
+		head: &HeadData,
+	) -> Result<(), VerifyError> {
+		verify_leaf_proof(root, leaf, leaf_proof)?;
+		let heads_root = match Leaf::decode(&mut &leaf[..]) {
+			Ok(Leaf::V1(leaf)) => leaf.leaf.parachain_heads,
+			_ => return Err(VerifyError::LeafFormat),
+		};
+		let proven = verify_para_head_proof(heads_root, para_id, para_head_proof);
+		if !proven || &para_head_proof.head != head {
+			return Err(VerifyError::ParaHead);
+		}
+		Ok(())
+	}
+}
+
+/// An error in generating a proof of an MMR leaf.
+#[derive(RuntimeDebug, PartialEq, Eq, Encode, Decode)]
+pub enum ProofError {
+	/// Nodes the proof needs were pruned from the offchain database, see
+	/// [`prune_offchain_nodes`]. The proof can be generated by an archive node.
+	Pruned,
+	/// Generating the proof failed.
+	Mmr(MmrError),
+}
+
+impl From<ProofError> for MmrError {
+	fn from(error: ProofError) -> Self {
+		match error {
+			ProofError::Pruned => MmrError::GenerateProof,
+			ProofError::Mmr(error) => error,
+		}
+	}
+}
+
+/// The maximum number of leaves whose offchain MMR nodes are pruned by one offchain worker run.
+///
+/// This bounds the work of catching up with a long chain when pruning is first enabled.
+pub const MAX_PRUNED_LEAVES_PER_RUN: LeafIndex = 1_000;
+
+type LeafOf<T> = <<T as pallet_mmr::Config>::LeafData as LeafDataProvider>::LeafData;
+type NodeOf<T> = DataOrHash<<T as pallet_mmr::Config>::Hashing, LeafOf<T>>;
+type NodeHashOf<T> = <<T as pallet_mmr::Config>::Hashing as sp_runtime::traits::Hash>::Output;
+
+/// The number of MMR nodes for `leaves` leaves, which is also the position of the next leaf.
+fn mmr_size(leaves: LeafIndex) -> u64 {
+	2 * leaves - u64::from(leaves.count_ones())
+}
+
+/// The positions and heights of the peaks of the MMR of `leaves` leaves, from left to right.
+fn mmr_peaks(leaves: LeafIndex) -> Vec<(u64, u32)> {
+	let mut peaks = Vec::new();
+	let mut offset = 0;
+	for height in (0..63).rev() {
+		if leaves & (1 << height) != 0 {
+			let tree_size = (1u64 << (height + 1)) - 1;
+			peaks.push((offset + tree_size - 1, height));
+			offset += tree_size;
+		}
+	}
+	peaks
+}
+
+/// Whether the MMR node at `pos` is a leaf.
+fn is_leaf(pos: u64) -> bool {
+	// peaks of the MMR of any number of leaves are at positions `2^(h + 1) - 2` from the start of
+	// their tree, so jump to the start of the rightmost tree containing `pos` until it is a peak.
+	let mut pos = pos + 1;
+	while pos.count_zeros() != pos.leading_zeros() {
+		let most_significant_bit = 1 << (63 - pos.leading_zeros());
+		pos -= most_significant_bit - 1;
+	}
+	pos == 1
+}
+
+fn offchain_node_key<T: pallet_mmr::Config>(pos: u64) -> Vec<u8> {
+	// must match the key `pallet_mmr` indexes its nodes at.
+	(T::INDEXING_PREFIX, pos).encode()
+}
+
+fn pruned_leaves_key<T: pallet_mmr::Config>() -> Vec<u8> {
+	(T::INDEXING_PREFIX, b"pruned-leaves").encode()
+}
+
+/// The number of leaves, from the first, whose interior MMR nodes may have been pruned.
+fn pruned_leaves<T: pallet_mmr::Config>() -> LeafIndex {
+	sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &pruned_leaves_key::<T>())
+		.and_then(|pruned| LeafIndex::decode(&mut &pruned[..]).ok())
+		.unwrap_or_default()
+}
+
+fn offchain_node<T: pallet_mmr::Config>(pos: u64) -> Option<NodeOf<T>> {
+	sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &offchain_node_key::<T>(pos))
+		.and_then(|node| NodeOf::<T>::decode(&mut &node[..]).ok())
+}
+
+fn offchain_node_hash<T: pallet_mmr::Config>(pos: u64) -> Option<NodeHashOf<T>> {
+	offchain_node::<T>(pos).map(|node| node.hash())
+}
+
+/// Prune the offchain MMR nodes of all but the latest `retained` leaves of runtime `T`.
+///
+/// `pallet_mmr` keeps every node of the MMR in the offchain database, but only needs the peaks to
+/// add new leaves, and interior nodes can be recomputed from the leaves below them. So for the
+/// leaves older than the retention window, this removes all interior nodes but the peaks of the
+/// MMR of these older leaves, and keeps the leaves themselves. Proofs which need removed nodes
+/// fail with [`ProofError::Pruned`], and have to be generated by a node which does not prune.
+///
+/// At most [`MAX_PRUNED_LEAVES_PER_RUN`] more leaves are pruned per call. This must be called
+/// from an offchain worker.
+pub fn prune_offchain_nodes<T: pallet_mmr::Config>(retained: LeafIndex) {
+	let pruned = pruned_leaves::<T>();
+	let to_prune = pallet_mmr::Pallet::<T>::mmr_leaves()
+		.saturating_sub(retained)
+		.min(pruned.saturating_add(MAX_PRUNED_LEAVES_PER_RUN));
+	if to_prune <= pruned {
+		return;
+	}
+
+	// the nodes pruned before are still below a peak of the longer prefix of leaves, so only the
+	// nodes added since and the peaks of before need to be looked at.
+	let peaks = mmr_peaks(to_prune).into_iter().map(|(pos, _)| pos).collect::<Vec<_>>();
+	let candidates = mmr_peaks(pruned)
+		.into_iter()
+		.filter(|(_, height)| *height > 0)
+		.map(|(pos, _)| pos)
+		.chain((mmr_size(pruned)..mmr_size(to_prune)).filter(|pos| !is_leaf(*pos)));
+	for pos in candidates.filter(|pos| !peaks.contains(pos)) {
+		sp_io::offchain::local_storage_clear(StorageKind::PERSISTENT, &offchain_node_key::<T>(pos));
+	}
+	sp_io::offchain::local_storage_set(
+		StorageKind::PERSISTENT,
+		&pruned_leaves_key::<T>(),
+		&to_prune.encode(),
+	);
+}
+
+/// Generate a proof of the MMR leaf of runtime `T` at `leaf_index`, against the current root.
+///
+/// Unlike [`pallet_mmr::Pallet::generate_proof`] this fails with [`ProofError::Pruned`] if
+/// [`prune_offchain_nodes`] removed nodes the proof needs. It never writes to the offchain
+/// database.
+pub fn generate_proof<T: pallet_mmr::Config>(
+	leaf_index: LeafIndex,
+) -> Result<(LeafOf<T>, MmrProof<<T as pallet_mmr::Config>::Hash>), ProofError> {
+	let pruned = pruned_leaves::<T>();
+	if leaf_index < pruned {
+		// find the peak of the pruned leaves the leaf is below, and the siblings on its path.
+		let mut first_leaf = 0;
+		for (mut pos, mut height) in mmr_peaks(pruned) {
+			if leaf_index >= first_leaf + (1 << height) {
+				first_leaf += 1 << height;
+				continue;
+			}
+			while height > 0 {
+				height -= 1;
+				let (left, right) = (pos - (2 << height), pos - 1);
+				let (path, sibling) = if leaf_index < first_leaf + (1 << height) {
+					(left, right)
+				} else {
+					first_leaf += 1 << height;
+					(right, left)
+				};
+				if offchain_node_hash::<T>(sibling).is_none() {
+					log::debug!(
+						target: "runtime::mmr",
+						"MMR leaf {} cannot be proven as nodes above it were pruned, use an archive node",
+						leaf_index,
+					);
+					return Err(ProofError::Pruned);
+				}
+				pos = path;
+			}
+			break;
+		}
+	}
+	pallet_mmr::Pallet::<T>::generate_proof(leaf_index).map_err(ProofError::Mmr)
+}
+
 #[frame_support::pallet]
 pub mod pallet {
 	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
 	use super::*;
 
 	#[pallet::pallet]
@@ -134,9 +922,11 @@ pub mod pallet {
 	pub trait Config: pallet_mmr::Config + pallet_beefy::Config {
 		/// Convert BEEFY AuthorityId to a form that would end up in the Merkle Tree.
 		///
-		/// For instance for ECDSA (secp256k1) we want to store uncompressed public keys (65 bytes)
-		/// to simplify using them on Ethereum chain, but the rest of the Substrate codebase
-		/// is storing them compressed (33 bytes) for efficiency reasons.
+		/// For instance for ECDSA (secp256k1) public keys can be stored compressed (33 bytes)
+		/// with [`CompressedBeefyEcdsaKeys`], like the rest of the Substrate codebase does, or
+		/// uncompressed (65 bytes) with [`UncompressBeefyEcdsaKeys`] to simplify using them on
+		/// Ethereum chain. Changing it changes the leaves, so it should go with a new
+		/// [`Config::LeafVersion`].
 		type BeefyAuthorityToMerkleLeaf: Convert<<Self as pallet_beefy::Config>::BeefyId, Vec<u8>>;
 
 		/// Retrieve a list of current parachain heads.
@@ -145,6 +935,33 @@ pub mod pallet {
 		/// and we want to keep the MMR leaf structure uniform, it's possible to use `()` as well to
 		/// simply put dummy data to the leaf.
 		type ParachainHeads: ParachainHeadsProvider;
+
+		/// The version of the leaves to add to the MMR.
+		///
+		/// Light clients may hard-code the leaf format, so a new version should only be configured
+		/// once they can decode it. Switching to major version 1 also switches the commitments to
+		/// the parachain heads and the BEEFY authorities to binary merkle trees, see
+		/// [`MmrLeafV1`]. The next leaf after the runtime upgrade is the first in the new format,
+		/// and the older leaves stay as they were.
+		type LeafVersion: Get<MmrLeafVersion>;
+
+		/// The number of latest leaves whose offchain MMR nodes are all kept, or `None` to keep the
+		/// nodes of all leaves.
+		///
+		/// The interior nodes of older leaves are pruned by the offchain worker, after which most
+		/// of them can no longer be proven by this node, see [`prune_offchain_nodes`]. As every
+		/// node of the network runs the same runtime, this should stay `None` unless other nodes
+		/// keep proving the older leaves.
+		type RetainedLeaves: Get<Option<LeafIndex>>;
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn offchain_worker(_n: BlockNumberFor<T>) {
+			if let Some(retained) = T::RetainedLeaves::get() {
+				prune_offchain_nodes::<T>(retained);
+			}
+		}
 	}
 
 	/// Details of next BEEFY authority set.
@@ -157,12 +974,18 @@ pub mod pallet {
 		BeefyNextAuthoritySet<MerkleRootOf<T>>,
 		ValueQuery,
 	>;
+
+	/// The major leaf version the merkle root in [`BeefyNextAuthorities`] was computed for.
+	///
+	/// Defaults to 0, which is right for a root cached before leaves were versioned.
+	#[pallet::storage]
+	pub type BeefyNextAuthoritiesMajorVersion<T: Config> = StorageValue<_, u8, ValueQuery>;
 }
 
 impl<T: Config> LeafDataProvider for Pallet<T> where
 	MerkleRootOf<T>: From<H256>,
 {
-	type LeafData = MmrLeaf<
+	type LeafData = VersionedLeaf<
 		<T as frame_system::Config>::BlockNumber,
 		<T as frame_system::Config>::Hash,
 		MerkleRootOf<T>,
@@ -173,7 +996,7 @@ impl<T: Config> LeafDataProvider for Pallet<T> where
 			parent_number_and_hash: frame_system::Pallet::<T>::leaf_data(),
 			parachain_heads: Pallet::<T>::parachain_heads_merkle_root(),
 			beefy_next_authority_set: Pallet::<T>::update_beefy_next_authority_set(),
-		}
+		}.into_versioned(T::LeafVersion::get())
 	}
 }
 
@@ -189,22 +1012,44 @@ impl<T: Config> Pallet<T> where
 	/// the merkle tree every block. Instead we should update the merkle root in [Self::on_initialize]
 	/// call of this pallet and update the merkle tree efficiently (use on-chain storage to persist inner nodes).
 	fn parachain_heads_merkle_root() -> MerkleRootOf<T> {
-		let para_heads = T::ParachainHeads::encoded_heads();
-		sp_io::trie::keccak_256_ordered_root(para_heads).into()
+		if T::LeafVersion::get().major() == 0 {
+			sp_io::trie::keccak_256_ordered_root(T::ParachainHeads::encoded_heads()).into()
+		} else {
+			para_heads_merkle_root(&T::ParachainHeads::heads()).into()
+		}
+	}
+
+	/// Generate a proof of the head of `para_id` in the MMR leaf `leaf_index`.
+	///
+	/// Only the heads of the next leaf to be added to the MMR are available, so this must be called
+	/// at the parent of the block adding leaf `leaf_index`.
+	pub fn para_head_proof(
+		para_id: ParaId,
+		leaf_index: u64,
+	) -> Result<ParaHeadProof, ParaHeadProofError> {
+		if leaf_index != pallet_mmr::Pallet::<T>::mmr_leaves() {
+			return Err(ParaHeadProofError::LeafNotAvailable);
+		}
+		if T::LeafVersion::get().major() == 0 {
+			return Err(ParaHeadProofError::UnsupportedLeafVersion);
+		}
+		para_head_proof(&T::ParachainHeads::heads(), para_id)
 	}
 
 	/// Returns details of the next BEEFY authority set.
 	///
 	/// Details contain authority set id, authority set length and a merkle root,
-	/// constructed from uncompressed secp256k1 public keys of the next BEEFY authority set.
+	/// constructed from the [`Config::BeefyAuthorityToMerkleLeaf`] form of the public keys
+	/// of the next BEEFY authority set.
 	///
 	/// This function will use a storage-cached entry in case the set didn't change, or compute and cache
 	/// new one in case it did.
 	fn update_beefy_next_authority_set() -> BeefyNextAuthoritySet<MerkleRootOf<T>> {
 		let id = pallet_beefy::Pallet::<T>::validator_set_id() + 1;
+		let major_version = T::LeafVersion::get().major();
 		let current_next = Self::beefy_next_authorities();
-		// avoid computing the merkle tree if validator set id didn't change.
-		if id == current_next.id {
+		// avoid computing the merkle tree if validator set id and the tree kind didn't change.
+		if id == current_next.id && major_version == BeefyNextAuthoritiesMajorVersion::<T>::get() {
 			return current_next;
 		}
 
@@ -213,7 +1058,11 @@ impl<T: Config> Pallet<T> where
 			.map(T::BeefyAuthorityToMerkleLeaf::convert)
 			.collect::<Vec<_>>();
 		let len = beefy_public_keys.len() as u32;
-		let root: MerkleRootOf<T> = sp_io::trie::keccak_256_ordered_root(beefy_public_keys).into();
+		let root: MerkleRootOf<T> = if major_version == 0 {
+			sp_io::trie::keccak_256_ordered_root(beefy_public_keys).into()
+		} else {
+			beefy_authorities_merkle_root(&beefy_public_keys).into()
+		};
 		let next_set = BeefyNextAuthoritySet {
 			id,
 			len,
@@ -221,6 +1070,616 @@ impl<T: Config> Pallet<T> where
 		};
 		// cache the result
 		BeefyNextAuthorities::<T>::put(&next_set);
+		BeefyNextAuthoritiesMajorVersion::<T>::put(major_version);
 		next_set
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mmr as mmr_common;
+	use frame_support::{parameter_types, traits::{OffchainWorker, OnInitialize}};
+	use std::cell::RefCell;
+	use sp_core::offchain::{testing::TestOffchainExt, OffchainDbExt, OffchainWorkerExt};
+	use sp_runtime::{
+		testing::Header,
+		traits::{BlakeTwo256, IdentityLookup, Keccak256},
+	};
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
+
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Mmr: pallet_mmr::{Pallet, Call, Storage},
+			Beefy: pallet_beefy::{Pallet, Config<T>, Storage},
+			BeefyMmr: mmr_common::{Pallet, Storage},
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const RetainedLeaves: Option<u64> = Some(50);
+	}
+
+	impl frame_system::Config for Test {
+		type BaseCallFilter = frame_support::traits::AllowAll;
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Call = Call;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	impl pallet_mmr::Config for Test {
+		const INDEXING_PREFIX: &'static [u8] = b"mmr";
+		type Hashing = Keccak256;
+		type Hash = H256;
+		type LeafData = BeefyMmr;
+		type OnNewRoot = ();
+		type WeightInfo = ();
+	}
+
+	impl pallet_beefy::Config for Test {
+		type BeefyId = beefy_primitives::crypto::AuthorityId;
+	}
+
+	thread_local! {
+		static LEAF_VERSION: RefCell<MmrLeafVersion> = RefCell::new(MmrLeafVersion::new(0, 0));
+	}
+
+	pub struct TestLeafVersion;
+	impl Get<MmrLeafVersion> for TestLeafVersion {
+		fn get() -> MmrLeafVersion {
+			LEAF_VERSION.with(|v| *v.borrow())
+		}
+	}
+
+	impl Config for Test {
+		type BeefyAuthorityToMerkleLeaf = CompressedBeefyEcdsaKeys;
+		type ParachainHeads = ();
+		type LeafVersion = TestLeafVersion;
+		type RetainedLeaves = RetainedLeaves;
+	}
+
+	/// Test externalities with an MMR of the leaves of `blocks` blocks.
+	fn chain_of(blocks: u64) -> sp_io::TestExternalities {
+		chain_with_versions(blocks, |_| MmrLeafVersion::new(0, 0))
+	}
+
+	/// Like [`chain_of`], with the leaf of block `n` in the format of `version_at(n)`.
+	fn chain_with_versions(
+		blocks: u64,
+		version_at: impl Fn(u64) -> MmrLeafVersion,
+	) -> sp_io::TestExternalities {
+		let mut ext: sp_io::TestExternalities =
+			frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into();
+		ext.execute_with(|| {
+			for number in 1..=blocks {
+				LEAF_VERSION.with(|v| *v.borrow_mut() = version_at(number));
+				let parent_hash = H256::from_low_u64_be(number);
+				let kind = frame_system::InitKind::Full;
+				System::initialize(&number, &parent_hash, &Default::default(), kind);
+				Mmr::on_initialize(number);
+			}
+		});
+		// the MMR nodes are only kept in the offchain database.
+		ext.persist_offchain_overlay();
+		let (offchain, _) = TestOffchainExt::with_offchain_db(ext.offchain_db());
+		ext.register_extension(OffchainDbExt::new(offchain.clone()));
+		ext.register_extension(OffchainWorkerExt::new(offchain));
+		ext
+	}
+
+	fn assert_batch_proves(leaf_indices: Vec<LeafIndex>) {
+		let prove = || generate_batch_proof::<Test>(leaf_indices.clone()).unwrap();
+		let (leaves, proof) = prove();
+		let mut sorted = leaf_indices.clone();
+		sorted.sort();
+		assert_eq!(proof.leaf_indices, sorted);
+		assert_eq!(proof.leaf_count, Mmr::mmr_leaves());
+		assert_eq!(leaves.len(), sorted.len());
+		for (leaf, index) in leaves.iter().zip(&sorted) {
+			// leaf `i` is added by block `i + 1`, and holds its parent.
+			assert_eq!(leaf.leaf().parent_number_and_hash.0, *index);
+		}
+
+		// no node is repeated, and the proof is no larger than the proofs of single leaves.
+		let mut items = proof.items.clone();
+		items.sort();
+		items.dedup();
+		assert_eq!(items.len(), proof.items.len());
+		let single_items = sorted
+			.iter()
+			.map(|index| generate_proof::<Test>(*index).unwrap().1.items.len())
+			.sum::<usize>();
+		assert!(proof.items.len() <= single_items);
+
+		let root = Mmr::mmr_root_hash();
+		assert_eq!(verify_batch_proof::<Keccak256, _>(root, leaves, proof), Ok(()));
+
+		// the proof is only valid for its own leaves, and against its own root.
+		let (mut leaves, proof) = prove();
+		leaves[0].leaf_mut().parent_number_and_hash.1 = H256::repeat_byte(0xff);
+		assert!(verify_batch_proof::<Keccak256, _>(root, leaves, proof).is_err());
+		let (leaves, proof) = prove();
+		assert!(verify_batch_proof::<Keccak256, _>(H256::zero(), leaves, proof).is_err());
+
+		// and with its own items only.
+		let (leaves, mut proof) = prove();
+		proof.items.push(H256::zero());
+		assert!(verify_batch_proof::<Keccak256, _>(root, leaves, proof).is_err());
+		let (leaves, mut proof) = prove();
+		if let Some(item) = proof.items.first_mut() {
+			*item = H256::repeat_byte(0xff);
+			assert!(verify_batch_proof::<Keccak256, _>(root, leaves, proof).is_err());
+		}
+	}
+
+	#[test]
+	fn batch_proofs_work() {
+		chain_of(300).execute_with(|| {
+			assert_batch_proves(vec![42]);
+			assert_batch_proves((0..16).map(|i| i * 17).collect());
+			assert_batch_proves((0..MAX_BATCH_PROOF_LEAVES as u64).map(|i| 299 - i).collect());
+		});
+	}
+
+	#[test]
+	fn batch_proofs_share_nodes() {
+		chain_of(7).execute_with(|| {
+			// the MMR has peaks over leaves 0..4, 4..6 and 6.
+			assert_batch_proves(vec![0, 1, 2, 3, 4, 5, 6]);
+			let (_, proof) = generate_batch_proof::<Test>(vec![0, 1, 2, 3, 4, 5, 6]).unwrap();
+			assert!(proof.items.is_empty());
+
+			// leaves 0 and 1 need the node above leaves 2 and 3, and the two other peaks.
+			let (_, proof) = generate_batch_proof::<Test>(vec![1, 0, 1]).unwrap();
+			assert_eq!(proof.leaf_indices, vec![0, 1]);
+			let nodes = [node_position(1, 1), node_position(1, 2), node_position(0, 6)];
+			assert_eq!(nodes, [5, 9, 10]);
+			let expected = nodes.iter().map(|pos| offchain_node_hash::<Test>(*pos).unwrap());
+			assert_eq!(proof.items, expected.collect::<Vec<_>>());
+		});
+	}
+
+	#[test]
+	fn batch_proofs_are_limited() {
+		chain_of(300).execute_with(|| {
+			assert_eq!(generate_batch_proof::<Test>(vec![]), Err(BatchProofError::NoLeaves));
+			let too_many = (0..=MAX_BATCH_PROOF_LEAVES as u64).collect();
+			assert_eq!(
+				generate_batch_proof::<Test>(too_many),
+				Err(BatchProofError::TooManyLeaves {
+					requested: MAX_BATCH_PROOF_LEAVES + 1,
+					max: MAX_BATCH_PROOF_LEAVES,
+				}),
+			);
+			assert_eq!(
+				generate_batch_proof::<Test>(vec![1, 300]),
+				Err(BatchProofError::Mmr(MmrError::LeafNotFound)),
+			);
+
+			let (leaves, proof) = generate_batch_proof::<Test>(vec![1, 2]).unwrap();
+			assert_eq!(
+				verify_batch_proof::<Keccak256, _>(Mmr::mmr_root_hash(), leaves[..1].to_vec(), proof),
+				Err(BatchProofError::LeafCountMismatch),
+			);
+			let (mut leaves, mut proof) = generate_batch_proof::<Test>(vec![1, 2]).unwrap();
+			leaves.reverse();
+			proof.leaf_indices.reverse();
+			assert_eq!(
+				verify_batch_proof::<Keccak256, _>(Mmr::mmr_root_hash(), leaves, proof),
+				Err(BatchProofError::Mmr(MmrError::Verify)),
+			);
+		});
+	}
+
+	#[test]
+	fn latest_leaf_is_proven() {
+		chain_of(300).execute_with(|| {
+			let (leaf, proof) = latest_leaf_proof::<Test>().unwrap();
+			assert_eq!(leaf.leaf().parent_number_and_hash.0, 299);
+			assert_eq!(proof.leaf_index, 299);
+			let root = Mmr::mmr_root_hash();
+			assert_eq!(verify::verify_leaf_proof(root, &leaf.encode(), proof), Ok(()));
+		});
+		chain_of(0).execute_with(|| {
+			assert!(latest_leaf_proof::<Test>().is_err());
+		});
+	}
+
+	/// The leaf at index 2 of an MMR of three relay chain leaves, with the heads of three paras.
+	///
+	/// The leaf is of version 1.0, as the heads of older leaves cannot be proven.
+	///
+	/// The vectors were computed independently of this crate, following the hashing scheme, so
+	/// any change of it fails the tests.
+	mod fixture {
+		use super::*;
+		use hex_literal::hex;
+
+		pub fn leaf() -> Vec<u8> {
+			// the version, the parent number and hash, parachain heads root, the next BEEFY
+			// authority set and the empty extra commitments.
+			hex!["200200000003030303030303030303030303030303030303030303030303030303030303031b4077b2e0c6dfe28f0ceeb6f0ed836cda8eb3757f767d80cd55b87bb2dc31fe010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"].to_vec()
+		}
+
+		pub fn root() -> H256 {
+			hex!["2310506c792f757954a3034d489fed7ef468d70d36d94560f72ed9df6b36a26f"].into()
+		}
+
+		pub fn leaf_proof() -> MmrProof<H256> {
+			MmrProof {
+				leaf_index: 2,
+				leaf_count: 3,
+				// the peak of the first two leaves.
+				items: vec![hex!["96d987700e1f5522655e16c3b66b799eb57f87bc6e981fab7531ba5ba12da734"].into()],
+			}
+		}
+
+		pub fn heads() -> Vec<(ParaId, HeadData)> {
+			vec![
+				(2000.into(), HeadData(vec![0xaa; 3])),
+				(2001.into(), HeadData(vec![0xbb; 2])),
+				(2002.into(), HeadData(vec![0xcc; 5])),
+			]
+		}
+
+		pub fn para_head_proof() -> ParaHeadProof {
+			ParaHeadProof {
+				head: HeadData(vec![0xbb; 2]),
+				index: 1,
+				count: 3,
+				proof: vec![
+					hex!["6edcff29963837b67ca677374647987e5de33ff706fd0880fe48d0b82ab7c7ae"].into(),
+					hex!["26ea04f81aa1fbbb761bf4223d070810d6c11fcdbb3ebb2f9a965ad28206c03d"].into(),
+				],
+			}
+		}
+	}
+
+	#[test]
+	fn fixture_leaf_is_verified() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let leaf = verify::Leaf::decode(&mut &fixture::leaf()[..]).unwrap();
+			assert_eq!(leaf.version(), MmrLeafVersion::new(1, 0));
+			assert_eq!(leaf.leaf().parent_number_and_hash, (2, H256::repeat_byte(3)));
+			assert_eq!(leaf.leaf().parachain_heads, para_heads_merkle_root(&fixture::heads()));
+			assert_eq!(para_head_proof(&fixture::heads(), 2001.into()), Ok(fixture::para_head_proof()));
+
+			let verify_leaf = |leaf: &[u8]| {
+				verify::verify_leaf_proof(fixture::root(), leaf, fixture::leaf_proof())
+			};
+			assert_eq!(verify_leaf(&fixture::leaf()), Ok(()));
+			let mut forged = fixture::leaf();
+			forged[0] = 3;
+			assert_eq!(verify_leaf(&forged), Err(verify::VerifyError::Leaf));
+		});
+	}
+
+	#[test]
+	fn para_heads_of_original_leaves_are_not_verified() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			// the same leaf in the original format, which commits to the heads with a trie root.
+			let leaf = fixture::leaf()[1..fixture::leaf().len() - 1].to_vec();
+			assert_eq!(verify::Leaf::decode(&mut &leaf[..]).unwrap().version(), MmrLeafVersion::new(0, 0));
+			// as the only leaf of an MMR, the leaf is the root.
+			let root = sp_io::hashing::keccak_256(&leaf).into();
+			let leaf_proof = MmrProof { leaf_index: 0, leaf_count: 1, items: vec![] };
+			assert_eq!(verify::verify_leaf_proof(root, &leaf, leaf_proof.clone()), Ok(()));
+			let verified = verify::verify_para_head(
+				root,
+				&leaf,
+				leaf_proof,
+				&fixture::para_head_proof(),
+				2001.into(),
+				&HeadData(vec![0xbb; 2]),
+			);
+			assert_eq!(verified, Err(verify::VerifyError::LeafFormat));
+		});
+	}
+
+	#[test]
+	fn fixture_para_head_is_verified() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let verify_head = |para_id: u32, head: HeadData| verify::verify_para_head(
+				fixture::root(),
+				&fixture::leaf(),
+				fixture::leaf_proof(),
+				&fixture::para_head_proof(),
+				para_id.into(),
+				&head,
+			);
+			assert_eq!(verify_head(2001, HeadData(vec![0xbb; 2])), Ok(()));
+			let not_proven = Err(verify::VerifyError::ParaHead);
+			assert_eq!(verify_head(2001, HeadData(vec![0xbb; 3])), not_proven);
+			assert_eq!(verify_head(2000, HeadData(vec![0xbb; 2])), not_proven);
+		});
+	}
+
+	#[test]
+	fn chain_leaves_are_verified() {
+		chain_of(20).execute_with(|| {
+			for leaf_index in 0..20 {
+				let (leaf, proof) = generate_proof::<Test>(leaf_index).unwrap();
+				let root = Mmr::mmr_root_hash();
+				assert_eq!(verify::verify_leaf_proof(root, &leaf.encode(), proof), Ok(()));
+			}
+		});
+	}
+
+	#[test]
+	fn mmr_positions_work() {
+		let leaves = (0..11).filter(|pos| is_leaf(*pos)).collect::<Vec<_>>();
+		assert_eq!(leaves, vec![0, 1, 3, 4, 7, 8, 10]);
+		assert_eq!(mmr_size(7), 11);
+		assert_eq!(mmr_peaks(7), vec![(6, 2), (9, 1), (10, 0)]);
+		assert_eq!(mmr_peaks(0), vec![]);
+	}
+
+	#[test]
+	fn offchain_nodes_are_pruned_outside_retention_window() {
+		chain_of(300).execute_with(|| {
+			let proofs_before = (0..300)
+				.map(|leaf| generate_proof::<Test>(leaf).unwrap().1.encode())
+				.collect::<Vec<_>>();
+			BeefyMmr::offchain_worker(300);
+			assert_eq!(pruned_leaves::<Test>(), 250);
+
+			// the nodes above the first two leaves are pruned, but the leaves and peaks are kept.
+			let node_exists = |pos: u64| offchain_node_hash::<Test>(pos).is_some();
+			assert!(!node_exists(2));
+			assert!(node_exists(0) && node_exists(1));
+			assert!(mmr_peaks(250).into_iter().all(|(pos, _)| node_exists(pos)));
+			assert!((mmr_size(250)..mmr_size(300)).all(node_exists));
+
+			// proofs of leaves in the window need no pruned nodes.
+			for leaf in 250..300 {
+				let proof = generate_proof::<Test>(leaf).unwrap().1;
+				assert_eq!(proof.encode(), proofs_before[leaf as usize]);
+			}
+			assert!(!node_exists(2));
+
+			// older ones need pruned nodes, but for the two leaves below the lowest pruned peak,
+			// which are each other's sibling.
+			let root = Mmr::mmr_root_hash();
+			for leaf in 0..250 {
+				if leaf < 248 {
+					assert_eq!(generate_proof::<Test>(leaf), Err(ProofError::Pruned));
+					continue;
+				}
+				let (data, proof) = generate_proof::<Test>(leaf).unwrap();
+				assert_eq!(proof.encode(), proofs_before[leaf as usize]);
+				assert_eq!(verify::verify_leaf_proof(root, &data.encode(), proof), Ok(()));
+			}
+			assert_eq!(generate_batch_proof::<Test>(vec![42, 260]), Err(BatchProofError::Pruned));
+			assert!(generate_batch_proof::<Test>(vec![248, 299]).is_ok());
+
+			// proof generation does not restore any nodes.
+			assert!(!node_exists(2));
+
+			// running again does not prune anything more.
+			BeefyMmr::offchain_worker(300);
+			assert_eq!(pruned_leaves::<Test>(), 250);
+		});
+	}
+
+	#[test]
+	fn pruning_catches_up_in_bounded_runs() {
+		chain_of(300).execute_with(|| {
+			assert_eq!(pruned_leaves::<Test>(), 0);
+			prune_offchain_nodes::<Test>(50);
+			assert_eq!(pruned_leaves::<Test>(), 250);
+			// a smaller window prunes more, and a larger one never un-prunes.
+			prune_offchain_nodes::<Test>(10);
+			assert_eq!(pruned_leaves::<Test>(), 290);
+			prune_offchain_nodes::<Test>(100);
+			assert_eq!(pruned_leaves::<Test>(), 290);
+			assert_eq!(generate_proof::<Test>(100), Err(ProofError::Pruned));
+			assert!(generate_proof::<Test>(295).is_ok());
+		});
+	}
+
+	#[test]
+	fn leaf_versions_work() {
+		let version = MmrLeafVersion::new(1, 3);
+		assert_eq!((version.major(), version.minor()), (1, 3));
+		assert_eq!(version.encode(), vec![0b001_00011]);
+		assert_eq!(MmrLeafVersion::new(7, 31).encode(), vec![0xff]);
+		// out of range numbers are truncated.
+		assert_eq!(MmrLeafVersion::new(8, 32), MmrLeafVersion::new(0, 0));
+
+		let leaf = MmrLeaf {
+			parent_number_and_hash: (42u32, H256::repeat_byte(1)),
+			parachain_heads: H256::repeat_byte(2),
+			beefy_next_authority_set: BeefyNextAuthoritySet { id: 3, len: 4, root: H256::repeat_byte(5) },
+		};
+		type Leaf = VersionedLeaf<u32, H256, H256>;
+
+		// the original format is encoded as the unversioned leaf.
+		let v0 = leaf.clone().into_versioned(MmrLeafVersion::new(0, 0));
+		assert_eq!(v0.encode(), leaf.encode());
+		assert_eq!(Leaf::decode(&mut &leaf.encode()[..]).unwrap(), v0);
+
+		// the extended format is prefixed by its version.
+		let mut v1 = leaf.clone().into_versioned(version);
+		assert_eq!(v1.version(), version);
+		assert_eq!(v1.encode(), [&[0b001_00011][..], &leaf.encode(), &[0]].concat());
+		assert_eq!(Leaf::decode(&mut &v1.encode()[..]).unwrap(), v1);
+		if let VersionedLeaf::V1(extended) = &mut v1 {
+			extended.leaf_extra = vec![1, 2, 3];
+		}
+		assert_eq!(Leaf::decode(&mut &v1.encode()[..]).unwrap(), v1);
+		assert_eq!(v1.into_leaf(), leaf);
+
+		// an original leaf starting with a major version 1 byte still decodes as such.
+		let mut leaf = leaf;
+		leaf.parent_number_and_hash.0 = 0b001_00000;
+		assert_eq!(Leaf::decode(&mut &leaf.encode()[..]).unwrap(), VersionedLeaf::V0(leaf.clone()));
+		let mut trailing = leaf.encode();
+		trailing.push(0);
+		assert!(Leaf::decode(&mut &trailing[..]).is_err());
+	}
+
+	#[test]
+	fn original_leaves_are_proven_after_version_bump() {
+		let bump = 150;
+		let old_index = 42;
+		let old_leaf = chain_of(bump - 1).execute_with(|| {
+			generate_batch_proof::<Test>(vec![old_index]).unwrap().0[0].encode()
+		});
+
+		let version = MmrLeafVersion::new(1, 0);
+		chain_with_versions(300, |n| if n < bump { MmrLeafVersion::new(0, 0) } else { version })
+			.execute_with(|| {
+				let (leaves, proof) = generate_batch_proof::<Test>(vec![old_index, 200]).unwrap();
+				assert_eq!(leaves[0].version(), MmrLeafVersion::new(0, 0));
+				assert_eq!(leaves[1].version(), version);
+				assert_eq!(leaves[0].encode(), old_leaf);
+
+				// the bytes of the leaf from before the bump prove it.
+				let old_leaf = VersionedLeaf::decode(&mut &old_leaf[..]).unwrap();
+				let leaves = vec![old_leaf, leaves[1].clone()];
+				let root = Mmr::mmr_root_hash();
+				assert_eq!(verify_batch_proof::<Keccak256, _>(root, leaves, proof), Ok(()));
+			});
+	}
+
+	#[test]
+	fn leaf_version_switches_the_merkle_roots() {
+		let version = MmrLeafVersion::new(1, 0);
+		chain_with_versions(4, |n| if n < 3 { MmrLeafVersion::new(0, 0) } else { version })
+			.execute_with(|| {
+				let (leaves, _) = generate_batch_proof::<Test>(vec![1, 2]).unwrap();
+				let trie_root = sp_io::trie::keccak_256_ordered_root(vec![]);
+				assert_eq!(leaves[0].leaf().parachain_heads, trie_root);
+				assert_eq!(leaves[0].leaf().beefy_next_authority_set.root, trie_root);
+
+				// the cached BEEFY root is recomputed for the new version within the same set.
+				assert_eq!(leaves[1].leaf().beefy_next_authority_set.id, 1);
+				assert_eq!(leaves[1].leaf().parachain_heads, H256::zero());
+				assert_eq!(leaves[1].leaf().beefy_next_authority_set.root, H256::zero());
+				assert_eq!(BeefyNextAuthoritiesMajorVersion::<Test>::get(), 1);
+
+				let next_leaf = Mmr::mmr_leaves();
+				assert_eq!(
+					BeefyMmr::para_head_proof(1.into(), next_leaf),
+					Err(ParaHeadProofError::UnknownPara),
+				);
+				LEAF_VERSION.with(|v| *v.borrow_mut() = MmrLeafVersion::new(0, 0));
+				assert_eq!(
+					BeefyMmr::para_head_proof(1.into(), next_leaf),
+					Err(ParaHeadProofError::UnsupportedLeafVersion),
+				);
+			});
+	}
+
+	#[test]
+	fn beefy_authorities_root_of_dummy_keys_is_pinned() {
+		use keyring::Sr25519Keyring;
+		sp_io::TestExternalities::default().execute_with(|| {
+			let ids = [Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie]
+				.iter()
+				.map(|who| crate::dummy_beefy_id_from_account_id(who.to_account_id()))
+				.collect::<Vec<_>>();
+			let keys = ids.iter().cloned().map(CompressedBeefyEcdsaKeys::convert).collect::<Vec<_>>();
+			assert_eq!(keys[0], hex_literal::hex!["0360a39c81f674a9434aed8a99f289ec601dab5f90b2beff0710c749eef0494e3f"]);
+			assert_eq!(
+				beefy_authorities_merkle_root(&keys),
+				hex_literal::hex!["bbede3252301d01caed5a66d62510ade107d1d04e645b049c68f8d4b042e0caa"].into(),
+			);
+
+			// dummy keys are valid secp256k1 keys, so they can be uncompressed too.
+			assert!(ids.into_iter().map(UncompressBeefyEcdsaKeys::convert).all(|key| key.len() == 65));
+			assert_eq!(beefy_authorities_merkle_root(&[]), H256::zero());
+		});
+	}
+
+	fn heads(ids: &[u32]) -> Vec<(ParaId, HeadData)> {
+		ids.iter().map(|&id| (ParaId::from(id), HeadData(vec![id as u8; id as usize]))).collect()
+	}
+
+	#[test]
+	fn para_head_proofs_round_trip() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			for count in 1..=9 {
+				let ids = (0..count).map(|i| 100 + i * 3).collect::<Vec<_>>();
+				let heads = heads(&ids);
+				let root = para_heads_merkle_root(&heads);
+
+				for (id, head) in &heads {
+					let proof = para_head_proof(&heads, *id).unwrap();
+					assert_eq!(&proof.head, head);
+					assert!(verify_para_head_proof(root, *id, &proof));
+
+					// another para cannot use the proof.
+					assert!(!verify_para_head_proof(root, ParaId::from(u32::from(*id) + 1), &proof));
+					// nor can a different head.
+					let mut forged = proof.clone();
+					forged.head.0.push(0);
+					assert!(!verify_para_head_proof(root, *id, &forged));
+				}
+			}
+		});
+	}
+
+	#[test]
+	fn para_head_proof_of_missing_para_fails() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let heads = heads(&[1, 2, 4, 5]);
+			let root = para_heads_merkle_root(&heads);
+			assert_eq!(para_head_proof(&heads, 3.into()), Err(ParaHeadProofError::UnknownPara));
+			assert_eq!(para_head_proof(&[], 3.into()), Err(ParaHeadProofError::UnknownPara));
+			assert_eq!(para_heads_merkle_root(&[]), H256::zero());
+
+			// the proof of a neighbour does not prove a missing para.
+			let mut proof = para_head_proof(&heads, 2.into()).unwrap();
+			assert!(!verify_para_head_proof(root, 3.into(), &proof));
+			proof.index = proof.count;
+			assert!(!verify_para_head_proof(root, 2.into(), &proof));
+		});
+	}
+
+	#[test]
+	fn para_head_proof_must_be_complete() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let heads = heads(&[1, 2, 3, 4, 5]);
+			let root = para_heads_merkle_root(&heads);
+			let proof = para_head_proof(&heads, 5.into()).unwrap();
+			// the last head of an odd count is carried up, so it is proven by a single sibling.
+			assert_eq!(proof.proof.len(), 1);
+			assert!(verify_para_head_proof(root, 5.into(), &proof));
+
+			let mut truncated = para_head_proof(&heads, 1.into()).unwrap();
+			truncated.proof.pop();
+			assert!(!verify_para_head_proof(root, 1.into(), &truncated));
+			let mut extended = para_head_proof(&heads, 1.into()).unwrap();
+			extended.proof.push(H256::zero());
+			assert!(!verify_para_head_proof(root, 1.into(), &extended));
+		});
+	}
+}
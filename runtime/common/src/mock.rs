@@ -21,7 +21,8 @@ use parity_scale_codec::{Encode, Decode};
 use sp_runtime::traits::SaturatedConversion;
 use frame_support::dispatch::{DispatchError, DispatchResult};
 use primitives::v1::{HeadData, ValidationCode, Id as ParaId};
-use crate::traits::Registrar;
+use xcm::opaque::{VersionedXcm, v0::{SendXcm, MultiLocation, Xcm, Result as XcmResult, Error as XcmError}};
+use crate::{traits::{BlockNumberProvider, Registrar}, xcm_sender::XcmHash};
 
 thread_local! {
 	static OPERATIONS: RefCell<Vec<(ParaId, u32, bool)>> = RefCell::new(Vec::new());
@@ -29,6 +30,10 @@ thread_local! {
 	static PARATHREADS: RefCell<Vec<ParaId>> = RefCell::new(Vec::new());
 	static LOCKS: RefCell<HashMap<ParaId, bool>> = RefCell::new(HashMap::new());
 	static MANAGERS: RefCell<HashMap<ParaId, Vec<u8>>> = RefCell::new(HashMap::new());
+	static SENT_XCM: RefCell<Vec<(MultiLocation, Xcm, XcmHash)>> = RefCell::new(Vec::new());
+	static SEND_ATTEMPTS: RefCell<u32> = RefCell::new(0);
+	static SEND_FAILURE: RefCell<Option<(u32, XcmError)>> = RefCell::new(None);
+	static BLOCK_NUMBER: RefCell<Option<u32>> = RefCell::new(None);
 }
 
 pub struct TestRegistrar<T>(sp_std::marker::PhantomData<T>);
@@ -200,3 +205,84 @@ impl<T: frame_system::Config> TestRegistrar<T> {
 		MANAGERS.with(|x| x.borrow_mut().clear());
 	}
 }
+
+/// Accepts every message, like a destination which can always be reached.
+pub struct XcmSink;
+
+impl SendXcm for XcmSink {
+	fn send_xcm(_: MultiLocation, _: Xcm) -> XcmResult {
+		Ok(())
+	}
+}
+
+/// Records every message `R` accepts.
+///
+/// `fail_nth_send` makes a chosen send fail before it reaches `R`.
+pub struct TestSendXcm<R = XcmSink>(sp_std::marker::PhantomData<R>);
+
+impl<R: SendXcm> SendXcm for TestSendXcm<R> {
+	fn send_xcm(dest: MultiLocation, msg: Xcm) -> XcmResult {
+		let attempt = SEND_ATTEMPTS.with(|x| {
+			let mut attempts = x.borrow_mut();
+			*attempts += 1;
+			*attempts
+		});
+		let failure = SEND_FAILURE.with(|x| match &*x.borrow() {
+			Some((n, error)) if *n == attempt => Some(error.clone()),
+			_ => None,
+		});
+		if let Some(error) = failure {
+			return Err(error);
+		}
+		let hash = sp_io::hashing::blake2_256(&VersionedXcm::from(msg.clone()).encode());
+		R::send_xcm(dest.clone(), msg.clone())?;
+		SENT_XCM.with(|x| x.borrow_mut().push((dest, msg, hash)));
+		Ok(())
+	}
+}
+
+impl<R> TestSendXcm<R> {
+	/// The messages sent so far, oldest first, with the hash of their encoding.
+	pub fn sent() -> Vec<(MultiLocation, Xcm, XcmHash)> {
+		SENT_XCM.with(|x| x.borrow().clone())
+	}
+
+	/// The destinations of the messages sent so far, oldest first.
+	pub fn sent_to() -> Vec<MultiLocation> {
+		SENT_XCM.with(|x| x.borrow().iter().map(|(dest, _, _)| dest.clone()).collect())
+	}
+
+	/// Make the `n`th send from now, counting from 1, fail with `error`.
+	pub fn fail_nth_send(n: u32, error: XcmError) {
+		let attempts = SEND_ATTEMPTS.with(|x| *x.borrow());
+		SEND_FAILURE.with(|x| *x.borrow_mut() = Some((attempts + n, error)));
+	}
+}
+
+/// A relay chain block number provider which can be set ahead of `frame_system`, so that tests can
+/// jump in time without running every block in between.
+///
+/// Follows the `frame_system` block number until `set` is called.
+pub struct MockBlockNumberProvider<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: frame_system::Config> BlockNumberProvider for MockBlockNumberProvider<T> {
+	type BlockNumber = T::BlockNumber;
+
+	fn current_block_number() -> Self::BlockNumber {
+		BLOCK_NUMBER.with(|x| *x.borrow())
+			.map(Into::into)
+			.unwrap_or_else(frame_system::Pallet::<T>::block_number)
+	}
+}
+
+impl<T: frame_system::Config> MockBlockNumberProvider<T> {
+	/// Report `n` as the current relay chain block number.
+	pub fn set(n: u32) {
+		BLOCK_NUMBER.with(|x| *x.borrow_mut() = Some(n));
+	}
+
+	/// Follow the `frame_system` block number again.
+	pub fn clear() {
+		BLOCK_NUMBER.with(|x| *x.borrow_mut() = None);
+	}
+}
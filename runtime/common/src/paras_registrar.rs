@@ -21,7 +21,7 @@ use sp_std::{prelude::*, result};
 use frame_support::{
 	ensure,
 	dispatch::DispatchResult,
-	traits::{Get, Currency, ReservableCurrency},
+	traits::{Get, Currency, EnsureOrigin, ReservableCurrency},
 	pallet_prelude::Weight,
 };
 use frame_system::{self, ensure_root, ensure_signed};
@@ -34,11 +34,10 @@ use runtime_parachains::{
 		ParaGenesisArgs,
 	},
 	configuration,
-	ensure_parachain,
 	Origin, ParaLifecycle,
 };
 
-use crate::traits::{Registrar, OnSwap};
+use crate::traits::{Registrar, OnSwap, EnsureAnyChildParachain};
 use parity_scale_codec::{Encode, Decode};
 use sp_runtime::{RuntimeDebug, traits::{Saturating, CheckedSub}};
 pub use pallet::*;
@@ -93,7 +92,8 @@ pub mod pallet {
 		/// infallibly convert between this origin and the system origin, but in reality, they're the
 		/// same type, we just can't express that to the Rust type system without writing a `where`
 		/// clause everywhere.
-		type Origin: From<<Self as frame_system::Config>::Origin>
+		type Origin: From<Origin>
+			+ From<<Self as frame_system::Config>::Origin>
 			+ Into<result::Result<Origin, <Self as Config>::Origin>>;
 
 		/// The system's currency for parathread payment.
@@ -417,7 +417,9 @@ impl<T: Config> Pallet<T> {
 		})
 		.or_else(|_| -> DispatchResult {
 			// Else check if para origin...
-			let caller_id = ensure_parachain(<T as Config>::Origin::from(origin.clone()))?;
+			let caller_id = EnsureAnyChildParachain::ensure_origin(
+				<T as Config>::Origin::from(origin.clone()),
+			)?;
 			ensure!(caller_id == id, Error::<T>::NotOwner);
 			Ok(())
 		}).or_else(|_| -> DispatchResult {
@@ -1000,6 +1002,9 @@ mod tests {
 
 			// Owner cannot call swap anymore
 			assert_noop!(Registrar::swap(Origin::signed(1), para_id, para_id + 2), BadOrigin);
+			// Nor can another para, but the locked para itself can.
+			assert_noop!(Registrar::swap(para_origin(para_id + 1), para_id, para_id + 2), BadOrigin);
+			assert_ok!(Registrar::swap(para_origin(para_id), para_id, para_id + 2));
 		});
 	}
 }
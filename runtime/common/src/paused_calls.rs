@@ -0,0 +1,356 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pallet to pause calls, either a single call or all calls of a pallet, without a runtime
+//! upgrade.
+//!
+//! Runtimes exclude the paused calls by adding `EverythingBut<PausedCalls<Runtime>>` to their
+//! `BaseCallFilter`. A paused call dispatched with a filtered origin fails with `BadOrigin`, as
+//! `frame_system` gives no other error for filtered calls. `Pallet::is_paused` tells whether a call
+//! is paused, for anything checking ahead of dispatch.
+
+use frame_support::pallet_prelude::*;
+use frame_support::traits::{CallMetadata, EnsureOrigin, Filter, GetCallMetadata, PalletInfo};
+use frame_system::pallet_prelude::*;
+use sp_std::{marker::PhantomData, prelude::*};
+pub use pallet::*;
+
+/// The name of a pallet, as given to `construct_runtime`.
+pub type PalletNameOf = Vec<u8>;
+/// The name of a call, as declared by its pallet.
+pub type CallNameOf = Vec<u8>;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type Event: From<Event> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The overarching call type, used to check that paused calls exist.
+		type Call: GetCallMetadata;
+
+		/// The origin allowed to pause and unpause calls.
+		type PauseOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event {
+		/// Calls were paused, either a single call or all of a pallet's. [pallet, call]
+		CallPaused(PalletNameOf, Option<CallNameOf>),
+		/// Calls were unpaused. [pallet, call]
+		CallUnpaused(PalletNameOf, Option<CallNameOf>),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The call, or all calls of the pallet, are not paused.
+		NotPaused,
+		/// The calls are already paused.
+		AlreadyPaused,
+		/// The runtime has no such pallet or call.
+		UnknownCall,
+		/// Calls of `System` and of this pallet cannot be paused.
+		Unpausable,
+	}
+
+	/// The paused calls, keyed by the pallet name and the call name. A key without a call name
+	/// pauses all calls of the pallet.
+	#[pallet::storage]
+	pub(super) type Paused<T> = StorageMap<
+		_,
+		Blake2_128Concat, (PalletNameOf, Option<CallNameOf>),
+		(),
+		OptionQuery,
+	>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Pause `call` of `pallet`, or all of its calls if `call` is `None`.
+		///
+		/// Origin must match the `PauseOrigin`.
+		#[pallet::weight((T::DbWeight::get().reads_writes(1, 1), DispatchClass::Operational))]
+		pub fn pause(
+			origin: OriginFor<T>,
+			pallet: PalletNameOf,
+			call: Option<CallNameOf>,
+		) -> DispatchResult {
+			T::PauseOrigin::ensure_origin(origin)?;
+			Self::ensure_pausable(&pallet, call.as_deref())?;
+			let key = (pallet, call);
+			ensure!(!Paused::<T>::contains_key(&key), Error::<T>::AlreadyPaused);
+
+			Paused::<T>::insert(&key, ());
+			Self::deposit_event(Event::CallPaused(key.0, key.1));
+			Ok(())
+		}
+
+		/// Unpause `call` of `pallet`, or all of its calls if `call` is `None`.
+		///
+		/// Unpausing all calls of a pallet leaves any of its single paused calls paused.
+		///
+		/// Origin must match the `PauseOrigin`.
+		#[pallet::weight((T::DbWeight::get().reads_writes(1, 1), DispatchClass::Operational))]
+		pub fn unpause(
+			origin: OriginFor<T>,
+			pallet: PalletNameOf,
+			call: Option<CallNameOf>,
+		) -> DispatchResult {
+			T::PauseOrigin::ensure_origin(origin)?;
+			let key = (pallet, call);
+			ensure!(Paused::<T>::contains_key(&key), Error::<T>::NotPaused);
+
+			Paused::<T>::remove(&key);
+			Self::deposit_event(Event::CallUnpaused(key.0, key.1));
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Whether the call described by `metadata` is paused, by itself or with its whole pallet.
+	pub fn is_paused(metadata: &CallMetadata) -> bool {
+		let pallet = metadata.pallet_name.as_bytes().to_vec();
+		Paused::<T>::contains_key(&(pallet.clone(), None)) ||
+			Paused::<T>::contains_key(&(pallet, Some(metadata.function_name.as_bytes().to_vec())))
+	}
+
+	fn ensure_pausable(pallet: &[u8], call: Option<&[u8]>) -> DispatchResult {
+		let pallet_name = <T as Config>::Call::get_module_names()
+			.iter()
+			.find(|name| name.as_bytes() == pallet)
+			.ok_or(Error::<T>::UnknownCall)?;
+		if let Some(call) = call {
+			ensure!(
+				<T as Config>::Call::get_call_names(pallet_name).iter().any(|name| name.as_bytes() == call),
+				Error::<T>::UnknownCall,
+			);
+		}
+
+		let unpausable = [
+			T::PalletInfo::name::<frame_system::Pallet<T>>(),
+			T::PalletInfo::name::<Pallet<T>>(),
+		];
+		ensure!(!unpausable.contains(&Some(*pallet_name)), Error::<T>::Unpausable);
+		Ok(())
+	}
+}
+
+/// Matches the calls paused in the pallet.
+pub struct PausedCalls<T>(PhantomData<T>);
+impl<T: Config, C: GetCallMetadata> Filter<C> for PausedCalls<T> {
+	fn filter(call: &C) -> bool {
+		Pallet::<T>::is_paused(&call.get_call_metadata())
+	}
+}
+
+/// Matches everything `F` does not match, so that `EverythingBut<PausedCalls<T>>` is a
+/// `BaseCallFilter` letting through all calls which are not paused.
+pub struct EverythingBut<F>(PhantomData<F>);
+impl<C, F: Filter<C>> Filter<C> for EverythingBut<F> {
+	fn filter(call: &C) -> bool {
+		!F::filter(call)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sp_core::H256;
+	use sp_runtime::{
+		traits::{BlakeTwo256, IdentityLookup, Dispatchable},
+		testing::Header,
+	};
+	use frame_support::{
+		assert_ok, assert_noop, parameter_types,
+		dispatch::DispatchError::BadOrigin,
+	};
+	use frame_system::EnsureRoot;
+	use crate::paused_calls;
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
+
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+			PausedCalls: paused_calls::{Pallet, Call, Storage, Event},
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u32 = 250;
+	}
+	impl frame_system::Config for Test {
+		type BaseCallFilter = EverythingBut<paused_calls::PausedCalls<Test>>;
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Call = Call;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<u64>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = pallet_balances::AccountData<u64>;
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 1;
+	}
+
+	impl pallet_balances::Config for Test {
+		type Balance = u64;
+		type Event = Event;
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type AccountStore = System;
+		type MaxLocks = ();
+		type MaxReserves = ();
+		type ReserveIdentifier = [u8; 8];
+		type WeightInfo = ();
+	}
+
+	impl Config for Test {
+		type Event = Event;
+		type Call = Call;
+		type PauseOrigin = EnsureRoot<u64>;
+	}
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		pallet_balances::GenesisConfig::<Test> {
+			balances: vec![(1, 100), (2, 100)],
+		}.assimilate_storage(&mut t).unwrap();
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+
+	fn transfer() -> Call {
+		Call::Balances(pallet_balances::Call::transfer(2, 10))
+	}
+
+	fn transfer_keep_alive() -> Call {
+		Call::Balances(pallet_balances::Call::transfer_keep_alive(2, 10))
+	}
+
+	fn pause(pallet: &str, call: Option<&str>) -> DispatchResult {
+		PausedCalls::pause(
+			Origin::root(),
+			pallet.as_bytes().to_vec(),
+			call.map(|call| call.as_bytes().to_vec()),
+		)
+	}
+
+	fn unpause(pallet: &str, call: Option<&str>) -> DispatchResult {
+		PausedCalls::unpause(
+			Origin::root(),
+			pallet.as_bytes().to_vec(),
+			call.map(|call| call.as_bytes().to_vec()),
+		)
+	}
+
+	fn last_event() -> Event {
+		System::events().pop().expect("an event was deposited").event
+	}
+
+	#[test]
+	fn pausing_a_single_call_works() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(pause("Balances", Some("transfer")));
+			assert_eq!(
+				last_event(),
+				Event::PausedCalls(paused_calls::Event::CallPaused(b"Balances".to_vec(), Some(b"transfer".to_vec()))),
+			);
+
+			assert_noop!(transfer().dispatch(Origin::signed(1)), BadOrigin);
+			assert!(PausedCalls::is_paused(&transfer().get_call_metadata()));
+			// Other calls of the pallet are unaffected.
+			assert!(!PausedCalls::is_paused(&transfer_keep_alive().get_call_metadata()));
+			assert_ok!(transfer_keep_alive().dispatch(Origin::signed(1)));
+
+			assert_ok!(unpause("Balances", Some("transfer")));
+			assert_eq!(
+				last_event(),
+				Event::PausedCalls(paused_calls::Event::CallUnpaused(b"Balances".to_vec(), Some(b"transfer".to_vec()))),
+			);
+			assert_ok!(transfer().dispatch(Origin::signed(1)));
+			assert_eq!(Balances::free_balance(2), 120);
+		});
+	}
+
+	#[test]
+	fn pausing_a_whole_pallet_works() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(pause("Balances", None));
+			assert_noop!(transfer().dispatch(Origin::signed(1)), BadOrigin);
+			assert_noop!(transfer_keep_alive().dispatch(Origin::signed(1)), BadOrigin);
+			assert_noop!(pause("Balances", None), Error::<Test>::AlreadyPaused);
+
+			// A single call paused on its own stays paused when the pallet is unpaused.
+			assert_ok!(pause("Balances", Some("transfer")));
+			assert_ok!(unpause("Balances", None));
+			assert_noop!(transfer().dispatch(Origin::signed(1)), BadOrigin);
+			assert_ok!(transfer_keep_alive().dispatch(Origin::signed(1)));
+			assert_noop!(unpause("Balances", None), Error::<Test>::NotPaused);
+		});
+	}
+
+	#[test]
+	fn only_known_and_pausable_calls_can_be_paused() {
+		new_test_ext().execute_with(|| {
+			assert_noop!(
+				PausedCalls::pause(Origin::signed(1), b"Balances".to_vec(), None),
+				BadOrigin,
+			);
+			assert_noop!(pause("Treasury", None), Error::<Test>::UnknownCall);
+			assert_noop!(pause("Balances", Some("teleport")), Error::<Test>::UnknownCall);
+			assert_noop!(pause("System", None), Error::<Test>::Unpausable);
+			assert_noop!(pause("System", Some("remark")), Error::<Test>::Unpausable);
+			assert_noop!(pause("PausedCalls", None), Error::<Test>::Unpausable);
+			assert_noop!(pause("PausedCalls", Some("unpause")), Error::<Test>::Unpausable);
+		});
+	}
+}
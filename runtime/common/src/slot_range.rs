@@ -17,6 +17,12 @@
 //! The SlotRange struct which succinctly handles the 36 values that
 //! represent all sub ranges between 0 and 7 inclusive.
 
+use sp_std::{convert::{TryFrom, TryInto}, ops::{Index, IndexMut}};
+use sp_runtime::{RuntimeDebug, traits::CheckedSub};
+use parity_scale_codec::{Encode, Decode};
+use primitives::v1::Id as ParaId;
+use static_assertions::const_assert_eq;
+
 slot_range_helper::generate_slot_range!(Zero(0), One(1), Two(2), Three(3), Four(4), Five(5), Six(6), Seven(7));
 
 // Will generate:
@@ -58,3 +64,217 @@ slot_range_helper::generate_slot_range!(Zero(0), One(1), Two(2), Three(3), Four(
 // 	SixSeven,		34
 // 	SevenSeven,		35
 // }
+
+// The index mapping below relies on the variants being numbered contiguously from zero.
+const_assert_eq!(SlotRange::SevenSeven as usize + 1, SLOT_RANGE_COUNT);
+const_assert_eq!(SLOT_RANGE_COUNT, LEASE_PERIODS_PER_SLOT * (LEASE_PERIODS_PER_SLOT + 1) / 2);
+
+/// The reasons for which a pair of lease periods cannot be turned into a `SlotRange`.
+#[derive(Copy, Clone, Eq, PartialEq, RuntimeDebug)]
+pub enum SlotRangeError {
+	/// The range begins before the first lease period it is relative to.
+	OutOfBounds,
+	/// The last lease period of the range is before its first lease period.
+	Inverted,
+	/// The range ends `LEASE_PERIODS_PER_SLOT` or more lease periods after the first lease period
+	/// it is relative to.
+	TooLong,
+}
+
+impl SlotRange {
+	/// Construct a `SlotRange` from the current lease period, the first lease period of the range,
+	/// and the last lease period of the range, reporting why the range is invalid if it is.
+	///
+	/// This is a checked version of `new_bounded`.
+	///
+	/// For example: `SlotRange::new_checked(1, 2, 3) == Ok(SlotRange::OneTwo)`.
+	pub fn new_checked<Index: CheckedSub + Copy + Ord + TryInto<u32>>(
+		current: Index,
+		first: Index,
+		last: Index,
+	) -> Result<Self, SlotRangeError> {
+		if first > last {
+			return Err(SlotRangeError::Inverted)
+		}
+		let first: u32 = first.checked_sub(&current)
+			.ok_or(SlotRangeError::OutOfBounds)?
+			.try_into()
+			.map_err(|_| SlotRangeError::TooLong)?;
+		let last: u32 = last.checked_sub(&current)
+			.ok_or(SlotRangeError::OutOfBounds)?
+			.try_into()
+			.map_err(|_| SlotRangeError::TooLong)?;
+		Self::try_from((first, last))
+	}
+
+	/// The index of this `SlotRange` in a table with one entry per `SlotRange`.
+	pub fn as_index(&self) -> usize {
+		*self as u8 as usize
+	}
+
+	/// The `SlotRange` stored at `index` in a table with one entry per `SlotRange`, if any.
+	pub fn from_index(index: usize) -> Option<Self> {
+		u8::try_from(index).ok().and_then(SlotRange::n)
+	}
+}
+
+/// Convert a pair of lease period offsets, relative to the first lease period of the slot, into a
+/// `SlotRange`.
+///
+/// For example: `SlotRange::try_from((1, 2)) == Ok(SlotRange::OneTwo)`.
+impl TryFrom<(u32, u32)> for SlotRange {
+	type Error = SlotRangeError;
+
+	fn try_from((first, last): (u32, u32)) -> Result<Self, SlotRangeError> {
+		if first > last {
+			return Err(SlotRangeError::Inverted)
+		}
+		if last >= LEASE_PERIODS_PER_SLOT as u32 {
+			return Err(SlotRangeError::TooLong)
+		}
+		(0..SLOT_RANGE_COUNT as u8)
+			.filter_map(SlotRange::n)
+			.find(|range| range.as_pair() == (first as u8, last as u8))
+			.ok_or(SlotRangeError::TooLong)
+	}
+}
+
+/// The winning bid for each `SlotRange`, indexed by the range itself.
+///
+/// This encodes exactly like the underlying array.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct WinnersTable<AccountId, Balance>([Option<(AccountId, ParaId, Balance)>; SLOT_RANGE_COUNT]);
+
+impl<AccountId, Balance> WinnersTable<AccountId, Balance> {
+	// A trick to allow initializing the table without `Copy` entries.
+	const EMPTY: Option<(AccountId, ParaId, Balance)> = None;
+
+	/// A table without any winners.
+	pub fn new() -> Self {
+		Self([Self::EMPTY; SLOT_RANGE_COUNT])
+	}
+
+	/// Iterate over the entries of every `SlotRange`, in index order.
+	pub fn iter(&self) -> impl Iterator<Item = &Option<(AccountId, ParaId, Balance)>> {
+		self.0.iter()
+	}
+
+	/// Iterate over the `SlotRange`s which have a winner together with that winner, in index order.
+	pub fn occupied(&self) -> impl Iterator<Item = (SlotRange, &(AccountId, ParaId, Balance))> {
+		self.0.iter()
+			.enumerate()
+			.filter_map(|(i, entry)| Some((SlotRange::from_index(i)?, entry.as_ref()?)))
+	}
+}
+
+impl<AccountId, Balance> Default for WinnersTable<AccountId, Balance> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<AccountId, Balance> Index<SlotRange> for WinnersTable<AccountId, Balance> {
+	type Output = Option<(AccountId, ParaId, Balance)>;
+
+	fn index(&self, range: SlotRange) -> &Self::Output {
+		&self.0[range.as_index()]
+	}
+}
+
+impl<AccountId, Balance> IndexMut<SlotRange> for WinnersTable<AccountId, Balance> {
+	fn index_mut(&mut self, range: SlotRange) -> &mut Self::Output {
+		&mut self.0[range.as_index()]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn try_from_pair_works() {
+		assert_eq!(SlotRange::try_from((0, 0)), Ok(SlotRange::ZeroZero));
+		assert_eq!(SlotRange::try_from((1, 2)), Ok(SlotRange::OneTwo));
+		assert_eq!(SlotRange::try_from((0, 7)), Ok(SlotRange::ZeroSeven));
+		assert_eq!(SlotRange::try_from((7, 7)), Ok(SlotRange::SevenSeven));
+		assert_eq!(SlotRange::try_from((2, 1)), Err(SlotRangeError::Inverted));
+		assert_eq!(SlotRange::try_from((0, 8)), Err(SlotRangeError::TooLong));
+		assert_eq!(SlotRange::try_from((8, 8)), Err(SlotRangeError::TooLong));
+	}
+
+	#[test]
+	fn try_from_pair_covers_all_ranges() {
+		for i in 0..SlotRange::SLOT_RANGE_COUNT as u8 {
+			let range = SlotRange::n(i).unwrap();
+			let (first, last) = range.as_pair();
+			assert_eq!(SlotRange::try_from((first as u32, last as u32)), Ok(range));
+		}
+	}
+
+	#[test]
+	fn index_mapping_works() {
+		assert_eq!(SlotRange::ZeroZero.as_index(), 0);
+		assert_eq!(SlotRange::ZeroSeven.as_index(), 7);
+		assert_eq!(SlotRange::OneOne.as_index(), 8);
+		assert_eq!(SlotRange::SevenSeven.as_index(), SlotRange::SLOT_RANGE_COUNT - 1);
+		for i in 0..SlotRange::SLOT_RANGE_COUNT {
+			assert_eq!(SlotRange::from_index(i).unwrap().as_index(), i);
+		}
+		assert_eq!(SlotRange::from_index(SlotRange::SLOT_RANGE_COUNT), None);
+		assert_eq!(SlotRange::from_index(256), None);
+	}
+
+	#[test]
+	fn winners_table_indexing_works() {
+		let mut table = WinnersTable::<u64, u64>::new();
+		assert_eq!(table.occupied().count(), 0);
+		table[SlotRange::OneTwo] = Some((1, 2.into(), 3));
+		table[SlotRange::ZeroZero] = Some((4, 5.into(), 6));
+		assert_eq!(table[SlotRange::OneTwo], Some((1, 2.into(), 3)));
+		assert_eq!(table[SlotRange::OneThree], None);
+		assert_eq!(
+			table.occupied().collect::<Vec<_>>(),
+			vec![(SlotRange::ZeroZero, &(4, 5.into(), 6)), (SlotRange::OneTwo, &(1, 2.into(), 3))],
+		);
+		assert_eq!(table.iter().count(), SlotRange::SLOT_RANGE_COUNT);
+	}
+
+	#[test]
+	fn winners_table_encoding_matches_array() {
+		let mut array: [Option<(u64, ParaId, u64)>; SlotRange::SLOT_RANGE_COUNT] = [None; SlotRange::SLOT_RANGE_COUNT];
+		array[SlotRange::ZeroZero as u8 as usize] = Some((1, 2.into(), 3));
+		array[SlotRange::SevenSeven as u8 as usize] = Some((4, 5.into(), 6));
+		let mut table = WinnersTable::new();
+		table[SlotRange::ZeroZero] = Some((1, 2.into(), 3));
+		table[SlotRange::SevenSeven] = Some((4, 5.into(), 6));
+
+		let encoded = table.encode();
+		assert_eq!(encoded, array.encode());
+		assert_eq!(WinnersTable::<u64, u64>::decode(&mut &array.encode()[..]).unwrap(), table);
+
+		// One byte per empty entry, and a `Some` byte followed by the fixed width entry otherwise.
+		let mut expected = vec![1u8];
+		expected.extend_from_slice(&1u64.to_le_bytes());
+		expected.extend_from_slice(&2u32.to_le_bytes());
+		expected.extend_from_slice(&3u64.to_le_bytes());
+		expected.extend(sp_std::iter::repeat(0u8).take(SlotRange::SLOT_RANGE_COUNT - 2));
+		expected.push(1u8);
+		expected.extend_from_slice(&4u64.to_le_bytes());
+		expected.extend_from_slice(&5u32.to_le_bytes());
+		expected.extend_from_slice(&6u64.to_le_bytes());
+		assert_eq!(encoded, expected);
+	}
+
+	#[test]
+	fn new_checked_works() {
+		assert_eq!(SlotRange::new_checked(0u32, 1u32, 2u32), Ok(SlotRange::OneTwo));
+		assert_eq!(SlotRange::new_checked(5u32, 6u32, 7u32), Ok(SlotRange::OneTwo));
+		assert_eq!(SlotRange::new_checked(10u32, 16u32, 17u32), Ok(SlotRange::SixSeven));
+		assert_eq!(SlotRange::new_checked(10u32, 6u32, 7u32), Err(SlotRangeError::OutOfBounds));
+		assert_eq!(SlotRange::new_checked(10u32, 9u32, 12u32), Err(SlotRangeError::OutOfBounds));
+		assert_eq!(SlotRange::new_checked(10u32, 11u32, 10u32), Err(SlotRangeError::Inverted));
+		assert_eq!(SlotRange::new_checked(10u32, 17u32, 18u32), Err(SlotRangeError::TooLong));
+		assert_eq!(SlotRange::new_checked(10u32, 20u32, 21u32), Err(SlotRangeError::TooLong));
+		assert_eq!(SlotRange::new_checked(0u64, 0u64, u64::max_value()), Err(SlotRangeError::TooLong));
+	}
+}
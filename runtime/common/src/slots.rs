@@ -29,7 +29,7 @@ use frame_support::{
 };
 use primitives::v1::Id as ParaId;
 use frame_system::{ensure_signed, ensure_root};
-use crate::traits::{Leaser, LeaseError, Registrar};
+use crate::traits::{BlockNumberProvider, Leaser, LeaseError, Registrar};
 
 type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 type LeasePeriodOf<T> = <T as frame_system::Config>::BlockNumber;
@@ -63,6 +63,9 @@ pub trait Config: frame_system::Config {
 	/// The number of blocks over which a single period lasts.
 	type LeasePeriod: Get<Self::BlockNumber>;
 
+	/// The source of the relay chain block number, which lease periods are counted in.
+	type BlockNumberProvider: BlockNumberProvider<BlockNumber = Self::BlockNumber>;
+
 	/// Weight Information for the Extrinsics in the Pallet
 	type WeightInfo: WeightInfo;
 }
@@ -124,11 +127,12 @@ decl_module! {
 
 		fn deposit_event() = default;
 
-		fn on_initialize(n: T::BlockNumber) -> Weight {
+		fn on_initialize(_n: T::BlockNumber) -> Weight {
 			// If we're beginning a new lease period then handle that.
+			let now = T::BlockNumberProvider::current_block_number();
 			let lease_period = T::LeasePeriod::get();
-			if (n % lease_period).is_zero() {
-				let lease_period_index = n / lease_period;
+			if (now % lease_period).is_zero() {
+				let lease_period_index = now / lease_period;
 				Self::manage_lease_period_start(lease_period_index)
 			} else {
 				0
@@ -419,7 +423,7 @@ impl<T: Config> Leaser for Module<T> {
 	}
 
 	fn lease_period_index() -> Self::LeasePeriod {
-		<frame_system::Pallet<T>>::block_number() / T::LeasePeriod::get()
+		T::BlockNumberProvider::current_block_number() / T::LeasePeriod::get()
 	}
 
 	fn already_leased(
@@ -472,7 +476,7 @@ mod tests {
 	};
 	use pallet_balances;
 	use primitives::v1::{BlockNumber, Header};
-	use crate::{slots, mock::TestRegistrar};
+	use crate::{slots, mock::{MockBlockNumberProvider, TestRegistrar}};
 
 	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 	type Block = frame_system::mocking::MockBlock<Test>;
@@ -544,6 +548,7 @@ mod tests {
 		type Currency = Balances;
 		type Registrar = TestRegistrar<Test>;
 		type LeasePeriod = LeasePeriod;
+		type BlockNumberProvider = MockBlockNumberProvider<Test>;
 		type WeightInfo = crate::slots::TestWeightInfo;
 	}
 
@@ -608,6 +613,38 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn lease_period_follows_block_number_provider() {
+		new_test_ext().execute_with(|| {
+			run_to_block(1);
+
+			assert_ok!(TestRegistrar::<Test>::register(1, ParaId::from(1), Default::default(), Default::default()));
+			assert_ok!(Slots::lease_out(1.into(), &1, 1, 1, 1));
+
+			// Jump to the start of the leased period without sealing any blocks.
+			MockBlockNumberProvider::<Test>::set(10);
+			assert_eq!(Slots::lease_period_index(), 1);
+			Slots::on_initialize(System::block_number());
+			assert_eq!(TestRegistrar::<Test>::parachains(), vec![ParaId::from(1)]);
+
+			MockBlockNumberProvider::<Test>::set(19);
+			Slots::on_initialize(System::block_number());
+			assert_eq!(Slots::deposit_held(1.into(), &1), 1);
+
+			// Crossing into the next lease period ends the lease, whatever `frame_system` says.
+			MockBlockNumberProvider::<Test>::set(20);
+			assert_eq!(Slots::lease_period_index(), 2);
+			Slots::on_initialize(System::block_number());
+			assert!(TestRegistrar::<Test>::parachains().is_empty());
+			assert_eq!(Slots::deposit_held(1.into(), &1), 0);
+			assert_eq!(Balances::reserved_balance(1), 0);
+			assert_eq!(System::block_number(), 1);
+
+			MockBlockNumberProvider::<Test>::clear();
+			assert_eq!(Slots::lease_period_index(), 0);
+		});
+	}
+
 	#[test]
 	fn lease_interrupted_lifecycle_works() {
 		new_test_ext().execute_with(|| {
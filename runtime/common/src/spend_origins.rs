@@ -0,0 +1,213 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Origins allowed to spend from the treasury, in tiers of council approval.
+//!
+//! Every tier also admits Root. A tier succeeds with the most the origin may spend, so that a
+//! spend can be checked against it.
+
+use frame_support::traits::{EnsureOrigin, Get};
+use frame_system::{EnsureOneOf, EnsureRoot};
+use pallet_collective::{EnsureMember, EnsureProportionAtLeast, EnsureProportionMoreThan};
+use primitives::v1::Balance;
+use sp_core::u32_trait::{_1, _2, _3, _4, _5};
+use sp_std::marker::PhantomData;
+
+/// Ensures `Inner`, succeeding with `Max` as the most the origin may spend.
+pub struct SpendTier<Inner, Max>(PhantomData<(Inner, Max)>);
+impl<O, Inner: EnsureOrigin<O>, Max: Get<Balance>> EnsureOrigin<O> for SpendTier<Inner, Max> {
+	type Success = Balance;
+
+	fn try_origin(o: O) -> Result<Self::Success, O> {
+		Inner::try_origin(o).map(|_| Max::get())
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> O {
+		Inner::successful_origin()
+	}
+}
+
+/// Root or any member of the collective `I` may tip up to `Max`.
+pub type SmallTipper<AccountId, I, Max> = SpendTier<
+	EnsureOneOf<AccountId, EnsureRoot<AccountId>, EnsureMember<AccountId, I>>,
+	Max,
+>;
+
+/// Root or more than half of the collective `I` may tip up to `Max`.
+pub type BigTipper<AccountId, I, Max> = SpendTier<
+	EnsureOneOf<AccountId, EnsureRoot<AccountId>, EnsureProportionMoreThan<_1, _2, AccountId, I>>,
+	Max,
+>;
+
+/// Root or at least three fifths of the collective `I` may spend up to `Max`.
+pub type SmallSpender<AccountId, I, Max> = SpendTier<
+	EnsureOneOf<AccountId, EnsureRoot<AccountId>, EnsureProportionAtLeast<_3, _5, AccountId, I>>,
+	Max,
+>;
+
+/// Root or at least two thirds of the collective `I` may spend up to `Max`.
+pub type MediumSpender<AccountId, I, Max> = SpendTier<
+	EnsureOneOf<AccountId, EnsureRoot<AccountId>, EnsureProportionAtLeast<_2, _3, AccountId, I>>,
+	Max,
+>;
+
+/// Root or at least three quarters of the collective `I` may spend up to `Max`.
+pub type BigSpender<AccountId, I, Max> = SpendTier<
+	EnsureOneOf<AccountId, EnsureRoot<AccountId>, EnsureProportionAtLeast<_3, _4, AccountId, I>>,
+	Max,
+>;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::parameter_types;
+	use pallet_collective::Instance1;
+	use sp_core::H256;
+	use sp_runtime::{
+		testing::Header,
+		traits::{BlakeTwo256, IdentityLookup},
+	};
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
+
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Council: pallet_collective::<Instance1>::{Pallet, Call, Storage, Origin<T>, Event<T>},
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u32 = 250;
+	}
+	impl frame_system::Config for Test {
+		type BaseCallFilter = frame_support::traits::AllowAll;
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Call = Call;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<u64>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	parameter_types! {
+		pub const MotionDuration: u64 = 10;
+		pub const MaxProposals: u32 = 10;
+		pub const MaxMembers: u32 = 10;
+		pub const Max: Balance = 1_000;
+	}
+	impl pallet_collective::Config<Instance1> for Test {
+		type Origin = Origin;
+		type Proposal = Call;
+		type Event = Event;
+		type MotionDuration = MotionDuration;
+		type MaxProposals = MaxProposals;
+		type MaxMembers = MaxMembers;
+		type DefaultVote = pallet_collective::PrimeDefaultVote;
+		type WeightInfo = ();
+	}
+
+	fn members(ayes: u32, of: u32) -> Origin {
+		pallet_collective::RawOrigin::<u64, Instance1>::Members(ayes, of).into()
+	}
+
+	fn member(who: u64) -> Origin {
+		pallet_collective::RawOrigin::<u64, Instance1>::Member(who).into()
+	}
+
+	fn accepts<Tier: EnsureOrigin<Origin, Success = Balance>>(origin: Origin) -> bool {
+		match Tier::try_origin(origin) {
+			Ok(cap) => {
+				assert_eq!(cap, Max::get());
+				true
+			},
+			Err(_) => false,
+		}
+	}
+
+	#[test]
+	fn every_tier_accepts_root_only_among_plain_origins() {
+		fn check<Tier: EnsureOrigin<Origin, Success = Balance>>() {
+			assert!(accepts::<Tier>(Origin::root()));
+			assert!(!accepts::<Tier>(Origin::signed(1)));
+			assert!(!accepts::<Tier>(Origin::none()));
+		}
+		check::<SmallTipper<u64, Instance1, Max>>();
+		check::<BigTipper<u64, Instance1, Max>>();
+		check::<SmallSpender<u64, Instance1, Max>>();
+		check::<MediumSpender<u64, Instance1, Max>>();
+		check::<BigSpender<u64, Instance1, Max>>();
+	}
+
+	#[test]
+	fn small_tipper_accepts_a_single_member() {
+		type Tier = SmallTipper<u64, Instance1, Max>;
+		assert!(accepts::<Tier>(member(1)));
+		// A collective decision is not a member.
+		assert!(!accepts::<Tier>(members(5, 5)));
+	}
+
+	#[test]
+	fn big_tipper_requires_more_than_half() {
+		type Tier = BigTipper<u64, Instance1, Max>;
+		assert!(accepts::<Tier>(members(3, 5)));
+		assert!(!accepts::<Tier>(members(2, 4)));
+		assert!(!accepts::<Tier>(member(1)));
+	}
+
+	#[test]
+	fn small_spender_requires_three_fifths() {
+		type Tier = SmallSpender<u64, Instance1, Max>;
+		assert!(accepts::<Tier>(members(3, 5)));
+		assert!(!accepts::<Tier>(members(4, 7)));
+	}
+
+	#[test]
+	fn medium_spender_requires_two_thirds() {
+		type Tier = MediumSpender<u64, Instance1, Max>;
+		assert!(accepts::<Tier>(members(2, 3)));
+		assert!(!accepts::<Tier>(members(3, 5)));
+	}
+
+	#[test]
+	fn big_spender_requires_three_quarters() {
+		type Tier = BigSpender<u64, Instance1, Max>;
+		assert!(accepts::<Tier>(members(3, 4)));
+		assert!(!accepts::<Tier>(members(2, 3)));
+	}
+}
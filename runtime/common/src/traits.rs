@@ -16,12 +16,16 @@
 
 //! Traits used across pallets for Polkadot.
 
-use sp_std::vec::*;
+use sp_std::{marker::PhantomData, vec::*};
 use primitives::v1::{HeadData, ValidationCode, Id as ParaId};
 use frame_support::{
 	dispatch::DispatchResult,
-	traits::{Currency, ReservableCurrency},
+	traits::{Currency, EnsureOrigin, Filter, Get, ReservableCurrency},
 };
+use runtime_parachains::Origin as ParachainOrigin;
+use xcm::v0::{Junction, MultiLocation};
+
+pub use sp_runtime::traits::BlockNumberProvider;
 
 /// Parachain registration API.
 pub trait Registrar {
@@ -246,3 +250,142 @@ pub trait OnSwap {
 	/// such as leases, deposits held and thread/chain nature are swapped.
 	fn on_swap(one: ParaId, other: ParaId);
 }
+
+/// Ensures that the origin is the child parachain `Id`, succeeding with its `ParaId`.
+///
+/// Calls sent by a child parachain over XCM carry this origin once converted with
+/// `ChildParachainAsNative`.
+pub struct EnsureParachainOrigin<Id>(PhantomData<Id>);
+impl<O, Id> EnsureOrigin<O> for EnsureParachainOrigin<Id> where
+	O: Into<Result<ParachainOrigin, O>> + From<ParachainOrigin>,
+	Id: Get<ParaId>,
+{
+	type Success = ParaId;
+
+	fn try_origin(o: O) -> Result<Self::Success, O> {
+		match o.into() {
+			Ok(ParachainOrigin::Parachain(id)) if id == Id::get() => Ok(id),
+			Ok(origin) => Err(O::from(origin)),
+			Err(o) => Err(o),
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> O {
+		O::from(ParachainOrigin::Parachain(Id::get()))
+	}
+}
+
+/// Ensures that the origin is any child parachain, succeeding with its `ParaId`.
+pub struct EnsureAnyChildParachain;
+impl<O> EnsureOrigin<O> for EnsureAnyChildParachain where
+	O: Into<Result<ParachainOrigin, O>> + From<ParachainOrigin>,
+{
+	type Success = ParaId;
+
+	fn try_origin(o: O) -> Result<Self::Success, O> {
+		match o.into() {
+			Ok(ParachainOrigin::Parachain(id)) => Ok(id),
+			Err(o) => Err(o),
+		}
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> O {
+		O::from(ParachainOrigin::Parachain(Default::default()))
+	}
+}
+
+/// Matches XCM locations which are exactly a child parachain, and not a location within one.
+///
+/// Meant for `pallet_xcm::EnsureXcm`, so that XCM origins are held to the same rule as
+/// `EnsureAnyChildParachain`.
+pub struct IsChildParachain;
+impl Filter<MultiLocation> for IsChildParachain {
+	fn filter(location: &MultiLocation) -> bool {
+		matches!(location, MultiLocation::X1(Junction::Parachain(_)))
+	}
+}
+
+/// Provides the current relay chain block number, for pallets on the relay chain itself.
+///
+/// Pallets which track relay chain time take a `BlockNumberProvider` rather than reading
+/// `frame_system` directly, so that they can be driven by another source in tests.
+pub struct RelayChainBlockNumberProvider<T>(PhantomData<T>);
+impl<T: frame_system::Config> BlockNumberProvider for RelayChainBlockNumberProvider<T> {
+	type BlockNumber = T::BlockNumber;
+
+	fn current_block_number() -> Self::BlockNumber {
+		frame_system::Pallet::<T>::block_number()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::parameter_types;
+	use xcm::v0::NetworkId;
+
+	/// A stand-in for a runtime's outer origin.
+	#[derive(Debug, PartialEq)]
+	enum MockOrigin {
+		Root,
+		Signed(u64),
+		Parachain(ParachainOrigin),
+	}
+
+	impl From<ParachainOrigin> for MockOrigin {
+		fn from(origin: ParachainOrigin) -> Self {
+			MockOrigin::Parachain(origin)
+		}
+	}
+
+	impl From<MockOrigin> for Result<ParachainOrigin, MockOrigin> {
+		fn from(origin: MockOrigin) -> Self {
+			match origin {
+				MockOrigin::Parachain(origin) => Ok(origin),
+				other => Err(other),
+			}
+		}
+	}
+
+	parameter_types! {
+		pub const OwnId: ParaId = ParaId::new(2000);
+	}
+
+	fn para(id: u32) -> MockOrigin {
+		MockOrigin::from(ParachainOrigin::Parachain(id.into()))
+	}
+
+	#[test]
+	fn ensure_parachain_origin_only_accepts_its_para() {
+		type Ensure = EnsureParachainOrigin<OwnId>;
+		assert_eq!(Ensure::try_origin(para(2000)), Ok(ParaId::new(2000)));
+		assert_eq!(Ensure::try_origin(para(2001)), Err(para(2001)));
+		assert_eq!(Ensure::try_origin(MockOrigin::Root), Err(MockOrigin::Root));
+		assert_eq!(Ensure::try_origin(MockOrigin::Signed(2000)), Err(MockOrigin::Signed(2000)));
+	}
+
+	#[test]
+	fn ensure_any_child_parachain_yields_para_id() {
+		assert_eq!(EnsureAnyChildParachain::try_origin(para(2000)), Ok(ParaId::new(2000)));
+		assert_eq!(EnsureAnyChildParachain::try_origin(para(3)), Ok(ParaId::new(3)));
+		assert_eq!(EnsureAnyChildParachain::try_origin(MockOrigin::Root), Err(MockOrigin::Root));
+		assert_eq!(EnsureAnyChildParachain::try_origin(MockOrigin::Signed(1)), Err(MockOrigin::Signed(1)));
+	}
+
+	#[test]
+	fn only_child_parachain_locations_are_matched() {
+		use Junction::*;
+		assert!(IsChildParachain::filter(&MultiLocation::X1(Parachain(2000))));
+
+		// Locations nested within a child parachain are not the parachain itself.
+		let account = AccountId32 { network: NetworkId::Any, id: [1; 32] };
+		assert!(!IsChildParachain::filter(&MultiLocation::X2(Parachain(2000), account)));
+		assert!(!IsChildParachain::filter(&MultiLocation::X2(Parachain(2000), Parachain(1))));
+		// Nor are the parachains of another consensus system, or the relay chain itself.
+		assert!(!IsChildParachain::filter(&MultiLocation::X2(Parent, Parachain(2000))));
+		assert!(!IsChildParachain::filter(&MultiLocation::X1(Parent)));
+		assert!(!IsChildParachain::filter(&MultiLocation::Null));
+	}
+}
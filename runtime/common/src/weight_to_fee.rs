@@ -0,0 +1,108 @@
+// Copyright 2021 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The weight to fee conversion shared by the relay chain runtimes.
+
+use frame_support::{
+	traits::Get,
+	weights::{Weight, WeightToFeeCoefficient, WeightToFeeCoefficients, WeightToFeePolynomial},
+};
+use primitives::v1::Balance;
+use smallvec::smallvec;
+use sp_runtime::Perbill;
+
+/// Handles converting a weight scalar to a fee value, based on the scale and granularity of the
+/// node's balance type.
+///
+/// The conversion is linear, with a weight of `BaseWeight` (usually `ExtrinsicBaseWeight`, the
+/// smallest non-zero weight) costing a tenth of `Cent`. A full block of `MAXIMUM_BLOCK_WEIGHT`
+/// thus costs a tenth of `Cent` for each `BaseWeight` it holds, which the relay chain runtimes
+/// keep at no more than 16 dollars. The tenth is taken in the denominator, so a `Cent` that is not
+/// a multiple of ten is not rounded down first.
+pub struct LinearWeightToFee<BaseWeight, Cent>(sp_std::marker::PhantomData<(BaseWeight, Cent)>);
+impl<BaseWeight, Cent> WeightToFeePolynomial for LinearWeightToFee<BaseWeight, Cent>
+where
+	BaseWeight: Get<Weight>,
+	Cent: Get<Balance>,
+{
+	type Balance = Balance;
+	fn polynomial() -> WeightToFeeCoefficients<Self::Balance> {
+		let p = Cent::get();
+		let q = 10 * Balance::from(BaseWeight::get());
+		smallvec![WeightToFeeCoefficient {
+			degree: 1,
+			negative: false,
+			coeff_frac: Perbill::from_rational(p % q, q),
+			coeff_integer: p / q,
+		}]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ExtrinsicBaseWeight, MAXIMUM_BLOCK_WEIGHT};
+	use frame_support::parameter_types;
+
+	parameter_types! {
+		pub const BaseWeight: Weight = 1_000;
+		// Not a multiple of ten.
+		pub const Cent: Balance = 33_333;
+		// The `FeeCent` of each relay chain runtime, its `CENTS`.
+		pub const PolkadotCent: Balance = 10_000_000_000 / 100;
+		pub const KusamaCent: Balance = 1_000_000_000_000 / 30_000;
+		pub const WestendCent: Balance = 1_000_000_000_000 / 100;
+		pub const RococoCent: Balance = 1_000_000_000_000 / 100;
+	}
+
+	/// The most a full block may cost, in cents: 16 dollars.
+	const MAX_FULL_BLOCK_FEE_CENTS: Balance = 16 * 100;
+
+	/// Check the fees of a runtime with `FeeCent` and the shared `ExtrinsicBaseWeight`.
+	fn check_runtime_fees<FeeCent: Get<Balance>>() {
+		type Fee<C> = LinearWeightToFee<ExtrinsicBaseWeight, C>;
+		let cent = FeeCent::get();
+		let millicent = cent / 1_000;
+
+		// The base extrinsic costs a tenth of a cent.
+		let base_fee = Fee::<FeeCent>::calc(&ExtrinsicBaseWeight::get());
+		let expected = cent / 10;
+		assert!(base_fee.max(expected) - base_fee.min(expected) < millicent, "base fee {}", base_fee);
+
+		let full_block_fee = Fee::<FeeCent>::calc(&MAXIMUM_BLOCK_WEIGHT);
+		assert!(
+			full_block_fee <= MAX_FULL_BLOCK_FEE_CENTS * cent + millicent,
+			"full block fee {}",
+			full_block_fee,
+		);
+	}
+
+	#[test]
+	fn runtime_fees_are_within_bounds() {
+		check_runtime_fees::<PolkadotCent>();
+		check_runtime_fees::<KusamaCent>();
+		check_runtime_fees::<WestendCent>();
+		check_runtime_fees::<RococoCent>();
+	}
+
+	#[test]
+	fn cent_is_not_rounded_before_division() {
+		let coefficient = &LinearWeightToFee::<BaseWeight, Cent>::polynomial()[0];
+		assert_eq!(coefficient.coeff_integer, 3);
+		assert_eq!(coefficient.coeff_frac, Perbill::from_rational(3_333u128, 10_000));
+		assert_eq!(LinearWeightToFee::<BaseWeight, Cent>::calc(&10_000), 33_333);
+	}
+}
@@ -16,28 +16,1292 @@
 
 //! Xcm sender for relay chain.
 
-use parity_scale_codec::Encode;
-use sp_std::marker::PhantomData;
-use xcm::opaque::{VersionedXcm, v0::{SendXcm, MultiLocation, Junction, Xcm, Result, Error}};
-use runtime_parachains::{configuration, dmp};
+use parity_scale_codec::{Encode, Decode};
+use sp_std::{marker::PhantomData, prelude::*, result};
+use sp_runtime::RuntimeDebug;
+use sp_io::hashing::blake2_256;
+use frame_support::{ensure, traits::{Contains, Get}, weights::Weight};
+use xcm::{Version as XcmVersion, LATEST_VERSION, opaque::{
+	VersionedXcm,
+	v0::{SendXcm, MultiLocation, MultiAsset, Junction, Xcm, Result as XcmResult, Error as XcmError},
+}};
+use runtime_parachains::{configuration, dmp, paras};
+use primitives::v1::Id as ParaId;
+pub use pallet::*;
+
+/// Something which can compute the price of delivering a downward message to a parachain.
+pub trait PriceForParachainDelivery {
+	/// The price, in units of the relay chain's native token, of delivering the encoded `message`
+	/// to `para`.
+	fn price_for_parachain_delivery(para: ParaId, message: &[u8]) -> u128;
+
+	/// Whether delivery to `para` is free whatever the message.
+	fn is_exempt(_para: ParaId) -> bool {
+		false
+	}
+}
+
+/// Delivery is free.
+impl PriceForParachainDelivery for () {
+	fn price_for_parachain_delivery(_: ParaId, _: &[u8]) -> u128 {
+		0
+	}
+}
+
+/// A delivery price of `Base` plus `PerByte` for every byte of the encoded message.
+pub struct SizeBasedPrice<Base, PerByte>(PhantomData<(Base, PerByte)>);
+
+impl<Base: Get<u128>, PerByte: Get<u128>> PriceForParachainDelivery for SizeBasedPrice<Base, PerByte> {
+	fn price_for_parachain_delivery(_: ParaId, message: &[u8]) -> u128 {
+		Base::get().saturating_add(PerByte::get().saturating_mul(message.len() as u128))
+	}
+}
+
+/// Delivery to paras in `Exempt` is free; any other delivery is priced by `P`.
+pub struct WithExemptions<Exempt, P>(PhantomData<(Exempt, P)>);
+
+impl<Exempt: Contains<ParaId>, P: PriceForParachainDelivery> PriceForParachainDelivery
+	for WithExemptions<Exempt, P>
+{
+	fn price_for_parachain_delivery(para: ParaId, message: &[u8]) -> u128 {
+		if Exempt::contains(&para) {
+			0
+		} else {
+			P::price_for_parachain_delivery(para, message)
+		}
+	}
+
+	fn is_exempt(para: ParaId) -> bool {
+		Exempt::contains(&para) || P::is_exempt(para)
+	}
+}
+
+/// Something which knows the versions of the XCM format paras have advertised support for.
+pub trait AdvertisedXcmVersion {
+	/// The version of the XCM format `para` expects its messages in, if known.
+	fn advertised_version(para: ParaId) -> Option<XcmVersion>;
+}
+
+/// Nothing is known, so every para is assumed to understand the latest version.
+impl AdvertisedXcmVersion for () {
+	fn advertised_version(_: ParaId) -> Option<XcmVersion> {
+		None
+	}
+}
+
+/// Something which knows which paras are currently registered.
+pub trait ParaInfoProvider {
+	/// Whether `para` is registered and able to receive downward messages.
+	fn is_registered(para: ParaId) -> bool;
+}
+
+/// Nothing is known, so every para is assumed to be registered.
+impl ParaInfoProvider for () {
+	fn is_registered(_: ParaId) -> bool {
+		true
+	}
+}
+
+/// Only paras which are neither onboarding nor offboarding are registered.
+impl<T: paras::Config> ParaInfoProvider for paras::Pallet<T> {
+	fn is_registered(para: ParaId) -> bool {
+		paras::Pallet::<T>::is_valid_para(para)
+	}
+}
+
+/// Somewhere to park downward messages while the queue of their destination is full.
+pub trait DownwardOverflow {
+	/// Whether new messages for `para` can't be enqueued right away.
+	fn is_full(para: ParaId) -> bool;
+
+	/// Park the encoded message `blob` for `para` until there is room in its queue.
+	fn park(para: ParaId, blob: Vec<u8>) -> result::Result<(), ()>;
+}
+
+/// Queues are never full, so nothing is ever parked.
+impl DownwardOverflow for () {
+	fn is_full(_: ParaId) -> bool {
+		false
+	}
+
+	fn park(_: ParaId, _: Vec<u8>) -> result::Result<(), ()> {
+		Err(())
+	}
+}
+
+/// Something which wants to know about the outcome of sending downward messages.
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+pub trait OnDownwardSend {
+	/// A message of `size` encoded bytes was enqueued for `para`.
+	fn on_sent(para: ParaId, size: u32);
+
+	/// A message could not be sent because of `error`.
+	fn on_failed(error: &XcmError);
+
+	/// `charged` was paid for delivering a message to `para` which only cost `actual`, and the
+	/// difference was refunded.
+	fn on_refunded(para: ParaId, charged: u128, actual: u128);
+}
+
+/// The kind of error with which a downward message failed to send.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum SendFailure {
+	/// The destination is not a parachain of this relay chain.
+	Unreachable,
+	/// The encoded message is larger than the maximum downward message size.
+	TooLarge,
+	/// The price of delivering the message was not covered.
+	TooExpensive,
+	/// Any other error.
+	Other,
+	/// The message cannot be expressed in a version of the XCM format the destination understands.
+	Unsupported,
+	/// The destination's downward message queue is full and the message could not be parked.
+	QueueFull,
+}
+
+impl From<&XcmError> for SendFailure {
+	fn from(error: &XcmError) -> Self {
+		match error {
+			XcmError::CannotReachDestination(..) => SendFailure::Unreachable,
+			XcmError::ExceedsMaxMessageSize => SendFailure::TooLarge,
+			XcmError::TooExpensive => SendFailure::TooExpensive,
+			XcmError::UnhandledXcmVersion => SendFailure::Unsupported,
+			XcmError::DestinationBufferOverflow => SendFailure::QueueFull,
+			_ => SendFailure::Other,
+		}
+	}
+}
+
+/// The number of downward messages sent to a para and their total encoded size.
+#[derive(Encode, Decode, Clone, Copy, Default, Eq, PartialEq, RuntimeDebug)]
+pub struct DeliveryStats {
+	/// The number of messages sent.
+	pub messages: u32,
+	/// The total encoded size of the messages sent, in bytes.
+	pub bytes: u64,
+}
+
+/// The hash of a downward message, as enqueued.
+pub type XcmHash = [u8; 32];
+
+/// A downward message which passed validation and is ready to be delivered.
+#[derive(Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct DeliveryTicket {
+	/// The parachain the message is addressed to.
+	pub para: ParaId,
+	/// The message, encoded as it will be enqueued.
+	pub blob: Vec<u8>,
+	/// The price of delivering the message, which is never less than its actual cost.
+	pub price: u128,
+}
+
+/// The given `amount` of the relay chain's native token.
+pub fn native_asset(amount: u128) -> MultiAsset {
+	MultiAsset::ConcreteFungible { id: MultiLocation::Null, amount }
+}
+
+/// The total amount of the relay chain's native token in `assets`.
+fn native_amount(assets: &[MultiAsset]) -> u128 {
+	assets.iter()
+		.filter_map(|asset| match asset {
+			MultiAsset::ConcreteFungible { id: MultiLocation::Null, amount } => Some(*amount),
+			_ => None,
+		})
+		.fold(0, u128::saturating_add)
+}
+
+/// The maximum size, in bytes, of an encoded downward message according to the active host
+/// configuration.
+///
+/// `ChildParachainRouter` refuses to send larger messages.
+pub fn max_downward_message_size<T: configuration::Config>() -> u32 {
+	<configuration::Module<T>>::config().max_downward_message_size
+}
 
 /// Xcm sender for relay chain. It only sends downward message.
-pub struct ChildParachainRouter<T>(PhantomData<T>);
-
-impl<T: configuration::Config + dmp::Config> SendXcm for ChildParachainRouter<T> {
-	fn send_xcm(dest: MultiLocation, msg: Xcm) -> Result {
-		match dest {
-			MultiLocation::X1(Junction::Parachain(id)) => {
-				// Downward message passing.
-				let config = <configuration::Module<T>>::config();
-				<dmp::Module<T>>::queue_downward_message(
-					&config,
-					id.into(),
-					VersionedXcm::from(msg).encode(),
-				).map_err(Into::<Error>::into)?;
-				Ok(())
-			}
-			d => Err(Error::CannotReachDestination(d, msg)),
+///
+/// Delivery is priced by `P`. Messages sent through `SendXcm` pay nothing, so they only go
+/// through when their delivery is free; paid deliveries go through `validate` and `deliver`.
+///
+/// `E` is told about every message sent and every failure to send one.
+///
+/// Messages are encoded in the version of the XCM format the destination advertised through `V`,
+/// or the latest version for destinations which advertised none.
+///
+/// Only immediate children of the relay chain which `R` knows to be registered can be reached.
+///
+/// Messages for a para whose queue `O` considers full are parked in `O` rather than enqueued.
+pub struct ChildParachainRouter<T, P = (), E = (), V = (), R = (), O = ()>(
+	PhantomData<(T, P, E, V, R, O)>,
+);
+
+impl<T, P, E, V, R, O> ChildParachainRouter<T, P, E, V, R, O> where
+	T: configuration::Config + dmp::Config,
+	P: PriceForParachainDelivery,
+	E: OnDownwardSend,
+	V: AdvertisedXcmVersion,
+	R: ParaInfoProvider,
+	O: DownwardOverflow,
+{
+	/// Check that `msg` can be sent to `dest`, returning the message ready for delivery together
+	/// with the assets required to pay for its delivery.
+	///
+	/// Fails with `CannotReachDestination` unless `dest` is `X1(Parachain(id))` for a registered
+	/// para, with `UnhandledXcmVersion` if the message cannot be expressed in the version of the
+	/// XCM format the destination advertised, and with `ExceedsMaxMessageSize` if the encoded
+	/// message is larger than `max_downward_message_size`.
+	pub fn validate(
+		dest: MultiLocation,
+		msg: Xcm,
+	) -> result::Result<(DeliveryTicket, Vec<MultiAsset>), XcmError> {
+		let para = match dest {
+			MultiLocation::X1(Junction::Parachain(id)) => ParaId::from(id),
+			d => return Err(XcmError::CannotReachDestination(d, msg)),
+		};
+		if !R::is_registered(para) {
+			return Err(XcmError::CannotReachDestination(dest, msg));
+		}
+		// A version unknown here can't be produced, which `into_version` reports.
+		let version = V::advertised_version(para).unwrap_or(LATEST_VERSION);
+		let msg = VersionedXcm::from(msg);
+		let written = msg.encode();
+		let blob = msg
+			.into_version(version)
+			.map_err(|()| XcmError::UnhandledXcmVersion)?
+			.encode();
+		ensure!(
+			blob.len() <= max_downward_message_size::<T>() as usize,
+			XcmError::ExceedsMaxMessageSize,
+		);
+		// The message is quoted as written, and may cost less once re-encoded for the destination;
+		// `deliver` refunds the difference.
+		let quoted = P::price_for_parachain_delivery(para, &written);
+		let price = quoted.max(P::price_for_parachain_delivery(para, &blob));
+		let required = if price == 0 { Vec::new() } else { vec![native_asset(price)] };
+		Ok((DeliveryTicket { para, blob, price }, required))
+	}
+
+	/// Deliver a message previously checked by `validate`, returning the part of `paid` which
+	/// wasn't needed to cover the actual cost of delivering it. Nothing is refunded for
+	/// destinations which are exempt from paying for delivery.
+	///
+	/// Fails with `TooExpensive` if `paid` doesn't cover the price of the delivery and with
+	/// `DestinationBufferOverflow` if the destination's queue is full and the message can't be
+	/// parked.
+	pub fn deliver(
+		ticket: DeliveryTicket,
+		paid: &[MultiAsset],
+	) -> result::Result<Vec<MultiAsset>, XcmError> {
+		Self::do_deliver(ticket, paid).map_err(|e| {
+			E::on_failed(&e);
+			e
+		})
+	}
+
+	/// Send a batch of messages, none of which may require payment for delivery.
+	///
+	/// Every message is validated before any is delivered, so a bad message anywhere in the batch
+	/// aborts it before anything is enqueued. Returns the hash of each message as enqueued, or the
+	/// index of the first message which failed together with the error.
+	pub fn send_batch(
+		messages: Vec<(MultiLocation, Xcm)>,
+	) -> result::Result<Vec<XcmHash>, (usize, XcmError)> {
+		let tickets = messages.into_iter()
+			.enumerate()
+			.map(|(i, (dest, msg))| {
+				Self::validate(dest, msg)
+					.and_then(|(ticket, _)| {
+						ensure!(ticket.price == 0, XcmError::TooExpensive);
+						Ok(ticket)
+					})
+					.map_err(|e| {
+						E::on_failed(&e);
+						(i, e)
+					})
+			})
+			.collect::<result::Result<Vec<_>, _>>()?;
+		tickets.into_iter()
+			.enumerate()
+			.map(|(i, ticket)| {
+				let hash = blake2_256(&ticket.blob);
+				Self::deliver(ticket, &[]).map(|_| hash).map_err(|e| (i, e))
+			})
+			.collect()
+	}
+
+	fn do_deliver(
+		ticket: DeliveryTicket,
+		paid: &[MultiAsset],
+	) -> result::Result<Vec<MultiAsset>, XcmError> {
+		let charged = native_amount(paid);
+		ensure!(charged >= ticket.price, XcmError::TooExpensive);
+		let para = ticket.para;
+		let actual = if P::is_exempt(para) {
+			charged
+		} else {
+			P::price_for_parachain_delivery(para, &ticket.blob).min(charged)
+		};
+		if O::is_full(para) {
+			O::park(para, ticket.blob).map_err(|()| XcmError::DestinationBufferOverflow)?;
+		} else {
+			let size = ticket.blob.len() as u32;
+			// Downward message passing.
+			let config = <configuration::Module<T>>::config();
+			<dmp::Module<T>>::queue_downward_message(
+				&config,
+				para,
+				ticket.blob,
+			).map_err(Into::<XcmError>::into)?;
+			E::on_sent(para, size);
+		}
+		if actual == charged {
+			return Ok(Vec::new());
+		}
+		E::on_refunded(para, charged, actual);
+		Ok(vec![native_asset(charged - actual)])
+	}
+}
+
+impl<T, P, E, V, R, O> SendXcm for ChildParachainRouter<T, P, E, V, R, O> where
+	T: configuration::Config + dmp::Config,
+	P: PriceForParachainDelivery,
+	E: OnDownwardSend,
+	V: AdvertisedXcmVersion,
+	R: ParaInfoProvider,
+	O: DownwardOverflow,
+{
+	fn send_xcm(dest: MultiLocation, msg: Xcm) -> XcmResult {
+		let (ticket, _) = Self::validate(dest, msg).map_err(|e| {
+			E::on_failed(&e);
+			e
+		})?;
+		Self::deliver(ticket, &[]).map(|_| ())
+	}
+}
+
+/// A downward message waiting for room in the queue of its destination.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct ParkedMessage {
+	/// The message, encoded as it will be enqueued.
+	pub blob: Vec<u8>,
+	/// The number of blocks in which delivery was retried without success.
+	pub retries: u32,
+}
+
+/// A companion pallet to `ChildParachainRouter` which records the outcome of downward message
+/// sends. Use `Pallet<T>` as the router's `OnDownwardSend` to enable it.
+///
+/// Used as the router's `DownwardOverflow`, it also parks messages for paras whose queue is full
+/// and retries their delivery at the start of every block. Runtimes which would rather have such
+/// sends fail straight away can leave it out of the router, or set `MaxParked` to zero to keep the
+/// queue length limit without parking.
+///
+/// It also keeps a list of paras, managed by `ExemptOrigin`, to which delivery is free when
+/// `Pallet<T>` is used as the exemptions of `WithExemptions`.
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use super::*;
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + configuration::Config + dmp::Config {
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The number of messages a para's downward message queue may hold before further
+		/// messages for it are parked.
+		#[pallet::constant]
+		type MaxQueueLength: Get<u32>;
+
+		/// The number of messages which may be parked for each para.
+		#[pallet::constant]
+		type MaxParked: Get<u32>;
+
+		/// The number of blocks in which delivery of a parked message is retried before it is
+		/// dropped.
+		#[pallet::constant]
+		type MaxRetries: Get<u32>;
+
+		/// The number of paras whose parked messages are retried in a block, the others wait for
+		/// their turn in the next blocks.
+		#[pallet::constant]
+		type MaxDrainPerBlock: Get<u32>;
+
+		/// Told about the parked messages which are enqueued once there is room, usually the same
+		/// as the `E` of the router.
+		type OnParkedSent: OnDownwardSend;
+
+		/// The origin which may change the list of paras exempt from delivery fees.
+		type ExemptOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A downward message was enqueued. \[para, size\]
+		DownwardMessageSent(ParaId, u32),
+		/// A downward message could not be sent. \[failure\]
+		DownwardMessageFailed(SendFailure),
+		/// A downward message was parked until there is room in the queue. \[para, hash\]
+		DownwardMessageParked(ParaId, XcmHash),
+		/// A parked downward message was enqueued. \[para, hash\]
+		ParkedMessageDelivered(ParaId, XcmHash),
+		/// A parked downward message was dropped. \[para, hash\]
+		ParkedMessageDropped(ParaId, XcmHash),
+		/// Part of the fee paid for delivering a downward message was refunded.
+		/// \[para, charged, actual\]
+		DeliveryFeeRefunded(ParaId, u128, u128),
+		/// Delivery to a para became free. \[para\]
+		ExemptParaAdded(ParaId),
+		/// Delivery to a para is no longer free. \[para\]
+		ExemptParaRemoved(ParaId),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The para is already exempt from delivery fees.
+		AlreadyExempt,
+		/// The para is not exempt from delivery fees.
+		NotExempt,
+	}
+
+	/// The downward messages sent to each para so far.
+	#[pallet::storage]
+	#[pallet::getter(fn delivery_stats)]
+	pub type Delivered<T> = StorageMap<_, Twox64Concat, ParaId, DeliveryStats, ValueQuery>;
+
+	/// The number of downward messages which failed to send so far, by kind of failure.
+	#[pallet::storage]
+	#[pallet::getter(fn failures)]
+	pub type Failures<T> = StorageMap<_, Twox64Concat, SendFailure, u32, ValueQuery>;
+
+	/// The messages waiting for room in the downward message queue of each para, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn parked)]
+	pub type Parked<T> = StorageMap<_, Twox64Concat, ParaId, Vec<ParkedMessage>, ValueQuery>;
+
+	/// The paras with parked messages, in the order they get their turn to be retried.
+	#[pallet::storage]
+	pub type ParkedParas<T> = StorageValue<_, Vec<ParaId>, ValueQuery>;
+
+	/// The paras exempt from delivery fees.
+	#[pallet::storage]
+	pub type ExemptParas<T> = StorageMap<_, Twox64Concat, ParaId, ()>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+			Self::drain_parked()
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Make delivery to `para` free.
+		///
+		/// Must be called by `ExemptOrigin`.
+		#[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+		pub fn add_exempt_para(origin: OriginFor<T>, para: ParaId) -> DispatchResult {
+			T::ExemptOrigin::ensure_origin(origin)?;
+			ensure!(!ExemptParas::<T>::contains_key(para), Error::<T>::AlreadyExempt);
+			ExemptParas::<T>::insert(para, ());
+			Self::deposit_event(Event::<T>::ExemptParaAdded(para));
+			Ok(())
+		}
+
+		/// Make delivery to `para` priced again.
+		///
+		/// Must be called by `ExemptOrigin`.
+		#[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+		pub fn remove_exempt_para(origin: OriginFor<T>, para: ParaId) -> DispatchResult {
+			T::ExemptOrigin::ensure_origin(origin)?;
+			ensure!(ExemptParas::<T>::contains_key(para), Error::<T>::NotExempt);
+			ExemptParas::<T>::remove(para);
+			Self::deposit_event(Event::<T>::ExemptParaRemoved(para));
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Contains<ParaId> for Pallet<T> {
+	fn contains(para: &ParaId) -> bool {
+		ExemptParas::<T>::contains_key(para)
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Enqueue as many parked messages as there is room for, retrying the rest in the next block
+	/// unless they have run out of retries.
+	///
+	/// Only the parked messages of the next `MaxDrainPerBlock` paras in `ParkedParas` are retried,
+	/// paras with messages left over move to the back.
+	fn drain_parked() -> Weight {
+		let mut queue = ParkedParas::<T>::get();
+		if queue.is_empty() {
+			return T::DbWeight::get().reads(1)
+		}
+		let config = <configuration::Module<T>>::config();
+		let mut reads = 2;
+		let mut writes = 1;
+		let turns = queue.len().min(T::MaxDrainPerBlock::get() as usize);
+		let paras = queue.drain(..turns).collect::<Vec<_>>();
+		for para in paras {
+			reads += 2;
+			writes += 1;
+			let parked = Parked::<T>::take(para);
+			let room = T::MaxQueueLength::get()
+				.saturating_sub(<dmp::Module<T>>::dmq_length(para));
+			let mut remaining = Vec::new();
+			for (i, mut message) in parked.into_iter().enumerate() {
+				let hash = blake2_256(&message.blob);
+				if (i as u32) < room {
+					writes += 3;
+					let size = message.blob.len() as u32;
+					let sent = <dmp::Module<T>>::queue_downward_message(&config, para, message.blob);
+					let event = match sent {
+						Ok(()) => {
+							T::OnParkedSent::on_sent(para, size);
+							Event::<T>::ParkedMessageDelivered(para, hash)
+						}
+						// The host configuration changed since the message was parked.
+						Err(_) => Event::<T>::ParkedMessageDropped(para, hash),
+					};
+					Self::deposit_event(event);
+				} else if message.retries >= T::MaxRetries::get() {
+					Self::deposit_event(Event::<T>::ParkedMessageDropped(para, hash));
+				} else {
+					message.retries += 1;
+					remaining.push(message);
+				}
+			}
+			if !remaining.is_empty() {
+				Parked::<T>::insert(para, remaining);
+				queue.push(para);
+			}
+		}
+		ParkedParas::<T>::put(queue);
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+}
+
+impl<T: Config> DownwardOverflow for Pallet<T> {
+	fn is_full(para: ParaId) -> bool {
+		// Anything already parked must be delivered first.
+		Parked::<T>::contains_key(para)
+			|| <dmp::Module<T>>::dmq_length(para) >= T::MaxQueueLength::get()
+	}
+
+	fn park(para: ParaId, blob: Vec<u8>) -> result::Result<(), ()> {
+		let hash = blake2_256(&blob);
+		let first = Parked::<T>::try_mutate(para, |parked| {
+			ensure!((parked.len() as u32) < T::MaxParked::get(), ());
+			parked.push(ParkedMessage { blob, retries: 0 });
+			Ok(parked.len() == 1)
+		})?;
+		if first {
+			ParkedParas::<T>::append(para);
+		}
+		Self::deposit_event(Event::<T>::DownwardMessageParked(para, hash));
+		Ok(())
+	}
+}
+
+impl<T: Config> OnDownwardSend for Pallet<T> {
+	fn on_sent(para: ParaId, size: u32) {
+		Delivered::<T>::mutate(para, |stats| {
+			stats.messages = stats.messages.saturating_add(1);
+			stats.bytes = stats.bytes.saturating_add(size as u64);
+		});
+		Self::deposit_event(Event::<T>::DownwardMessageSent(para, size));
+	}
+
+	fn on_failed(error: &XcmError) {
+		let failure = SendFailure::from(error);
+		Failures::<T>::mutate(failure, |count| *count = count.saturating_add(1));
+		Self::deposit_event(Event::<T>::DownwardMessageFailed(failure));
+	}
+
+	fn on_refunded(para: ParaId, charged: u128, actual: u128) {
+		Self::deposit_event(Event::<T>::DeliveryFeeRefunded(para, charged, actual));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+	use sp_runtime::traits::{BlakeTwo256, IdentityLookup};
+	use frame_support::{assert_noop, assert_ok, parameter_types, traits::OnInitialize};
+	use primitives::v1::{BlockNumber, Header};
+	use runtime_parachains::runtime_api_impl::v1::dmq_contents;
+	use xcm::v0::OriginKind;
+	use crate::xcm_sender;
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+	type Block = frame_system::mocking::MockBlock<Test>;
+
+	frame_support::construct_runtime!(
+		pub enum Test where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Configuration: configuration::{Pallet, Call, Storage, Config<T>},
+			Dmp: dmp::{Pallet, Call, Storage},
+			XcmSender: xcm_sender::{Pallet, Call, Storage, Event<T>},
+		}
+	);
+
+	parameter_types! {
+		pub const BlockHashCount: u32 = 250;
+	}
+
+	impl frame_system::Config for Test {
+		type BaseCallFilter = frame_support::traits::AllowAll;
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Call = Call;
+		type Index = u64;
+		type BlockNumber = BlockNumber;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = ();
+		type OnSetCode = ();
+	}
+
+	impl runtime_parachains::shared::Config for Test { }
+
+	impl configuration::Config for Test { }
+
+	impl dmp::Config for Test { }
+
+	thread_local! {
+		static MAX_QUEUE_LENGTH: std::cell::RefCell<u32> = std::cell::RefCell::new(u32::max_value());
+	}
+
+	pub struct MaxQueueLength;
+	impl Get<u32> for MaxQueueLength {
+		fn get() -> u32 {
+			MAX_QUEUE_LENGTH.with(|v| *v.borrow())
+		}
+	}
+
+	fn set_max_queue_length(length: u32) {
+		MAX_QUEUE_LENGTH.with(|v| *v.borrow_mut() = length);
+	}
+
+	parameter_types! {
+		pub const MaxParked: u32 = 2;
+		pub const MaxRetries: u32 = 1;
+		pub const MaxDrainPerBlock: u32 = 2;
+	}
+
+	impl Config for Test {
+		type Event = Event;
+		type MaxQueueLength = MaxQueueLength;
+		type MaxParked = MaxParked;
+		type MaxRetries = MaxRetries;
+		type MaxDrainPerBlock = MaxDrainPerBlock;
+		type OnParkedSent = XcmSender;
+		type ExemptOrigin = frame_system::EnsureRoot<u64>;
+	}
+
+	parameter_types! {
+		pub const BaseDeliveryFee: u128 = 100;
+		pub const ByteDeliveryFee: u128 = 2;
+		pub const MaxFee: u128 = u128::max_value();
+	}
+
+	type Price = SizeBasedPrice<BaseDeliveryFee, ByteDeliveryFee>;
+	type FreeRouter = ChildParachainRouter<Test>;
+	type PricedRouter = ChildParachainRouter<Test, Price>;
+	type ObservedRouter = ChildParachainRouter<Test, Price, XcmSender>;
+
+	const MAX_DOWNWARD_MESSAGE_SIZE: u32 = 1024;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		configuration::GenesisConfig::<Test> {
+			config: configuration::HostConfiguration {
+				max_downward_message_size: MAX_DOWNWARD_MESSAGE_SIZE,
+				..Default::default()
+			},
+		}.assimilate_storage(&mut t).unwrap();
+		let mut ext: sp_io::TestExternalities = t.into();
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+
+	fn para(id: u32) -> MultiLocation {
+		MultiLocation::X1(Junction::Parachain(id))
+	}
+
+	fn message() -> Xcm {
+		Xcm::TransferAsset { assets: vec![native_asset(1)], dest: MultiLocation::Null }
+	}
+
+	/// A message whose encoding, as enqueued, is exactly `size` bytes long.
+	fn message_of_size(size: usize) -> Xcm {
+		let transact = |len| Xcm::Transact {
+			origin_type: OriginKind::Native,
+			require_weight_at_most: 0,
+			call: vec![0u8; len].into(),
+		};
+		let len = (0..size)
+			.find(|&len| VersionedXcm::from(transact(len)).encoded_size() >= size)
+			.unwrap();
+		let msg = transact(len);
+		assert_eq!(VersionedXcm::from(msg.clone()).encoded_size(), size);
+		msg
+	}
+
+	#[test]
+	fn size_based_price_works() {
+		assert_eq!(<() as PriceForParachainDelivery>::price_for_parachain_delivery(1.into(), &[0; 10]), 0);
+		assert_eq!(Price::price_for_parachain_delivery(1.into(), &[]), 100);
+		assert_eq!(Price::price_for_parachain_delivery(1.into(), &[0; 10]), 120);
+		assert_eq!(
+			SizeBasedPrice::<BaseDeliveryFee, MaxFee>::price_for_parachain_delivery(1.into(), &[0; 10]),
+			u128::max_value(),
+		);
+	}
+
+	#[test]
+	fn validate_reports_price() {
+		new_test_ext().execute_with(|| {
+			let (ticket, required) = FreeRouter::validate(para(1), message()).unwrap();
+			assert_eq!(ticket.price, 0);
+			assert!(required.is_empty());
+
+			let (ticket, required) = PricedRouter::validate(para(1), message()).unwrap();
+			let expected = 100 + 2 * VersionedXcm::from(message()).encode().len() as u128;
+			assert_eq!(ticket.para, 1.into());
+			assert_eq!(ticket.blob, VersionedXcm::from(message()).encode());
+			assert_eq!(ticket.price, expected);
+			assert_eq!(required, vec![native_asset(expected)]);
+
+			// Nothing is enqueued by validation.
+			assert!(dmq_contents::<Test>(1.into()).is_empty());
+		});
+	}
+
+	#[test]
+	fn validate_rejects_unreachable_destinations() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				PricedRouter::validate(MultiLocation::Null, message()),
+				Err(XcmError::CannotReachDestination(MultiLocation::Null, message())),
+			);
+		});
+	}
+
+	#[test]
+	fn deliver_requires_price_to_be_covered() {
+		new_test_ext().execute_with(|| {
+			let (ticket, required) = PricedRouter::validate(para(1), message()).unwrap();
+			let price = ticket.price;
+
+			assert_noop!(PricedRouter::deliver(ticket.clone(), &[]), XcmError::TooExpensive);
+			assert_noop!(
+				PricedRouter::deliver(ticket.clone(), &[native_asset(price - 1)]),
+				XcmError::TooExpensive,
+			);
+			// Only the native token counts towards the price.
+			let foreign = MultiAsset::ConcreteFungible { id: para(1), amount: price };
+			assert_noop!(PricedRouter::deliver(ticket.clone(), &[foreign]), XcmError::TooExpensive);
+
+			assert_eq!(PricedRouter::deliver(ticket.clone(), &required), Ok(vec![]));
+			assert_eq!(
+				PricedRouter::deliver(ticket, &[native_asset(price / 2), native_asset(price)]),
+				Ok(vec![native_asset(price / 2)]),
+			);
+
+			let queue = dmq_contents::<Test>(1.into());
+			assert_eq!(queue.len(), 2);
+			assert_eq!(queue[0].msg, VersionedXcm::from(message()).encode());
+		});
+	}
+
+	#[test]
+	fn send_xcm_only_sends_free_messages() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(FreeRouter::send_xcm(para(1), message()));
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 1);
+
+			assert_noop!(PricedRouter::send_xcm(para(2), message()), XcmError::TooExpensive);
+		});
+	}
+
+	#[test]
+	fn max_downward_message_size_is_enforced() {
+		new_test_ext().execute_with(|| {
+			let max = max_downward_message_size::<Test>() as usize;
+			assert_eq!(max, MAX_DOWNWARD_MESSAGE_SIZE as usize);
+
+			assert_ok!(FreeRouter::send_xcm(para(1), message_of_size(max)));
+			assert_noop!(
+				FreeRouter::send_xcm(para(1), message_of_size(max + 1)),
+				XcmError::ExceedsMaxMessageSize,
+			);
+			assert_noop!(
+				FreeRouter::send_xcm(para(1), message_of_size(4 * max)),
+				XcmError::ExceedsMaxMessageSize,
+			);
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 1);
+
+			// Oversized messages are rejected before being priced.
+			assert_eq!(
+				PricedRouter::validate(para(1), message_of_size(max + 1)),
+				Err(XcmError::ExceedsMaxMessageSize),
+			);
+		});
+	}
+
+	#[test]
+	fn sends_are_recorded() {
+		new_test_ext().execute_with(|| {
+			let size = VersionedXcm::from(message()).encoded_size() as u32;
+			let max = max_downward_message_size::<Test>() as usize;
+
+			// Paid deliveries.
+			for id in &[1, 1, 2] {
+				let (ticket, required) = ObservedRouter::validate(para(*id), message()).unwrap();
+				assert_ok!(ObservedRouter::deliver(ticket, &required));
+			}
+			// Unpaid delivery.
+			assert_eq!(ObservedRouter::send_xcm(para(3), message()), Err(XcmError::TooExpensive));
+			// Oversized message.
+			assert_eq!(
+				ObservedRouter::send_xcm(para(1), message_of_size(max + 1)),
+				Err(XcmError::ExceedsMaxMessageSize),
+			);
+			// Unreachable destination.
+			assert!(ObservedRouter::send_xcm(MultiLocation::Null, message()).is_err());
+
+			assert_eq!(XcmSender::delivery_stats(ParaId::from(1)), DeliveryStats { messages: 2, bytes: 2 * size as u64 });
+			assert_eq!(XcmSender::delivery_stats(ParaId::from(2)), DeliveryStats { messages: 1, bytes: size as u64 });
+			assert_eq!(XcmSender::delivery_stats(ParaId::from(3)), DeliveryStats::default());
+			assert_eq!(XcmSender::failures(SendFailure::TooExpensive), 1);
+			assert_eq!(XcmSender::failures(SendFailure::TooLarge), 1);
+			assert_eq!(XcmSender::failures(SendFailure::Unreachable), 1);
+			assert_eq!(XcmSender::failures(SendFailure::Other), 0);
+
+			let events = System::events().into_iter().map(|r| r.event).collect::<Vec<_>>();
+			assert_eq!(events, vec![
+				Event::XcmSender(xcm_sender::Event::DownwardMessageSent(1.into(), size)),
+				Event::XcmSender(xcm_sender::Event::DownwardMessageSent(1.into(), size)),
+				Event::XcmSender(xcm_sender::Event::DownwardMessageSent(2.into(), size)),
+				Event::XcmSender(xcm_sender::Event::DownwardMessageFailed(SendFailure::TooExpensive)),
+				Event::XcmSender(xcm_sender::Event::DownwardMessageFailed(SendFailure::TooLarge)),
+				Event::XcmSender(xcm_sender::Event::DownwardMessageFailed(SendFailure::Unreachable)),
+			]);
+		});
+	}
+
+	#[test]
+	fn failure_kinds_are_classified() {
+		assert_eq!(SendFailure::from(&XcmError::ExceedsMaxMessageSize), SendFailure::TooLarge);
+		assert_eq!(SendFailure::from(&XcmError::TooExpensive), SendFailure::TooExpensive);
+		assert_eq!(
+			SendFailure::from(&XcmError::CannotReachDestination(MultiLocation::Null, message())),
+			SendFailure::Unreachable,
+		);
+		assert_eq!(SendFailure::from(&XcmError::Undefined), SendFailure::Other);
+	}
+
+	thread_local! {
+		static ADVERTISED_VERSIONS: std::cell::RefCell<std::collections::HashMap<ParaId, XcmVersion>> =
+			Default::default();
+	}
+
+	pub struct TestAdvertisedVersions;
+	impl AdvertisedXcmVersion for TestAdvertisedVersions {
+		fn advertised_version(para: ParaId) -> Option<XcmVersion> {
+			ADVERTISED_VERSIONS.with(|v| v.borrow().get(&para).cloned())
+		}
+	}
+
+	fn advertise_version(para: ParaId, version: XcmVersion) {
+		ADVERTISED_VERSIONS.with(|v| v.borrow_mut().insert(para, version));
+	}
+
+	type VersionedRouter = ChildParachainRouter<Test, (), (), TestAdvertisedVersions>;
+
+	#[test]
+	fn messages_are_sent_in_advertised_version() {
+		new_test_ext().execute_with(|| {
+			advertise_version(1.into(), 0);
+			advertise_version(2.into(), LATEST_VERSION + 1);
+
+			for id in &[1, 3] {
+				let (ticket, _) = VersionedRouter::validate(para(*id), message()).unwrap();
+				let sent = VersionedXcm::decode(&mut &ticket.blob[..]).unwrap();
+				assert_eq!(sent.version(), 0);
+				assert_eq!(sent, VersionedXcm::from(message()));
+			}
+			// Versions newer than ours are rejected rather than downgraded.
+			assert!(matches!(
+				VersionedRouter::validate(para(2), message()),
+				Err(XcmError::UnhandledXcmVersion),
+			));
+		});
+	}
+
+	#[test]
+	fn unsupported_destinations_are_classified() {
+		assert_eq!(SendFailure::from(&XcmError::UnhandledXcmVersion), SendFailure::Unsupported);
+		assert_eq!(
+			VersionedXcm::from(message()).into_version(LATEST_VERSION + 1),
+			Err(()),
+		);
+	}
+
+	thread_local! {
+		static REGISTERED_PARAS: std::cell::RefCell<Vec<ParaId>> = std::cell::RefCell::new(vec![1.into()]);
+	}
+
+	pub struct TestParaInfo;
+	impl ParaInfoProvider for TestParaInfo {
+		fn is_registered(para: ParaId) -> bool {
+			REGISTERED_PARAS.with(|p| p.borrow().contains(&para))
 		}
 	}
+
+	type CheckedRouter = ChildParachainRouter<Test, (), (), (), TestParaInfo>;
+
+	#[test]
+	fn only_registered_children_can_be_reached() {
+		use Junction::{Parent, Parachain, AccountIndex64};
+		use xcm::v0::NetworkId;
+
+		new_test_ext().execute_with(|| {
+			assert!(CheckedRouter::validate(para(1), message()).is_ok());
+
+			let unreachable = vec![
+				// Sibling-style location, as seen from a parachain.
+				MultiLocation::X2(Parent, Parachain(1)),
+				// Something inside a para rather than the para itself.
+				MultiLocation::X2(Parachain(1), Parachain(2)),
+				MultiLocation::X2(Parachain(1), AccountIndex64 { network: NetworkId::Any, index: 1 }),
+				// Not registered.
+				para(2),
+			];
+			for dest in unreachable {
+				assert_eq!(
+					CheckedRouter::validate(dest.clone(), message()),
+					Err(XcmError::CannotReachDestination(dest.clone(), message())),
+				);
+				assert_eq!(
+					CheckedRouter::send_xcm(dest.clone(), message()),
+					Err(XcmError::CannotReachDestination(dest, message())),
+				);
+			}
+			assert!(dmq_contents::<Test>(2.into()).is_empty());
+
+			REGISTERED_PARAS.with(|p| p.borrow_mut().push(2.into()));
+			assert_ok!(CheckedRouter::send_xcm(para(2), message()));
+			assert_eq!(dmq_contents::<Test>(2.into()).len(), 1);
+		});
+	}
+
+	#[test]
+	fn send_batch_works() {
+		new_test_ext().execute_with(|| {
+			let hashes = FreeRouter::send_batch(vec![
+				(para(1), message()),
+				(para(2), message_of_size(100)),
+			]).unwrap();
+
+			let sent = [dmq_contents::<Test>(1.into()), dmq_contents::<Test>(2.into())];
+			assert_eq!(sent.iter().map(Vec::len).collect::<Vec<_>>(), vec![1, 1]);
+			assert_eq!(
+				hashes,
+				sent.iter().map(|queue| blake2_256(&queue[0].msg)).collect::<Vec<_>>(),
+			);
+		});
+	}
+
+	#[test]
+	fn send_batch_is_all_or_nothing() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				FreeRouter::send_batch(vec![
+					(para(1), message()),
+					(MultiLocation::Null, message()),
+					(para(1), message_of_size(MAX_DOWNWARD_MESSAGE_SIZE as usize + 1)),
+				]),
+				Err((1, XcmError::CannotReachDestination(MultiLocation::Null, message()))),
+			);
+			assert_eq!(
+				FreeRouter::send_batch(vec![
+					(para(1), message()),
+					(para(2), message()),
+					(para(1), message_of_size(MAX_DOWNWARD_MESSAGE_SIZE as usize + 1)),
+				]),
+				Err((2, XcmError::ExceedsMaxMessageSize)),
+			);
+			// The batch is unpaid, so priced deliveries are rejected up front.
+			assert_eq!(
+				PricedRouter::send_batch(vec![(para(1), message())]),
+				Err((0, XcmError::TooExpensive)),
+			);
+			assert!(dmq_contents::<Test>(1.into()).is_empty());
+			assert!(dmq_contents::<Test>(2.into()).is_empty());
+
+			assert_eq!(FreeRouter::send_batch(vec![]), Ok(vec![]));
+		});
+	}
+
+	type ParkingRouter = ChildParachainRouter<Test, (), (), (), (), XcmSender>;
+
+	fn parked_events() -> Vec<xcm_sender::Event<Test>> {
+		System::events().into_iter()
+			.filter_map(|r| match r.event {
+				Event::XcmSender(e @ xcm_sender::Event::DownwardMessageParked(..))
+				| Event::XcmSender(e @ xcm_sender::Event::ParkedMessageDelivered(..))
+				| Event::XcmSender(e @ xcm_sender::Event::ParkedMessageDropped(..)) => Some(e),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn messages_are_parked_when_queue_is_full() {
+		new_test_ext().execute_with(|| {
+			set_max_queue_length(1);
+			let hash = blake2_256(&VersionedXcm::from(message()).encode());
+
+			assert_ok!(ParkingRouter::send_xcm(para(1), message()));
+			assert_ok!(ParkingRouter::send_xcm(para(1), message()));
+			assert_ok!(ParkingRouter::send_xcm(para(1), message()));
+			assert_eq!(
+				ParkingRouter::send_xcm(para(1), message()),
+				Err(XcmError::DestinationBufferOverflow),
+			);
+			assert_eq!(SendFailure::from(&XcmError::DestinationBufferOverflow), SendFailure::QueueFull);
+			// Other paras are unaffected.
+			assert_ok!(ParkingRouter::send_xcm(para(2), message()));
+
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 1);
+			assert_eq!(dmq_contents::<Test>(2.into()).len(), 1);
+			assert_eq!(XcmSender::parked(ParaId::from(1)).len(), 2);
+			assert_eq!(parked_events(), vec![
+				xcm_sender::Event::DownwardMessageParked(1.into(), hash),
+				xcm_sender::Event::DownwardMessageParked(1.into(), hash),
+			]);
+		});
+	}
+
+	#[test]
+	fn parked_messages_are_retried() {
+		new_test_ext().execute_with(|| {
+			set_max_queue_length(1);
+			let hash = blake2_256(&VersionedXcm::from(message()).encode());
+			for _ in 0..3 {
+				assert_ok!(ParkingRouter::send_xcm(para(1), message()));
+			}
+			System::reset_events();
+
+			// Room for one more.
+			set_max_queue_length(2);
+			XcmSender::on_initialize(2);
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 2);
+			assert_eq!(
+				XcmSender::parked(ParaId::from(1)),
+				vec![ParkedMessage { blob: VersionedXcm::from(message()).encode(), retries: 1 }],
+			);
+			// New messages wait behind the parked one even though there is room.
+			set_max_queue_length(3);
+			assert_ok!(ParkingRouter::send_xcm(para(1), message()));
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 2);
+
+			// Room for one more, so the oldest parked message goes through and the other waits.
+			XcmSender::on_initialize(3);
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 3);
+			assert_eq!(XcmSender::parked(ParaId::from(1)).len(), 1);
+
+			// Out of room, and then out of retries.
+			XcmSender::on_initialize(4);
+			assert_eq!(XcmSender::parked(ParaId::from(1)), vec![]);
+			assert!(!Parked::<Test>::contains_key(ParaId::from(1)));
+			assert_eq!(dmq_contents::<Test>(1.into()).len(), 3);
+
+			assert_eq!(parked_events(), vec![
+				xcm_sender::Event::ParkedMessageDelivered(1.into(), hash),
+				xcm_sender::Event::DownwardMessageParked(1.into(), hash),
+				xcm_sender::Event::ParkedMessageDelivered(1.into(), hash),
+				xcm_sender::Event::ParkedMessageDropped(1.into(), hash),
+			]);
+			// Only the parked messages are recorded, the router itself tells nobody.
+			let size = VersionedXcm::from(message()).encode().len() as u64;
+			assert_eq!(
+				XcmSender::delivery_stats(ParaId::from(1)),
+				DeliveryStats { messages: 2, bytes: 2 * size },
+			);
+		});
+	}
+
+	#[test]
+	fn parked_paras_are_drained_in_turns() {
+		new_test_ext().execute_with(|| {
+			set_max_queue_length(1);
+			for id in 1..=3 {
+				assert_ok!(ParkingRouter::send_xcm(para(id), message()));
+				assert_ok!(ParkingRouter::send_xcm(para(id), message()));
+			}
+			assert_eq!(ParkedParas::<Test>::get(), vec![1.into(), 2.into(), 3.into()]);
+
+			// Only the first two paras get their turn, and there is no room for them yet.
+			XcmSender::on_initialize(2);
+			assert_eq!(ParkedParas::<Test>::get(), vec![3.into(), 1.into(), 2.into()]);
+			assert_eq!(XcmSender::parked(ParaId::from(3))[0].retries, 0);
+
+			set_max_queue_length(2);
+			XcmSender::on_initialize(3);
+			assert_eq!(ParkedParas::<Test>::get(), vec![2.into()]);
+			for id in [1, 3].iter().copied() {
+				assert_eq!(dmq_contents::<Test>(id.into()).len(), 2);
+				assert!(!Parked::<Test>::contains_key(ParaId::from(id)));
+			}
+
+			XcmSender::on_initialize(4);
+			assert_eq!(dmq_contents::<Test>(2.into()).len(), 2);
+			assert_eq!(ParkedParas::<Test>::get(), vec![]);
+		});
+	}
+
+	type ExemptingRouter = ChildParachainRouter<Test, WithExemptions<XcmSender, Price>>;
+
+	#[test]
+	fn exempt_paras_are_not_charged() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(XcmSender::add_exempt_para(Origin::root(), 1.into()));
+
+			let (exempt, required) = ExemptingRouter::validate(para(1), message()).unwrap();
+			assert_eq!((exempt.price, required), (0, vec![]));
+			let (charged, _) = ExemptingRouter::validate(para(2), message()).unwrap();
+			assert_eq!(charged.price, Price::price_for_parachain_delivery(2.into(), &charged.blob));
+			assert_eq!(exempt.blob, charged.blob);
+
+			// Free deliveries go through without payment.
+			assert_ok!(ExemptingRouter::send_xcm(para(1), message()));
+			assert_eq!(ExemptingRouter::send_xcm(para(2), message()), Err(XcmError::TooExpensive));
+
+			assert_ok!(XcmSender::remove_exempt_para(Origin::root(), 1.into()));
+			let (ticket, _) = ExemptingRouter::validate(para(1), message()).unwrap();
+			assert_eq!(ticket.price, charged.price);
+		});
+	}
+
+	#[test]
+	fn exempt_paras_are_managed_by_exempt_origin() {
+		use sp_runtime::DispatchError::BadOrigin;
+
+		new_test_ext().execute_with(|| {
+			assert_noop!(XcmSender::add_exempt_para(Origin::signed(1), 1.into()), BadOrigin);
+			assert_noop!(
+				XcmSender::remove_exempt_para(Origin::root(), 1.into()),
+				Error::<Test>::NotExempt,
+			);
+
+			assert_ok!(XcmSender::add_exempt_para(Origin::root(), 1.into()));
+			assert!(XcmSender::contains(&1.into()));
+			assert!(!XcmSender::contains(&2.into()));
+			assert_noop!(
+				XcmSender::add_exempt_para(Origin::root(), 1.into()),
+				Error::<Test>::AlreadyExempt,
+			);
+
+			assert_noop!(XcmSender::remove_exempt_para(Origin::signed(1), 1.into()), BadOrigin);
+			assert_ok!(XcmSender::remove_exempt_para(Origin::root(), 1.into()));
+			assert!(!XcmSender::contains(&1.into()));
+
+			let events = System::events().into_iter().map(|r| r.event).collect::<Vec<_>>();
+			assert_eq!(events, vec![
+				Event::XcmSender(xcm_sender::Event::ExemptParaAdded(1.into())),
+				Event::XcmSender(xcm_sender::Event::ExemptParaRemoved(1.into())),
+			]);
+		});
+	}
+
+	/// Pseudo-random numbers below `bound`, the same in every run.
+	fn sample(seed: u32, bound: usize) -> usize {
+		let hash = blake2_256(&seed.encode());
+		u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) as usize % bound
+	}
+
+	#[test]
+	fn overpayments_are_refunded() {
+		new_test_ext().execute_with(|| {
+			for seed in 0..100 {
+				let msg = Xcm::Transact {
+					origin_type: OriginKind::Native,
+					require_weight_at_most: 0,
+					call: vec![0u8; sample(seed, 1_000)].into(),
+				};
+				let extra = sample(seed + 1_000, 1_000) as u128;
+				let (ticket, _) = ObservedRouter::validate(para(1), msg).unwrap();
+				let actual = Price::price_for_parachain_delivery(1.into(), &ticket.blob);
+				assert!(ticket.price >= actual);
+
+				let charged = ticket.price + extra;
+				let refund = ObservedRouter::deliver(ticket, &[native_asset(charged)]).unwrap();
+				assert_eq!(native_amount(&refund) + actual, charged);
+				assert!(native_amount(&refund) <= charged);
+				assert_eq!(refund.is_empty(), charged == actual);
+			}
+
+			let refunds = System::events().into_iter()
+				.filter_map(|r| match r.event {
+					Event::XcmSender(xcm_sender::Event::DeliveryFeeRefunded(para, charged, actual)) =>
+						Some((para, charged, actual)),
+					_ => None,
+				})
+				.collect::<Vec<_>>();
+			assert!(!refunds.is_empty());
+			assert!(refunds.iter().all(|(para, charged, actual)| {
+				*para == ParaId::from(1) && charged > actual
+			}));
+		});
+	}
+
+	#[test]
+	fn exempt_paras_are_not_refunded() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(XcmSender::add_exempt_para(Origin::root(), 1.into()));
+			type Router = ChildParachainRouter<Test, WithExemptions<XcmSender, Price>, XcmSender>;
+
+			let (ticket, _) = Router::validate(para(1), message()).unwrap();
+			assert_eq!(Router::deliver(ticket, &[native_asset(1_000)]), Ok(vec![]));
+			let (ticket, _) = Router::validate(para(2), message()).unwrap();
+			let price = ticket.price;
+			assert_eq!(
+				Router::deliver(ticket, &[native_asset(price + 1_000)]),
+				Ok(vec![native_asset(1_000)]),
+			);
+
+			let refunded = xcm_sender::Event::DeliveryFeeRefunded(2.into(), price + 1_000, price);
+			assert_eq!(System::events().pop().map(|r| r.event), Some(Event::XcmSender(refunded)));
+		});
+	}
 }
@@ -167,3 +167,48 @@ fn era_payout_should_give_sensible_results() {
 		0,
 	), (6, 4));
 }
+
+#[test]
+fn election_bounds_are_respected() {
+	runtime_common::assert_election_bounds!(Runtime);
+}
+
+#[test]
+fn miner_solution_weight_matches_benchmarks() {
+	// The `submit_unsigned` formula in `weights::pallet_election_provider_multi_phase`.
+	let benchmarked = |v: u32, t: u32, a: u32, d: u32| -> Weight {
+		4_805_000 * v as Weight +
+			305_000 * t as Weight +
+			16_090_000 * a as Weight +
+			5_619_000 * d as Weight +
+			RocksDbWeight::get().reads_writes(7, 1)
+	};
+	runtime_common::elections::assert_miner_weight_matches::<Runtime>(benchmarked);
+}
+
+#[test]
+fn multiplier_can_grow_from_zero() {
+	use runtime_common::{AdjustmentVariable, MinimumMultiplier, TargetBlockFullness};
+	runtime_common::assert_multiplier_recovers::<
+		Runtime,
+		TargetBlockFullness,
+		AdjustmentVariable,
+		MinimumMultiplier,
+	>();
+}
+
+#[test]
+fn constructed_signed_extras_match_runtime() {
+	use parity_scale_codec::Compact;
+	let extra: SignedExtra = runtime_common::construct_signed_extras!(
+		Runtime,
+		era: generic::Era::Immortal,
+		nonce: 7,
+		tip: 3,
+	);
+	// Only the era, nonce and tip are part of the encoded extra.
+	assert_eq!(
+		extra.encode(),
+		(generic::Era::Immortal, Compact(7 as Nonce), Compact(3 as Balance)).encode(),
+	);
+}
@@ -22,12 +22,13 @@
 
 use pallet_transaction_payment::CurrencyAdapter;
 use runtime_common::{
+	migrations::{self, RunOnce},
 	claims, SlowAdjustingFeeUpdate, CurrencyToVote,
-	impls::DealWithFees,
+	impls::{SplitFees, ToAuthor},
 	BlockHashCount, RocksDbWeight, BlockWeights, BlockLength,
-	OffchainSolutionWeightLimit, OffchainSolutionLengthLimit,
+	OffchainSolutionWeightLimit, OffchainSolutionLengthLimit, BetterUnsignedThreshold,
 	elections::fee_for_submit_call,
-	ParachainSessionKeyPlaceholder, AssignmentSessionKeyPlaceholder,
+	ParachainSessionKeyHandler, AssignmentSessionKeyHandler,
 };
 
 use sp_std::prelude::*;
@@ -95,7 +96,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("polkadot"),
 	impl_name: create_runtime_str!("parity-polkadot"),
 	authoring_version: 0,
-	spec_version: 9090,
+	spec_version: 9100,
 	impl_version: 0,
 	#[cfg(not(feature = "disable-runtime-api"))]
 	apis: RUNTIME_API_VERSIONS,
@@ -263,10 +264,14 @@ impl pallet_balances::Config for Runtime {
 
 parameter_types! {
 	pub const TransactionByteFee: Balance = 10 * MILLICENTS;
+	/// The share of transaction fees, excluding tips, that goes to the treasury.
+	pub const TreasuryFeeShare: Perbill = Perbill::from_percent(80);
 }
 
+type DealWithFees = SplitFees<Treasury, ToAuthor<Runtime>, TreasuryFeeShare>;
+
 impl pallet_transaction_payment::Config for Runtime {
-	type OnChargeTransaction = CurrencyAdapter<Balances, DealWithFees<Runtime>>;
+	type OnChargeTransaction = CurrencyAdapter<Balances, DealWithFees>;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = WeightToFee;
 	type FeeMultiplierUpdate = SlowAdjustingFeeUpdate<Self>;
@@ -299,8 +304,8 @@ impl_opaque_keys! {
 		pub grandpa: Grandpa,
 		pub babe: Babe,
 		pub im_online: ImOnline,
-		pub para_validator: ParachainSessionKeyPlaceholder<Runtime>,
-		pub para_assignment: AssignmentSessionKeyPlaceholder<Runtime>,
+		pub para_validator: ParachainSessionKeyHandler<Runtime>,
+		pub para_assignment: AssignmentSessionKeyHandler<Runtime>,
 		pub authority_discovery: AuthorityDiscovery,
 	}
 }
@@ -352,7 +357,6 @@ parameter_types! {
 	// fallback: emergency phase.
 	pub const Fallback: pallet_election_provider_multi_phase::FallbackStrategy =
 		pallet_election_provider_multi_phase::FallbackStrategy::Nothing;
-	pub SolutionImprovementThreshold: Perbill = Perbill::from_rational(5u32, 10_000);
 
 	// miner configs
 	pub const MinerMaxIterations: u32 = 10;
@@ -381,7 +385,7 @@ impl pallet_election_provider_multi_phase::Config for Runtime {
 	type SignedMaxWeight = Self::MinerMaxWeight;
 	type SlashHandler = (); // burn slashes
 	type RewardHandler = (); // nothing to do upon rewards
-	type SolutionImprovementThreshold = SolutionImprovementThreshold;
+	type SolutionImprovementThreshold = BetterUnsignedThreshold;
 	type MinerMaxIterations = MinerMaxIterations;
 	type MinerMaxWeight = OffchainSolutionWeightLimit; // For now use the one from staking.
 	type MinerMaxLength = OffchainSolutionLengthLimit;
@@ -451,10 +455,7 @@ impl pallet_staking::Config for Runtime {
 	type MaxNominatorRewardedPerValidator = MaxNominatorRewardedPerValidator;
 	type NextNewSession = Session;
 	type ElectionProvider = ElectionProviderMultiPhase;
-	type GenesisElectionProvider =
-		frame_election_provider_support::onchain::OnChainSequentialPhragmen<
-			pallet_election_provider_multi_phase::OnChainConfig<Self>
-		>;
+	type GenesisElectionProvider = runtime_common::elections::OnChainSeqPhragmen<Self>;
 	type WeightInfo = weights::pallet_staking::WeightInfo<Runtime>;
 }
 
@@ -660,12 +661,14 @@ parameter_types! {
 	pub const BountyCuratorDeposit: Permill = Permill::from_percent(50);
 	pub const BountyValueMinimum: Balance = 10 * DOLLARS;
 	pub const MaxApprovals: u32 = 100;
+	// The treasury does not check approved spends against a cap yet, so approvals stay unbounded.
+	pub const MaxApprovedSpend: Balance = Balance::max_value();
 }
 
-type ApproveOrigin = EnsureOneOf<
+type ApproveOrigin = runtime_common::spend_origins::SmallSpender<
 	AccountId,
-	EnsureRoot<AccountId>,
-	pallet_collective::EnsureProportionAtLeast<_3, _5, AccountId, CouncilCollective>
+	CouncilCollective,
+	MaxApprovedSpend,
 >;
 
 impl pallet_treasury::Config for Runtime {
@@ -776,15 +779,12 @@ impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for R
 			// so the actual block number is `n`.
 			.saturating_sub(1);
 		let tip = 0;
-		let extra: SignedExtra = (
-			frame_system::CheckSpecVersion::<Runtime>::new(),
-			frame_system::CheckTxVersion::<Runtime>::new(),
-			frame_system::CheckGenesis::<Runtime>::new(),
-			frame_system::CheckMortality::<Runtime>::from(generic::Era::mortal(period, current_block)),
-			frame_system::CheckNonce::<Runtime>::from(nonce),
-			frame_system::CheckWeight::<Runtime>::new(),
-			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
-			claims::PrevalidateAttests::<Runtime>::new(),
+		let extra: SignedExtra = runtime_common::construct_signed_extras!(
+			Runtime,
+			era: generic::Era::mortal(period, current_block),
+			nonce: nonce,
+			tip: tip,
+			prevalidate_claims,
 		);
 		let raw_payload = SignedPayload::new(call, extra).map_err(|e| {
 			log::warn!("Unable to create signed payload: {:?}", e);
@@ -1002,6 +1002,10 @@ impl pallet_proxy::Config for Runtime {
 	type AnnouncementDepositFactor = AnnouncementDepositFactor;
 }
 
+impl migrations::Config for Runtime {
+	type Event = Event;
+}
+
 construct_runtime! {
 	pub enum Runtime where
 		Block = Block,
@@ -1063,6 +1067,9 @@ construct_runtime! {
 		// Election pallet. Only works with staking, but placed here to maintain indices.
 		ElectionProviderMultiPhase: pallet_election_provider_multi_phase::{Pallet, Call, Storage, Event<T>, ValidateUnsigned} = 36,
 
+		// Tracker of the executed storage migrations.
+		Migrations: migrations::{Pallet, Storage, Event<T>} = 37,
+
 	}
 }
 
@@ -1096,11 +1103,16 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPallets,
-	RemoveCollectiveFlip,
+	RunOnce<Runtime, CollectiveFlipPalletName, RemoveCollectiveFlipName, RemoveCollectiveFlip>,
 >;
 /// The payload being signed in transactions.
 pub type SignedPayload = generic::SignedPayload<Call, SignedExtra>;
 
+parameter_types! {
+	pub const CollectiveFlipPalletName: &'static [u8] = b"RandomnessCollectiveFlip";
+	pub const RemoveCollectiveFlipName: &'static [u8] = b"remove_random_material";
+}
+
 pub struct RemoveCollectiveFlip;
 impl frame_support::traits::OnRuntimeUpgrade for RemoveCollectiveFlip {
 	fn on_runtime_upgrade() -> Weight {
@@ -1382,6 +1394,12 @@ sp_api::impl_runtime_apis! {
 		}
 	}
 
+	impl migrations::MigrationsApi<Block, BlockNumber> for Runtime {
+		fn executed_migrations() -> Vec<(migrations::MigrationId, BlockNumber)> {
+			Migrations::executed_migrations()
+		}
+	}
+
 	#[cfg(feature = "try-runtime")]
 	impl frame_try_runtime::TryRuntime<Block> for Runtime {
 		fn on_runtime_upgrade() -> Result<(Weight, Weight), sp_runtime::RuntimeString> {
@@ -1474,6 +1492,56 @@ mod test_fees {
 	use pallet_transaction_payment::Multiplier;
 	use separator::Separatable;
 
+	#[test]
+	fn emergency_election_call_decodes() {
+		use runtime_common::elections::{emergency, FallbackConfig};
+
+		let account = |i: u8| AccountId::from([i; 32]);
+		let voters = vec![
+			(account(1), 100, vec![account(10), account(20)]),
+			(account(2), 50, vec![account(20)]),
+		];
+		let targets = vec![account(10), account(20)];
+		let bounds = FallbackConfig { max_voters: 2, max_targets: 2 };
+		let (supports, _) =
+			emergency::emergency_supports(voters, targets, 2, 2, bounds).unwrap();
+
+		let call = emergency::emergency_call::<Runtime>(supports.clone());
+		assert_eq!(
+			Call::decode(&mut &call[..]).unwrap(),
+			Call::ElectionProviderMultiPhase(
+				pallet_election_provider_multi_phase::Call::set_emergency_election_result(supports),
+			),
+		);
+	}
+
+	#[test]
+	fn election_bounds_are_respected() {
+		runtime_common::assert_election_bounds!(Runtime);
+	}
+
+	#[test]
+	fn miner_solution_weight_matches_benchmarks() {
+		// The `submit_unsigned` formula in `weights::pallet_election_provider_multi_phase`.
+		let benchmarked = |v: u32, t: u32, a: u32, d: u32| -> Weight {
+			3_352_000 * v as Weight +
+				150_000 * t as Weight +
+				10_531_000 * a as Weight +
+				3_302_000 * d as Weight +
+				RocksDbWeight::get().reads_writes(7, 1)
+		};
+		runtime_common::elections::assert_miner_weight_matches::<Runtime>(benchmarked);
+	}
+
+	#[test]
+	fn voter_snapshot_per_block_is_pinned() {
+		use frame_support::traits::Get;
+		use runtime_common::elections::VoterSnapshotPerBlock;
+		// Half of a 2 second block, at 27_162_000 and three reads of the staking weights per
+		// nominator: 10^12 / (27_162_000 + 3 * 25_000_000).
+		assert_eq!(VoterSnapshotPerBlock::<Runtime>::get(), 9_788);
+	}
+
 	#[test]
 	fn payout_weight_portion() {
 		use pallet_staking::WeightInfo;
@@ -1505,6 +1573,47 @@ mod test_fees {
 		);
 	}
 
+	#[test]
+	fn fee_estimate_matches_transaction_payment() {
+		use frame_support::weights::{DispatchClass, DispatchInfo, Pays};
+		use runtime_common::estimate_fee_parts;
+
+		let multiplier = Multiplier::saturating_from_rational(3, 2);
+		let max_block = BlockWeights::get().max_block;
+		sp_io::TestExternalities::new_empty().execute_with(|| {
+			pallet_transaction_payment::NextFeeMultiplier::<Runtime>::put(multiplier);
+			let cases = [
+				(0, 0),
+				(100, 1_000),
+				(1_000, max_block / 10),
+				(5_000, max_block),
+				(10, Weight::max_value()),
+			];
+			for &(len, weight) in &cases {
+				let info = DispatchInfo { weight, class: DispatchClass::Normal, pays_fee: Pays::Yes };
+				let details = TransactionPayment::compute_fee_details(len, &info, 0);
+				let inclusion_fee = details.inclusion_fee.expect("the call pays fees; qed");
+				let estimate = estimate_fee_parts::<Runtime>(len, weight, multiplier);
+
+				assert_eq!(estimate.base_fee, inclusion_fee.base_fee);
+				assert_eq!(estimate.len_fee, inclusion_fee.len_fee);
+				assert_eq!(estimate.adjusted_weight_fee, inclusion_fee.adjusted_weight_fee);
+				assert_eq!(estimate.total(), TransactionPayment::compute_fee(len, &info, 0));
+			}
+		});
+	}
+
+	#[test]
+	fn multiplier_can_grow_from_zero() {
+		use runtime_common::{AdjustmentVariable, MinimumMultiplier, TargetBlockFullness};
+		runtime_common::assert_multiplier_recovers::<
+			Runtime,
+			TargetBlockFullness,
+			AdjustmentVariable,
+			MinimumMultiplier,
+		>();
+	}
+
 	#[test]
 	#[ignore]
 	fn transfer_cost_min_multiplier() {
@@ -1571,7 +1680,7 @@ mod test_fees {
 
 	#[test]
 	fn nominator_limit() {
-		use pallet_election_provider_multi_phase::WeightInfo;
+		use runtime_common::elections::{BenchmarkedMinerWeight, MinerWeight};
 		// starting point of the nominators.
 		let target_voters: u32 = 50_000;
 
@@ -1579,12 +1688,7 @@ mod test_fees {
 		let all_targets: u32 = 5_000;
 		let desired: u32 = 1_000;
 		let weight_with = |active| {
-			<Runtime as pallet_election_provider_multi_phase::Config>::WeightInfo::submit_unsigned(
-				active,
-				all_targets,
-				active,
-				desired,
-			)
+			BenchmarkedMinerWeight::<Runtime>::solution_weight(active, all_targets, active, desired)
 		};
 
 		let mut active = target_voters;
@@ -1596,3 +1700,25 @@ mod test_fees {
 		assert!(active > target_voters, "we need to reevaluate the weight of the election system");
 	}
 }
+
+#[cfg(test)]
+mod signed_extra_tests {
+	use super::*;
+	use parity_scale_codec::Compact;
+
+	#[test]
+	fn constructed_signed_extras_match_runtime() {
+		let extra: SignedExtra = runtime_common::construct_signed_extras!(
+			Runtime,
+			era: generic::Era::Immortal,
+			nonce: 7,
+			tip: 3,
+			prevalidate_claims,
+		);
+		// Only the era, nonce and tip are part of the encoded extra.
+		assert_eq!(
+			extra.encode(),
+			(generic::Era::Immortal, Compact(7 as Nonce), Compact(3 as Balance)).encode(),
+		);
+	}
+}
@@ -50,67 +50,16 @@ pub mod time {
 pub mod fee {
 	pub use sp_runtime::Perbill;
 	use primitives::v0::Balance;
-	use runtime_common::ExtrinsicBaseWeight;
-	use frame_support::weights::{
-		WeightToFeePolynomial, WeightToFeeCoefficient, WeightToFeeCoefficients,
-	};
-	use smallvec::smallvec;
+	use runtime_common::{ExtrinsicBaseWeight, weight_to_fee::LinearWeightToFee};
 
 	/// The block saturation level. Fees will be updates based on this value.
 	pub const TARGET_BLOCK_FULLNESS: Perbill = Perbill::from_percent(25);
 
-	/// Handles converting a weight scalar to a fee value, based on the scale and granularity of the
-	/// node's balance type.
-	///
-	/// This should typically create a mapping between the following ranges:
-	///   - [0, frame_system::MaximumBlockWeight]
-	///   - [Balance::min, Balance::max]
-	///
-	/// Yet, it can be used for any other sort of change to weight-fee. Some examples being:
-	///   - Setting it to `0` will essentially disable the weight fee.
-	///   - Setting it to `1` will cause the literal `#[weight = x]` values to be charged.
-	pub struct WeightToFee;
-	impl WeightToFeePolynomial for WeightToFee {
-		type Balance = Balance;
-		fn polynomial() -> WeightToFeeCoefficients<Self::Balance> {
-			// in Westend, extrinsic base weight (smallest non-zero weight) is mapped to 1/10 CENT:
-			let p = super::currency::CENTS;
-			let q = 10 * Balance::from(ExtrinsicBaseWeight::get());
-			smallvec![WeightToFeeCoefficient {
-				degree: 1,
-				negative: false,
-				coeff_frac: Perbill::from_rational(p % q, q),
-				coeff_integer: p / q,
-			}]
-		}
-	}
-}
-
-#[cfg(test)]
-mod tests {
-	use frame_support::weights::{WeightToFeePolynomial, DispatchClass};
-	use runtime_common::BlockWeights;
-	use super::fee::WeightToFee;
-	use super::currency::{CENTS, DOLLARS, MILLICENTS};
-
-	#[test]
-	// This function tests that the fee for `MaximumBlockWeight` of weight is correct
-	fn full_block_fee_is_correct() {
-		// A full block should cost 16 DOLLARS
-		println!("Base: {}", BlockWeights::get().get(DispatchClass::Normal).base_extrinsic);
-		let x = WeightToFee::calc(&BlockWeights::get().max_block);
-		let y = 16 * DOLLARS;
-		assert!(x.max(y) - x.min(y) < MILLICENTS);
+	frame_support::parameter_types! {
+		/// In Rococo, extrinsic base weight (smallest non-zero weight) is mapped to 1/10 of this.
+		pub const FeeCent: Balance = super::currency::CENTS;
 	}
 
-	#[test]
-	// This function tests that the fee for `ExtrinsicBaseWeight` of weight is correct
-	fn extrinsic_base_fee_is_correct() {
-		// `ExtrinsicBaseWeight` should cost 1/10 of a CENT
-		let base_weight = BlockWeights::get().get(DispatchClass::Normal).base_extrinsic;
-		println!("Base: {}", base_weight);
-		let x = WeightToFee::calc(&base_weight);
-		let y = CENTS / 10;
-		assert!(x.max(y) - x.min(y) < MILLICENTS);
-	}
+	/// Handles converting a weight scalar to a fee value, see `LinearWeightToFee`.
+	pub type WeightToFee = LinearWeightToFee<ExtrinsicBaseWeight, FeeCent>;
 }
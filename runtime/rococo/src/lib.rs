@@ -33,7 +33,7 @@ use primitives::v1::{
 };
 use runtime_common::{
 	mmr as mmr_common,
-	SlowAdjustingFeeUpdate, impls::ToAuthor, BlockHashCount, BlockWeights, BlockLength, RocksDbWeight,
+	SlowAdjustingFeeUpdate, impls::{ToAuthor, ToDefaultAccount}, BlockHashCount, BlockWeights, BlockLength, RocksDbWeight,
 };
 use runtime_parachains::{
 	self,
@@ -360,14 +360,11 @@ impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for R
 			// so the actual block number is `n`.
 			.saturating_sub(1);
 		let tip = 0;
-		let extra: SignedExtra = (
-			frame_system::CheckSpecVersion::<Runtime>::new(),
-			frame_system::CheckTxVersion::<Runtime>::new(),
-			frame_system::CheckGenesis::<Runtime>::new(),
-			frame_system::CheckMortality::<Runtime>::from(generic::Era::mortal(period, current_block)),
-			frame_system::CheckNonce::<Runtime>::from(nonce),
-			frame_system::CheckWeight::<Runtime>::new(),
-			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+		let extra: SignedExtra = runtime_common::construct_signed_extras!(
+			Runtime,
+			era: generic::Era::mortal(period, current_block),
+			nonce: nonce,
+			tip: tip,
 		);
 		let raw_payload = SignedPayload::new(call, extra).map_err(|e| {
 			log::warn!("Unable to create signed payload: {:?}", e);
@@ -468,7 +465,9 @@ parameter_types! {
 }
 
 impl pallet_transaction_payment::Config for Runtime {
-	type OnChargeTransaction = CurrencyAdapter<Balances, ToAuthor<Runtime>>;
+	// There is no treasury, so fees paid in blocks without a known author go to the default
+	// account, as they always have.
+	type OnChargeTransaction = CurrencyAdapter<Balances, ToAuthor<Runtime, ToDefaultAccount<Runtime>>>;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = WeightToFee;
 	type FeeMultiplierUpdate = SlowAdjustingFeeUpdate<Self>;
@@ -638,7 +637,7 @@ parameter_types! {
 /// individual routers.
 pub type XcmRouter = (
 	// Only one router so far - use DMP to communicate with child parachains.
-	xcm_sender::ChildParachainRouter<Runtime>,
+	xcm_sender::ChildParachainRouter<Runtime, (), (), (), Paras>,
 );
 
 use xcm::v0::{MultiAsset, MultiAsset::AllConcreteFungible, MultiLocation::{Null, X1}, Junction::Parachain};
@@ -687,7 +686,7 @@ impl xcm_executor::Config for XcmConfig {
 	type LocationInverter = LocationInverter<Ancestry>;
 	type Barrier = Barrier;
 	type Weigher = FixedWeightBounds<BaseXcmWeight, Call>;
-	type Trader = UsingComponents<WeightToFee, RocLocation, AccountId, Balances, ToAuthor<Runtime>>;
+	type Trader = UsingComponents<WeightToFee, RocLocation, AccountId, Balances, ToAuthor<Runtime, ToDefaultAccount<Runtime>>>;
 	type ResponseHandler = ();
 }
 
@@ -830,9 +829,20 @@ impl pallet_mmr::Config for Runtime {
 	type LeafData = mmr_common::Pallet<Runtime>;
 }
 
+parameter_types! {
+	/// Light clients decode the original leaf format, with its trie roots and uncompressed BEEFY
+	/// keys, so keep it until they support the next one.
+	pub const LeafVersion: mmr_common::MmrLeafVersion = mmr_common::MmrLeafVersion::new(0, 0);
+	/// Keep all offchain MMR nodes. Pruning applies to every node of the network, and there are no
+	/// archive nodes yet to prove the older leaves.
+	pub const RetainedMmrLeaves: Option<u64> = None;
+}
+
 impl mmr_common::Config for Runtime {
 	type BeefyAuthorityToMerkleLeaf = mmr_common::UncompressBeefyEcdsaKeys;
 	type ParachainHeads = Paras;
+	type LeafVersion = LeafVersion;
+	type RetainedLeaves = RetainedMmrLeaves;
 }
 
 parameter_types! {
@@ -1006,6 +1016,7 @@ impl slots::Config for Runtime {
 	type Currency = Balances;
 	type Registrar = Registrar;
 	type LeasePeriod = LeasePeriod;
+	type BlockNumberProvider = runtime_common::traits::RelayChainBlockNumberProvider<Runtime>;
 	type WeightInfo = slots::TestWeightInfo;
 }
 
@@ -1027,6 +1038,7 @@ impl crowdloan::Config for Runtime {
 	type Registrar = Registrar;
 	type Auctioneer = Auctions;
 	type MaxMemoLength = MaxMemoLength;
+	type BlockNumberProvider = runtime_common::traits::RelayChainBlockNumberProvider<Runtime>;
 	type WeightInfo = crowdloan::TestWeightInfo;
 }
 
@@ -1366,8 +1378,9 @@ sp_api::impl_runtime_apis! {
 		fn generate_proof(leaf_index: u64)
 			-> Result<(mmr::EncodableOpaqueLeaf, mmr::Proof<Hash>), mmr::Error>
 		{
-			Mmr::generate_proof(leaf_index)
+			mmr_common::generate_proof::<Runtime>(leaf_index)
 				.map(|(leaf, proof)| (mmr::EncodableOpaqueLeaf::from_leaf(&leaf), proof))
+				.map_err(Into::into)
 		}
 
 		fn verify_proof(leaf: mmr::EncodableOpaqueLeaf, proof: mmr::Proof<Hash>)
@@ -1395,6 +1408,36 @@ sp_api::impl_runtime_apis! {
 		}
 	}
 
+	impl mmr_common::MmrBatchProofApi<Block, Hash> for Runtime {
+		fn generate_batch_proof(leaf_indices: Vec<mmr::LeafIndex>) -> Result<
+			(Vec<mmr::EncodableOpaqueLeaf>, mmr_common::BatchProof<Hash>),
+			mmr_common::BatchProofError,
+		> {
+			mmr_common::generate_batch_proof::<Runtime>(leaf_indices)
+				.map(|(leaves, proof)| {
+					(leaves.iter().map(mmr::EncodableOpaqueLeaf::from_leaf).collect(), proof)
+				})
+		}
+	}
+
+	impl mmr_common::BeefyNextAuthoritySetApi<Block, Hash> for Runtime {
+		fn next_authority_set_proof()
+			-> Result<(mmr::EncodableOpaqueLeaf, mmr::Proof<Hash>), mmr::Error>
+		{
+			mmr_common::latest_leaf_proof::<Runtime>()
+				.map(|(leaf, proof)| (mmr::EncodableOpaqueLeaf::from_leaf(&leaf), proof))
+		}
+	}
+
+	impl mmr_common::ParaHeadProofApi<Block> for Runtime {
+		fn generate_para_head_proof(
+			para_id: Id,
+			leaf_index: u64,
+		) -> Result<mmr_common::ParaHeadProof, mmr_common::ParaHeadProofError> {
+			MmrLeaf::para_head_proof(para_id, leaf_index)
+		}
+	}
+
 	impl bp_rococo::RococoFinalityApi<Block> for Runtime {
 		fn best_finalized() -> (bp_rococo::BlockNumber, bp_rococo::Hash) {
 			let header = BridgeRococoGrandpa::best_finalized();
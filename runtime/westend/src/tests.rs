@@ -36,3 +36,65 @@ fn sample_size_is_sensible() {
 	assert!(max_weight * 2 < BlockWeights::get().max_block);
 	assert!(<Runtime as auctions::Config>::WeightInfo::on_initialize() * 2 < BlockWeights::get().max_block);
 }
+
+#[test]
+fn election_bounds_are_respected() {
+	runtime_common::assert_election_bounds!(Runtime);
+}
+
+#[test]
+fn miner_solution_weight_matches_benchmarks() {
+	// The `submit_unsigned` formula in `weights::pallet_election_provider_multi_phase`.
+	let benchmarked = |v: u32, t: u32, a: u32, d: u32| -> Weight {
+		3_399_000 * v as Weight +
+			169_000 * t as Weight +
+			10_510_000 * a as Weight +
+			3_259_000 * d as Weight +
+			RocksDbWeight::get().reads_writes(7, 1)
+	};
+	runtime_common::elections::assert_miner_weight_matches::<Runtime>(benchmarked);
+}
+
+#[test]
+fn multiplier_can_grow_from_zero() {
+	use runtime_common::{FastAdjustmentVariable, MinimumMultiplier, TargetBlockFullness};
+	runtime_common::assert_multiplier_recovers::<
+		Runtime,
+		TargetBlockFullness,
+		FastAdjustmentVariable,
+		MinimumMultiplier,
+	>();
+}
+
+#[test]
+fn periods_match_compiled_mode() {
+	if FAST_RUNTIME {
+		assert_eq!(SessionsPerEra::get(), 1);
+		assert_eq!(BondingDuration::get(), 2);
+		assert!(VotingPeriod::get() < HOURS);
+	} else {
+		assert_eq!(SessionsPerEra::get(), 6);
+		assert_eq!(BondingDuration::get(), 28);
+		assert_eq!(VotingPeriod::get(), 7 * DAYS);
+		assert_eq!(FastTrackVotingPeriod::get(), 3 * HOURS);
+		assert_eq!(EnactmentPeriod::get(), 8 * DAYS);
+	}
+	// Slashes must be deferred for less than the unbonding period in either mode.
+	assert!(SlashDeferDuration::get() < BondingDuration::get());
+}
+
+#[test]
+fn constructed_signed_extras_match_runtime() {
+	use parity_scale_codec::Compact;
+	let extra: SignedExtra = runtime_common::construct_signed_extras!(
+		Runtime,
+		era: generic::Era::Immortal,
+		nonce: 7,
+		tip: 3,
+	);
+	// Only the era, nonce and tip are part of the encoded extra.
+	assert_eq!(
+		extra.encode(),
+		(generic::Era::Immortal, Compact(7 as Nonce), Compact(3 as Balance)).encode(),
+	);
+}
@@ -23,6 +23,7 @@
 #![no_std]
 extern crate alloc;
 
+use core::convert::TryInto;
 use parity_scale_codec::{Encode, Decode};
 use derivative::Derivative;
 
@@ -40,6 +41,31 @@ pub enum VersionedXcm<Call> {
 	V0(v0::Xcm<Call>),
 }
 
+/// A version of the XCM format.
+pub type Version = u32;
+
+/// The latest version of the XCM format known to this crate.
+pub const LATEST_VERSION: Version = 0;
+
+impl<Call> VersionedXcm<Call> {
+	/// The version of the XCM format this message is expressed in.
+	pub fn version(&self) -> Version {
+		match self {
+			VersionedXcm::V0(_) => 0,
+		}
+	}
+
+	/// Express this message in the given `version` of the XCM format.
+	///
+	/// Fails if `version` is unknown or the message cannot be expressed in it.
+	pub fn into_version(self, version: Version) -> core::result::Result<Self, ()> {
+		match version {
+			0 => Ok(VersionedXcm::V0(self.try_into()?)),
+			_ => Err(()),
+		}
+	}
+}
+
 pub mod opaque {
 	pub mod v0 {
 		// Everything from v0
@@ -63,3 +89,17 @@ pub enum VersionedMultiLocation {
 pub enum VersionedMultiAsset {
 	V0(v0::MultiAsset),
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn into_version_works() {
+		let xcm = VersionedXcm::<()>::V0(v0::Xcm::HrmpChannelAccepted { recipient: 1 });
+		assert_eq!(xcm.version(), 0);
+		assert_eq!(xcm.clone().into_version(0), Ok(xcm.clone()));
+		assert_eq!(xcm.clone().into_version(LATEST_VERSION), Ok(xcm.clone()));
+		assert_eq!(xcm.into_version(LATEST_VERSION + 1), Err(()));
+	}
+}